@@ -0,0 +1,189 @@
+//! Color management. Decoded images are converted from their embedded ICC profile to sRGB
+//! and, for the linear working space, to linear light. Results go back to sRGB before
+//! encoding, and outputs of profiled inputs are tagged as sRGB.
+
+use std::sync::OnceLock;
+
+use image::{DynamicImage, ImageBuffer, Pixel, Rgb, Rgba};
+use lcms2::{ColorSpaceSignature, InfoType, Intent, Locale, PixelFormat, Pod, Profile, Transform};
+
+use crate::filters::shrink_retina::{linear_to_srgb, srgb_to_linear};
+use crate::types::{AugeError, EncodableFormats, SampleDepth, WorkingSpace};
+
+/// Working space and whether embedded profiles are ignored, set once from the command line
+static SETTINGS: OnceLock<(WorkingSpace, bool)> = OnceLock::new();
+
+pub fn configure(space: WorkingSpace, ignore_icc: bool) {
+    let _ = SETTINGS.set((space, ignore_icc));
+}
+
+fn settings() -> (WorkingSpace, bool) {
+    SETTINGS.get().copied().unwrap_or_default()
+}
+
+/// Rec. 709 luma weights, the same as the Y row of linear sRGB to XYZ
+pub const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// Rec. 709 luma of an RGB triple, in the range of its channels
+pub fn luma([r, g, b]: [f32; 3]) -> f32 {
+    LUMA[0] * r + LUMA[1] * g + LUMA[2] * b
+}
+
+/// 8-bit sRGB color in the working space, see [`to_working_space`]
+pub fn srgb_to_working(color: Rgb<u8>) -> Rgb<u8> {
+    match settings().0 {
+        WorkingSpace::Srgb => color,
+        WorkingSpace::Linear => Rgb(color
+            .0
+            .map(|c| (srgb_to_linear(c as f32 / 255.0) * 255.0).round() as u8)),
+    }
+}
+
+/// 8-bit color in the working space back in sRGB, see [`from_working_space`]
+pub fn working_to_srgb(color: Rgb<u8>) -> Rgb<u8> {
+    match settings().0 {
+        WorkingSpace::Srgb => color,
+        WorkingSpace::Linear => Rgb(color
+            .0
+            .map(|c| (linear_to_srgb(c as f32 / 255.0) * 255.0).round() as u8)),
+    }
+}
+
+/// The sRGB profile outputs are tagged with
+pub fn srgb_icc() -> Result<Vec<u8>, AugeError> {
+    Ok(Profile::new_srgb().icc()?)
+}
+
+/// Converts RGBA pixels in place from `source` to sRGB
+fn transform<S: Pod>(
+    buffer: &mut ImageBuffer<Rgba<S>, Vec<S>>,
+    source: &Profile,
+    format: PixelFormat,
+) -> Result<(), AugeError>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+{
+    let mut pixels: Vec<[S; 4]> = buffer.pixels().map(|pixel| pixel.0).collect();
+    let srgb = Profile::new_srgb();
+    Transform::new(source, format, &srgb, format, Intent::Perceptual)?
+        .transform_in_place(&mut pixels);
+    for (pixel, converted) in buffer.pixels_mut().zip(pixels) {
+        pixel.0 = converted;
+    }
+    Ok(())
+}
+
+/// Pixels in sRGB, for RGB profiles. Other profiles, like the CMYK ones of JPEGs the decoder
+/// has already turned into RGB, are left alone, as are sRGB ones.
+fn icc_to_srgb(img: DynamicImage, icc: &[u8]) -> Result<DynamicImage, AugeError> {
+    let profile = Profile::new_icc(icc)?;
+    let description = profile
+        .info(InfoType::Description, Locale::none())
+        .unwrap_or_default();
+    if profile.color_space() != ColorSpaceSignature::RgbData || description.starts_with("sRGB") {
+        return Ok(img);
+    }
+
+    let alpha = img.color().has_alpha();
+    let converted = match img {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            let mut buffer = img.to_rgba32f();
+            transform(&mut buffer, &profile, PixelFormat::RGBA_FLT)?;
+            DynamicImage::ImageRgba32F(buffer)
+        }
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => {
+            let mut buffer = img.to_rgba16();
+            transform(&mut buffer, &profile, PixelFormat::RGBA_16)?;
+            DynamicImage::ImageRgba16(buffer)
+        }
+        _ => {
+            let mut buffer = img.to_rgba8();
+            transform(&mut buffer, &profile, PixelFormat::RGBA_8)?;
+            DynamicImage::ImageRgba8(buffer)
+        }
+    };
+    if alpha {
+        return Ok(converted);
+    }
+    Ok(match converted {
+        DynamicImage::ImageRgba32F(_) => DynamicImage::ImageRgb32F(converted.to_rgb32f()),
+        DynamicImage::ImageRgba16(_) => DynamicImage::ImageRgb16(converted.to_rgb16()),
+        _ => DynamicImage::ImageRgb8(converted.to_rgb8()),
+    })
+}
+
+/// Freshly decoded image in the working space. Float images, from EXR or HDR files,
+/// are linear already and kept as they are.
+pub fn to_working_space(img: DynamicImage, icc: Option<&[u8]>) -> Result<DynamicImage, AugeError> {
+    let (space, ignore_icc) = settings();
+    let img = match icc {
+        Some(icc) if !ignore_icc => icc_to_srgb(img, icc)?,
+        _ => img,
+    };
+    if space == WorkingSpace::Srgb
+        || matches!(
+            img,
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+        )
+    {
+        return Ok(img);
+    }
+
+    let alpha = img.color().has_alpha();
+    let mut linear = img.to_rgba32f();
+    for pixel in linear.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = srgb_to_linear(pixel[c]);
+        }
+    }
+    let linear = DynamicImage::ImageRgba32F(linear);
+    Ok(if alpha {
+        linear
+    } else {
+        DynamicImage::ImageRgb32F(linear.to_rgb32f())
+    })
+}
+
+/// Filter result back in sRGB for encoding, at `depth` when given. Otherwise integer results
+/// keep their depth, float ones stay linear for EXR and HDR and become 8-bit for everything else.
+pub fn from_working_space(
+    img: DynamicImage,
+    format: &EncodableFormats,
+    depth: Option<SampleDepth>,
+) -> DynamicImage {
+    let (space, _) = settings();
+    let float = SampleDepth::of(&img) == SampleDepth::F32;
+    if space == WorkingSpace::Srgb
+        || (float && matches!(format, EncodableFormats::Exr | EncodableFormats::Hdr))
+    {
+        return match depth {
+            Some(depth) if depth != SampleDepth::of(&img) => depth.convert(&img),
+            _ => img,
+        };
+    }
+
+    let depth = depth.unwrap_or(if float {
+        SampleDepth::Eight
+    } else {
+        SampleDepth::of(&img)
+    });
+    let (color, alpha) = (img.color().has_color(), img.color().has_alpha());
+    let mut srgb = img.to_rgba32f();
+    for pixel in srgb.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = linear_to_srgb(pixel[c].clamp(0.0, 1.0));
+        }
+    }
+    // Floats have no gray layout, 16 bits carry gray losslessly enough for any depth
+    let srgb = DynamicImage::ImageRgba32F(srgb);
+    let srgb = match (color, alpha) {
+        (true, true) => srgb,
+        (true, false) => DynamicImage::ImageRgb32F(srgb.to_rgb32f()),
+        (false, true) => DynamicImage::ImageLumaA16(srgb.to_luma_alpha16()),
+        (false, false) => DynamicImage::ImageLuma16(srgb.to_luma16()),
+    };
+    depth.convert(&srgb)
+}
@@ -0,0 +1,152 @@
+use std::str::FromStr;
+
+use image::DynamicImage;
+
+use crate::types::AugeError;
+
+/// Image property a condition can be checked against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Width,
+    Height,
+    /// Width divided by height
+    Aspect,
+    /// Mean luma in `0..=1`
+    Brightness,
+    /// Variance of the Laplacian on the luma channel, low values mean a blurry image
+    Sharpness,
+    /// 1 when any pixel is not fully opaque, 0 otherwise
+    Alpha,
+}
+
+impl Metric {
+    const NAMES: [(&'static str, Metric); 6] = [
+        ("width", Metric::Width),
+        ("height", Metric::Height),
+        ("aspect", Metric::Aspect),
+        ("brightness", Metric::Brightness),
+        ("sharpness", Metric::Sharpness),
+        ("alpha", Metric::Alpha),
+    ];
+
+    pub fn measure(&self, img: &DynamicImage) -> f64 {
+        match self {
+            Metric::Width => img.width() as f64,
+            Metric::Height => img.height() as f64,
+            Metric::Aspect => img.width() as f64 / img.height().max(1) as f64,
+            Metric::Brightness => {
+                let luma = img.to_luma8();
+                let total: u64 = luma.pixels().map(|p| p[0] as u64).sum();
+                total as f64 / (luma.len().max(1) as f64 * 255.0)
+            }
+            Metric::Sharpness => laplacian_variance(img),
+            Metric::Alpha => {
+                let opaque = !img.color().has_alpha() || img.to_rgba8().pixels().all(|p| p[3] == 255);
+                if opaque { 0.0 } else { 1.0 }
+            }
+        }
+    }
+}
+
+fn laplacian_variance(img: &DynamicImage) -> f64 {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let at = |x: u32, y: u32| luma.get_pixel(x, y)[0] as f64;
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let value = at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y);
+            sum += value;
+            sum_sq += value * value;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    sum_sq / count - mean * mean
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+/// A single check written as `METRIC OP VALUE`, e.g. `width > 2000`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Condition {
+    pub metric: Metric,
+    pub comparison: Comparison,
+    pub value: f64,
+}
+
+impl Condition {
+    pub fn holds(&self, img: &DynamicImage) -> bool {
+        let measured = self.metric.measure(img);
+        match self.comparison {
+            Comparison::Less => measured < self.value,
+            Comparison::LessOrEqual => measured <= self.value,
+            Comparison::Greater => measured > self.value,
+            Comparison::GreaterOrEqual => measured >= self.value,
+            Comparison::Equal => measured == self.value,
+            Comparison::NotEqual => measured != self.value,
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = AugeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Two character operators first so `>=` is not read as `>`
+        const OPERATORS: [(&str, Comparison); 6] = [
+            (">=", Comparison::GreaterOrEqual),
+            ("<=", Comparison::LessOrEqual),
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+        ];
+
+        let (metric, comparison, value) = OPERATORS
+            .iter()
+            .find_map(|(op, comparison)| {
+                s.split_once(op)
+                    .map(|(metric, value)| (metric, *comparison, value))
+            })
+            .ok_or_else(|| {
+                AugeError::InvalidArgument(format!(
+                    "expected 'METRIC OP VALUE' with one of > >= < <= == !=, got {}",
+                    s
+                ))
+            })?;
+
+        let metric = metric.trim().to_lowercase();
+        let metric = Metric::NAMES
+            .iter()
+            .find(|(name, _)| *name == metric)
+            .map(|(_, metric)| *metric)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Metric::NAMES.iter().map(|(name, _)| *name).collect();
+                AugeError::InvalidArgument(format!(
+                    "unknown metric '{}', expected one of {}",
+                    metric,
+                    names.join(", ")
+                ))
+            })?;
+
+        Ok(Condition {
+            metric,
+            comparison,
+            value: value.trim().parse()?,
+        })
+    }
+}
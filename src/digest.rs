@@ -0,0 +1,65 @@
+//! SHA-256 digests of results, to check that a pipeline reproduces its output byte for byte.
+//! The pixel digest covers the raw samples, so it also matches across encoders and metadata.
+
+use image::{DynamicImage, Frame};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::types::AugeError;
+
+#[derive(Debug, Serialize)]
+struct DigestReport {
+    /// Of the bytes written, `None` for frames not piped as one animation
+    output_sha256: Option<String>,
+    /// Of the raw pixel buffer of every image, one for a single image
+    pixels_sha256: Vec<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    color_type: Option<String>,
+}
+
+pub fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Report for an image and its encoded bytes, as compact JSON
+pub fn image_report(img: &DynamicImage, encoded: &[u8]) -> Result<String, AugeError> {
+    Ok(serde_json::to_string(&DigestReport {
+        output_sha256: Some(sha256(encoded)),
+        pixels_sha256: vec![sha256(img.as_bytes())],
+        width: Some(img.width()),
+        height: Some(img.height()),
+        color_type: Some(format!("{:?}", img.color()).to_lowercase()),
+    })?)
+}
+
+/// Report for text output, printed with a trailing newline
+pub fn text_report(text: &str) -> Result<String, AugeError> {
+    Ok(serde_json::to_string(&DigestReport {
+        output_sha256: Some(sha256(format!("{}\n", text).as_bytes())),
+        pixels_sha256: Vec::new(),
+        width: None,
+        height: None,
+        color_type: None,
+    })?)
+}
+
+/// Pixel digests of frames, taken before encoding consumes them
+pub fn frame_digests(frames: &[Frame]) -> Vec<String> {
+    frames
+        .iter()
+        .map(|frame| sha256(frame.buffer().as_raw()))
+        .collect()
+}
+
+/// Report for frames, one pixel digest per frame. Frames piped as one animation have its
+/// encoded bytes, frames written to separate files or previewed have none.
+pub fn frames_report(pixels: Vec<String>, encoded: Option<&[u8]>) -> Result<String, AugeError> {
+    Ok(serde_json::to_string(&DigestReport {
+        output_sha256: encoded.map(sha256),
+        pixels_sha256: pixels,
+        width: None,
+        height: None,
+        color_type: None,
+    })?)
+}
@@ -0,0 +1,63 @@
+use image::{DynamicImage, GenericImageView};
+use serde::Serialize;
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Finds the tight bounds of the visible content, e.g. for trimming sprites.
+pub struct AlphaBboxFilter {
+    /// Pixels with an alpha at or below this count as transparent
+    pub threshold: u8,
+    /// Return the trimmed image instead of the JSON report
+    pub crop: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AlphaReport {
+    pub width: u32,
+    pub height: u32,
+    /// `[x, y, width, height]` of the content, `null` when nothing is visible
+    pub bbox: Option<[u32; 4]>,
+    pub transparent_pixels: u64,
+    pub transparent_percent: f64,
+}
+
+impl AugeFilter for AlphaBboxFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let (width, height) = img.dimensions();
+        let rgba = img.to_rgba8();
+
+        let mut transparent_pixels = 0u64;
+        let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+        for (x, y, pixel) in rgba.enumerate_pixels() {
+            if pixel[3] <= self.threshold {
+                transparent_pixels += 1;
+                continue;
+            }
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+        let bbox = (left <= right).then(|| [left, top, right - left + 1, bottom - top + 1]);
+
+        if self.crop {
+            let Some([x, y, w, h]) = bbox else {
+                return Err(AugeError::InvalidArgument(
+                    "nothing to crop to, the image is fully transparent".to_string(),
+                ));
+            };
+            return Ok(img.crop_imm(x, y, w, h).into());
+        }
+
+        let report = AlphaReport {
+            width,
+            height,
+            bbox,
+            transparent_pixels,
+            transparent_percent: transparent_pixels as f64 * 100.0
+                / (width as f64 * height as f64).max(1.0),
+        };
+        Ok(FilterResult::Text(serde_json::to_string(&report)?))
+    }
+}
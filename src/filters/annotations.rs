@@ -0,0 +1,133 @@
+use std::fs;
+
+use image::{DynamicImage, GenericImageView};
+use serde_json::{Value, json};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Filters that move pixels around and can tell where a source position ends up
+pub trait Geometric {
+    /// Maps a position in edge coordinates of the `input` sized image into the `output` one
+    fn map_point(&self, x: f64, y: f64, input: (u32, u32), output: (u32, u32)) -> (f64, f64);
+}
+
+/// Runs a geometric filter and rewrites a COCO-style annotation file to match the result.
+///
+/// Accepts either a COCO document (`images` and `annotations` arrays) or a plain array of
+/// objects. Every `bbox` (`[x, y, width, height]`) becomes the bounds of its moved corners,
+/// clipped to the new image; boxes that end up outside are dropped. `segmentation` polygons
+/// are moved point by point and everything else is passed through untouched.
+pub struct Annotated<F> {
+    pub filter: F,
+    pub input: String,
+    pub output: String,
+}
+
+impl<F: AugeFilter + Geometric> AugeFilter for Annotated<F> {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut document: Value = serde_json::from_str(&fs::read_to_string(&self.input)?)?;
+        let input = img.dimensions();
+        let result = self.filter.apply(img)?;
+        let FilterResult::Image(out) = &result else {
+            return Err(AugeError::InvalidArgument(
+                "annotations can only follow a single image".to_string(),
+            ));
+        };
+        let output = out.dimensions();
+        // Trims float noise so right angles keep whole pixel coordinates, `+ 0.0` turns -0 into 0
+        let tidy = |v: f64| (v * 1e6).round() / 1e6 + 0.0;
+        let map = |x: f64, y: f64| {
+            let (x, y) = self.filter.map_point(x, y, input, output);
+            (tidy(x), tidy(y))
+        };
+
+        let annotations = match &mut document {
+            Value::Array(annotations) => Some(annotations),
+            Value::Object(root) => {
+                if let Some(Value::Array(images)) = root.get_mut("images") {
+                    for image in images.iter_mut().filter_map(Value::as_object_mut) {
+                        image.insert("width".to_string(), json!(output.0));
+                        image.insert("height".to_string(), json!(output.1));
+                    }
+                }
+                match root.get_mut("annotations") {
+                    Some(Value::Array(annotations)) => Some(annotations),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+        .ok_or_else(|| {
+            AugeError::InvalidArgument(format!(
+                "{} holds neither an array nor an 'annotations' list",
+                self.input
+            ))
+        })?;
+
+        annotations.retain_mut(|annotation| {
+            let Some(annotation) = annotation.as_object_mut() else {
+                return true;
+            };
+            if let Some(Value::Array(polygons)) = annotation.get_mut("segmentation") {
+                for polygon in polygons.iter_mut().filter_map(Value::as_array_mut) {
+                    for pair in polygon.chunks_exact_mut(2) {
+                        if let (Some(x), Some(y)) = (pair[0].as_f64(), pair[1].as_f64()) {
+                            let (x, y) = map(x, y);
+                            (pair[0], pair[1]) = (json!(x), json!(y));
+                        }
+                    }
+                }
+            }
+
+            let Some(bbox) = annotation.get("bbox").and_then(Value::as_array) else {
+                return true;
+            };
+            let bbox: Vec<f64> = bbox.iter().filter_map(Value::as_f64).collect();
+            let [x, y, w, h] = bbox[..] else {
+                return true;
+            };
+            let corners = [(x, y), (x + w, y), (x, y + h), (x + w, y + h)].map(|(x, y)| map(x, y));
+            let bound = |pick: fn(&(f64, f64)) -> f64, limit: u32| {
+                let values = corners.iter().map(pick);
+                let min = values.clone().fold(f64::INFINITY, f64::min).clamp(0.0, limit as f64);
+                let max = values.fold(f64::NEG_INFINITY, f64::max).clamp(0.0, limit as f64);
+                (min, max)
+            };
+            let (left, right) = bound(|c| c.0, output.0);
+            let (top, bottom) = bound(|c| c.1, output.1);
+            if right <= left || bottom <= top {
+                return false;
+            }
+
+            let (w, h) = (tidy(right - left), tidy(bottom - top));
+            annotation.insert("bbox".to_string(), json!([left, top, w, h]));
+            if annotation.contains_key("area") {
+                annotation.insert("area".to_string(), json!(tidy(w * h)));
+            }
+            true
+        });
+
+        fs::write(&self.output, serde_json::to_string_pretty(&document)?)?;
+        Ok(result)
+    }
+}
+
+/// Wraps the filter when an annotation file is given, `clap` guarantees both paths come together
+pub fn annotated<F>(
+    filter: F,
+    input: Option<String>,
+    output: Option<String>,
+) -> Box<dyn AugeFilter>
+where
+    F: AugeFilter + Geometric + 'static,
+{
+    match (input, output) {
+        (Some(input), Some(output)) => Box::new(Annotated {
+            filter,
+            input,
+            output,
+        }),
+        _ => Box::new(filter),
+    }
+}
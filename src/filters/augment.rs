@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use image::{Delay, DynamicImage, Frame, Rgba, RgbaImage, imageops};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::Serialize;
+
+use super::warp::{Interpolation, reflect, warp};
+use super::{AugeFilter, FilterResult};
+use crate::{color::luma, inout::load_image, types::AugeError};
+
+/// Randomly drawn parameters of one augmented copy, logged as JSON under its source file
+#[derive(Debug, Clone, Serialize)]
+pub struct AugmentParams {
+    /// Index of the emitted frame, the `{}` of `--output-template`
+    pub frame: usize,
+    pub variant: usize,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    /// Degrees, positive is clockwise
+    pub rotation: f32,
+    /// Crop before resizing back to the original size, as `[x, y, width, height]`
+    pub crop: [u32; 4],
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    /// Standard deviation of the gaussian noise in channel units
+    pub noise: f32,
+}
+
+/// Randomized flips, rotations, crops, color jitter and noise for ML datasets.
+/// Every source yields `count` copies of its original size, emitted as frames.
+pub struct AugmentFilter {
+    pub others: Vec<String>,
+    pub count: usize,
+    pub seed: u64,
+    pub max_rotation: f32,
+    /// Smallest crop side relative to the image side
+    pub min_crop: f32,
+    /// Largest relative change of brightness, contrast and saturation
+    pub jitter: f32,
+    /// Largest noise standard deviation in channel units
+    pub noise: f32,
+    pub vertical_flips: bool,
+    /// Write the drawn parameters of every copy to this JSON file, keyed by source file,
+    /// `-` for the main input
+    pub log: Option<String>,
+}
+
+impl AugmentFilter {
+    fn draw(
+        &self,
+        rng: &mut StdRng,
+        frame: usize,
+        variant: usize,
+        width: u32,
+        height: u32,
+    ) -> AugmentParams {
+        let min_crop = self.min_crop.clamp(0.01, 1.0);
+        let crop_scale = rng.gen_range(min_crop..=1.0);
+        let crop_width = ((width as f32 * crop_scale).round() as u32).clamp(1, width);
+        let crop_height = ((height as f32 * crop_scale).round() as u32).clamp(1, height);
+        let crop_x = rng.gen_range(0..=width - crop_width);
+        let crop_y = rng.gen_range(0..=height - crop_height);
+
+        let max_rotation = self.max_rotation.abs();
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = |rng: &mut StdRng| 1.0 + rng.gen_range(-jitter..=jitter);
+        AugmentParams {
+            frame,
+            variant,
+            flip_horizontal: rng.gen_bool(0.5),
+            flip_vertical: self.vertical_flips && rng.gen_bool(0.5),
+            rotation: rng.gen_range(-max_rotation..=max_rotation),
+            crop: [crop_x, crop_y, crop_width, crop_height],
+            brightness: factor(rng),
+            contrast: factor(rng),
+            saturation: factor(rng),
+            noise: rng.gen_range(0.0..=self.noise.max(0.0)),
+        }
+    }
+
+    fn augment(&self, src: &RgbaImage, params: &AugmentParams, rng: &mut StdRng) -> RgbaImage {
+        let (width, height) = src.dimensions();
+
+        let mut img = src.clone();
+        if params.flip_horizontal {
+            imageops::flip_horizontal_in_place(&mut img);
+        }
+        if params.flip_vertical {
+            imageops::flip_vertical_in_place(&mut img);
+        }
+
+        // Mirrored borders keep rotated corners filled with plausible content
+        let (sin, cos) = params.rotation.to_radians().sin_cos();
+        let (cx, cy) = ((width as f32 - 1.0) / 2.0, (height as f32 - 1.0) / 2.0);
+        let img = warp(
+            &img,
+            width,
+            height,
+            Interpolation::Bilinear,
+            Rgba([0, 0, 0, 0]),
+            |x, y| {
+                let (dx, dy) = (x - cx, y - cy);
+                let sx = cx + dx * cos + dy * sin;
+                let sy = cy - dx * sin + dy * cos;
+                Some((reflect(sx, width), reflect(sy, height)))
+            },
+        );
+
+        let [x, y, crop_width, crop_height] = params.crop;
+        let cropped = imageops::crop_imm(&img, x, y, crop_width, crop_height).to_image();
+        let mut img = if (crop_width, crop_height) == (width, height) {
+            cropped
+        } else {
+            imageops::resize(&cropped, width, height, imageops::FilterType::Triangle)
+        };
+
+        for pixel in img.pixels_mut() {
+            let mut rgb = [pixel[0], pixel[1], pixel[2]].map(|c| c as f32 * params.brightness);
+            let luma = luma(rgb);
+            for c in rgb.iter_mut() {
+                *c = luma + (*c - luma) * params.saturation;
+                *c = 127.5 + (*c - 127.5) * params.contrast;
+                if params.noise > 0.0 {
+                    *c += gaussian(rng) * params.noise;
+                }
+            }
+            for (channel, value) in pixel.0.iter_mut().zip(rgb) {
+                *channel = value.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        img
+    }
+}
+
+/// Standard normal sample via Box-Muller
+fn gaussian(rng: &mut StdRng) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen_range(0.0..1.0);
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+impl AugeFilter for AugmentFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut frames = Vec::new();
+        let mut log: BTreeMap<&str, Vec<AugmentParams>> = BTreeMap::new();
+
+        let sources = std::iter::once(("-", Ok(img))).chain(
+            self.others
+                .iter()
+                .map(|path| (path.as_str(), load_image(path))),
+        );
+        for (source, img) in sources {
+            let src = img?.to_rgba8();
+            let (width, height) = src.dimensions();
+            if width == 0 || height == 0 {
+                continue;
+            }
+            for variant in 0..self.count {
+                let params = self.draw(&mut rng, frames.len(), variant, width, height);
+                let out = self.augment(&src, &params, &mut rng);
+                frames.push(Frame::from_parts(
+                    out,
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(500, 1),
+                ));
+                log.entry(source).or_default().push(params);
+            }
+        }
+
+        if let Some(path) = &self.log {
+            std::fs::write(path, serde_json::to_string_pretty(&log)?)?;
+        }
+        Ok(FilterResult::Frames(frames))
+    }
+}
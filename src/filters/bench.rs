@@ -0,0 +1,221 @@
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use image::{DynamicImage, Rgb, RgbImage};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::Serialize;
+
+use super::{AugeFilter, FilterResult, filter_from_args};
+use crate::progress::{self, Progress};
+use crate::types::{AugeError, Grid};
+
+/// Commands timed when none are given, cheap color filters and heavier neighborhood ones
+const DEFAULT_SUITE: &[&str] = &[
+    "grayscale",
+    "invert",
+    "sepia",
+    "saturate -a 1.5",
+    "dynthres --auto otsu",
+    "resize -t 50%",
+    "g-blur -s 3",
+    "edge",
+    "edge -a canny",
+    "bilateral",
+];
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum BenchOutput {
+    /// Aligned columns to read
+    Table,
+    /// One object per command, to keep and compare in scripts
+    Json,
+}
+
+/// Runs commands over and over on the same image and reports how long they took
+pub struct BenchFilter {
+    /// Command lines as typed after `auge`, the default suite when empty
+    pub commands: Vec<String>,
+    pub runs: u32,
+    /// Untimed runs first, so caches and the thread pool are warm
+    pub warmup: u32,
+    /// Size of a generated test image to use instead of the input
+    pub generate: Option<Grid>,
+    pub output: BenchOutput,
+}
+
+#[derive(Debug, Serialize)]
+struct Timing {
+    command: String,
+    runs: u32,
+    min_ms: f64,
+    median_ms: f64,
+    mean_ms: f64,
+    max_ms: f64,
+    stddev_ms: f64,
+    /// Input megapixels per second at the median time
+    megapixels_per_second: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    width: u32,
+    height: u32,
+    timings: Vec<Timing>,
+}
+
+/// Gradients, a hard-edged disc, a checkerboard and noise, so filters meet smooth areas,
+/// edges and texture alike. The same size always gives the same pixels.
+pub fn test_image(width: u32, height: u32) -> DynamicImage {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let radius = width.min(height) as f32 / 3.0;
+    let img = RgbImage::from_fn(width, height, |x, y| {
+        let mut pixel = [
+            x as f32 / width as f32 * 255.0,
+            y as f32 / height as f32 * 255.0,
+            if (x / 32 + y / 32) % 2 == 0 {
+                200.0
+            } else {
+                60.0
+            },
+        ];
+        if (x as f32 - cx).hypot(y as f32 - cy) < radius {
+            pixel = pixel.map(|c| 255.0 - c);
+        }
+        Rgb(pixel.map(|c| (c + rng.gen_range(-16.0..16.0)).clamp(0.0, 255.0) as u8))
+    });
+    DynamicImage::ImageRgb8(img)
+}
+
+fn timing(command: &str, durations: &mut [Duration], pixels: u64) -> Timing {
+    durations.sort();
+    let ms: Vec<f64> = durations.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+    let runs = ms.len();
+    let median = if runs.is_multiple_of(2) {
+        (ms[runs / 2 - 1] + ms[runs / 2]) / 2.0
+    } else {
+        ms[runs / 2]
+    };
+    let mean = ms.iter().sum::<f64>() / runs as f64;
+    let variance = ms.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / runs as f64;
+    Timing {
+        command: command.to_string(),
+        runs: runs as u32,
+        min_ms: ms[0],
+        median_ms: median,
+        mean_ms: mean,
+        max_ms: ms[runs - 1],
+        stddev_ms: variance.sqrt(),
+        megapixels_per_second: if median > 0.0 {
+            pixels as f64 / 1e6 / (median / 1000.0)
+        } else {
+            0.0
+        },
+    }
+}
+
+fn table(report: &Report) -> String {
+    let width = report
+        .timings
+        .iter()
+        .map(|timing| timing.command.len())
+        .chain(["command".len()])
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![format!(
+        "{}x{} input\n{:width$}  {:>4}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}  {:>8}",
+        report.width,
+        report.height,
+        "command",
+        "runs",
+        "min ms",
+        "median ms",
+        "mean ms",
+        "max ms",
+        "stddev ms",
+        "MP/s"
+    )];
+    for timing in &report.timings {
+        lines.push(format!(
+            "{:width$}  {:>4}  {:>9.2}  {:>9.2}  {:>9.2}  {:>9.2}  {:>9.2}  {:>8.2}",
+            timing.command,
+            timing.runs,
+            timing.min_ms,
+            timing.median_ms,
+            timing.mean_ms,
+            timing.max_ms,
+            timing.stddev_ms,
+            timing.megapixels_per_second
+        ));
+    }
+    lines.join("\n")
+}
+
+impl AugeFilter for BenchFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let img = match self.generate {
+            Some(Grid(width, height)) => test_image(width, height),
+            None => img,
+        };
+        let commands: Vec<String> = if self.commands.is_empty() {
+            DEFAULT_SUITE
+                .iter()
+                .map(|command| command.to_string())
+                .collect()
+        } else {
+            self.commands.clone()
+        };
+
+        // Every command is parsed before the first one runs, a typo should not wait for a suite
+        let mut filters = Vec::with_capacity(commands.len());
+        for command in &commands {
+            let words: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+            if words.first().is_some_and(|word| word == "bench") {
+                return Err(AugeError::InvalidArgument(
+                    "bench cannot time itself".to_string(),
+                ));
+            }
+            let Some(filter) = filter_from_args("bench", words)? else {
+                return Err(AugeError::InvalidArgument(
+                    "commands to time cannot be empty".to_string(),
+                ));
+            };
+            filters.push(filter);
+        }
+
+        let pixels = img.width() as u64 * img.height() as u64;
+        let bar = progress::bar(
+            "bench",
+            commands.len() as u64 * (self.warmup + self.runs) as u64,
+        );
+        let mut timings = Vec::with_capacity(commands.len());
+        for (command, filter) in commands.iter().zip(&filters) {
+            let mut durations = Vec::with_capacity(self.runs as usize);
+            for run in 0..self.warmup + self.runs {
+                // Copying the input is not part of the filter's time
+                let input = img.clone();
+                let started = Instant::now();
+                let result = filter
+                    .apply(input)
+                    .map_err(|e| AugeError::InvalidArgument(format!("{}: {}", command, e)))?;
+                let elapsed = started.elapsed();
+                drop(result);
+                if run >= self.warmup {
+                    durations.push(elapsed);
+                }
+                bar.advance(1);
+            }
+            timings.push(timing(command, &mut durations, pixels));
+        }
+
+        let report = Report {
+            width: img.width(),
+            height: img.height(),
+            timings,
+        };
+        Ok(FilterResult::Text(match self.output {
+            BenchOutput::Table => table(&report),
+            BenchOutput::Json => serde_json::to_string(&report)?,
+        }))
+    }
+}
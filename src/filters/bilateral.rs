@@ -0,0 +1,86 @@
+use image::{DynamicImage, RgbaImage};
+
+use super::{AugeFilter, FilterResult, par_rows};
+use crate::progress::{self, Progress};
+use crate::types::AugeError;
+
+/// Edge-preserving smoothing: neighbors are weighted both by distance
+/// and by how close their color is to the center pixel.
+pub struct BilateralFilter {
+    pub sigma_spatial: f32,
+    pub sigma_color: f32,
+    pub iterations: u32,
+}
+
+/// One bilateral pass over RGB, alpha is carried over unchanged. Every finished row is
+/// reported to `progress`.
+pub fn bilateral(
+    src: &RgbaImage,
+    sigma_spatial: f32,
+    sigma_color: f32,
+    progress: &dyn Progress,
+) -> RgbaImage {
+    let (width, height) = src.dimensions();
+    let radius = (sigma_spatial * 2.0).ceil().max(1.0) as i64;
+    let spatial_denominator = 2.0 * sigma_spatial.max(f32::EPSILON).powi(2);
+    let color_denominator = 2.0 * sigma_color.max(f32::EPSILON).powi(2);
+
+    let spatial_weights: Vec<f32> = (-radius..=radius)
+        .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| (-((dx * dx + dy * dy) as f32) / spatial_denominator).exp())
+        .collect();
+    // Squared RGB distance is at most 3 * 255^2
+    let color_weights: Vec<f32> = (0..=3 * 255 * 255)
+        .map(|d| (-(d as f32) / color_denominator).exp())
+        .collect();
+
+    let mut out = src.clone();
+    if width == 0 || height == 0 {
+        return out;
+    }
+
+    par_rows(&mut out, width as usize * 4, |y, row| {
+        let y = y as i64;
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let x = x as i64;
+            let center = src.get_pixel(x as u32, y as u32);
+            let mut acc = [0.0f32; 3];
+            let mut total = 0.0f32;
+            let mut k = 0;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let sx = (x + dx).clamp(0, width as i64 - 1) as u32;
+                    let sy = (y + dy).clamp(0, height as i64 - 1) as u32;
+                    let p = src.get_pixel(sx, sy);
+                    let distance: i32 = (0..3)
+                        .map(|c| (p[c] as i32 - center[c] as i32).pow(2))
+                        .sum();
+                    let weight = spatial_weights[k] * color_weights[distance as usize];
+                    k += 1;
+                    for c in 0..3 {
+                        acc[c] += p[c] as f32 * weight;
+                    }
+                    total += weight;
+                }
+            }
+            for c in 0..3 {
+                pixel[c] = (acc[c] / total).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        progress.advance(1);
+    });
+
+    out
+}
+
+impl AugeFilter for BilateralFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut rgba = img.to_rgba8();
+        let iterations = self.iterations.max(1);
+        let bar = progress::bar("bilateral", iterations as u64 * rgba.height() as u64);
+        for _ in 0..iterations {
+            rgba = bilateral(&rgba, self.sigma_spatial, self.sigma_color, &bar);
+        }
+        Ok(DynamicImage::ImageRgba8(rgba).into())
+    }
+}
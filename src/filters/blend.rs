@@ -0,0 +1,225 @@
+use std::borrow::Cow;
+
+use clap::ValueEnum;
+use image::{DynamicImage, Rgba, RgbaImage, imageops::FilterType};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, SampleDepth};
+
+/// How the colors of the top layer mix with the ones below
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum BlendMode {
+    /// Top layer as is
+    Normal,
+    /// Darkens, white is neutral
+    Multiply,
+    /// Lightens, black is neutral
+    Screen,
+    /// Multiply in the shadows, screen in the highlights of the base
+    Overlay,
+    /// Overlay driven by the top layer instead of the base
+    HardLight,
+    /// Gentle contrast, like a diffuse spotlight
+    SoftLight,
+    Darken,
+    Lighten,
+    /// Absolute difference, identical pixels turn black
+    Difference,
+    /// Softer difference
+    Exclusion,
+    Add,
+    Subtract,
+    /// Brightens the base to reflect the top layer
+    ColorDodge,
+    /// Darkens the base to reflect the top layer
+    ColorBurn,
+}
+
+impl BlendMode {
+    /// Mixes one channel of the base `b` with the top `s`, both in `0..=1`
+    fn mix(self, b: f32, s: f32) -> f32 {
+        let hard_light = |b: f32, s: f32| {
+            if s <= 0.5 {
+                2.0 * b * s
+            } else {
+                1.0 - 2.0 * (1.0 - b) * (1.0 - s)
+            }
+        };
+        match self {
+            BlendMode::Normal => s,
+            BlendMode::Multiply => b * s,
+            BlendMode::Screen => b + s - b * s,
+            BlendMode::Overlay => hard_light(s, b),
+            BlendMode::HardLight => hard_light(b, s),
+            BlendMode::SoftLight => {
+                if s <= 0.5 {
+                    b - (1.0 - 2.0 * s) * b * (1.0 - b)
+                } else {
+                    let d = if b <= 0.25 {
+                        ((16.0 * b - 12.0) * b + 4.0) * b
+                    } else {
+                        b.sqrt()
+                    };
+                    b + (2.0 * s - 1.0) * (d - b)
+                }
+            }
+            BlendMode::Darken => b.min(s),
+            BlendMode::Lighten => b.max(s),
+            BlendMode::Difference => (b - s).abs(),
+            BlendMode::Exclusion => b + s - 2.0 * b * s,
+            BlendMode::Add => (b + s).min(1.0),
+            BlendMode::Subtract => (b - s).max(0.0),
+            BlendMode::ColorDodge => {
+                if b == 0.0 {
+                    0.0
+                } else if s >= 1.0 {
+                    1.0
+                } else {
+                    (b / (1.0 - s)).min(1.0)
+                }
+            }
+            BlendMode::ColorBurn => {
+                if b >= 1.0 {
+                    1.0
+                } else if s == 0.0 {
+                    0.0
+                } else {
+                    1.0 - ((1.0 - b) / s).min(1.0)
+                }
+            }
+        }
+    }
+}
+
+/// Porter-Duff operator deciding which parts of both layers survive
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Composite {
+    /// Top layer over the base
+    Over,
+    /// Top layer only where the base is opaque
+    In,
+    /// Top layer only where the base is transparent
+    Out,
+    /// Top layer over the base, clipped to the base
+    Atop,
+    /// Either layer where the other one is transparent
+    Xor,
+    /// Base over the top layer
+    DestOver,
+    /// Base only where the top layer is opaque
+    DestIn,
+    /// Base only where the top layer is transparent
+    DestOut,
+}
+
+impl Composite {
+    /// Porter-Duff fractions `(Fa, Fb)` of the top and the base layer
+    fn fractions(self, top_alpha: f32, base_alpha: f32) -> (f32, f32) {
+        match self {
+            Composite::Over => (1.0, 1.0 - top_alpha),
+            Composite::In => (base_alpha, 0.0),
+            Composite::Out => (1.0 - base_alpha, 0.0),
+            Composite::Atop => (base_alpha, 1.0 - top_alpha),
+            Composite::Xor => (1.0 - base_alpha, 1.0 - top_alpha),
+            Composite::DestOver => (1.0 - base_alpha, 1.0),
+            Composite::DestIn => (0.0, top_alpha),
+            Composite::DestOut => (0.0, 1.0 - top_alpha),
+        }
+    }
+}
+
+/// Blends and composites a single pixel following the W3C compositing model.
+/// `opacity` scales the alpha of the top layer.
+pub fn blend_pixel(
+    base: Rgba<u8>,
+    top: Rgba<u8>,
+    mode: BlendMode,
+    composite: Composite,
+    opacity: f32,
+) -> Rgba<u8> {
+    let unit = |pixel: Rgba<u8>| Rgba(pixel.0.map(|c| c as f32 / 255.0));
+    let out = blend_pixel_f32(unit(base), unit(top), mode, composite, opacity);
+    Rgba(out.0.map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8))
+}
+
+/// `blend_pixel` on channels from 0 to 1, for float images such as linear light ones
+pub fn blend_pixel_f32(
+    base: Rgba<f32>,
+    top: Rgba<f32>,
+    mode: BlendMode,
+    composite: Composite,
+    opacity: f32,
+) -> Rgba<f32> {
+    let base_alpha = base[3].clamp(0.0, 1.0);
+    let top_alpha = top[3].clamp(0.0, 1.0) * opacity.clamp(0.0, 1.0);
+    let (fa, fb) = composite.fractions(top_alpha, base_alpha);
+
+    let alpha = top_alpha * fa + base_alpha * fb;
+    if alpha <= 0.0 {
+        return Rgba([0.0; 4]);
+    }
+
+    let mut out = [0.0f32; 4];
+    for c in 0..3 {
+        let (b, s) = (base[c], top[c]);
+        // Where the base is transparent the top color shows unblended
+        let mixed = (1.0 - base_alpha) * s + base_alpha * mode.mix(b, s);
+        let premultiplied = top_alpha * fa * mixed + base_alpha * fb * b;
+        out[c] = premultiplied / alpha;
+    }
+    out[3] = alpha;
+    Rgba(out)
+}
+
+/// Blends `top` onto `base` with its top-left corner at `x, y`. Only the covered area changes.
+pub fn blend_onto(
+    base: &mut RgbaImage,
+    top: &RgbaImage,
+    x: i64,
+    y: i64,
+    mode: BlendMode,
+    composite: Composite,
+    opacity: f32,
+) {
+    let (width, height) = (base.width() as i64, base.height() as i64);
+    for (tx, ty, pixel) in top.enumerate_pixels() {
+        let (bx, by) = (x + tx as i64, y + ty as i64);
+        if bx < 0 || by < 0 || bx >= width || by >= height {
+            continue;
+        }
+        let below = base.get_pixel_mut(bx as u32, by as u32);
+        *below = blend_pixel(*below, *pixel, mode, composite, opacity);
+    }
+}
+
+/// Blends a second image, stretched to the input size, on top of the input.
+pub struct BlendFilter {
+    pub other: DynamicImage,
+    pub mode: BlendMode,
+    pub composite: Composite,
+    pub opacity: f32,
+}
+
+impl AugeFilter for BlendFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let (width, height) = (img.width(), img.height());
+        let other = if self.other.width() == width && self.other.height() == height {
+            Cow::Borrowed(&self.other)
+        } else {
+            Cow::Owned(self.other.resize_exact(width, height, FilterType::CatmullRom))
+        };
+
+        // Float input, e.g. in the linear working space, is blended without quantizing
+        if SampleDepth::of(&img) == SampleDepth::F32 {
+            let mut base = img.to_rgba32f();
+            for (below, pixel) in base.pixels_mut().zip(other.to_rgba32f().pixels()) {
+                *below = blend_pixel_f32(*below, *pixel, self.mode, self.composite, self.opacity);
+            }
+            return Ok(DynamicImage::ImageRgba32F(base).into());
+        }
+
+        let mut base = img.to_rgba8();
+        blend_onto(&mut base, &other.to_rgba8(), 0, 0, self.mode, self.composite, self.opacity);
+        Ok(DynamicImage::ImageRgba8(base).into())
+    }
+}
@@ -1,20 +1,31 @@
+use crate::color::{self, luma};
+use crate::filters::colormap::Colormap;
+use crate::filters::font::{GLYPH_HEIGHT, draw_text, text_width};
+use crate::filters::shrink_retina::srgb_to_linear;
 use crate::filters::{AugeFilter, FilterResult};
-use crate::types::AugeError;
+use crate::types::{AugeError, EncodableFormats};
 use clap::ValueEnum;
 use image::{DynamicImage, GenericImageView, Pixel, Rgb, RgbImage, Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut};
+use imageproc::rect::Rect;
 use rayon::prelude::*;
+use serde::Serialize;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Write;
+use tracing::info;
 
 #[derive(Debug, Clone)]
 pub struct Blob {
     pub points: Vec<(u32, u32)>,
     pub size: usize,
+    /// Topmost, then leftmost pixel as `(y, x)`, unique since blobs do not overlap
+    first: (u32, u32),
 }
 
 impl PartialEq for Blob {
     fn eq(&self, other: &Self) -> bool {
-        self.size == other.size
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -26,22 +37,191 @@ impl PartialOrd for Blob {
     }
 }
 
+/// Larger blobs rank higher, blobs of one size in reading order of their first pixel, so
+/// ids, `--top` and every output are the same from run to run
 impl Ord for Blob {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.size.cmp(&other.size)
+        self.size
+            .cmp(&other.size)
+            .then_with(|| other.first.cmp(&self.first))
     }
 }
 
 impl Blob {
     pub fn new(points: Vec<(u32, u32)>) -> Self {
         let size = points.len();
-        Self { points, size }
+        let first = points.iter().map(|&(x, y)| (y, x)).min().unwrap_or_default();
+        Self {
+            points,
+            size,
+            first,
+        }
+    }
+
+    /// `[x, y, width, height]` of the pixels
+    pub fn bbox(&self) -> [u32; 4] {
+        let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+        for &(x, y) in &self.points {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+        [left, top, right - left + 1, bottom - top + 1]
+    }
+
+    /// Outer boundary pixels in clockwise order, traced by Moore neighbor tracing with
+    /// Jacob's stopping criterion. Holes are not traced, pixels on one pixel wide parts
+    /// come up twice.
+    pub fn contour(&self) -> Vec<(u32, u32)> {
+        let [left, top, width, height] = self.bbox();
+        // One pixel of padding keeps every neighbor of a blob pixel inside the mask
+        let (mask_width, mask_height) = (width as i32 + 2, height as i32 + 2);
+        let mut mask = vec![false; (mask_width * mask_height) as usize];
+        for &(x, y) in &self.points {
+            mask[((y - top + 1) as i32 * mask_width + (x - left + 1) as i32) as usize] = true;
+        }
+        let inside = |(x, y): (i32, i32)| mask[(y * mask_width + x) as usize];
+        let to_image = |(x, y): (i32, i32)| ((x - 1) as u32 + left, (y - 1) as u32 + top);
+
+        // First blob pixel in raster order, its west neighbor is background
+        let Some(start) = (0..mask_height)
+            .flat_map(|y| (0..mask_width).map(move |x| (x, y)))
+            .find(|&p| inside(p))
+        else {
+            return Vec::new();
+        };
+
+        // Next blob pixel clockwise around `p`, starting after the background cell in
+        // direction `from`, and the direction of the cell before it as seen from there
+        let step = |p: (i32, i32), from: usize| {
+            (1..8).map(|k| (from + k) % 8).find_map(|d| {
+                let next = (p.0 + NEIGHBORS[d].0, p.1 + NEIGHBORS[d].1);
+                if !inside(next) {
+                    return None;
+                }
+                let (bx, by) = NEIGHBORS[(d + 7) % 8];
+                let offset = (p.0 + bx - next.0, p.1 + by - next.1);
+                let back = NEIGHBORS.iter().position(|&o| o == offset).unwrap_or(0);
+                Some((next, back))
+            })
+        };
+
+        let mut contour = vec![to_image(start)];
+        let Some((first, mut from)) = step(start, 0) else {
+            return contour;
+        };
+        let mut p = first;
+        while let Some((next, back)) = step(p, from) {
+            if p == start && next == first {
+                break;
+            }
+            contour.push(to_image(p));
+            (p, from) = (next, back);
+        }
+        contour
+    }
+
+    /// Mean position of the pixels
+    pub fn centroid(&self) -> [f64; 2] {
+        let (sum_x, sum_y) = self
+            .points
+            .iter()
+            .fold((0u64, 0u64), |(sx, sy), &(x, y)| (sx + x as u64, sy + y as u64));
+        let n = self.size.max(1) as f64;
+        [sum_x as f64 / n, sum_y as f64 / n]
     }
 }
 
+/// Moore neighborhood clockwise from west, y grows downwards
+const NEIGHBORS: [(i32, i32); 8] = [
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+];
+
+/// Length of the closed polyline through pixel centers, straight steps count 1 and
+/// diagonal ones √2
+pub fn perimeter(contour: &[(u32, u32)]) -> f64 {
+    if contour.len() < 2 {
+        return 0.0;
+    }
+    contour
+        .iter()
+        .zip(contour.iter().cycle().skip(1))
+        .map(|(&(ax, ay), &(bx, by))| (ax as f64 - bx as f64).hypot(ay as f64 - by as f64))
+        .sum()
+}
+
+/// Space the distance to the target color is measured in
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum BlobColorSpace {
+    /// Euclidean distance of the 0-255 channels
+    #[default]
+    Rgb,
+    /// CIE76 ΔE, close to how different colors look, about 2.3 is just noticeable
+    Lab,
+    /// Distance in the HSV cone scaled to 0-255, hue wraps around and matters less
+    /// the darker and greyer the colors
+    Hsv,
+}
+
+impl BlobColorSpace {
+    /// Coordinates in which the Euclidean distance is the color difference
+    fn coords(self, color: Rgb<u8>) -> [f32; 3] {
+        match self {
+            BlobColorSpace::Rgb => color.0.map(|c| c as f32),
+            BlobColorSpace::Lab => to_lab(color),
+            BlobColorSpace::Hsv => to_hsv_cone(color),
+        }
+    }
+}
+
+/// sRGB to CIELAB under D65
+fn to_lab(color: Rgb<u8>) -> [f32; 3] {
+    let [r, g, b] = color.0.map(|c| srgb_to_linear(c as f32 / 255.0));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = luma([r, g, b]);
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// HSV as a point in a cone: hue is the angle, saturation times value the radius and
+/// value the height, all scaled to 0-255
+fn to_hsv_cone(color: Rgb<u8>) -> [f32; 3] {
+    let [r, g, b] = color.0.map(|c| c as f32);
+    let max = r.max(g).max(b);
+    let chroma = max - r.min(g).min(b);
+    let hue = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    let angle = hue * std::f32::consts::FRAC_PI_3;
+    [chroma * angle.cos(), chroma * angle.sin(), max]
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum BlobColorMode {
     Rainbow,
+    /// Sizes relative to the largest blob through the colormap
     Heatmap,
     Solid,
 }
@@ -53,11 +233,67 @@ pub enum BlobBackground {
     Original,
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+pub enum BlobOutput {
+    /// Blobs colored over the background
+    Raster,
+    /// Size, bounding box and centroid of every blob, largest first
+    Json,
+    /// Outer contour of every blob as a filled polygon
+    Svg,
+    /// One row per blob with its bounding box, centroid and mean color in the input
+    Csv,
+}
+
+/// Which of the found blobs are kept
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlobSelection {
+    /// Smallest size in pixels to keep
+    pub min_size: usize,
+    /// Largest size in pixels to keep
+    pub max_size: Option<usize>,
+    /// Keep only this many of the largest blobs left by the size limits
+    pub top: Option<usize>,
+}
+
 pub struct BlobDetectFilter {
     pub threshold: u8,
     pub target_color: Option<Rgb<u8>>,
+    pub color_space: BlobColorSpace,
+    pub selection: BlobSelection,
     pub mode: BlobColorMode,
+    pub colormap: Colormap,
+    pub solid_color: Rgb<u8>,
     pub background: BlobBackground,
+    pub output: BlobOutput,
+    /// Also list the pixels of every blob in the JSON output
+    pub points: bool,
+    /// Outline blobs with their bounding box, centroid and size instead of filling them
+    pub annotate: bool,
+    /// Trace outer contours: raster output draws only them, JSON lists them
+    pub contours: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct BlobEntry {
+    id: usize,
+    size: usize,
+    /// `[x, y, width, height]`
+    bbox: [u32; 4],
+    centroid: [f64; 2],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    points: Option<Vec<(u32, u32)>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contour: Option<Vec<(u32, u32)>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    perimeter: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct BlobReport {
+    width: u32,
+    height: u32,
+    blobs: Vec<BlobEntry>,
 }
 
 impl AugeFilter for BlobDetectFilter {
@@ -65,27 +301,101 @@ impl AugeFilter for BlobDetectFilter {
         let width = img.width();
         let height = img.height();
 
+        if let Some(max_size) = self.selection.max_size
+            && max_size < self.selection.min_size
+        {
+            return Err(AugeError::InvalidArgument(format!(
+                "--max-size {} is below --min-size {}",
+                max_size, self.selection.min_size
+            )));
+        }
+
+        let blobs = find_blobs_tiled(
+            &img,
+            self.threshold,
+            self.target_color,
+            self.color_space,
+            self.selection,
+        );
+        let max_blob_size = blobs.peek().map(|b| b.size).unwrap_or(0);
+        let palette = Palette {
+            mode: &self.mode,
+            colormap: self.colormap,
+            solid_color: self.solid_color,
+            max_blob_size,
+        };
+        info!("found {} blobs, the largest of {} pixels", blobs.len(), max_blob_size);
+
+        if let BlobOutput::Json = self.output {
+            let report = BlobReport {
+                width,
+                height,
+                blobs: blobs
+                    .into_sorted_vec()
+                    .into_iter()
+                    .rev()
+                    .enumerate()
+                    .map(|(id, blob)| {
+                        let contour = self.contours.then(|| blob.contour());
+                        BlobEntry {
+                            id,
+                            size: blob.size,
+                            bbox: blob.bbox(),
+                            centroid: blob.centroid(),
+                            perimeter: contour.as_deref().map(perimeter),
+                            contour,
+                            points: self.points.then_some(blob.points),
+                        }
+                    })
+                    .collect(),
+            };
+            return Ok(FilterResult::Text(serde_json::to_string(&report)?));
+        }
+
+        if let BlobOutput::Csv = self.output {
+            return Ok(FilterResult::Text(blobs_csv(&img, blobs)));
+        }
+
+        if let BlobOutput::Svg = self.output {
+            return Ok(FilterResult::Text(contours_svg(
+                width, height, blobs, &palette,
+            )));
+        }
+
         if width == 0 || height == 0 {
             return Ok(FilterResult::Image(DynamicImage::new_rgba8(0, 0)));
         }
 
-        let blobs = find_blobs_tiled(&img, self.threshold, self.target_color);
-        let max_blob_size = blobs.peek().map(|b| b.size).unwrap_or(0);
+        if self.annotate {
+            let mut canvas = match self.background {
+                BlobBackground::Black => RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255])),
+                BlobBackground::Transparent => RgbaImage::new(width, height),
+                BlobBackground::Original => img.to_rgba8(),
+            };
+            annotate_blobs(&mut canvas, blobs, &palette);
+            let output_image = match self.background {
+                BlobBackground::Black => {
+                    DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).into_rgb8())
+                }
+                _ => DynamicImage::ImageRgba8(canvas),
+            };
+            return Ok(FilterResult::Image(output_image));
+        }
 
         let output_image = match self.background {
             BlobBackground::Black => {
                 let mut canvas = RgbImage::new(width, height);
-                draw_blobs(&mut canvas, blobs, &self.mode, max_blob_size);
+                draw_blobs(&mut canvas, blobs, &palette, self.contours);
                 DynamicImage::ImageRgb8(canvas)
             }
             BlobBackground::Transparent => {
                 let mut canvas = RgbaImage::new(width, height);
-                draw_blobs(&mut canvas, blobs, &self.mode, max_blob_size);
+                draw_blobs(&mut canvas, blobs, &palette, self.contours);
                 DynamicImage::ImageRgba8(canvas)
             }
             BlobBackground::Original => {
                 let mut canvas = img.to_rgba8();
-                draw_blobs(&mut canvas, blobs, &self.mode, max_blob_size);
+                draw_blobs(&mut canvas, blobs, &palette, self.contours);
                 DynamicImage::ImageRgba8(canvas)
             }
         };
@@ -128,8 +438,11 @@ impl BitboardTile {
         ty: u32,
         threshold: u8,
         target: Option<Rgb<u8>>,
+        space: BlobColorSpace,
     ) -> Self {
         let mut mask: u64 = 0;
+        let target = target.map(|t| space.coords(t));
+        let max_distance = threshold as f32 * threshold as f32;
         let start_x = tx * TILE_DIM;
         let start_y = ty * TILE_DIM;
         let width = img.width();
@@ -145,11 +458,11 @@ impl BitboardTile {
             if px_x < width && px_y < height {
                 // Inlining the match check logic
                 let matches = if let Some(t) = target {
-                    let p = img.get_pixel(px_x, px_y).to_rgb();
-                    let dr = p.0[0] as i32 - t.0[0] as i32;
-                    let dg = p.0[1] as i32 - t.0[1] as i32;
-                    let db = p.0[2] as i32 - t.0[2] as i32;
-                    (dr * dr + dg * dg + db * db) <= (threshold as i32 * threshold as i32)
+                    let p = space.coords(img.get_pixel(px_x, px_y).to_rgb());
+                    let d0 = p[0] - t[0];
+                    let d1 = p[1] - t[1];
+                    let d2 = p[2] - t[2];
+                    (d0 * d0 + d1 * d1 + d2 * d2) <= max_distance
                 } else {
                     // Use luma
                     let l = img.get_pixel(px_x, px_y).to_luma().0[0];
@@ -287,6 +600,8 @@ fn find_blobs_tiled(
     img: &DynamicImage,
     threshold: u8,
     target_color: Option<Rgb<u8>>,
+    space: BlobColorSpace,
+    selection: BlobSelection,
 ) -> BinaryHeap<Blob> {
     let width = img.width();
     let height = img.height();
@@ -295,8 +610,8 @@ fn find_blobs_tiled(
         return BinaryHeap::new();
     }
 
-    let tiles_x = (width + TILE_DIM - 1) / TILE_DIM;
-    let tiles_y = (height + TILE_DIM - 1) / TILE_DIM;
+    let tiles_x = width.div_ceil(TILE_DIM);
+    let tiles_y = height.div_ceil(TILE_DIM);
 
     // 1. Parallel processing of tiles
     // We use Rayon to process tiles in parallel.
@@ -305,7 +620,7 @@ fn find_blobs_tiled(
         .map(|tile_idx| {
             let ty = tile_idx / tiles_x;
             let tx = tile_idx % tiles_x;
-            let tile = BitboardTile::from_image(img, tx, ty, threshold, target_color);
+            let tile = BitboardTile::from_image(img, tx, ty, threshold, target_color, space);
             tile.process((tx, ty))
         })
         .collect();
@@ -353,18 +668,16 @@ fn find_blobs_tiled(
                     // Current tile pixel at (7, row) -> index row*8 + 7
                     let curr_idx = row * 8 + 7;
                     // Right tile pixel at (0, row) -> index row*8 + 0
-                    let right_idx = row * 8 + 0;
+                    let right_idx = row * 8;
 
                     let lid_a = curr_res.labels[curr_idx];
                     let lid_b = right_res.labels[right_idx];
 
-                    if lid_a != 0 && lid_b != 0 {
-                        if let (Some(gid_a), Some(gid_b)) = (
-                            get_global_id(curr_tile_idx, lid_a),
-                            get_global_id(right_tile_idx, lid_b),
-                        ) {
-                            dsu.union(gid_a, gid_b);
-                        }
+                    if let (Some(gid_a), Some(gid_b)) = (
+                        get_global_id(curr_tile_idx, lid_a),
+                        get_global_id(right_tile_idx, lid_b),
+                    ) {
+                        dsu.union(gid_a, gid_b);
                     }
                 }
             }
@@ -383,13 +696,11 @@ fn find_blobs_tiled(
                     let lid_a = curr_res.labels[curr_idx];
                     let lid_b = bottom_res.labels[bottom_idx];
 
-                    if lid_a != 0 && lid_b != 0 {
-                        if let (Some(gid_a), Some(gid_b)) = (
-                            get_global_id(curr_tile_idx, lid_a),
-                            get_global_id(bottom_tile_idx, lid_b),
-                        ) {
-                            dsu.union(gid_a, gid_b);
-                        }
+                    if let (Some(gid_a), Some(gid_b)) = (
+                        get_global_id(curr_tile_idx, lid_a),
+                        get_global_id(bottom_tile_idx, lid_b),
+                    ) {
+                        dsu.union(gid_a, gid_b);
                     }
                 }
             }
@@ -415,14 +726,24 @@ fn find_blobs_tiled(
                 let gx = start_x + lx;
                 let gy = start_y + ly;
 
-                blob_map.entry(root).or_insert_with(Vec::new).push((gx, gy));
+                blob_map.entry(root).or_default().push((gx, gy));
             }
         }
     }
 
-    let mut blobs = BinaryHeap::new();
-    for points in blob_map.into_values() {
-        blobs.push(Blob::new(points));
+    let mut blobs: BinaryHeap<Blob> = blob_map
+        .into_values()
+        .filter(|points| {
+            points.len() >= selection.min_size
+                && selection.max_size.is_none_or(|max_size| points.len() <= max_size)
+        })
+        .map(Blob::new)
+        .collect();
+
+    if let Some(top) = selection.top
+        && top < blobs.len()
+    {
+        blobs = (0..top).filter_map(|_| blobs.pop()).collect();
     }
 
     blobs
@@ -447,12 +768,91 @@ impl PixelCanvas for RgbaImage {
 fn draw_blobs<C: PixelCanvas>(
     canvas: &mut C,
     blobs: BinaryHeap<Blob>,
-    mode: &BlobColorMode,
-    max_blob_size: usize,
+    palette: &Palette,
+    contours: bool,
 ) {
     let sorted_blobs = blobs.into_sorted_vec();
     for (i, blob) in sorted_blobs.iter().rev().enumerate() {
-        let (r, g, b) = match mode {
+        let (r, g, b) = palette.color(i, blob.size);
+        let contour;
+        let pixels = if contours {
+            contour = blob.contour();
+            &contour
+        } else {
+            &blob.points
+        };
+        for &(x, y) in pixels {
+            canvas.put_pixel_rgba(x, y, r, g, b, 255);
+        }
+    }
+}
+
+/// Header and one row per blob, largest first, colors averaged over the blob's input pixels
+fn blobs_csv(img: &DynamicImage, blobs: BinaryHeap<Blob>) -> String {
+    // Means are of the sRGB input colors, not of the pixels in the working space
+    let img = color::from_working_space(img.clone(), &EncodableFormats::Png, None);
+    let mut lines = vec![
+        "id,size,x,y,width,height,centroid_x,centroid_y,mean_red,mean_green,mean_blue".to_string(),
+    ];
+    for (id, blob) in blobs.into_sorted_vec().iter().rev().enumerate() {
+        let [x, y, width, height] = blob.bbox();
+        let [cx, cy] = blob.centroid();
+        let mut sum = [0u64; 3];
+        for &(px, py) in &blob.points {
+            let Rgb(rgb) = img.get_pixel(px, py).to_rgb();
+            for (total, c) in sum.iter_mut().zip(rgb) {
+                *total += c as u64;
+            }
+        }
+        let [r, g, b] = sum.map(|total| total as f64 / blob.size.max(1) as f64);
+        lines.push(format!(
+            "{},{},{},{},{},{},{:.2},{:.2},{:.1},{:.1},{:.1}",
+            id, blob.size, x, y, width, height, cx, cy, r, g, b
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Contours as polygons through pixel centers, largest blob at the bottom. The stroke keeps
+/// blobs one pixel wide visible.
+fn contours_svg(width: u32, height: u32, blobs: BinaryHeap<Blob>, palette: &Palette) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = width,
+        h = height
+    );
+    let sorted_blobs = blobs.into_sorted_vec();
+    for (i, blob) in sorted_blobs.iter().rev().enumerate() {
+        let (r, g, b) = palette.color(i, blob.size);
+        let points = blob
+            .contour()
+            .iter()
+            .map(|(x, y)| format!("{}.5,{}.5", x, y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let hex = format!("#{:02x}{:02x}{:02x}", r, g, b);
+        let _ = writeln!(
+            svg,
+            "<polygon points=\"{}\" fill=\"{hex}\" stroke=\"{hex}\" stroke-width=\"1\" stroke-linejoin=\"round\"/>",
+            points
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Coloring of the blobs, shared by every kind of output
+struct Palette<'a> {
+    mode: &'a BlobColorMode,
+    colormap: Colormap,
+    solid_color: Rgb<u8>,
+    max_blob_size: usize,
+}
+
+impl Palette<'_> {
+    /// Color of the `i`-th largest blob
+    fn color(&self, i: usize, size: usize) -> (u8, u8, u8) {
+        match self.mode {
             BlobColorMode::Rainbow => {
                 let r = ((i * 100 + 50) % 255) as u8;
                 let g = ((i * 50 + 100) % 255) as u8;
@@ -460,31 +860,55 @@ fn draw_blobs<C: PixelCanvas>(
                 (r, g, b)
             }
             BlobColorMode::Heatmap => {
-                let size = blob.size;
-                if max_blob_size == 0 {
-                    (0, 0, 255)
+                let t = if self.max_blob_size == 0 {
+                    0.0
                 } else {
-                    let t = size as f32 / max_blob_size as f32;
-                    // Cool (Blue) -> Hot (Red) -> White
-                    if t < 0.8 {
-                        // Blue (0,0,255) to Red (255,0,0)
-                        let ratio = t / 0.8;
-                        let r_val = (255.0 * ratio) as u8;
-                        let b_val = (255.0 * (1.0 - ratio)) as u8;
-                        (r_val, 0, b_val)
-                    } else {
-                        // Red (255,0,0) to White (255,255,255)
-                        let ratio = (t - 0.8) / 0.2;
-                        let gb_val = (255.0 * ratio) as u8;
-                        (255, gb_val, gb_val)
-                    }
-                }
+                    size as f32 / self.max_blob_size as f32
+                };
+                let Rgb([r, g, b]) = self.colormap.sample(t);
+                (r, g, b)
+            }
+            BlobColorMode::Solid => {
+                let Rgb([r, g, b]) = self.solid_color;
+                (r, g, b)
             }
-            BlobColorMode::Solid => (0, 255, 0), // Green
-        };
-
-        for &(x, y) in &blob.points {
-            canvas.put_pixel_rgba(x, y, r, g, b, 255);
         }
     }
 }
+
+/// Arm length of the centroid marker in pixels
+const MARKER_ARM: i32 = 3;
+
+/// Draws the bounding box, a cross on the centroid and the size over a dark label of every
+/// blob. Labels sit above the box, or inside it when the box touches the top edge.
+fn annotate_blobs(canvas: &mut RgbaImage, blobs: BinaryHeap<Blob>, palette: &Palette) {
+    // Smallest first, so labels of the largest blobs end up on top where they overlap
+    let sorted_blobs = blobs.into_sorted_vec();
+    let count = sorted_blobs.len();
+    for (index, blob) in sorted_blobs.iter().enumerate() {
+        let (r, g, b) = palette.color(count - 1 - index, blob.size);
+        let color = Rgba([r, g, b, 255]);
+        let [x, y, w, h] = blob.bbox();
+        draw_hollow_rect_mut(canvas, Rect::at(x as i32, y as i32).of_size(w, h), color);
+
+        let [cx, cy] = blob.centroid();
+        let (cx, cy) = (cx.round() as i32, cy.round() as i32);
+        let arm = MARKER_ARM as u32 * 2 + 1;
+        draw_filled_rect_mut(canvas, Rect::at(cx - MARKER_ARM, cy).of_size(arm, 1), color);
+        draw_filled_rect_mut(canvas, Rect::at(cx, cy - MARKER_ARM).of_size(1, arm), color);
+
+        let label = blob.size.to_string();
+        let label_height = GLYPH_HEIGHT + 2;
+        let top = if y >= label_height {
+            y - label_height
+        } else {
+            y + 1
+        };
+        draw_filled_rect_mut(
+            canvas,
+            Rect::at(x as i32, top as i32).of_size(text_width(&label, 1) + 2, label_height),
+            Rgba([0, 0, 0, 255]),
+        );
+        draw_text(canvas, x as i64 + 1, top as i64 + 1, &label, 1, color);
+    }
+}
@@ -0,0 +1,62 @@
+use image::{DynamicImage, RgbaImage};
+
+use super::bilateral::bilateral;
+use super::edge::{EdgeAlgorithm, EdgeFilter};
+use super::{AugeFilter, FilterResult};
+use crate::progress;
+use crate::types::AugeError;
+
+/// Cel shading: flatten regions with bilateral smoothing, reduce them
+/// to a few tones per channel and ink the outlines.
+pub struct CartoonFilter {
+    /// Number of bilateral passes, more gives flatter regions
+    pub smoothing: u32,
+    /// Tones per channel
+    pub levels: u8,
+    /// Gradient magnitude above which a pixel is inked
+    pub edge_threshold: f32,
+    /// Opacity of the ink, 0.0 disables outlines
+    pub edge_strength: f32,
+}
+
+/// Snaps every RGB channel to one of `levels` evenly spaced values
+pub fn posterize(img: &mut RgbaImage, levels: u8) {
+    let steps = levels.max(2) as f32 - 1.0;
+    for pixel in img.pixels_mut() {
+        for c in 0..3 {
+            let value = (pixel[c] as f32 / 255.0 * steps).round() / steps;
+            pixel[c] = (value * 255.0).round() as u8;
+        }
+    }
+}
+
+impl AugeFilter for CartoonFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut smoothed = img.to_rgba8();
+        let bar = progress::bar("smooth", self.smoothing as u64 * smoothed.height() as u64);
+        for _ in 0..self.smoothing {
+            smoothed = bilateral(&smoothed, 3.0, 30.0, &bar);
+        }
+        let smoothed = DynamicImage::ImageRgba8(smoothed);
+
+        let edges = EdgeFilter {
+            algorithm: EdgeAlgorithm::Gradient,
+            threshold: Some(self.edge_threshold),
+            ..Default::default()
+        }
+        .edge_map(&smoothed)?;
+
+        let mut out = smoothed.to_rgba8();
+        posterize(&mut out, self.levels);
+
+        let ink = self.edge_strength.clamp(0.0, 1.0);
+        for (pixel, edge) in out.pixels_mut().zip(edges.pixels()) {
+            let t = edge[0] as f32 / 255.0 * ink;
+            for c in 0..3 {
+                pixel[c] = (pixel[c] as f32 * (1.0 - t)).round() as u8;
+            }
+        }
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
@@ -0,0 +1,221 @@
+use image::{DynamicImage, GrayImage, Rgba, RgbaImage};
+
+use super::{AugeFilter, FilterResult};
+use crate::progress::{self, Progress};
+use crate::types::{AugeError, ResizeInput};
+
+/// Energy added to protected pixels, large enough that seams go around them
+const PROTECTION: f32 = 1e5;
+
+/// Content-aware resize: removes or duplicates the lowest energy seams
+/// instead of scaling, so important structures keep their proportions.
+pub struct CarveFilter {
+    pub target: ResizeInput,
+    /// Grayscale mask of the input size, brighter pixels are avoided by seams
+    pub protect: Option<GrayImage>,
+}
+
+/// Working copy being carved along its width, height is handled by transposing
+#[derive(Clone)]
+struct Carver {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgba<u8>>,
+    protect: Vec<f32>,
+}
+
+impl Carver {
+    fn new(img: &RgbaImage, protect: Option<&GrayImage>) -> Self {
+        let protect = match protect {
+            Some(mask) => mask.pixels().map(|p| p[0] as f32 / 255.0 * PROTECTION).collect(),
+            None => vec![0.0; img.width() as usize * img.height() as usize],
+        };
+        Self {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            pixels: img.pixels().copied().collect(),
+            protect,
+        }
+    }
+
+    fn into_image(self) -> RgbaImage {
+        let raw = self.pixels.iter().flat_map(|p| p.0).collect();
+        RgbaImage::from_raw(self.width as u32, self.height as u32, raw)
+            .expect("buffer matches dimensions")
+    }
+
+    fn transpose(&self) -> Self {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        let mut protect = Vec::with_capacity(self.protect.len());
+        for x in 0..self.width {
+            for y in 0..self.height {
+                pixels.push(self.pixels[y * self.width + x]);
+                protect.push(self.protect[y * self.width + x]);
+            }
+        }
+        Self {
+            width: self.height,
+            height: self.width,
+            pixels,
+            protect,
+        }
+    }
+
+    /// Gradient magnitude of luma with central differences
+    fn energy(&self) -> Vec<f32> {
+        let luma: Vec<f32> = self
+            .pixels
+            .iter()
+            .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+            .collect();
+        let (w, h) = (self.width, self.height);
+        (0..w * h)
+            .map(|i| {
+                let (x, y) = (i % w, i / w);
+                let dx = luma[y * w + (x + 1).min(w - 1)] - luma[y * w + x.saturating_sub(1)];
+                let dy = luma[(y + 1).min(h - 1) * w + x] - luma[y.saturating_sub(1) * w + x];
+                dx.abs() + dy.abs() + self.protect[i]
+            })
+            .collect()
+    }
+
+    /// Cheapest top to bottom 8-connected path, one column per row
+    fn find_seam(&self) -> Vec<usize> {
+        let (w, h) = (self.width, self.height);
+        let mut cost = self.energy();
+        for y in 1..h {
+            for x in 0..w {
+                let above = &cost[(y - 1) * w..y * w];
+                let best = above[x.saturating_sub(1)..=(x + 1).min(w - 1)]
+                    .iter()
+                    .cloned()
+                    .fold(f32::INFINITY, f32::min);
+                cost[y * w + x] += best;
+            }
+        }
+
+        let mut seam = vec![0; h];
+        let last = &cost[(h - 1) * w..];
+        seam[h - 1] = (0..w).min_by(|&a, &b| last[a].total_cmp(&last[b])).unwrap_or(0);
+        for y in (0..h - 1).rev() {
+            let below = seam[y + 1];
+            let row = &cost[y * w..(y + 1) * w];
+            seam[y] = (below.saturating_sub(1)..=(below + 1).min(w - 1))
+                .min_by(|&a, &b| row[a].total_cmp(&row[b]))
+                .unwrap_or(below);
+        }
+        seam
+    }
+
+    fn remove_seam(&mut self, seam: &[usize]) {
+        let w = self.width;
+        let keep = |i: usize| i % w != seam[i / w];
+        let mut index = 0;
+        self.pixels.retain(|_| {
+            index += 1;
+            keep(index - 1)
+        });
+        index = 0;
+        self.protect.retain(|_| {
+            index += 1;
+            keep(index - 1)
+        });
+        self.width -= 1;
+    }
+
+    /// Widens by `count` columns, duplicating the seams a removal would have taken first
+    fn insert_seams(&mut self, count: usize, progress: &dyn Progress) {
+        let (w, h) = (self.width, self.height);
+
+        // Track original columns while carving a throwaway copy
+        let mut probe = self.clone();
+        let mut origin: Vec<usize> = (0..w * h).map(|i| i % w).collect();
+        let mut chosen = vec![vec![false; w]; h];
+        for _ in 0..count {
+            let seam = probe.find_seam();
+            for (y, &x) in seam.iter().enumerate() {
+                chosen[y][origin[y * probe.width + x]] = true;
+            }
+            let pw = probe.width;
+            let mut index = 0;
+            origin.retain(|_| {
+                index += 1;
+                (index - 1) % pw != seam[(index - 1) / pw]
+            });
+            probe.remove_seam(&seam);
+            progress.advance(1);
+        }
+
+        let mut pixels = Vec::with_capacity((w + count) * h);
+        let mut protect = Vec::with_capacity((w + count) * h);
+        for (y, row) in chosen.iter().enumerate() {
+            for (x, &duplicate) in row.iter().enumerate() {
+                let i = y * w + x;
+                pixels.push(self.pixels[i]);
+                protect.push(self.protect[i]);
+                if duplicate {
+                    let (a, b) = (self.pixels[i], self.pixels[y * w + (x + 1).min(w - 1)]);
+                    pixels.push(Rgba(std::array::from_fn(|c| {
+                        ((a[c] as u16 + b[c] as u16) / 2) as u8
+                    })));
+                    protect.push(self.protect[i]);
+                }
+            }
+        }
+        self.pixels = pixels;
+        self.protect = protect;
+        self.width += count;
+    }
+
+    /// Every seam removed or inserted is reported to `progress`
+    fn carve_width(&mut self, target: usize, progress: &dyn Progress) {
+        while self.width > target {
+            let seam = self.find_seam();
+            self.remove_seam(&seam);
+            progress.advance(1);
+        }
+        // Inserting too many seams at once would duplicate the same area over and over
+        while self.width < target {
+            let count = (target - self.width).min((self.width / 2).max(1));
+            self.insert_seams(count, progress);
+        }
+    }
+}
+
+impl AugeFilter for CarveFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        // Unlike plain resizing, `auto` keeps the original size of that side
+        let (target_width, target_height) = self.target.size_keeping_auto(width, height);
+        if target_width == 0 || target_height == 0 {
+            return Err(AugeError::InvalidArgument(format!(
+                "cannot carve down to {}x{}",
+                target_width, target_height
+            )));
+        }
+        if let Some(mask) = &self.protect
+            && mask.dimensions() != (width, height)
+        {
+            return Err(AugeError::InvalidArgument(format!(
+                "protection mask is {}x{}, expected {}x{} like the input",
+                mask.width(),
+                mask.height(),
+                width,
+                height
+            )));
+        }
+        if width == 0 || height == 0 {
+            return Ok(img.into());
+        }
+
+        let seams = width.abs_diff(target_width) + height.abs_diff(target_height);
+        let bar = progress::bar("seams", seams as u64);
+        let mut carver = Carver::new(&src, self.protect.as_ref());
+        carver.carve_width(target_width as usize, &bar);
+        let mut carver = carver.transpose();
+        carver.carve_width(target_height as usize, &bar);
+
+        Ok(DynamicImage::ImageRgba8(carver.transpose().into_image()).into())
+    }
+}
@@ -0,0 +1,79 @@
+use clap::ValueEnum;
+use image::{imageops, DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum ChromaView {
+    /// Y, Cb and Cr side by side as grayscale panels
+    Planes,
+    /// Only the luma plane
+    Luma,
+    /// Blue-difference plane, mid gray means no chroma
+    Cb,
+    /// Red-difference plane, mid gray means no chroma
+    Cr,
+    /// Colors on a flat mid gray luma, blocking and bleeding stand out
+    Chroma,
+}
+
+/// Splits an image into full range BT.601 YCbCr (as used by JPEG) to inspect chroma damage.
+pub struct ChromaViewFilter {
+    pub view: ChromaView,
+    /// Multiplies the distance of chroma from neutral
+    pub gain: f32,
+}
+
+fn to_ycbcr(p: &Rgb<u8>) -> [f32; 3] {
+    let [r, g, b] = p.0.map(|c| c as f32);
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b,
+        128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b,
+    ]
+}
+
+fn to_rgb(y: f32, cb: f32, cr: f32) -> Rgb<u8> {
+    let (cb, cr) = (cb - 128.0, cr - 128.0);
+    Rgb([
+        y + 1.402 * cr,
+        y - 0.344136 * cb - 0.714136 * cr,
+        y + 1.772 * cb,
+    ]
+    .map(|c| c.round().clamp(0.0, 255.0) as u8))
+}
+
+impl AugeFilter for ChromaViewFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let rgb = img.to_rgb8();
+        let (width, height) = rgb.dimensions();
+        let amplify = |c: f32| (128.0 + (c - 128.0) * self.gain).round().clamp(0.0, 255.0);
+        let plane = |index: usize| {
+            GrayImage::from_fn(width, height, |x, y| {
+                let value = to_ycbcr(rgb.get_pixel(x, y))[index];
+                let value = if index == 0 { value.round() } else { amplify(value) };
+                Luma([value as u8])
+            })
+        };
+
+        let out = match self.view {
+            ChromaView::Luma => DynamicImage::ImageLuma8(plane(0)),
+            ChromaView::Cb => DynamicImage::ImageLuma8(plane(1)),
+            ChromaView::Cr => DynamicImage::ImageLuma8(plane(2)),
+            ChromaView::Planes => {
+                let mut panels = GrayImage::new(width * 3, height);
+                for index in 0..3 {
+                    imageops::replace(&mut panels, &plane(index), (index as u32 * width) as i64, 0);
+                }
+                DynamicImage::ImageLuma8(panels)
+            }
+            ChromaView::Chroma => DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+                let [_, cb, cr] = to_ycbcr(rgb.get_pixel(x, y));
+                to_rgb(128.0, amplify(cb), amplify(cr))
+            })),
+        };
+
+        Ok(out.into())
+    }
+}
@@ -0,0 +1,110 @@
+use image::{DynamicImage, Rgb, RgbImage, Rgba};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, SampleDepth};
+
+/// A Hald CLUT of level `L` is an `L^3` x `L^3` image holding a color cube
+/// with `L^2` entries per channel, red varying fastest and blue slowest.
+pub struct HaldClutFilter {
+    table: Vec<[f32; 3]>,
+    cube_size: usize,
+}
+
+impl HaldClutFilter {
+    pub fn new(clut: &DynamicImage) -> Result<Self, AugeError> {
+        let clut = clut.to_rgb8();
+        let (width, height) = clut.dimensions();
+        if width != height {
+            return Err(AugeError::InvalidClut(format!(
+                "Hald CLUT must be square, got {}x{}",
+                width, height
+            )));
+        }
+        let level = (width as f64).cbrt().round() as u32;
+        if level < 2 || level * level * level != width {
+            return Err(AugeError::InvalidClut(format!(
+                "Hald CLUT side must be a cube of the level (e.g. 512 for level 8), got {}",
+                width
+            )));
+        }
+
+        let table = clut
+            .pixels()
+            .map(|p| [p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0])
+            .collect();
+        Ok(Self {
+            table,
+            cube_size: (level * level) as usize,
+        })
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        let n = self.cube_size;
+        self.table[r + g * n + b * n * n]
+    }
+
+    /// Trilinear lookup of a color in the cube, channels from 0 to 1
+    fn lookup(&self, color: [f32; 3]) -> [f32; 3] {
+        let max_index = (self.cube_size - 1) as f32;
+        let pos = color.map(|c| c.clamp(0.0, 1.0) * max_index);
+        let lo = pos.map(|p| (p.floor() as usize).min(self.cube_size - 2));
+        let t = [
+            pos[0] - lo[0] as f32,
+            pos[1] - lo[1] as f32,
+            pos[2] - lo[2] as f32,
+        ];
+
+        let mut out = [0.0f32; 3];
+        for corner in 0..8 {
+            let (dr, dg, db) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let weight = (if dr == 1 { t[0] } else { 1.0 - t[0] })
+                * (if dg == 1 { t[1] } else { 1.0 - t[1] })
+                * (if db == 1 { t[2] } else { 1.0 - t[2] });
+            let value = self.entry(lo[0] + dr, lo[1] + dg, lo[2] + db);
+            for c in 0..3 {
+                out[c] += value[c] * weight;
+            }
+        }
+        out
+    }
+}
+
+impl AugeFilter for HaldClutFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut rgba = img.to_rgba32f();
+        for pixel in rgba.pixels_mut() {
+            let [r, g, b] = self.lookup([pixel[0], pixel[1], pixel[2]]);
+            *pixel = Rgba([r, g, b, pixel[3]]);
+        }
+        Ok(SampleDepth::of(&img).convert(&DynamicImage::ImageRgba32F(rgba)).into())
+    }
+}
+
+/// Produces an identity Hald CLUT, ready to be graded in an external editor.
+pub struct GenerateHaldClutFilter {
+    pub level: u32,
+}
+
+impl AugeFilter for GenerateHaldClutFilter {
+    fn apply(&self, _img: DynamicImage) -> Result<FilterResult, AugeError> {
+        if !(2..=16).contains(&self.level) {
+            return Err(AugeError::InvalidClut(format!(
+                "Hald CLUT level must be between 2 and 16, got {}",
+                self.level
+            )));
+        }
+
+        let side = self.level * self.level * self.level;
+        let cube_size = self.level * self.level;
+        let step = 255.0 / (cube_size - 1) as f32;
+        let clut = RgbImage::from_fn(side, side, |x, y| {
+            let index = y * side + x;
+            let r = index % cube_size;
+            let g = (index / cube_size) % cube_size;
+            let b = index / (cube_size * cube_size);
+            Rgb([r, g, b].map(|v| (v as f32 * step).round() as u8))
+        });
+
+        Ok(DynamicImage::ImageRgb8(clut).into())
+    }
+}
@@ -0,0 +1,95 @@
+use clap::ValueEnum;
+use image::Rgb;
+
+/// Maps values in `0..=1` to colors, e.g. sizes or intensities for false color output
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum Colormap {
+    /// Blue through red to white, hot values stand out
+    #[default]
+    Heat,
+    /// Dark blue through green to yellow, perceptually uniform and colorblind friendly
+    Viridis,
+    /// Black through purple and orange to pale yellow
+    Magma,
+    /// Dark blue through magenta to yellow
+    Plasma,
+    /// Rainbow-like with a smooth lightness, for spotting small differences
+    Turbo,
+}
+
+/// Coefficients of degree 6 polynomial fits to matplotlib's maps, lowest power first
+const VIRIDIS: [[f32; 3]; 7] = [
+    [0.277_727_33, 0.005_407_344_5, 0.334_099_8],
+    [0.105_093_04, 1.404_613_5, 1.384_590_2],
+    [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+    [-4.634_230_5, -5.799_101, -19.332_441],
+    [6.228_27, 14.179_933, 56.690_55],
+    [4.776_385, -13.745_145, -65.353_03],
+    [-5.435_456, 4.645_852_6, 26.312_435],
+];
+
+const MAGMA: [[f32; 3]; 7] = [
+    [-0.002_136_485, -0.000_749_655, -0.005_386_128],
+    [0.251_660_54, 0.677_523_2, 2.494_026_6],
+    [8.353_717, -3.577_719_5, 0.314_467_9],
+    [-27.668_733, 14.264_731, -13.649_213],
+    [52.176_14, -27.943_607, 12.944_169],
+    [-50.768_524, 29.046_583, 4.234_153],
+    [18.655_705, -11.489_774, -5.601_961_5],
+];
+
+const PLASMA: [[f32; 3]; 7] = [
+    [0.058_732_344, 0.023_336_709, 0.543_340_2],
+    [2.176_514_6, 0.238_383_42, 0.753_960_5],
+    [-2.689_460_5, -7.455_851, 3.110_8],
+    [6.130_348, 42.346_188, -28.518_854],
+    [-11.107_436, -82.666_31, 60.139_847],
+    [10.023_066, 71.413_62, -54.072_186],
+    [-3.658_713_8, -22.931_535, 18.191_908],
+];
+
+/// Google's degree 5 polynomial approximation of Turbo
+const TURBO: [[f32; 3]; 6] = [
+    [0.135_721_38, 0.091_402_61, 0.106_673_3],
+    [4.615_392_6, 2.194_188_4, 12.641_946],
+    [-42.660_324, 4.842_966_6, -60.582_047],
+    [132.131_08, -14.185_033, 110.362_77],
+    [-152.942_4, 4.277_298_5, -89.903_11],
+    [59.286_38, 2.829_566, 27.348_25],
+];
+
+/// Evaluates the per-channel polynomial at `t` with Horner's scheme
+fn polynomial(coefficients: &[[f32; 3]], t: f32) -> Rgb<u8> {
+    let mut color = [0.0f32; 3];
+    for coefficient in coefficients.iter().rev() {
+        for (c, k) in color.iter_mut().zip(coefficient) {
+            *c = *c * t + k;
+        }
+    }
+    Rgb(color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
+
+impl Colormap {
+    /// Color at `t`, values outside `0..=1` are clamped
+    pub fn sample(self, t: f32) -> Rgb<u8> {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Colormap::Heat => {
+                if t < 0.8 {
+                    // Blue to red
+                    let ratio = t / 0.8;
+                    Rgb([(255.0 * ratio) as u8, 0, (255.0 * (1.0 - ratio)) as u8])
+                } else {
+                    // Red to white
+                    let ratio = (t - 0.8) / 0.2;
+                    let gb = (255.0 * ratio) as u8;
+                    Rgb([255, gb, gb])
+                }
+            }
+            Colormap::Viridis => polynomial(&VIRIDIS, t),
+            Colormap::Magma => polynomial(&MAGMA, t),
+            Colormap::Plasma => polynomial(&PLASMA, t),
+            Colormap::Turbo => polynomial(&TURBO, t),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use clap::ValueEnum;
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma};
+use imageproc::filter::gaussian_blur_f32;
+use serde::Serialize;
+
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, OutputKind};
+
+/// Window of the local SSIM statistics, as in the original paper
+const SSIM_SIGMA: f32 = 1.5;
+const C1: f32 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f32 = (0.03 * 255.0) * (0.03 * 255.0);
+
+type FloatImage = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+#[derive(Debug, Clone, Copy, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CompareMetric {
+    /// Structural similarity on luma, 1 for identical images
+    Ssim,
+    /// Peak signal-to-noise ratio in dB over RGB, higher is closer
+    Psnr,
+    /// Mean squared error over RGB channels, 0 for identical images
+    Mse,
+}
+
+/// Full-reference quality score of the input against a second image of the same size.
+pub struct CompareFilter {
+    pub other: DynamicImage,
+    pub metric: CompareMetric,
+    /// Json prints the score, raster renders the per-pixel SSIM map
+    pub output: OutputKind,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareScore {
+    pub metric: CompareMetric,
+    /// `null` for the PSNR of identical images, which is infinite
+    pub score: Option<f64>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Per-pixel SSIM of two equally sized luma images
+fn ssim_map(a: &GrayImage, b: &GrayImage) -> FloatImage {
+    let (width, height) = a.dimensions();
+    let float =
+        |f: &dyn Fn(u32, u32) -> f32| FloatImage::from_fn(width, height, |x, y| Luma([f(x, y)]));
+    let at = |img: &GrayImage, x, y| img.get_pixel(x, y)[0] as f32;
+
+    let mean_a = gaussian_blur_f32(&float(&|x, y| at(a, x, y)), SSIM_SIGMA);
+    let mean_b = gaussian_blur_f32(&float(&|x, y| at(b, x, y)), SSIM_SIGMA);
+    let mean_aa = gaussian_blur_f32(&float(&|x, y| at(a, x, y).powi(2)), SSIM_SIGMA);
+    let mean_bb = gaussian_blur_f32(&float(&|x, y| at(b, x, y).powi(2)), SSIM_SIGMA);
+    let mean_ab = gaussian_blur_f32(&float(&|x, y| at(a, x, y) * at(b, x, y)), SSIM_SIGMA);
+
+    FloatImage::from_fn(width, height, |x, y| {
+        let (ma, mb) = (mean_a.get_pixel(x, y)[0], mean_b.get_pixel(x, y)[0]);
+        let var_a = mean_aa.get_pixel(x, y)[0] - ma * ma;
+        let var_b = mean_bb.get_pixel(x, y)[0] - mb * mb;
+        let covariance = mean_ab.get_pixel(x, y)[0] - ma * mb;
+        let ssim = ((2.0 * ma * mb + C1) * (2.0 * covariance + C2))
+            / ((ma * ma + mb * mb + C1) * (var_a + var_b + C2));
+        Luma([ssim])
+    })
+}
+
+fn mean_squared_error(a: &DynamicImage, b: &DynamicImage) -> f64 {
+    let (a, b) = (a.to_rgb8(), b.to_rgb8());
+    let total: f64 = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+        .sum();
+    total / a.as_raw().len().max(1) as f64
+}
+
+impl AugeFilter for CompareFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        if img.width() != self.other.width() || img.height() != self.other.height() {
+            return Err(AugeError::InvalidArgument(format!(
+                "cannot compare a {}x{} image against a {}x{} one",
+                img.width(),
+                img.height(),
+                self.other.width(),
+                self.other.height()
+            )));
+        }
+        let (width, height) = (img.width(), img.height());
+
+        if let OutputKind::Raster = self.output {
+            // Bright where the images agree, dark where structure differs
+            let map = ssim_map(&img.to_luma8(), &self.other.to_luma8());
+            let heatmap = GrayImage::from_fn(width, height, |x, y| {
+                Luma([(map.get_pixel(x, y)[0].clamp(0.0, 1.0) * 255.0).round() as u8])
+            });
+            return Ok(DynamicImage::ImageLuma8(heatmap).into());
+        }
+
+        let score = match self.metric {
+            CompareMetric::Ssim => {
+                let map = ssim_map(&img.to_luma8(), &self.other.to_luma8());
+                let total: f64 = map.pixels().map(|p| p[0] as f64).sum();
+                Some(total / (width as f64 * height as f64).max(1.0))
+            }
+            CompareMetric::Psnr => {
+                let mse = mean_squared_error(&img, &self.other);
+                (mse > 0.0).then(|| 10.0 * (255.0 * 255.0 / mse).log10())
+            }
+            CompareMetric::Mse => Some(mean_squared_error(&img, &self.other)),
+        };
+
+        let score = CompareScore {
+            metric: self.metric,
+            score,
+            width,
+            height,
+        };
+        Ok(FilterResult::Text(serde_json::to_string(&score)?))
+    }
+}
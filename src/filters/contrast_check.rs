@@ -0,0 +1,100 @@
+use image::{DynamicImage, Rgb};
+use serde::Serialize;
+
+use super::shrink_retina::srgb_to_linear;
+use super::{AugeFilter, FilterResult};
+use crate::color::{luma, working_to_srgb};
+use crate::types::{AugeError, Color, ColorSample};
+
+/// WCAG 2 contrast ratio between two colors, sampled from the input or given directly
+pub struct ContrastCheckFilter {
+    pub foreground: ColorSample,
+    pub background: ColorSample,
+    /// Sampled colors are averaged over a square of this radius, to smooth out anti-aliasing
+    pub radius: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Levels {
+    /// Body text
+    pub normal: bool,
+    /// Text of at least 18pt, or 14pt bold
+    pub large: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContrastReport {
+    pub foreground: Color,
+    pub background: Color,
+    pub ratio: f64,
+    pub aa: Levels,
+    pub aaa: Levels,
+    /// Icons, borders and other graphical objects (3:1)
+    pub ui_components: bool,
+}
+
+/// Relative luminance as WCAG defines it
+fn luminance(color: Rgb<u8>) -> f64 {
+    luma(color.0.map(|c| srgb_to_linear(c as f32 / 255.0))) as f64
+}
+
+impl ContrastCheckFilter {
+    fn sample(&self, img: &DynamicImage, sample: &ColorSample) -> Result<Rgb<u8>, AugeError> {
+        let point = match sample {
+            ColorSample::Fixed(color) => return Ok(color.0),
+            ColorSample::At(point) => point,
+        };
+        let (x, y) = (point.0.round() as i64, point.1.round() as i64);
+        let (width, height) = (img.width() as i64, img.height() as i64);
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return Err(AugeError::InvalidArgument(format!(
+                "sample point {},{} lies outside the {}x{} input",
+                x, y, width, height
+            )));
+        }
+
+        let rgb = img.to_rgb8();
+        let r = self.radius as i64;
+        let mut sums = [0u64; 3];
+        let mut count = 0u64;
+        for sy in (y - r).max(0)..=(y + r).min(height - 1) {
+            for sx in (x - r).max(0)..=(x + r).min(width - 1) {
+                let pixel = rgb.get_pixel(sx as u32, sy as u32);
+                for c in 0..3 {
+                    sums[c] += pixel[c] as u64;
+                }
+                count += 1;
+            }
+        }
+        // Contrast is defined on sRGB colors, the ones given on the command line
+        Ok(working_to_srgb(Rgb(
+            sums.map(|sum| (sum as f64 / count as f64).round() as u8)
+        )))
+    }
+}
+
+impl AugeFilter for ContrastCheckFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let foreground = self.sample(&img, &self.foreground)?;
+        let background = self.sample(&img, &self.background)?;
+
+        let (a, b) = (luminance(foreground), luminance(background));
+        let ratio = (a.max(b) + 0.05) / (a.min(b) + 0.05);
+        // Thresholds compare against the exact ratio, the report rounds it like checkers do
+        let report = ContrastReport {
+            foreground: Color(foreground),
+            background: Color(background),
+            ratio: (ratio * 100.0).round() / 100.0,
+            aa: Levels {
+                normal: ratio >= 4.5,
+                large: ratio >= 3.0,
+            },
+            aaa: Levels {
+                normal: ratio >= 7.0,
+                large: ratio >= 4.5,
+            },
+            ui_components: ratio >= 3.0,
+        };
+        Ok(FilterResult::Text(serde_json::to_string(&report)?))
+    }
+}
@@ -0,0 +1,53 @@
+use image::{DynamicImage, GenericImageView};
+
+use super::annotations::Geometric;
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Point, ResizeInput};
+
+/// Cuts out a rectangle, parts reaching past the image are clipped.
+pub struct CropFilter {
+    /// Top-left corner in pixels
+    pub origin: Point,
+    /// Size of the cut, `auto` runs to the image edge
+    pub size: ResizeInput,
+}
+
+impl CropFilter {
+    fn origin(&self) -> (u32, u32) {
+        (
+            self.origin.0.round().max(0.0) as u32,
+            self.origin.1.round().max(0.0) as u32,
+        )
+    }
+}
+
+impl AugeFilter for CropFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let (width, height) = img.dimensions();
+        let (x, y) = self.origin();
+        if x >= width || y >= height {
+            return Err(AugeError::InvalidArgument(format!(
+                "crop origin {},{} lies outside of the {}x{} image",
+                x, y, width, height
+            )));
+        }
+        let (crop_width, crop_height) = match self.size {
+            ResizeInput::Relative(_) => self.size.size_keeping_auto(width, height),
+            _ => self.size.size_keeping_auto(width - x, height - y),
+        };
+        let crop_width = crop_width.min(width - x);
+        let crop_height = crop_height.min(height - y);
+        if crop_width == 0 || crop_height == 0 {
+            return Err(AugeError::InvalidArgument("crop is empty".to_string()));
+        }
+
+        Ok(img.crop_imm(x, y, crop_width, crop_height).into())
+    }
+}
+
+impl Geometric for CropFilter {
+    fn map_point(&self, x: f64, y: f64, _: (u32, u32), _: (u32, u32)) -> (f64, f64) {
+        let (ox, oy) = self.origin();
+        (x - ox as f64, y - oy as f64)
+    }
+}
@@ -0,0 +1,49 @@
+use image::{imageops::FilterType, Delay, DynamicImage, Frame, Rgba, RgbaImage};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+pub struct CrossfadeFilter {
+    pub other: DynamicImage,
+    pub frames: u32,
+    pub delay_ms: u32,
+    /// Emit both inputs as the first and the last frame
+    pub include_ends: bool,
+}
+
+/// Linear per-channel mix of two equally sized images, `t = 0` gives `from`
+pub fn mix(from: &RgbaImage, to: &RgbaImage, t: f32) -> RgbaImage {
+    RgbaImage::from_fn(from.width(), from.height(), |x, y| {
+        let a = from.get_pixel(x, y);
+        let b = to.get_pixel(x, y);
+        Rgba(std::array::from_fn(|c| {
+            (a[c] as f32 * (1.0 - t) + b[c] as f32 * t).round() as u8
+        }))
+    })
+}
+
+impl AugeFilter for CrossfadeFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let from = img.to_rgba8();
+        let (width, height) = from.dimensions();
+        let to = if self.other.width() == width && self.other.height() == height {
+            self.other.to_rgba8()
+        } else {
+            self.other
+                .resize_exact(width, height, FilterType::CatmullRom)
+                .to_rgba8()
+        };
+
+        let delay = Delay::from_numer_denom_ms(self.delay_ms, 1);
+        let steps = self.frames + 1;
+        let range = if self.include_ends { 0..=steps } else { 1..=self.frames };
+        let frames = range
+            .map(|i| {
+                let buffer = mix(&from, &to, i as f32 / steps as f32);
+                Frame::from_parts(buffer, 0, 0, delay)
+            })
+            .collect();
+
+        Ok(FilterResult::Frames(frames))
+    }
+}
@@ -0,0 +1,47 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use super::guided::guided;
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Radius and regularization of the fine and the coarse base layer
+const FINE_LAYER: (usize, f64) = (2, 0.0025);
+const COARSE_LAYER: (usize, f64) = (16, 0.01);
+
+/// Clarity and texture control. Approximates local Laplacian filtering with an
+/// edge-aware two-band decomposition of luma: guided filtering keeps strong edges
+/// out of the detail bands, so boosting them does not draw halos.
+pub struct DetailFilter {
+    /// Gain of small-scale texture, `0` keeps it, negative values smooth it
+    pub fine: f32,
+    /// Gain of mid-scale local contrast
+    pub coarse: f32,
+}
+
+impl AugeFilter for DetailFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let (w, h) = (width as usize, height as usize);
+
+        let luma: Vec<f64> = img
+            .to_luma8()
+            .pixels()
+            .map(|p| p[0] as f64 / 255.0)
+            .collect();
+        let fine_base = guided(&luma, &luma, w, h, FINE_LAYER.0, FINE_LAYER.1);
+        let coarse_base = guided(&luma, &luma, w, h, COARSE_LAYER.0, COARSE_LAYER.1);
+
+        // Shifting every channel by the luma change keeps hue and saturation steady
+        let out = RgbaImage::from_fn(width, height, |x, y| {
+            let i = y as usize * w + x as usize;
+            let fine = luma[i] - fine_base[i];
+            let coarse = fine_base[i] - coarse_base[i];
+            let shift = (fine * self.fine as f64 + coarse * self.coarse as f64) * 255.0;
+            let p = src.get_pixel(x, y);
+            let channel = |c: usize| (p[c] as f64 + shift).round().clamp(0.0, 255.0) as u8;
+            Rgba([channel(0), channel(1), channel(2), p[3]])
+        });
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
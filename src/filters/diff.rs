@@ -0,0 +1,84 @@
+use image::{DynamicImage, Rgb, Rgba, RgbaImage};
+use serde::Serialize;
+
+use super::{AugeFilter, FilterResult};
+use crate::color::luma;
+use crate::types::{AugeError, OutputKind};
+
+/// Pixel-exact comparison for visual regression tests.
+pub struct DiffFilter {
+    pub other: DynamicImage,
+    /// Largest per-channel difference still treated as unchanged
+    pub tolerance: u8,
+    pub output: OutputKind,
+    pub highlight: Rgb<u8>,
+    /// Also write the stats as JSON to this file next to the marked image
+    pub stats: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffStats {
+    pub width: u32,
+    pub height: u32,
+    pub changed_pixels: u64,
+    pub changed_percent: f64,
+    /// Largest per-channel difference anywhere in the image
+    pub max_difference: u8,
+    /// Mean absolute per-channel difference over all pixels
+    pub mean_difference: f64,
+}
+
+impl AugeFilter for DiffFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let a = img.to_rgba8();
+        let b = self.other.to_rgba8();
+        if a.dimensions() != b.dimensions() {
+            return Err(AugeError::InvalidArgument(format!(
+                "cannot diff a {}x{} image against a {}x{} one",
+                a.width(),
+                a.height(),
+                b.width(),
+                b.height()
+            )));
+        }
+        let (width, height) = a.dimensions();
+
+        let mut changed_pixels = 0u64;
+        let mut max_difference = 0u8;
+        let mut total_difference = 0u64;
+        // Unchanged areas fade to a light gray copy so the highlight stands out
+        let marked = RgbaImage::from_fn(width, height, |x, y| {
+            let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+            let difference = (0..4).map(|c| pa[c].abs_diff(pb[c])).max().unwrap_or(0);
+            total_difference += (0..4).map(|c| pa[c].abs_diff(pb[c]) as u64).sum::<u64>();
+            max_difference = max_difference.max(difference);
+
+            if difference > self.tolerance {
+                changed_pixels += 1;
+                let [r, g, b] = self.highlight.0;
+                Rgba([r, g, b, 255])
+            } else {
+                let luma = luma([pa[0], pa[1], pa[2]].map(f32::from));
+                let faded = (255.0 - (255.0 - luma) * 0.25).round() as u8;
+                Rgba([faded, faded, faded, 255])
+            }
+        });
+
+        let pixels = (width as u64 * height as u64).max(1);
+        let stats = DiffStats {
+            width,
+            height,
+            changed_pixels,
+            changed_percent: changed_pixels as f64 * 100.0 / pixels as f64,
+            max_difference,
+            mean_difference: total_difference as f64 / (pixels * 4) as f64,
+        };
+        if let Some(path) = &self.stats {
+            std::fs::write(path, serde_json::to_string_pretty(&stats)?)?;
+        }
+        match self.output {
+            OutputKind::Raster => Ok(DynamicImage::ImageRgba8(marked).into()),
+            OutputKind::Json => Ok(FilterResult::Text(serde_json::to_string(&stats)?)),
+        }
+    }
+}
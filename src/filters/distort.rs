@@ -0,0 +1,75 @@
+use clap::ValueEnum;
+use image::{DynamicImage, Rgba};
+
+use super::warp::{Interpolation, warp};
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Point};
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum DistortEffect {
+    /// Bulge the center outwards like a wide angle lens
+    Fisheye,
+    /// Squeeze the center inwards
+    Pinch,
+    /// Twist around the center, strongest in the middle
+    Swirl,
+}
+
+pub struct DistortFilter {
+    pub effect: DistortEffect,
+    /// Fisheye and pinch intensity
+    pub strength: f32,
+    /// Swirl rotation at the center, in degrees
+    pub angle: f32,
+    /// Radius of the affected area, relative to half of the shorter side
+    pub radius: f32,
+    /// Center of the effect, relative to image size
+    pub center: Point,
+    pub interpolation: Interpolation,
+}
+
+impl AugeFilter for DistortFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let cx = self.center.0 * width as f32;
+        let cy = self.center.1 * height as f32;
+        let radius = (self.radius * width.min(height) as f32 / 2.0).max(f32::EPSILON);
+        let strength = self.strength.max(0.0);
+        let angle = self.angle.to_radians();
+
+        let out = warp(
+            &src,
+            width,
+            height,
+            self.interpolation,
+            Rgba([0, 0, 0, 0]),
+            |x, y| {
+                let (dx, dy) = (x - cx, y - cy);
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance >= radius || distance == 0.0 {
+                    return Some((x, y));
+                }
+                let t = distance / radius;
+
+                match self.effect {
+                    DistortEffect::Fisheye | DistortEffect::Pinch => {
+                        let exponent = match self.effect {
+                            DistortEffect::Fisheye => 1.0 + strength,
+                            _ => 1.0 / (1.0 + strength),
+                        };
+                        let scale = t.powf(exponent) / t;
+                        Some((cx + dx * scale, cy + dy * scale))
+                    }
+                    DistortEffect::Swirl => {
+                        let falloff = (1.0 - t) * (1.0 - t);
+                        let (sin, cos) = (angle * falloff).sin_cos();
+                        Some((cx + dx * cos - dy * sin, cy + dx * sin + dy * cos))
+                    }
+                }
+            },
+        );
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
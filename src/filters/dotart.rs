@@ -1,100 +1,310 @@
+use clap::ValueEnum;
 use image::imageops;
-use image::{DynamicImage, GenericImageView, ImageBuffer, Pixel, Rgb, Rgba};
-use imageproc::drawing::draw_filled_circle_mut;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Pixel, Rgb, Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_circle_mut, draw_filled_rect_mut};
+use imageproc::rect::Rect;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
 use super::dynthres::DynamicThresholdFilter;
+use super::font::{GLYPH_HEIGHT, GLYPH_WIDTH, draw_text};
+use super::plotter::Plotter;
 use super::{AugeFilter, FilterResult};
-use crate::types::{AugeError, Color, DotColorSource, OutputKind, Dot, DotFilterJson};
+use crate::color;
+use crate::types::{
+    AugeError, Color, DotColorSource, DotJsonMeta, DotJsonSchema, DotShape, Dot, DotFilterJson,
+};
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum DotOutput {
+    #[default]
+    Raster,
+    Json,
+    /// Dot outlines as G-code toolpaths for a pen plotter, colors are left to the pen
+    Gcode,
+    /// Dot outlines as HPGL for a pen plotter
+    Hpgl,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum DotSizeMode {
+    /// Every dot the same radius, brightness only shows in the alpha
+    #[default]
+    Fixed,
+    /// Radius grows with brightness between the minimum and maximum, like a halftone
+    Luma,
+}
 
 pub struct DotartFilter {
     pub scale: u32,
-    pub output: OutputKind,
+    pub output: DotOutput,
     pub lower_percentile: f32,
     pub upper_percentile: f32,
     pub dot_color: DotColorSource,
     pub bg_color: Color,
+    /// Fixed luma thresholds instead of the percentiles of this image
+    pub thresholds: Option<(u8, u8)>,
+    pub shape: DotShape,
+    pub size_mode: DotSizeMode,
+    /// Radius of the darkest dots in luma mode
+    pub min_radius: u32,
+    /// Radius of the brightest dots in luma mode, half the spacing when unset
+    pub max_radius: Option<u32>,
+    /// Radius of every dot in fixed mode, a quarter of the spacing when unset
+    pub radius: Option<u32>,
+    /// Distance between dot centers in the output, `scale` when unset. The output is
+    /// resized along with it.
+    pub spacing: Option<u32>,
+    /// How far dots stray from the grid, as a fraction of the spacing
+    pub jitter: f32,
+    pub seed: u64,
+    pub json_schema: DotJsonSchema,
+    /// Add dot positions relative to the image size to the JSON output
+    pub normalized: bool,
+    /// Paper and pen settings for plotter output
+    pub plotter: Plotter,
 }
 
 impl Default for DotartFilter {
     fn default() -> Self {
         Self {
             scale: 8,
-            output: OutputKind::Raster,
+            output: DotOutput::Raster,
             lower_percentile: 0.75,
             upper_percentile: 0.10,
             dot_color: DotColorSource::Preserve,
             bg_color: Color(Rgb::from([0u8; 3])),
+            thresholds: None,
+            shape: DotShape::Circle,
+            size_mode: DotSizeMode::Fixed,
+            min_radius: 1,
+            max_radius: None,
+            radius: None,
+            spacing: None,
+            jitter: 0.0,
+            seed: 0,
+            json_schema: DotJsonSchema::V2,
+            normalized: false,
+            plotter: Plotter::default(),
         }
     }
 }
 
-impl AugeFilter for DotartFilter {
-    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+/// Draws one dot centered on `center`, characters take up the whole `cell`
+fn draw_dot(
+    layer: &mut RgbaImage,
+    shape: DotShape,
+    center: (i32, i32),
+    radius: i32,
+    cell: u32,
+    color: Rgba<u8>,
+) {
+    let (cx, cy) = center;
+    match shape {
+        DotShape::Circle => draw_filled_circle_mut(layer, center, radius, color),
+        DotShape::Square => {
+            let side = radius as u32 * 2 + 1;
+            draw_filled_rect_mut(
+                layer,
+                Rect::at(cx - radius, cy - radius).of_size(side, side),
+                color,
+            );
+        }
+        DotShape::Diamond | DotShape::Ring => {
+            // Rings are a quarter of the radius thick, at least a pixel
+            let inner = (radius - (radius / 4).max(1)).pow(2);
+            let outer = radius * radius;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let inside = if shape == DotShape::Diamond {
+                        dx.abs() + dy.abs() <= radius
+                    } else {
+                        (inner + 1..=outer).contains(&(dx * dx + dy * dy))
+                    };
+                    let (x, y) = (cx + dx, cy + dy);
+                    if inside
+                        && (0..layer.width() as i32).contains(&x)
+                        && (0..layer.height() as i32).contains(&y)
+                    {
+                        layer.put_pixel(x as u32, y as u32, color);
+                    }
+                }
+            }
+        }
+        DotShape::Char(c) => {
+            // One font pixel of spacing around the glyph
+            let scale = (cell / (GLYPH_HEIGHT + 1)).max(1);
+            let (w, h) = ((GLYPH_WIDTH * scale) as i32, (GLYPH_HEIGHT * scale) as i32);
+            draw_text(
+                layer,
+                (cx - w / 2) as i64,
+                (cy - h / 2) as i64,
+                &c.to_string(),
+                scale,
+                color,
+            );
+        }
+    }
+}
+
+/// Outline of one dot for a pen plotter, a dot without radius is a single touch of the pen
+fn dot_path(shape: DotShape, center: (i32, i32), radius: i32) -> Vec<(f64, f64)> {
+    let (cx, cy, r) = (center.0 as f64, center.1 as f64, radius as f64);
+    if radius <= 0 {
+        return vec![(cx, cy)];
+    }
+    let corners: &[(f64, f64)] = match shape {
+        DotShape::Square => &[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0)],
+        DotShape::Diamond => &[(0.0, -1.0), (1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)],
+        _ => {
+            // Segments about two pixels long, enough to look round at any paper size
+            let segments = (std::f64::consts::TAU * r / 2.0).ceil().max(12.0) as usize;
+            return (0..=segments)
+                .map(|i| {
+                    let angle = std::f64::consts::TAU * i as f64 / segments as f64;
+                    (cx + r * angle.cos(), cy + r * angle.sin())
+                })
+                .collect();
+        }
+    };
+    corners.iter().map(|(x, y)| (cx + x * r, cy + y * r)).collect()
+}
+
+impl DotartFilter {
+    /// The image at one pixel per dot
+    pub fn small(&self, img: &DynamicImage) -> DynamicImage {
         let scale = self.scale.max(1);
         let (width, height) = img.dimensions();
 
         let scaled_width = (width / scale).max(1);
         let scaled_height = (height / scale).max(1);
 
-        let small_img = img.resize_exact(
+        img.resize_exact(
             scaled_width,
             scaled_height,
             image::imageops::FilterType::Gaussian,
-        );
+        )
+    }
+}
+
+impl AugeFilter for DotartFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let scale = self.scale.max(1);
+        let spacing = self.spacing.unwrap_or(scale).max(1);
+        let (width, height) = img.dimensions();
+        // The output keeps the input size unless dots are spaced apart from their cells
+        let width = (width as u64 * spacing as u64 / scale as u64) as u32;
+        let height = (height as u64 * spacing as u64 / scale as u64) as u32;
+
+        if self.jitter.is_nan() || self.jitter < 0.0 {
+            return Err(AugeError::InvalidArgument(format!(
+                "jitter must be 0 or above, got {}",
+                self.jitter
+            )));
+        }
+        if let (DotOutput::Gcode | DotOutput::Hpgl, DotShape::Char(_)) = (&self.output, self.shape) {
+            return Err(AugeError::InvalidArgument(
+                "character dots cannot be plotted, pick another --shape".to_string(),
+            ));
+        }
+        if self.normalized && self.json_schema == DotJsonSchema::V1 {
+            return Err(AugeError::InvalidArgument(
+                "--normalized needs --json-schema v2".to_string(),
+            ));
+        }
+        let min_radius = self.min_radius as i32;
+        let max_radius = self
+            .max_radius
+            .map_or(spacing as i32 / 2, |radius| radius as i32);
+        if let DotSizeMode::Luma = self.size_mode
+            && min_radius > max_radius
+        {
+            return Err(AugeError::InvalidArgument(format!(
+                "--min-radius {} is above --max-radius {}",
+                min_radius, max_radius
+            )));
+        }
+
+        let small_img = self.small(&img);
         let small_luma_img = small_img.to_luma8();
-        let small_dyn_luma_img = DynamicImage::ImageLuma8(small_luma_img);
+        let small_dyn_luma_img = DynamicImage::ImageLuma8(small_luma_img.clone());
+
+        let fixed_radius = self
+            .radius
+            .map_or(((spacing / 4) as i32).max(1), |radius| radius as i32);
+        // Radius of the dot of a cell of the small image
+        let radius_at = |x: u32, y: u32| match self.size_mode {
+            DotSizeMode::Fixed => fixed_radius,
+            DotSizeMode::Luma => {
+                let luma = small_luma_img.get_pixel(x, y)[0] as f32 / 255.0;
+                min_radius + ((max_radius - min_radius) as f32 * luma).round() as i32
+            }
+        };
+
+        // Centers of the dots of every cell, drawn once so raster and JSON output agree
+        let (small_width, small_height) = small_img.dimensions();
+        let reach = self.jitter * spacing as f32 / 2.0;
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut centers = Vec::with_capacity((small_width * small_height) as usize);
+        for y in 0..small_height {
+            for x in 0..small_width {
+                let (mut center_x, mut center_y) = (
+                    (x * spacing + spacing / 2) as i32,
+                    (y * spacing + spacing / 2) as i32,
+                );
+                if reach > 0.0 {
+                    center_x += rng.gen_range(-reach..=reach).round() as i32;
+                    center_y += rng.gen_range(-reach..=reach).round() as i32;
+                }
+                centers.push((center_x, center_y));
+            }
+        }
+        let center_at = |x: u32, y: u32| centers[(y * small_width + x) as usize];
 
         let filter = DynamicThresholdFilter {
             lower_percentile: self.lower_percentile,
             upper_percentile: self.upper_percentile,
+            thresholds: self.thresholds,
             ..Default::default()
         };
         let threshold_result = filter.apply(small_dyn_luma_img)?;
         let small_threshold_rgb_buffer = match threshold_result {
             FilterResult::Image(DynamicImage::ImageRgb8(buffer)) => buffer,
             FilterResult::Image(other) => other.to_rgb8(),
-            FilterResult::Text(_) => unreachable!(),
+            FilterResult::Text(_) | FilterResult::Frames(_) => unreachable!(),
         };
 
         match self.output {
-            OutputKind::Raster => {
+            DotOutput::Raster => {
                 let mut background_layer = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_pixel(
                     width,
                     height,
-                    Rgba::from([
-                        self.bg_color.0[0],
-                        self.bg_color.0[1],
-                        self.bg_color.0[2],
-                        255u8,
-                    ]),
+                    self.bg_color.working().to_rgba(),
                 );
                 let mut foreground_layer =
                     ImageBuffer::<Rgba<u8>, Vec<u8>>::from_pixel(width, height, Rgba([0, 0, 0, 0]));
 
-                let radius = (scale / 4) as i32;
-                let radius = radius.max(1);
-
                 for (x, y, threshold_pixel) in small_threshold_rgb_buffer.enumerate_pixels() {
                     let threshold_luma = threshold_pixel[0];
                     if threshold_luma == 0 {
                         continue;
                     }
 
-                    let center_x = (x * scale + scale / 2) as i32;
-                    let center_y = (y * scale + scale / 2) as i32;
+                    let (center_x, center_y) = center_at(x, y);
 
                     let rgb_part: Rgb<u8> = match &self.dot_color {
-                        DotColorSource::Fixed(fixed_color) => *fixed_color,
+                        DotColorSource::Fixed(fixed_color) => color::srgb_to_working(*fixed_color),
                         DotColorSource::Preserve => small_img.get_pixel(x, y).to_rgb(),
                     };
 
                     let alpha = threshold_luma;
                     let circle_rgba_color = Rgba([rgb_part[0], rgb_part[1], rgb_part[2], alpha]);
 
-                    draw_filled_circle_mut(
+                    draw_dot(
                         &mut foreground_layer,
+                        self.shape,
                         (center_x, center_y),
-                        radius,
+                        radius_at(x, y),
+                        spacing,
                         circle_rgba_color,
                     );
                 }
@@ -105,9 +315,7 @@ impl AugeFilter for DotartFilter {
                     background_layer,
                 )))
             }
-            OutputKind::Json => {
-                let radius = (scale / 4) as i32;
-                let radius = radius.max(1);
+            DotOutput::Json => {
                 let mut dots = Vec::new();
 
                 for (x, y, threshold_pixel) in small_threshold_rgb_buffer.enumerate_pixels() {
@@ -116,28 +324,71 @@ impl AugeFilter for DotartFilter {
                         continue;
                     }
 
-                    let center_x = (x * scale + scale / 2) as u32;
-                    let center_y = (y * scale + scale / 2) as u32;
+                    let (center_x, center_y) = center_at(x, y);
 
+                    // JSON colors are sRGB like the ones given on the command line
                     let rgb_part: Rgb<u8> = match &self.dot_color {
                         DotColorSource::Fixed(fixed_color) => *fixed_color,
-                        DotColorSource::Preserve => small_img.get_pixel(x, y).to_rgb(),
+                        DotColorSource::Preserve => {
+                            color::working_to_srgb(small_img.get_pixel(x, y).to_rgb())
+                        }
                     };
 
+                    let pos = (center_x.max(0) as u32, center_y.max(0) as u32);
                     dots.push(Dot {
-                        pos: (center_x, center_y),
-                        radius,
+                        pos,
+                        radius: radius_at(x, y),
                         color: Color(rgb_part),
+                        norm: self.normalized.then(|| {
+                            (
+                                pos.0 as f64 / width.max(1) as f64,
+                                pos.1 as f64 / height.max(1) as f64,
+                            )
+                        }),
                     });
                 }
 
+                let meta = match self.json_schema {
+                    DotJsonSchema::V1 => None,
+                    DotJsonSchema::V2 => Some(DotJsonMeta {
+                        version: 2,
+                        width,
+                        height,
+                        scale,
+                        spacing,
+                        grid: if reach > 0.0 { "jittered" } else { "square" },
+                        shape: self.shape.to_string(),
+                    }),
+                };
                 let json_data = DotFilterJson {
+                    meta,
                     bg: self.bg_color.clone(),
                     points: dots,
                 };
                 let json_string = serde_json::to_string(&json_data)?;
                 Ok(FilterResult::Text(json_string))
             }
+            DotOutput::Gcode | DotOutput::Hpgl => {
+                // Rows alternate direction so the pen does not travel back across the paper
+                let mut paths = Vec::new();
+                for y in 0..small_height {
+                    let row: Vec<u32> = if y % 2 == 0 {
+                        (0..small_width).collect()
+                    } else {
+                        (0..small_width).rev().collect()
+                    };
+                    for x in row {
+                        if small_threshold_rgb_buffer.get_pixel(x, y)[0] == 0 {
+                            continue;
+                        }
+                        paths.push(dot_path(self.shape, center_at(x, y), radius_at(x, y)));
+                    }
+                }
+                Ok(FilterResult::Text(match self.output {
+                    DotOutput::Gcode => self.plotter.gcode(&paths, width, height)?,
+                    _ => self.plotter.hpgl(&paths, width, height)?,
+                }))
+            }
         }
     }
 }
\ No newline at end of file
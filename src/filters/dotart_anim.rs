@@ -0,0 +1,186 @@
+use clap::ValueEnum;
+use image::{DynamicImage, Frame, GrayImage, imageops::FilterType};
+use rayon::prelude::*;
+
+use super::dotart::{DotOutput, DotartFilter};
+use super::dynthres::DynamicThresholdFilter;
+use super::{AugeFilter, FilterResult};
+use crate::progress::{self, Progress};
+use crate::types::{AugeError, Color, DotColorSource};
+
+/// Characters from empty to full for ASCII frames
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum AnimStyle {
+    /// Dotart on every frame, reassembled into an animation
+    Dots,
+    /// Braille characters, 2x4 dots per character
+    Braille,
+    /// ASCII characters by brightness
+    Ascii,
+}
+
+/// Stylizes every frame of an animation with thresholds shared by all frames,
+/// so the output does not flicker when single frames get brighter or darker.
+pub struct DotartAnimFilter {
+    pub frames: Vec<Frame>,
+    pub style: AnimStyle,
+    pub scale: u32,
+    pub lower_percentile: f32,
+    pub upper_percentile: f32,
+    pub dot_color: DotColorSource,
+    pub bg_color: Color,
+    /// Characters per line of text frames
+    pub columns: u32,
+}
+
+/// Luma histogram summed over images
+fn histogram<'a>(images: impl Iterator<Item = &'a GrayImage>) -> [u32; 256] {
+    let mut histogram = [0u32; 256];
+    for pixel in images.flat_map(|img| img.pixels()) {
+        histogram[pixel[0] as usize] += 1;
+    }
+    histogram
+}
+
+fn delay_ms(frame: &Frame) -> u32 {
+    let (numer, denom) = frame.delay().numer_denom_ms();
+    numer / denom.max(1)
+}
+
+impl DotartAnimFilter {
+    fn thresholds(&self, histogram: &[u32; 256]) -> (u8, u8) {
+        DynamicThresholdFilter {
+            lower_percentile: self.lower_percentile,
+            upper_percentile: self.upper_percentile,
+            ..Default::default()
+        }
+        .histogram_thresholds(histogram)
+    }
+
+    fn dots(&self) -> Result<FilterResult, AugeError> {
+        let mut dotart = DotartFilter {
+            scale: self.scale,
+            output: DotOutput::Raster,
+            lower_percentile: self.lower_percentile,
+            upper_percentile: self.upper_percentile,
+            dot_color: self.dot_color.clone(),
+            bg_color: self.bg_color.clone(),
+            thresholds: None,
+            ..Default::default()
+        };
+        let images: Vec<DynamicImage> = self
+            .frames
+            .iter()
+            .map(|frame| DynamicImage::ImageRgba8(frame.buffer().clone()))
+            .collect();
+        let small: Vec<GrayImage> = images
+            .iter()
+            .map(|img| dotart.small(img).to_luma8())
+            .collect();
+        dotart.thresholds = Some(self.thresholds(&histogram(small.iter())));
+
+        let bar = progress::bar("frames", images.len() as u64);
+        let frames = images
+            .into_par_iter()
+            .zip(&self.frames)
+            .map(|(img, frame)| {
+                let FilterResult::Image(dots) = dotart.apply(img)? else {
+                    unreachable!()
+                };
+                bar.advance(1);
+                Ok(Frame::from_parts(dots.to_rgba8(), 0, 0, frame.delay()))
+            })
+            .collect::<Result<Vec<_>, AugeError>>()?;
+        Ok(FilterResult::Frames(frames))
+    }
+
+    /// Every frame at `cell` pixels per character, `aspect` is the height of a pixel
+    /// relative to its width once printed
+    fn grids(&self, cell: (u32, u32), aspect: f32) -> Vec<GrayImage> {
+        let columns = self.columns.max(1);
+        self.frames
+            .iter()
+            .map(|frame| {
+                let (width, height) = frame.buffer().dimensions();
+                let dots_wide = columns * cell.0;
+                let dots_high =
+                    (height as f32 * dots_wide as f32 / width as f32 / aspect).round() as u32;
+                let rows = dots_high.div_ceil(cell.1).max(1);
+                let img = DynamicImage::ImageRgba8(frame.buffer().clone());
+                img.resize_exact(dots_wide, rows * cell.1, FilterType::Triangle)
+                    .to_luma8()
+            })
+            .collect()
+    }
+
+    fn text(&self) -> Result<FilterResult, AugeError> {
+        let (cell, aspect) = match self.style {
+            // Braille dots sit on a roughly square grid, character cells are twice as high as wide
+            AnimStyle::Braille => ((2, 4), 1.0),
+            _ => ((1, 1), 2.0),
+        };
+        let grids = self.grids(cell, aspect);
+        let (t_black, t_white) = self.thresholds(&histogram(grids.iter()));
+
+        let mut script = String::from("#!/bin/sh\n");
+        script += &format!(
+            "# {} frames from auge dotart-anim, play with `sh FILE`, Ctrl-C stops\n",
+            grids.len()
+        );
+        script += "trap 'printf \"\\033[?25h\"; exit' INT TERM\n";
+        script += "printf '\\033[2J\\033[?25l'\nwhile :; do\n";
+        for (grid, frame) in grids.iter().zip(&self.frames) {
+            script += "printf '\\033[H'\ncat <<'FRAME'\n";
+            for row in 0..grid.height() / cell.1 {
+                let line: String = (0..grid.width() / cell.0)
+                    .map(|column| match self.style {
+                        AnimStyle::Braille => braille(grid, column * 2, row * 4, t_black),
+                        _ => ascii(grid.get_pixel(column, row)[0], t_black, t_white),
+                    })
+                    .collect();
+                script += line.trim_end();
+                script += "\n";
+            }
+            script += &format!("FRAME\nsleep {:.3}\n", delay_ms(frame) as f32 / 1000.0);
+        }
+        script += "done";
+        Ok(FilterResult::Text(script))
+    }
+}
+
+/// Braille character for the 2x4 dots at `(x, y)`, dots are raised above `t_black`
+fn braille(grid: &GrayImage, x: u32, y: u32, t_black: u8) -> char {
+    // Bit of every dot, column by column, from the Unicode braille pattern layout
+    const BITS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+    let mut pattern = 0;
+    for (dx, column) in BITS.iter().enumerate() {
+        for (dy, bit) in column.iter().enumerate() {
+            if grid.get_pixel(x + dx as u32, y + dy as u32)[0] > t_black {
+                pattern |= bit;
+            }
+        }
+    }
+    char::from_u32(0x2800 + pattern).unwrap_or(' ')
+}
+
+fn ascii(luma: u8, t_black: u8, t_white: u8) -> char {
+    let span = (t_white as f32 - t_black as f32).max(1.0);
+    let t = ((luma as f32 - t_black as f32) / span).clamp(0.0, 1.0);
+    ASCII_RAMP[(t * (ASCII_RAMP.len() - 1) as f32).round() as usize] as char
+}
+
+impl AugeFilter for DotartAnimFilter {
+    fn apply(&self, _img: DynamicImage) -> Result<FilterResult, AugeError> {
+        if self.frames.is_empty() {
+            return Err(AugeError::InvalidArgument(
+                "no frames to animate".to_string(),
+            ));
+        }
+        match self.style {
+            AnimStyle::Dots => self.dots(),
+            AnimStyle::Braille | AnimStyle::Ascii => self.text(),
+        }
+    }
+}
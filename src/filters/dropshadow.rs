@@ -0,0 +1,50 @@
+use image::{DynamicImage, GrayImage, Luma, Rgb, Rgba, RgbaImage, imageops};
+use imageproc::filter::gaussian_blur_f32;
+
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Point};
+
+/// Blurred, offset copy of the alpha mask painted beneath the subject.
+/// The canvas grows so that neither the subject nor the shadow gets clipped.
+pub struct DropShadowFilter {
+    /// Shadow displacement in pixels, positive values go right and down
+    pub offset: Point,
+    pub sigma: f32,
+    /// Shadow opacity in `0..=1`
+    pub opacity: f32,
+    pub color: Rgb<u8>,
+}
+
+impl AugeFilter for DropShadowFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let sigma = self.sigma.max(0.0);
+        let (dx, dy) = (self.offset.0.round() as i64, self.offset.1.round() as i64);
+
+        // Three sigma covers practically all of the blurred mask
+        let spread = (3.0 * sigma).ceil() as i64;
+        let left = (spread - dx).max(0);
+        let right = (spread + dx).max(0);
+        let top = (spread - dy).max(0);
+        let bottom = (spread + dy).max(0);
+        let canvas_width = (width as i64 + left + right) as u32;
+        let canvas_height = (height as i64 + top + bottom) as u32;
+
+        let mut mask = GrayImage::new(canvas_width, canvas_height);
+        let alpha = GrayImage::from_fn(width, height, |x, y| Luma([src.get_pixel(x, y)[3]]));
+        imageops::replace(&mut mask, &alpha, left + dx, top + dy);
+        if sigma > 0.0 {
+            mask = gaussian_blur_f32(&mask, sigma);
+        }
+
+        let opacity = self.opacity.clamp(0.0, 1.0);
+        let [r, g, b] = self.color.0;
+        let mut out = RgbaImage::from_fn(canvas_width, canvas_height, |x, y| {
+            Rgba([r, g, b, (mask.get_pixel(x, y)[0] as f32 * opacity).round() as u8])
+        });
+        imageops::overlay(&mut out, &src, left, top);
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
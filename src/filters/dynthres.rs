@@ -1,15 +1,48 @@
-use image::{DynamicImage, ImageBuffer, Rgb};
+use clap::ValueEnum;
+use image::{DynamicImage, GrayImage, ImageBuffer, Pixel, Primitive, Rgb};
+use tracing::info;
 
-use super::{AugeFilter, FilterResult};
-use crate::types::AugeError;
+use super::{AugeFilter, FilterResult, par_rows};
+use crate::simd;
+use crate::types::{AugeError, SampleDepth};
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum AutoThreshold {
+    /// Two thresholds maximizing between-class variance (three-class Otsu)
+    Otsu,
+    /// Single threshold for skewed histograms with one dominant peak, no midtones
+    Triangle,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum ThresholdOutput {
+    /// The bands in their colors
+    #[default]
+    Image,
+    /// Single channel, white where the pixel falls in the masked bands and black elsewhere
+    Mask,
+    /// The input with everything outside the masked bands made transparent
+    Alpha,
+}
 
 pub struct DynamicThresholdFilter {
     pub lower_percentile: f32,
     pub upper_percentile: f32,
-    
+    /// Derive thresholds from the histogram shape instead of the percentiles
+    pub auto: Option<AutoThreshold>,
+    /// Fixed `(t_black, t_white)` used as is, e.g. to keep animation frames from flickering
+    pub thresholds: Option<(u8, u8)>,
+
     pub color_black: Rgb<u8>,
     pub color_mid: Rgb<u8>,
-    pub color_white: Rgb<u8>
+    pub color_white: Rgb<u8>,
+    /// Colors of evenly populated levels, darkest first, in place of the three bands
+    pub levels: Vec<Rgb<u8>>,
+    /// Threshold R, G and B on their own instead of luma
+    pub per_channel: bool,
+    pub output: ThresholdOutput,
+    /// First band counted into masks, 0 being the darkest
+    pub mask_from: u8,
 }
 
 impl Default for DynamicThresholdFilter {
@@ -17,30 +50,21 @@ impl Default for DynamicThresholdFilter {
         Self {
             lower_percentile: 0.2,
             upper_percentile: 0.2,
+            auto: None,
+            thresholds: None,
             color_black: Rgb::from([0u8; 3]),
             color_white: Rgb::from([255u8; 3]),
-            color_mid: Rgb::from([127u8; 3])
+            color_mid: Rgb::from([127u8; 3]),
+            levels: Vec::new(),
+            per_channel: false,
+            output: ThresholdOutput::Image,
+            mask_from: 1,
         }
     }
 }
 
-impl AugeFilter for DynamicThresholdFilter {
-    fn apply(&self, img: image::DynamicImage) -> Result<FilterResult, AugeError> {
-        let luma_img = img.to_luma8();
-        let (width, height) = luma_img.dimensions();
-        let total_pixels = width as usize * height as usize; // Используем умножение
-
-        if total_pixels == 0 {
-             return Ok(FilterResult::Image(DynamicImage::ImageRgb8(
-                 ImageBuffer::new(0, 0),
-             )));
-        }
-
-        let mut histogram = [0u32; 256];
-        for pixel in luma_img.pixels() {
-            histogram[pixel[0] as usize] += 1;
-        }
-
+impl DynamicThresholdFilter {
+    fn percentile_thresholds(&self, histogram: &[u32; 256], total_pixels: usize) -> (u8, u8) {
         let lower_cutoff_count = (total_pixels as f32 * self.lower_percentile).round() as u32;
         let lower_cutoff_count = lower_cutoff_count.min(total_pixels as u32);
 
@@ -55,8 +79,7 @@ impl AugeFilter for DynamicThresholdFilter {
         let mut t_black_found = false;
         let mut t_white_found = false;
 
-        for level in 0..=255 {
-            let count_at_level = histogram[level];
+        for (level, &count_at_level) in histogram.iter().enumerate() {
 
             if !t_black_found && cumulative_count + count_at_level >= lower_cutoff_count {
                 t_black = level as u8;
@@ -77,25 +100,320 @@ impl AugeFilter for DynamicThresholdFilter {
         if t_white <= t_black {
             if t_black > 0 {
                 t_black -= 1;
-            } else if t_white < 255 {
-                 t_white += 1;
+            } else {
+                 t_white = t_white.saturating_add(1);
             }
         }
 
-        let mut output_img = ImageBuffer::<Rgb<u8>, Vec<u8>>::new(width, height);
-        for (x, y, luma_pixel) in luma_img.enumerate_pixels() {
-            let luma_value = luma_pixel[0];
-            let output_pixel = output_img.get_pixel_mut(x, y);
+        (t_black, t_white)
+    }
+}
+
+impl DynamicThresholdFilter {
+    /// `(t_black, t_white)` for a luma histogram, by percentiles or the `auto` method
+    pub fn histogram_thresholds(&self, histogram: &[u32; 256]) -> (u8, u8) {
+        match self.auto {
+            Some(AutoThreshold::Otsu) => otsu_thresholds(histogram),
+            Some(AutoThreshold::Triangle) => {
+                let threshold = triangle_threshold(histogram);
+                (threshold, threshold.saturating_add(1))
+            }
+            None => {
+                let total_pixels = histogram.iter().map(|&count| count as usize).sum();
+                self.percentile_thresholds(histogram, total_pixels)
+            }
+        }
+    }
+}
+
+/// `n` colors evenly spaced from `dark` to `bright`
+pub fn ramp(dark: Rgb<u8>, bright: Rgb<u8>, n: u32) -> Vec<Rgb<u8>> {
+    (0..n)
+        .map(|i| {
+            let t = i as f32 / (n - 1).max(1) as f32;
+            Rgb(std::array::from_fn(|c| {
+                (dark[c] as f32 + (bright[c] as f32 - dark[c] as f32) * t).round() as u8
+            }))
+        })
+        .collect()
+}
+
+/// The first luma of each of `n` classes but the darkest, at the level boundary closest
+/// to the next `1/n` of the pixels. Every class keeps at least one luma value, so images
+/// with large flat areas still show all colors.
+fn quantile_starts(histogram: &[u32; 256], n: usize) -> Vec<u8> {
+    let (counts, _) = cumulative_moments(histogram);
+    let total = counts[256];
+    let mut previous = 0;
+    (1..n)
+        .map(|k| {
+            let target = total * k as f64 / n as f64;
+            let nearest = (1..256)
+                .min_by(|&a, &b| (counts[a] - target).abs().total_cmp(&(counts[b] - target).abs()))
+                .unwrap_or(1);
+            previous = nearest.clamp(previous + 1, 256 - (n - k));
+            previous as u8
+        })
+        .collect()
+}
+
+/// Cumulative pixel count and luma sum up to and including every level
+fn cumulative_moments(histogram: &[u32; 256]) -> ([f64; 257], [f64; 257]) {
+    let mut counts = [0.0f64; 257];
+    let mut sums = [0.0f64; 257];
+    for (level, &count) in histogram.iter().enumerate() {
+        counts[level + 1] = counts[level] + count as f64;
+        sums[level + 1] = sums[level] + count as f64 * level as f64;
+    }
+    (counts, sums)
+}
+
+/// Three-class Otsu: returns `(t_black, t_white)` so that the dark class ends at `t_black`
+/// and the bright class starts at `t_white`.
+fn otsu_thresholds(histogram: &[u32; 256]) -> (u8, u8) {
+    let (counts, sums) = cumulative_moments(histogram);
+    let total = counts[256];
+    let mean = sums[256] / total;
+
+    // Between-class variance contribution of levels `from..to`
+    let class_score = |from: usize, to: usize| {
+        let weight = counts[to] - counts[from];
+        if weight == 0.0 {
+            return 0.0;
+        }
+        let class_mean = (sums[to] - sums[from]) / weight;
+        weight * (class_mean - mean) * (class_mean - mean)
+    };
+
+    let mut best = (0usize, 1usize);
+    let mut best_score = -1.0f64;
+    for t1 in 0..255 {
+        for t2 in t1 + 1..256 {
+            let score = class_score(0, t1 + 1) + class_score(t1 + 1, t2 + 1) + class_score(t2 + 1, 256);
+            if score > best_score {
+                best_score = score;
+                best = (t1, t2);
+            }
+        }
+    }
+
+    (best.0 as u8, (best.1 + 1).min(255) as u8)
+}
+
+/// Triangle method: the level farthest from the line between the histogram peak
+/// and the end of its longer tail.
+fn triangle_threshold(histogram: &[u32; 256]) -> u8 {
+    let (peak, &peak_count) = histogram
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, count)| **count)
+        .unwrap_or((0, &0));
+    let first = histogram.iter().position(|&c| c > 0).unwrap_or(0);
+    let last = histogram.iter().rposition(|&c| c > 0).unwrap_or(255);
 
-            if luma_value <= t_black {
-                *output_pixel = self.color_black;
-            } else if luma_value >= t_white {
-                *output_pixel = self.color_white;
+    let tail_end = if peak - first > last - peak { first } else { last };
+    if tail_end == peak {
+        return peak as u8;
+    }
+
+    // Distance to the line, up to a constant factor
+    let (x1, y1) = (peak as f64, peak_count as f64);
+    let (x2, y2) = (tail_end as f64, histogram[tail_end] as f64);
+    let (lo, hi) = (peak.min(tail_end), peak.max(tail_end));
+    (lo..=hi)
+        .max_by(|&a, &b| {
+            let distance = |level: usize| {
+                let (x, y) = (level as f64, histogram[level] as f64);
+                ((y2 - y1) * x - (x2 - x1) * y + x2 * y1 - y2 * x1).abs()
+            };
+            distance(a).total_cmp(&distance(b))
+        })
+        .unwrap_or(peak) as u8
+}
+
+impl DynamicThresholdFilter {
+    /// Class of every value of a luma or channel histogram, and the colors of the classes
+    /// darkest first: the evenly populated `levels`, or the three bands
+    fn classes(&self, histogram: &[u32; 256], name: &str) -> ([u8; 256], Vec<Rgb<u8>>) {
+        if !self.levels.is_empty() {
+            let starts = quantile_starts(histogram, self.levels.len());
+            info!("{} levels start at: {:?}", name, starts);
+            let classes = std::array::from_fn(|value| {
+                starts.iter().filter(|&&start| value >= start as usize).count() as u8
+            });
+            return (classes, self.levels.clone());
+        }
+        let (t_black, t_white) = match self.thresholds {
+            Some(thresholds) => thresholds,
+            None => self.histogram_thresholds(histogram),
+        };
+        info!("{} thresholds: dark up to {}, bright from {}", name, t_black, t_white);
+        let classes = std::array::from_fn(|value| {
+            if value <= t_black as usize {
+                0
+            } else if value >= t_white as usize {
+                2
             } else {
-                *output_pixel = self.color_mid;
+                1
+            }
+        });
+        (classes, vec![self.color_black, self.color_mid, self.color_white])
+    }
+
+    /// Thresholds every channel on its own histogram, each taking its value from the same
+    /// channel of the class color
+    fn per_channel(&self, img: &DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut rgb = img.to_rgb8();
+        let mut histograms = [[0u32; 256]; 3];
+        for pixel in rgb.pixels() {
+            for (histogram, &value) in histograms.iter_mut().zip(&pixel.0) {
+                histogram[value as usize] += 1;
+            }
+        }
+        let luts: [[u8; 256]; 3] = std::array::from_fn(|c| {
+            let (classes, colors) = self.classes(&histograms[c], ["red", "green", "blue"][c]);
+            std::array::from_fn(|value| colors[classes[value] as usize][c])
+        });
+        let width = rgb.width() as usize;
+        par_rows(&mut rgb, width * 3, |_, row| {
+            for pixel in row.chunks_exact_mut(3) {
+                for (c, value) in pixel.iter_mut().enumerate() {
+                    *value = luts[c][*value as usize];
+                }
+            }
+        });
+        Ok(FilterResult::Image(
+            SampleDepth::of(img).convert(&DynamicImage::ImageRgb8(rgb)),
+        ))
+    }
+}
+
+/// Makes every pixel outside `mask` fully transparent
+fn cut_out<P: Pixel>(img: &mut ImageBuffer<P, Vec<P::Subpixel>>, mask: &GrayImage) {
+    for (pixel, keep) in img.pixels_mut().zip(mask.pixels()) {
+        if keep[0] == 0 {
+            let alpha = P::CHANNEL_COUNT as usize - 1;
+            pixel.channels_mut()[alpha] = <P::Subpixel as Primitive>::DEFAULT_MIN_VALUE;
+        }
+    }
+}
+
+impl DynamicThresholdFilter {
+    /// White where the luma falls in band `mask_from` or a brighter one, as a mask or as
+    /// the alpha of the input
+    fn mask(
+        &self,
+        img: &DynamicImage,
+        luma_img: &GrayImage,
+        histogram: &[u32; 256],
+    ) -> Result<FilterResult, AugeError> {
+        let (classes, colors) = self.classes(histogram, "luma");
+        if self.mask_from as usize >= colors.len() {
+            return Err(AugeError::InvalidArgument(format!(
+                "--mask-from {} is past the brightest band, {}",
+                self.mask_from,
+                colors.len() - 1
+            )));
+        }
+        let mut mask = luma_img.clone();
+        for pixel in mask.pixels_mut() {
+            pixel[0] = if classes[pixel[0] as usize] >= self.mask_from { 255 } else { 0 };
+        }
+        Ok(FilterResult::Image(match self.output {
+            ThresholdOutput::Alpha => match SampleDepth::of(img) {
+                SampleDepth::Eight => {
+                    let mut rgba = img.to_rgba8();
+                    cut_out(&mut rgba, &mask);
+                    DynamicImage::ImageRgba8(rgba)
+                }
+                SampleDepth::Sixteen => {
+                    let mut rgba = img.to_rgba16();
+                    cut_out(&mut rgba, &mask);
+                    DynamicImage::ImageRgba16(rgba)
+                }
+                SampleDepth::F32 => {
+                    let mut rgba = img.to_rgba32f();
+                    cut_out(&mut rgba, &mask);
+                    DynamicImage::ImageRgba32F(rgba)
+                }
+            },
+            _ => DynamicImage::ImageLuma8(mask),
+        }))
+    }
+}
+
+impl AugeFilter for DynamicThresholdFilter {
+    fn apply(&self, img: image::DynamicImage) -> Result<FilterResult, AugeError> {
+        if self.levels.len() == 1 || self.levels.len() > 256 {
+            return Err(AugeError::InvalidArgument(format!(
+                "posterizing needs 2 to 256 colors, one per luma level at most, got {}",
+                self.levels.len()
+            )));
+        }
+        if self.per_channel {
+            if let ThresholdOutput::Mask | ThresholdOutput::Alpha = self.output {
+                return Err(AugeError::InvalidArgument(
+                    "masks are cut on luma, they cannot be --per-channel".to_string(),
+                ));
             }
+            return self.per_channel(&img);
+        }
+
+        let luma_img = simd::to_luma8(&img);
+        let (width, height) = luma_img.dimensions();
+        let total_pixels = width as usize * height as usize; // Используем умножение
+
+        if total_pixels == 0 {
+             return Ok(FilterResult::Image(DynamicImage::ImageRgb8(
+                 ImageBuffer::new(0, 0),
+             )));
         }
 
-        Ok(FilterResult::Image(DynamicImage::ImageRgb8(output_img)))
+        let mut histogram = [0u32; 256];
+        for pixel in luma_img.pixels() {
+            histogram[pixel[0] as usize] += 1;
+        }
+
+        if let ThresholdOutput::Mask | ThresholdOutput::Alpha = self.output {
+            return self.mask(&img, &luma_img, &histogram);
+        }
+        if !self.levels.is_empty() {
+            let (classes, colors) = self.classes(&histogram, "luma");
+            let lut: [Rgb<u8>; 256] = std::array::from_fn(|luma| colors[classes[luma] as usize]);
+            let mut samples = vec![0u8; total_pixels * 3];
+            par_rows(&mut samples, width as usize * 3, |y, row| {
+                let luma_row = &luma_img.as_raw()[y * width as usize..(y + 1) * width as usize];
+                for (pixel, &luma) in row.chunks_exact_mut(3).zip(luma_row) {
+                    pixel.copy_from_slice(&lut[luma as usize].0);
+                }
+            });
+            let output_img = ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(width, height, samples)
+                .expect("three samples per pixel");
+            return Ok(FilterResult::Image(
+                SampleDepth::of(&img).convert(&DynamicImage::ImageRgb8(output_img)),
+            ));
+        }
+
+        let (t_black, t_white) = match self.thresholds {
+            Some(thresholds) => thresholds,
+            None => self.histogram_thresholds(&histogram),
+        };
+        info!("thresholds: dark up to {}, bright from {}", t_black, t_white);
+
+        let colors = [self.color_black, self.color_mid, self.color_white];
+        let mut samples = vec![0u8; total_pixels * 3];
+        par_rows(&mut samples, width as usize * 3, |y, row| {
+            let luma_row = &luma_img.as_raw()[y * width as usize..(y + 1) * width as usize];
+            let classes = simd::classify(luma_row, t_black, t_white);
+            for (pixel, class) in row.chunks_exact_mut(3).zip(classes) {
+                pixel.copy_from_slice(&colors[class as usize].0);
+            }
+        });
+        let output_img = ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(width, height, samples)
+            .expect("three samples per pixel");
+
+        // Three flat colors need no precision, but the pipeline keeps the input depth
+        let output_img = DynamicImage::ImageRgb8(output_img);
+        Ok(FilterResult::Image(SampleDepth::of(&img).convert(&output_img)))
     }
 }
\ No newline at end of file
@@ -1,38 +1,304 @@
-use image::{DynamicImage, GrayImage, Luma};
+use std::collections::VecDeque;
+
+use clap::ValueEnum;
+use image::{DynamicImage, GrayImage, Rgb, RgbImage};
+use imageproc::filter::gaussian_blur_f32;
+use rayon::prelude::*;
 use crate::types::AugeError;
-use super::{FilterResult, AugeFilter};
+use super::{FilterResult, AugeFilter, par_rows, param_map::ParamMap};
 
-pub struct EdgeFilter;
+#[derive(Debug, Clone, ValueEnum)]
+pub enum EdgeAlgorithm {
+    /// Raw gradient magnitude of the chosen operator
+    #[value(alias = "sobel")]
+    Gradient,
+    /// Thin, connected edges with smoothing, non-maximum suppression and hysteresis
+    Canny,
+}
 
-impl AugeFilter for EdgeFilter {
-    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
-        let gray_img = img.to_luma8();
+#[derive(Debug, Clone, ValueEnum)]
+pub enum EdgeOperator {
+    /// Classic 3x3 gradient, good all-rounder
+    Sobel,
+    /// More rotationally accurate than Sobel
+    Scharr,
+    /// Unweighted gradient, slightly noisier
+    Prewitt,
+    /// Second derivative, direction-less (not usable with canny)
+    Laplacian,
+}
+
+impl EdgeOperator {
+    /// Horizontal and vertical 3x3 kernels. Laplacian only has the first one.
+    fn kernels(&self) -> ([i32; 9], [i32; 9]) {
+        match self {
+            Self::Sobel => (
+                [-1, 0, 1, -2, 0, 2, -1, 0, 1],
+                [-1, -2, -1, 0, 0, 0, 1, 2, 1],
+            ),
+            Self::Scharr => (
+                [-3, 0, 3, -10, 0, 10, -3, 0, 3],
+                [-3, -10, -3, 0, 0, 0, 3, 10, 3],
+            ),
+            Self::Prewitt => (
+                [-1, 0, 1, -1, 0, 1, -1, 0, 1],
+                [-1, -1, -1, 0, 0, 0, 1, 1, 1],
+            ),
+            Self::Laplacian => ([0, 1, 0, 1, -4, 1, 0, 1, 0], [0; 9]),
+        }
+    }
+}
+
+pub struct EdgeFilter {
+    pub algorithm: EdgeAlgorithm,
+    pub operator: EdgeOperator,
+    pub sigma: f32,
+    pub low: f32,
+    pub high: f32,
+    /// Stretch gradient magnitudes so the strongest edge becomes full white
+    pub normalize: bool,
+    /// Turn the gradient map into a binary edge mask
+    pub threshold: Option<f32>,
+    /// Scales the threshold per pixel, black marks everything as an edge
+    pub param_map: Option<ParamMap>,
+    /// Paint edges in this color instead of producing a grayscale map
+    pub color: Option<Rgb<u8>>,
+    /// Draw edges on top of the original image
+    pub overlay: bool,
+}
+
+impl Default for EdgeFilter {
+    fn default() -> Self {
+        Self {
+            algorithm: EdgeAlgorithm::Gradient,
+            operator: EdgeOperator::Sobel,
+            sigma: 1.4,
+            low: 50.0,
+            high: 100.0,
+            normalize: false,
+            threshold: None,
+            param_map: None,
+            color: None,
+            overlay: false,
+        }
+    }
+}
+
+/// Per-pixel horizontal and vertical gradients. Border pixels are left at zero.
+struct Gradients {
+    width: u32,
+    height: u32,
+    gx: Vec<f32>,
+    gy: Vec<f32>,
+}
+
+/// Response to a 3x3 kernel, row by row in parallel
+fn convolve(gray_img: &GrayImage, kernel: &[i32; 9]) -> Vec<f32> {
+    let (width, height) = gray_img.dimensions();
+    let mut response = vec![0.0f32; width as usize * height as usize];
+
+    par_rows(&mut response, width as usize, |y, row| {
+        let y = y as u32;
+        if y == 0 || y + 1 >= height {
+            return;
+        }
+        for x in 1..width.saturating_sub(1) {
+            let mut sum = 0;
+            for ky in 0..3 {
+                for kx in 0..3 {
+                    let pixel = gray_img.get_pixel(x + kx - 1, y + ky - 1)[0] as i32;
+                    sum += pixel * kernel[(ky * 3 + kx) as usize];
+                }
+            }
+            row[x as usize] = sum as f32;
+        }
+    });
+
+    response
+}
+
+impl Gradients {
+    fn compute(gray_img: &GrayImage, operator: &EdgeOperator) -> Self {
         let (width, height) = gray_img.dimensions();
-        let mut edge_img = GrayImage::new(width, height);
-
-        // Sobel kernels
-        let sobel_x: [i32; 9] = [-1, 0, 1, -2, 0, 2, -1, 0, 1];
-        let sobel_y: [i32; 9] = [-1, -2, -1, 0, 0, 0, 1, 2, 1];
-
-        for y in 1..height-1 {
-            for x in 1..width-1 {
-                let mut gx = 0;
-                let mut gy = 0;
-                
-                // Apply Sobel operator
-                for ky in 0..3 {
-                    for kx in 0..3 {
-                        let pixel = gray_img.get_pixel(x + kx - 1, y + ky - 1)[0] as i32;
-                        gx += pixel * sobel_x[(ky * 3 + kx) as usize];
-                        gy += pixel * sobel_y[(ky * 3 + kx) as usize];
+        let (kernel_x, kernel_y) = operator.kernels();
+        let gx = convolve(gray_img, &kernel_x);
+        let gy = convolve(gray_img, &kernel_y);
+
+        Self { width, height, gx, gy }
+    }
+
+    fn magnitude(&self) -> Vec<f32> {
+        self.gx
+            .par_iter()
+            .zip(&self.gy)
+            .map(|(gx, gy)| (gx * gx + gy * gy).sqrt())
+            .collect()
+    }
+}
+
+impl EdgeFilter {
+    fn canny(&self, gray_img: &GrayImage) -> GrayImage {
+        let smoothed = if self.sigma > 0.0 {
+            gaussian_blur_f32(gray_img, self.sigma)
+        } else {
+            gray_img.clone()
+        };
+        let gradients = Gradients::compute(&smoothed, &self.operator);
+        let magnitude = gradients.magnitude();
+        let (width, height) = (gradients.width as usize, gradients.height as usize);
+
+        // Non-maximum suppression: keep a pixel only if it peaks across the edge
+        let mut thin = vec![0.0f32; width * height];
+        par_rows(&mut thin, width, |y, row| {
+            if y == 0 || y + 1 >= height {
+                return;
+            }
+            let inner = row.iter_mut().enumerate().take(width.saturating_sub(1)).skip(1);
+            for (x, thin) in inner {
+                let idx = y * width + x;
+                let m = magnitude[idx];
+                if m < self.low {
+                    continue;
+                }
+
+                let angle = gradients.gy[idx].atan2(gradients.gx[idx]).to_degrees();
+                let angle = if angle < 0.0 { angle + 180.0 } else { angle };
+                let (a, b) = if !(22.5..157.5).contains(&angle) {
+                    (idx - 1, idx + 1)
+                } else if angle < 67.5 {
+                    (idx - width - 1, idx + width + 1)
+                } else if angle < 112.5 {
+                    (idx - width, idx + width)
+                } else {
+                    (idx - width + 1, idx + width - 1)
+                };
+
+                if m >= magnitude[a] && m >= magnitude[b] {
+                    *thin = m;
+                }
+            }
+        });
+
+        // Hysteresis: grow strong edges through connected weak ones
+        let mut edges = GrayImage::new(gradients.width, gradients.height);
+        let mut queue: VecDeque<usize> = thin
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| **m >= self.high)
+            .map(|(idx, _)| idx)
+            .collect();
+        for &idx in &queue {
+            edges.as_mut()[idx] = 255;
+        }
+
+        while let Some(idx) = queue.pop_front() {
+            let (x, y) = ((idx % width) as i64, (idx / width) as i64);
+            for dy in -1..=1i64 {
+                for dx in -1..=1i64 {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                        continue;
+                    }
+                    let n = ny as usize * width + nx as usize;
+                    if edges.as_raw()[n] == 0 && thin[n] >= self.low {
+                        edges.as_mut()[n] = 255;
+                        queue.push_back(n);
                     }
                 }
-                
-                let magnitude = ((gx * gx + gy * gy) as f32).sqrt() as u8;
-                edge_img.put_pixel(x, y, Luma([magnitude]));
             }
         }
 
-        Ok(DynamicImage::ImageLuma8(edge_img).into())
+        edges
+    }
+}
+
+impl EdgeFilter {
+    fn gradient(&self, gray_img: &GrayImage) -> GrayImage {
+        let gradients = Gradients::compute(gray_img, &self.operator);
+        let magnitude = gradients.magnitude();
+        let scale = if self.normalize {
+            let max = magnitude.iter().cloned().fold(0.0f32, f32::max);
+            if max > 0.0 { 255.0 / max } else { 1.0 }
+        } else {
+            1.0
+        };
+
+        let weights = self
+            .param_map
+            .as_ref()
+            .map(|map| map.weights(gradients.width, gradients.height));
+
+        let mut edges = GrayImage::new(gradients.width, gradients.height);
+        let width = gradients.width as usize;
+        par_rows(&mut edges, width, |y, row| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let index = y * width + x;
+                let value = magnitude[index] * scale;
+                let weight = weights.as_ref().map_or(1.0, |weights| weights[index]);
+                *pixel = match self.threshold {
+                    Some(threshold) if value >= threshold * weight => 255,
+                    Some(_) => 0,
+                    None => value as u8,
+                };
+            }
+        });
+        edges
+    }
+}
+
+impl EdgeFilter {
+    /// Grayscale edge strength map, before any coloring or overlay
+    pub fn edge_map(&self, img: &DynamicImage) -> Result<GrayImage, AugeError> {
+        let gray_img = img.to_luma8();
+
+        match self.algorithm {
+            EdgeAlgorithm::Gradient => Ok(self.gradient(&gray_img)),
+            EdgeAlgorithm::Canny => {
+                if let EdgeOperator::Laplacian = self.operator {
+                    return Err(AugeError::InvalidArgument(
+                        "canny needs a directional operator, laplacian has none".to_string(),
+                    ));
+                }
+                Ok(self.canny(&gray_img))
+            }
+        }
+    }
+}
+
+impl AugeFilter for EdgeFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let edge_img = self.edge_map(&img)?;
+
+        if self.overlay {
+            // Edge strength acts as the opacity of the edge color
+            let color = self.color.unwrap_or(Rgb([255, 255, 255]));
+            let mut canvas = img.to_rgba8();
+            let width = edge_img.width() as usize;
+            par_rows(&mut canvas, width * 4, |y, row| {
+                let edges = &edge_img.as_raw()[y * width..(y + 1) * width];
+                for (pixel, &edge) in row.chunks_exact_mut(4).zip(edges) {
+                    let t = edge as f32 / 255.0;
+                    let blend = |base: u8, top: u8| (base as f32 * (1.0 - t) + top as f32 * t).round() as u8;
+                    pixel.copy_from_slice(&[
+                        blend(pixel[0], color[0]),
+                        blend(pixel[1], color[1]),
+                        blend(pixel[2], color[2]),
+                        pixel[3].max(edge),
+                    ]);
+                }
+            });
+            return Ok(DynamicImage::ImageRgba8(canvas).into());
+        }
+
+        match self.color {
+            Some(color) => {
+                let colored = RgbImage::from_fn(edge_img.width(), edge_img.height(), |x, y| {
+                    let t = edge_img.get_pixel(x, y)[0] as f32 / 255.0;
+                    Rgb(color.0.map(|c| (c as f32 * t).round() as u8))
+                });
+                Ok(DynamicImage::ImageRgb8(colored).into())
+            }
+            None => Ok(DynamicImage::ImageLuma8(edge_img).into()),
+        }
     }
 }
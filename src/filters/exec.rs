@@ -0,0 +1,92 @@
+use std::io::{Read, Write};
+use std::process::{Command as Process, Stdio};
+
+use image::{DynamicImage, RgbaImage};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Pipes the RGBA pixels through a shell command. Both ways the pixels follow an 8-byte
+/// header of width and height as big-endian `u32`s, so the command may change the size.
+/// Without the header the command must return pixels of the input size.
+pub struct ExecFilter {
+    pub cmd: String,
+    pub header: bool,
+}
+
+fn shell(cmd: &str) -> Process {
+    if cfg!(windows) {
+        let mut process = Process::new("cmd");
+        process.args(["/C", cmd]);
+        process
+    } else {
+        let mut process = Process::new("sh");
+        process.args(["-c", cmd]);
+        process
+    }
+}
+
+impl AugeFilter for ExecFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let (width, height) = (img.width(), img.height());
+        let mut input = Vec::with_capacity(8 + width as usize * height as usize * 4);
+        if self.header {
+            input.extend(width.to_be_bytes());
+            input.extend(height.to_be_bytes());
+        }
+        input.extend(img.to_rgba8().into_raw());
+
+        let mut child = shell(&self.cmd)
+            .env("AUGE_WIDTH", width.to_string())
+            .env("AUGE_HEIGHT", height.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        // Written from another thread, a command streaming its output would block on a full
+        // pipe otherwise. Commands that stop reading early are fine, their output counts.
+        let mut output = Vec::new();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                let _ = stdin.write_all(&input);
+            });
+            stdout.read_to_end(&mut output)
+        })?;
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(AugeError::InvalidArgument(format!(
+                "`{}` failed with {}",
+                self.cmd, status
+            )));
+        }
+
+        let (width, height, pixels) = if self.header {
+            if output.len() < 8 {
+                return Err(AugeError::InvalidArgument(format!(
+                    "`{}` returned {} bytes, too few for the 8-byte size header",
+                    self.cmd,
+                    output.len()
+                )));
+            }
+            let width = u32::from_be_bytes(output[0..4].try_into().unwrap());
+            let height = u32::from_be_bytes(output[4..8].try_into().unwrap());
+            (width, height, output.split_off(8))
+        } else {
+            (width, height, output)
+        };
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(AugeError::InvalidArgument(format!(
+                "`{}` returned {} bytes of pixels, {}x{} RGBA takes {}",
+                self.cmd,
+                pixels.len(),
+                width,
+                height,
+                expected
+            )));
+        }
+        let img = RgbaImage::from_raw(width, height, pixels).expect("length checked above");
+        Ok(FilterResult::Image(DynamicImage::ImageRgba8(img)))
+    }
+}
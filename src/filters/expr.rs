@@ -0,0 +1,429 @@
+use image::{DynamicImage, RgbaImage};
+
+use super::{AugeFilter, FilterResult, par_rows};
+use crate::color::luma;
+use crate::types::AugeError;
+
+/// Variables every program starts with, in register order. Channels are `0..=255`,
+/// `lum` is the relative luminance of the input pixel in `0..=1`.
+const BUILTINS: [&str; 9] = ["r", "g", "b", "a", "lum", "x", "y", "w", "h"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Ident(String),
+    Op(&'static str),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, AugeError> {
+    // Longest operators first so `<=` is not split into `<` and `=`
+    const OPERATORS: [&str; 20] = [
+        "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "(", ")", ",", "?", ":",
+        ";", "=", "<", ">",
+    ];
+
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_digit() || c == '.' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let number = rest[..end].parse().map_err(|_| {
+                AugeError::InvalidArgument(format!("invalid number '{}'", &rest[..end]))
+            })?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if c == '!' && !rest.starts_with("!=") {
+            tokens.push(Token::Op("!"));
+            rest = &rest[1..];
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(*op))
+                .ok_or_else(|| AugeError::InvalidArgument(format!("unexpected character '{}'", c)))?;
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Abs,
+    Sqrt,
+    Pow,
+    Sin,
+    Cos,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+    /// One argument clamps to the channel range, three clamp to `lo..=hi`
+    Clamp,
+    /// Linear interpolation `mix(a, b, t)`
+    Mix,
+}
+
+impl Function {
+    fn lookup(name: &str) -> Option<(Self, &'static [usize])> {
+        match name {
+            "abs" => Some((Self::Abs, &[1])),
+            "sqrt" => Some((Self::Sqrt, &[1])),
+            "pow" => Some((Self::Pow, &[2])),
+            "sin" => Some((Self::Sin, &[1])),
+            "cos" => Some((Self::Cos, &[1])),
+            "floor" => Some((Self::Floor, &[1])),
+            "ceil" => Some((Self::Ceil, &[1])),
+            "round" => Some((Self::Round, &[1])),
+            "min" => Some((Self::Min, &[2])),
+            "max" => Some((Self::Max, &[2])),
+            "clamp" => Some((Self::Clamp, &[1, 3])),
+            "mix" => Some((Self::Mix, &[3])),
+            _ => None,
+        }
+    }
+
+    fn call(self, args: &[f32]) -> f32 {
+        match self {
+            Self::Abs => args[0].abs(),
+            Self::Sqrt => args[0].max(0.0).sqrt(),
+            Self::Pow => args[0].powf(args[1]),
+            Self::Sin => args[0].sin(),
+            Self::Cos => args[0].cos(),
+            Self::Floor => args[0].floor(),
+            Self::Ceil => args[0].ceil(),
+            Self::Round => args[0].round(),
+            Self::Min => args[0].min(args[1]),
+            Self::Max => args[0].max(args[1]),
+            Self::Clamp if args.len() == 1 => args[0].clamp(0.0, 255.0),
+            Self::Clamp => args[0].max(args[1]).min(args[2]),
+            Self::Mix => args[0] + (args[1] - args[0]) * args[2],
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Const(f32),
+    Var(usize),
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    Binary(&'static str, Box<Node>, Box<Node>),
+    Ternary(Box<Node>, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+fn truth(value: bool) -> f32 {
+    if value { 1.0 } else { 0.0 }
+}
+
+impl Node {
+    fn eval(&self, registers: &[f32]) -> f32 {
+        match self {
+            Node::Const(value) => *value,
+            Node::Var(slot) => registers[*slot],
+            Node::Neg(node) => -node.eval(registers),
+            Node::Not(node) => truth(node.eval(registers) == 0.0),
+            Node::Ternary(condition, then, otherwise) => {
+                if condition.eval(registers) != 0.0 {
+                    then.eval(registers)
+                } else {
+                    otherwise.eval(registers)
+                }
+            }
+            Node::Binary("&&", lhs, rhs) => {
+                truth(lhs.eval(registers) != 0.0 && rhs.eval(registers) != 0.0)
+            }
+            Node::Binary("||", lhs, rhs) => {
+                truth(lhs.eval(registers) != 0.0 || rhs.eval(registers) != 0.0)
+            }
+            Node::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(registers), rhs.eval(registers));
+                match *op {
+                    "+" => lhs + rhs,
+                    "-" => lhs - rhs,
+                    "*" => lhs * rhs,
+                    "/" => lhs / rhs,
+                    "%" => lhs.rem_euclid(rhs),
+                    "==" => truth(lhs == rhs),
+                    "!=" => truth(lhs != rhs),
+                    "<" => truth(lhs < rhs),
+                    "<=" => truth(lhs <= rhs),
+                    ">" => truth(lhs > rhs),
+                    ">=" => truth(lhs >= rhs),
+                    _ => unreachable!("parser only emits known operators"),
+                }
+            }
+            Node::Call(function, args) => {
+                let values: Vec<f32> = args.iter().map(|arg| arg.eval(registers)).collect();
+                function.call(&values)
+            }
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    variables: Vec<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), AugeError> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(AugeError::InvalidArgument(format!(
+                "expected '{}' but found {}",
+                op,
+                self.describe_next()
+            )))
+        }
+    }
+
+    fn describe_next(&self) -> String {
+        match self.peek() {
+            Some(Token::Number(n)) => format!("'{}'", n),
+            Some(Token::Ident(name)) => format!("'{}'", name),
+            Some(Token::Op(op)) => format!("'{}'", op),
+            None => "end of expression".to_string(),
+        }
+    }
+
+    fn slot(&mut self, name: &str) -> usize {
+        match self.variables.iter().position(|v| v == name) {
+            Some(slot) => slot,
+            None => {
+                self.variables.push(name.to_string());
+                self.variables.len() - 1
+            }
+        }
+    }
+
+    fn statement(&mut self) -> Result<(usize, Node), AugeError> {
+        let Some(Token::Ident(name)) = self.peek().cloned() else {
+            return Err(AugeError::InvalidArgument(format!(
+                "expected 'name = expression' but found {}",
+                self.describe_next()
+            )));
+        };
+        self.position += 1;
+        self.expect("=")?;
+        let value = self.ternary()?;
+        // Declared after the value so `t = t + 1` on a fresh name is caught as unknown
+        Ok((self.slot(&name), value))
+    }
+
+    fn ternary(&mut self) -> Result<Node, AugeError> {
+        let condition = self.binary(0)?;
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+        let then = self.ternary()?;
+        self.expect(":")?;
+        let otherwise = self.ternary()?;
+        Ok(Node::Ternary(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    /// Precedence climbing over the binary operators, loosest binding first
+    fn binary(&mut self, level: usize) -> Result<Node, AugeError> {
+        const LEVELS: [&[&str]; 6] = [
+            &["||"],
+            &["&&"],
+            &["==", "!="],
+            &["<", "<=", ">", ">="],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let Some(op) = LEVELS[level].iter().find(|candidate| *candidate == op) else {
+                break;
+            };
+            self.position += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Node, AugeError> {
+        if self.eat("-") {
+            Ok(Node::Neg(Box::new(self.unary()?)))
+        } else if self.eat("!") {
+            Ok(Node::Not(Box::new(self.unary()?)))
+        } else if self.eat("+") {
+            self.unary()
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, AugeError> {
+        match self.peek().cloned() {
+            Some(Token::Number(value)) => {
+                self.position += 1;
+                Ok(Node::Const(value))
+            }
+            Some(Token::Op("(")) => {
+                self.position += 1;
+                let inner = self.ternary()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Some(Token::Ident(name)) => {
+                self.position += 1;
+                if self.eat("(") {
+                    return self.call(&name);
+                }
+                if name == "pi" {
+                    return Ok(Node::Const(std::f32::consts::PI));
+                }
+                self.variables
+                    .iter()
+                    .position(|v| *v == name)
+                    .map(Node::Var)
+                    .ok_or_else(|| {
+                        AugeError::InvalidArgument(format!("unknown variable '{}'", name))
+                    })
+            }
+            _ => Err(AugeError::InvalidArgument(format!(
+                "expected a value but found {}",
+                self.describe_next()
+            ))),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Node, AugeError> {
+        let (function, arities) = Function::lookup(name)
+            .ok_or_else(|| AugeError::InvalidArgument(format!("unknown function '{}'", name)))?;
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                args.push(self.ternary()?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        if !arities.contains(&args.len()) {
+            return Err(AugeError::InvalidArgument(format!(
+                "{}() takes {} arguments, got {}",
+                name,
+                arities
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+                args.len()
+            )));
+        }
+        Ok(Node::Call(function, args))
+    }
+}
+
+/// A compiled list of `name = expression` assignments, run top to bottom for every pixel.
+pub struct Program {
+    statements: Vec<(usize, Node)>,
+    registers: usize,
+}
+
+impl Program {
+    pub fn compile(source: &str) -> Result<Self, AugeError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+            variables: BUILTINS.iter().map(|v| v.to_string()).collect(),
+        };
+
+        let mut statements = Vec::new();
+        while parser.peek().is_some() {
+            if parser.eat(";") {
+                continue;
+            }
+            statements.push(parser.statement()?);
+            if parser.peek().is_some() {
+                parser.expect(";")?;
+            }
+        }
+
+        Ok(Self {
+            statements,
+            registers: parser.variables.len(),
+        })
+    }
+}
+
+/// Per-pixel math over the channels, an escape hatch for effects without a dedicated filter.
+pub struct ExprFilter {
+    pub program: Program,
+}
+
+impl AugeFilter for ExprFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut out: RgbaImage = img.to_rgba8();
+        let (width, height) = out.dimensions();
+        if width == 0 || height == 0 {
+            return Ok(DynamicImage::ImageRgba8(out).into());
+        }
+
+        par_rows(&mut out, width as usize * 4, |y, row| {
+            let mut registers = vec![0.0f32; self.program.registers];
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let [r, g, b, a] = [pixel[0], pixel[1], pixel[2], pixel[3]].map(f32::from);
+                registers[..BUILTINS.len()].copy_from_slice(&[
+                    r,
+                    g,
+                    b,
+                    a,
+                    luma([r, g, b]) / 255.0,
+                    x as f32,
+                    y as f32,
+                    width as f32,
+                    height as f32,
+                ]);
+                for (slot, node) in &self.program.statements {
+                    registers[*slot] = node.eval(&registers);
+                }
+                for (c, value) in pixel.iter_mut().enumerate() {
+                    *value = registers[c].round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        });
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
@@ -0,0 +1,263 @@
+use clap::ValueEnum;
+use image::{DynamicImage, GrayImage, Luma, RgbaImage};
+use rustfft::{FftPlanner, num_complex::Complex};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum PassKind {
+    /// Keep low frequencies, removing fine detail and print patterns
+    Low,
+    /// Keep high frequencies, removing smooth gradients and lighting
+    High,
+}
+
+/// In-place 2D FFT over a row-major buffer of `width * height` samples.
+/// The inverse transform is normalized, so a forward/inverse round trip is lossless.
+fn fft2d(
+    planner: &mut FftPlanner<f32>,
+    data: &mut [Complex<f32>],
+    width: usize,
+    height: usize,
+    inverse: bool,
+) {
+    let (row_fft, col_fft) = if inverse {
+        (planner.plan_fft_inverse(width), planner.plan_fft_inverse(height))
+    } else {
+        (planner.plan_fft_forward(width), planner.plan_fft_forward(height))
+    };
+
+    for row in data.chunks_exact_mut(width) {
+        row_fft.process(row);
+    }
+
+    let mut column = vec![Complex::default(); height];
+    for x in 0..width {
+        for (y, sample) in column.iter_mut().enumerate() {
+            *sample = data[y * width + x];
+        }
+        col_fft.process(&mut column);
+        for (y, sample) in column.iter().enumerate() {
+            data[y * width + x] = *sample;
+        }
+    }
+
+    if inverse {
+        let norm = 1.0 / (width * height) as f32;
+        for sample in data.iter_mut() {
+            *sample *= norm;
+        }
+    }
+}
+
+/// Distance of a frequency bin from DC, normalized so that Nyquist on either axis is 1.0.
+fn normalized_frequency(x: usize, y: usize, width: usize, height: usize) -> f32 {
+    let fx = if x <= width / 2 { x as f32 } else { x as f32 - width as f32 };
+    let fy = if y <= height / 2 { y as f32 } else { y as f32 - height as f32 };
+    let nx = fx / (width as f32 / 2.0).max(1.0);
+    let ny = fy / (height as f32 / 2.0).max(1.0);
+    (nx * nx + ny * ny).sqrt()
+}
+
+fn rgba_planes(img: &RgbaImage) -> [Vec<f32>; 4] {
+    let mut planes: [Vec<f32>; 4] = Default::default();
+    for plane in planes.iter_mut() {
+        plane.reserve(img.width() as usize * img.height() as usize);
+    }
+    for pixel in img.pixels() {
+        for (c, plane) in planes.iter_mut().enumerate() {
+            plane.push(pixel[c] as f32);
+        }
+    }
+    planes
+}
+
+fn planes_to_rgba(planes: &[Vec<f32>; 4], width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        let idx = (y * width + x) as usize;
+        image::Rgba(std::array::from_fn(|c| {
+            planes[c][idx].round().clamp(0.0, 255.0) as u8
+        }))
+    })
+}
+
+/// Convolves every plane with `kernel` (of odd size `kw`x`kh`) in the frequency domain.
+/// Edges are extended by clamping, so the result has no dark border.
+/// Cost is independent of the kernel size, which makes it the right choice for large kernels.
+pub fn fft_convolve(
+    planes: &mut [Vec<f32>],
+    width: usize,
+    height: usize,
+    kernel: &[f32],
+    kw: usize,
+    kh: usize,
+) {
+    let (rx, ry) = (kw / 2, kh / 2);
+    let (pw, ph) = (width + 2 * rx, height + 2 * ry);
+    let mut planner = FftPlanner::new();
+
+    // Place the kernel centered on the origin, wrapping negative offsets around.
+    let mut kernel_spectrum = vec![Complex::default(); pw * ph];
+    for ky in 0..kh {
+        for kx in 0..kw {
+            let x = (kx + pw - rx) % pw;
+            let y = (ky + ph - ry) % ph;
+            kernel_spectrum[y * pw + x] = Complex::new(kernel[ky * kw + kx], 0.0);
+        }
+    }
+    fft2d(&mut planner, &mut kernel_spectrum, pw, ph, false);
+
+    let mut buffer = vec![Complex::default(); pw * ph];
+    for plane in planes.iter_mut() {
+        for py in 0..ph {
+            let sy = py.saturating_sub(ry).min(height - 1);
+            for px in 0..pw {
+                let sx = px.saturating_sub(rx).min(width - 1);
+                buffer[py * pw + px] = Complex::new(plane[sy * width + sx], 0.0);
+            }
+        }
+
+        fft2d(&mut planner, &mut buffer, pw, ph, false);
+        for (sample, k) in buffer.iter_mut().zip(&kernel_spectrum) {
+            *sample *= k;
+        }
+        fft2d(&mut planner, &mut buffer, pw, ph, true);
+
+        for y in 0..height {
+            for x in 0..width {
+                plane[y * width + x] = buffer[(y + ry) * pw + x + rx].re;
+            }
+        }
+    }
+}
+
+/// Gaussian blur of raw planes through [`fft_convolve`].
+pub fn fft_gaussian_blur_planes(planes: &mut [Vec<f32>], width: usize, height: usize, sigma: f32) {
+    if width == 0 || height == 0 || sigma <= 0.0 {
+        return;
+    }
+
+    let radius = (sigma * 3.0).ceil() as usize;
+    let size = radius * 2 + 1;
+    let weights: Vec<f32> = (0..size)
+        .map(|i| {
+            let d = i as f32 - radius as f32;
+            (-(d * d) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let mut kernel: Vec<f32> = weights
+        .iter()
+        .flat_map(|wy| weights.iter().map(move |wx| wx * wy))
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|k| *k /= sum);
+
+    fft_convolve(planes, width, height, &kernel, size, size);
+}
+
+/// Gaussian blur through [`fft_convolve`], for sigmas where spatial kernels get slow.
+pub fn fft_gaussian_blur(img: &DynamicImage, sigma: f32) -> DynamicImage {
+    let img = img.to_rgba8();
+    let (width, height) = img.dimensions();
+
+    let mut planes = rgba_planes(&img);
+    fft_gaussian_blur_planes(&mut planes, width as usize, height as usize, sigma);
+    DynamicImage::ImageRgba8(planes_to_rgba(&planes, width, height))
+}
+
+pub struct FftViewFilter;
+
+impl AugeFilter for FftViewFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let luma = img.to_luma8();
+        let (width, height) = luma.dimensions();
+        let (w, h) = (width as usize, height as usize);
+        if w == 0 || h == 0 {
+            return Ok(DynamicImage::ImageLuma8(luma).into());
+        }
+
+        let mut spectrum: Vec<Complex<f32>> = luma
+            .pixels()
+            .map(|p| Complex::new(p[0] as f32 / 255.0, 0.0))
+            .collect();
+        fft2d(&mut FftPlanner::new(), &mut spectrum, w, h, false);
+
+        // Log scaling keeps the huge DC term from washing out everything else
+        let magnitudes: Vec<f32> = spectrum.iter().map(|c| c.norm().ln_1p()).collect();
+        let max = magnitudes.iter().cloned().fold(f32::EPSILON, f32::max);
+
+        // Shift quadrants so DC ends up in the middle of the output
+        let view = GrayImage::from_fn(width, height, |x, y| {
+            let sx = (x as usize + w / 2) % w;
+            let sy = (y as usize + h / 2) % h;
+            let value = magnitudes[sy * w + sx] / max * 255.0;
+            Luma([value.round() as u8])
+        });
+
+        Ok(DynamicImage::ImageLuma8(view).into())
+    }
+}
+
+pub struct FrequencyPassFilter {
+    pub kind: PassKind,
+    pub cutoff: f32,
+    pub ideal: bool,
+}
+
+impl FrequencyPassFilter {
+    fn response(&self, frequency: f32) -> f32 {
+        let cutoff = self.cutoff.max(f32::EPSILON);
+        let low = if self.ideal {
+            if frequency <= cutoff { 1.0 } else { 0.0 }
+        } else {
+            (-(frequency * frequency) / (2.0 * cutoff * cutoff)).exp()
+        };
+        match self.kind {
+            PassKind::Low => low,
+            PassKind::High => 1.0 - low,
+        }
+    }
+}
+
+impl AugeFilter for FrequencyPassFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let rgba = img.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        let (w, h) = (width as usize, height as usize);
+        if w == 0 || h == 0 {
+            return Ok(DynamicImage::ImageRgba8(rgba).into());
+        }
+
+        let mask: Vec<f32> = (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .map(|(x, y)| self.response(normalized_frequency(x, y, w, h)))
+            .collect();
+
+        // High-pass output is centered on mid gray, otherwise it would be mostly black
+        let offset = match self.kind {
+            PassKind::Low => 0.0,
+            PassKind::High => 127.5,
+        };
+
+        let mut planner = FftPlanner::new();
+        let mut planes = rgba_planes(&rgba);
+        let mut buffer = vec![Complex::default(); w * h];
+        // Alpha is left untouched
+        for plane in planes.iter_mut().take(3) {
+            for (sample, value) in buffer.iter_mut().zip(plane.iter()) {
+                *sample = Complex::new(*value, 0.0);
+            }
+            fft2d(&mut planner, &mut buffer, w, h, false);
+            for (sample, m) in buffer.iter_mut().zip(&mask) {
+                *sample *= m;
+            }
+            fft2d(&mut planner, &mut buffer, w, h, true);
+            for (value, sample) in plane.iter_mut().zip(&buffer) {
+                *value = sample.re + offset;
+            }
+        }
+
+        Ok(DynamicImage::ImageRgba8(planes_to_rgba(&planes, width, height)).into())
+    }
+}
@@ -0,0 +1,47 @@
+use image::{DynamicImage, Rgb, RgbImage};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Composites the image over a solid color or a checkerboard and drops alpha,
+/// so formats without transparency get a defined background.
+pub struct FlattenFilter {
+    pub color: Rgb<u8>,
+    /// Checkerboard cell size in pixels, solid `color` when unset
+    pub checker: Option<u32>,
+    /// Color of every other checkerboard cell
+    pub checker_color: Rgb<u8>,
+}
+
+impl FlattenFilter {
+    fn background(&self, x: u32, y: u32) -> Rgb<u8> {
+        match self.checker {
+            Some(size) if (x / size + y / size) % 2 == 1 => self.checker_color,
+            _ => self.color,
+        }
+    }
+}
+
+impl AugeFilter for FlattenFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        if self.checker == Some(0) {
+            return Err(AugeError::InvalidArgument(
+                "checkerboard cells must be at least 1 pixel".to_string(),
+            ));
+        }
+        if !img.color().has_alpha() {
+            return Ok(DynamicImage::ImageRgb8(img.to_rgb8()).into());
+        }
+
+        let rgba = img.to_rgba8();
+        let flat = RgbImage::from_fn(img.width(), img.height(), |x, y| {
+            let pixel = rgba.get_pixel(x, y);
+            let alpha = pixel[3] as u32;
+            let back = self.background(x, y);
+            Rgb([0, 1, 2].map(|c| {
+                ((pixel[c] as u32 * alpha + back[c] as u32 * (255 - alpha) + 127) / 255) as u8
+            }))
+        });
+        Ok(DynamicImage::ImageRgb8(flat).into())
+    }
+}
@@ -0,0 +1,42 @@
+use clap::ValueEnum;
+use image::DynamicImage;
+
+use super::annotations::Geometric;
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum FlipDirection {
+    /// Mirror left and right
+    Horizontal,
+    /// Mirror top and bottom
+    Vertical,
+    /// Both at once, same as rotating by 180 degrees
+    Both,
+}
+
+pub struct FlipFilter {
+    pub direction: FlipDirection,
+}
+
+impl AugeFilter for FlipFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        Ok(match self.direction {
+            FlipDirection::Horizontal => img.fliph(),
+            FlipDirection::Vertical => img.flipv(),
+            FlipDirection::Both => img.rotate180(),
+        }
+        .into())
+    }
+}
+
+impl Geometric for FlipFilter {
+    fn map_point(&self, x: f64, y: f64, input: (u32, u32), _: (u32, u32)) -> (f64, f64) {
+        let (width, height) = (input.0 as f64, input.1 as f64);
+        match self.direction {
+            FlipDirection::Horizontal => (width - x, y),
+            FlipDirection::Vertical => (x, height - y),
+            FlipDirection::Both => (width - x, height - y),
+        }
+    }
+}
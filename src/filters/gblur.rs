@@ -1,17 +1,61 @@
-use super::{AugeFilter, FilterResult};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use super::{AugeFilter, FilterResult, fft::fft_gaussian_blur, param_map::ParamMap};
 use crate::types::AugeError;
 
+/// Blur strengths rendered when sigma varies per pixel, neighbors are interpolated
+const MAP_LEVELS: usize = 6;
+
 pub struct GBlurFilter {
     pub fast: bool,
-    pub sigma: f32
+    pub fft: bool,
+    pub sigma: f32,
+    /// Scales sigma per pixel, black stays sharp and white gets the full blur
+    pub param_map: Option<ParamMap>,
 }
 
-impl AugeFilter for GBlurFilter {
-    fn apply(&self, img: image::DynamicImage) -> Result<FilterResult, AugeError> {
-        if self.fast {
-            Ok(img.fast_blur(self.sigma).into())
+impl GBlurFilter {
+    fn blur(&self, img: &DynamicImage, sigma: f32) -> DynamicImage {
+        if sigma <= 0.0 {
+            img.clone()
+        } else if self.fft {
+            fft_gaussian_blur(img, sigma)
+        } else if self.fast {
+            img.fast_blur(sigma)
         } else {
-            Ok(img.blur(self.sigma).into())
+            #[cfg(feature = "gpu")]
+            if let Some(blurred) = crate::gpu::gpu().and_then(|gpu| gpu.blur(img, sigma)) {
+                return blurred;
+            }
+            img.blur(sigma)
         }
     }
-}
\ No newline at end of file
+}
+
+impl AugeFilter for GBlurFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let Some(map) = &self.param_map else {
+            return Ok(self.blur(&img, self.sigma).into());
+        };
+
+        let levels: Vec<RgbaImage> = (0..MAP_LEVELS)
+            .map(|i| {
+                let sigma = self.sigma * i as f32 / (MAP_LEVELS - 1) as f32;
+                self.blur(&img, sigma).to_rgba8()
+            })
+            .collect();
+        let weights = map.weights(img.width(), img.height());
+
+        let out = RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+            let position = weights[(y * img.width() + x) as usize] * (MAP_LEVELS - 1) as f32;
+            let lower = (position.floor() as usize).min(MAP_LEVELS - 2);
+            let t = position - lower as f32;
+            let (a, b) = (levels[lower].get_pixel(x, y), levels[lower + 1].get_pixel(x, y));
+            Rgba(std::array::from_fn(|c| {
+                (a[c] as f32 * (1.0 - t) + b[c] as f32 * t).round() as u8
+            }))
+        });
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
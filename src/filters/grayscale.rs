@@ -1,10 +1,24 @@
+use image::{DynamicImage, GrayAlphaImage, LumaA};
+
 use super::{AugeFilter, FilterResult};
+use crate::simd;
 use crate::types::AugeError;
 
 pub struct GrayscaleFilter;
 
 impl AugeFilter for GrayscaleFilter {
-    fn apply(&self, img: image::DynamicImage) -> Result<FilterResult, AugeError> {
-        Ok(img.grayscale().into())
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        Ok(match &img {
+            DynamicImage::ImageRgb8(_) => DynamicImage::ImageLuma8(simd::to_luma8(&img)),
+            DynamicImage::ImageRgba8(buffer) => {
+                let luma = simd::to_luma8(&img);
+                let gray = GrayAlphaImage::from_fn(img.width(), img.height(), |x, y| {
+                    LumaA([luma.get_pixel(x, y)[0], buffer.get_pixel(x, y)[3]])
+                });
+                DynamicImage::ImageLumaA8(gray)
+            }
+            _ => img.grayscale(),
+        }
+        .into())
     }
 }
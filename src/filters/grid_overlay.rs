@@ -0,0 +1,135 @@
+use image::{DynamicImage, Rgb, Rgba, RgbaImage};
+
+use super::blend::{BlendMode, Composite, blend_onto};
+use super::font::draw_text;
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Guides for reviewing compositions and UI mocks, drawn over the image.
+/// Everything is painted on one layer first so crossings don't add up in opacity.
+pub struct GridOverlayFilter {
+    /// Pixel grid every `spacing` pixels
+    pub spacing: Option<u32>,
+    pub thirds: bool,
+    pub crosshair: bool,
+    /// Safe-area frames, each inset by this percentage of width and height on every side
+    pub safe_areas: Vec<f32>,
+    /// Write the pixel coordinate next to every grid line along the top and left edges
+    pub labels: bool,
+    pub color: Rgb<u8>,
+    /// Opacity of the guides in `0..=1`
+    pub opacity: f32,
+    pub thickness: u32,
+}
+
+struct Layer {
+    img: RgbaImage,
+    ink: Rgba<u8>,
+    thickness: u32,
+}
+
+impl Layer {
+    /// Filled rectangle clipped to the layer
+    fn rect(&mut self, x: i64, y: i64, width: i64, height: i64) {
+        let (w, h) = (self.img.width() as i64, self.img.height() as i64);
+        for py in y.max(0)..(y + height).min(h) {
+            for px in x.max(0)..(x + width).min(w) {
+                self.img.put_pixel(px as u32, py as u32, self.ink);
+            }
+        }
+    }
+
+    /// Line `thickness` wide centered on column `x`, from `top` to `bottom`
+    fn vertical(&mut self, x: i64, top: i64, bottom: i64) {
+        let t = self.thickness as i64;
+        self.rect(x - t / 2, top, t, bottom - top);
+    }
+
+    fn horizontal(&mut self, y: i64, left: i64, right: i64) {
+        let t = self.thickness as i64;
+        self.rect(left, y - t / 2, right - left, t);
+    }
+}
+
+impl AugeFilter for GridOverlayFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut out = img.to_rgba8();
+        let (width, height) = out.dimensions();
+        let (w, h) = (width as i64, height as i64);
+        let [r, g, b] = self.color.0;
+        let mut layer = Layer {
+            img: RgbaImage::new(width, height),
+            ink: Rgba([r, g, b, 255]),
+            thickness: self.thickness.max(1),
+        };
+
+        if let Some(spacing) = self.spacing.filter(|&s| s > 0) {
+            for x in (spacing..width).step_by(spacing as usize) {
+                layer.vertical(x as i64, 0, h);
+                if self.labels {
+                    draw_text(
+                        &mut layer.img,
+                        x as i64 + 2,
+                        2,
+                        &x.to_string(),
+                        1,
+                        layer.ink,
+                    );
+                }
+            }
+            for y in (spacing..height).step_by(spacing as usize) {
+                layer.horizontal(y as i64, 0, w);
+                if self.labels {
+                    draw_text(
+                        &mut layer.img,
+                        2,
+                        y as i64 + 2,
+                        &y.to_string(),
+                        1,
+                        layer.ink,
+                    );
+                }
+            }
+        }
+
+        if self.thirds {
+            for i in 1..3 {
+                layer.vertical(w * i / 3, 0, h);
+                layer.horizontal(h * i / 3, 0, w);
+            }
+        }
+
+        if self.crosshair {
+            let arm = (w.min(h) / 20).max(8);
+            let (cx, cy) = (w / 2, h / 2);
+            layer.vertical(cx, cy - arm, cy + arm + 1);
+            layer.horizontal(cy, cx - arm, cx + arm + 1);
+        }
+
+        for &percent in &self.safe_areas {
+            let fraction = (percent / 100.0).clamp(0.0, 0.5);
+            let (left, top) = (
+                (w as f32 * fraction).round() as i64,
+                (h as f32 * fraction).round() as i64,
+            );
+            let (right, bottom) = (w - 1 - left, h - 1 - top);
+            // Lines sit inside the frame so a 0% area still shows at the image border
+            let t = layer.thickness as i64;
+            layer.rect(left, top, right - left + 1, t);
+            layer.rect(left, bottom + 1 - t, right - left + 1, t);
+            layer.rect(left, top, t, bottom - top + 1);
+            layer.rect(right + 1 - t, top, t, bottom - top + 1);
+        }
+
+        blend_onto(
+            &mut out,
+            &layer.img,
+            0,
+            0,
+            BlendMode::Normal,
+            Composite::Over,
+            self.opacity.clamp(0.0, 1.0),
+        );
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
@@ -0,0 +1,114 @@
+use image::{DynamicImage, Rgba, RgbaImage, imageops::FilterType};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Guided filter (He et al.): a local linear model of the guide fitted to every channel.
+/// Edges of the guide survive, everything else is smoothed, and the cost does not
+/// depend on the radius thanks to box means over summed-area tables.
+pub struct GuidedFilter {
+    pub radius: u32,
+    /// Regularization on `0..=1` values, larger smooths across stronger edges
+    pub eps: f32,
+    /// Luma of this image steers the smoothing, each channel steers itself when `None`
+    pub guide: Option<DynamicImage>,
+    /// Scales the removed detail back in, `0` smooths and values above `0` sharpen
+    pub boost: f32,
+}
+
+/// Mean over a `(2r + 1)^2` window clipped to the image, via a summed-area table
+fn box_mean(values: &[f64], width: usize, height: usize, radius: usize) -> Vec<f64> {
+    let stride = width + 1;
+    let mut table = vec![0.0; stride * (height + 1)];
+    for y in 0..height {
+        let mut row = 0.0;
+        for x in 0..width {
+            row += values[y * width + x];
+            table[(y + 1) * stride + x + 1] = table[y * stride + x + 1] + row;
+        }
+    }
+
+    let mut out = vec![0.0; width * height];
+    for y in 0..height {
+        let (top, bottom) = (y.saturating_sub(radius), (y + radius + 1).min(height));
+        for x in 0..width {
+            let (left, right) = (x.saturating_sub(radius), (x + radius + 1).min(width));
+            let sum = table[bottom * stride + right]
+                - table[top * stride + right]
+                - table[bottom * stride + left]
+                + table[top * stride + left];
+            out[y * width + x] = sum / ((bottom - top) * (right - left)) as f64;
+        }
+    }
+    out
+}
+
+/// Smooths the `w` x `h` plane `p` along the edges of `guide`, both on `0..=1`
+pub fn guided(p: &[f64], guide: &[f64], w: usize, h: usize, r: usize, eps: f64) -> Vec<f64> {
+    let mean_guide = box_mean(guide, w, h, r);
+    let squares: Vec<f64> = guide.iter().map(|v| v * v).collect();
+    let mean_squares = box_mean(&squares, w, h, r);
+    let mean_p = box_mean(p, w, h, r);
+    let products: Vec<f64> = guide.iter().zip(p).map(|(i, p)| i * p).collect();
+    let mean_products = box_mean(&products, w, h, r);
+
+    let mut a = vec![0.0; w * h];
+    let mut b = vec![0.0; w * h];
+    for i in 0..w * h {
+        let variance = mean_squares[i] - mean_guide[i] * mean_guide[i];
+        let covariance = mean_products[i] - mean_guide[i] * mean_p[i];
+        a[i] = covariance / (variance + eps);
+        b[i] = mean_p[i] - a[i] * mean_guide[i];
+    }
+    let (mean_a, mean_b) = (box_mean(&a, w, h, r), box_mean(&b, w, h, r));
+    (0..w * h)
+        .map(|i| mean_a[i] * guide[i] + mean_b[i])
+        .collect()
+}
+
+impl AugeFilter for GuidedFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        // Flat regions have no variance, eps alone keeps their division finite
+        if self.eps.is_nan() || self.eps <= 0.0 {
+            return Err(AugeError::InvalidArgument(format!(
+                "eps must be positive, got {}",
+                self.eps
+            )));
+        }
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let (w, h, r) = (width as usize, height as usize, self.radius as usize);
+        // Without a guide image every channel steers itself, which avoids color bleeding
+        let luma = match &self.guide {
+            Some(guide) if (guide.width(), guide.height()) != (width, height) => Some(
+                guide
+                    .resize_exact(width, height, FilterType::CatmullRom)
+                    .to_luma8(),
+            ),
+            Some(guide) => Some(guide.to_luma8()),
+            None => None,
+        };
+        let luma: Option<Vec<f64>> =
+            luma.map(|luma| luma.pixels().map(|p| p[0] as f64 / 255.0).collect());
+
+        let mut channels = Vec::with_capacity(3);
+        for c in 0..3 {
+            let p: Vec<f64> = src.pixels().map(|px| px[c] as f64 / 255.0).collect();
+            let guide = luma.as_ref().unwrap_or(&p);
+            let smooth = guided(&p, guide, w, h, r, self.eps as f64);
+            let q: Vec<f64> = smooth
+                .iter()
+                .zip(&p)
+                .map(|(smooth, p)| smooth + (p - smooth) * self.boost as f64)
+                .collect();
+            channels.push(q);
+        }
+
+        let out = RgbaImage::from_fn(width, height, |x, y| {
+            let i = y as usize * w + x as usize;
+            let channel = |c: usize| (channels[c][i] * 255.0).round().clamp(0.0, 255.0) as u8;
+            Rgba([channel(0), channel(1), channel(2), src.get_pixel(x, y)[3]])
+        });
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
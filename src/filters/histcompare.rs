@@ -0,0 +1,68 @@
+use image::DynamicImage;
+use serde::Serialize;
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Compares the joint RGB histograms of two images. Cheap and blind to
+/// composition, so it complements perceptual hashing rather than replacing it.
+pub struct HistCompareFilter {
+    pub other: DynamicImage,
+    /// Bins per channel, the joint histogram has `bins^3` cells
+    pub bins: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistogramDistance {
+    pub bins: u32,
+    /// Symmetric chi-square distance, 0 for identical histograms, at most 2
+    pub chi_square: f64,
+    /// Hellinger form of the Bhattacharyya distance, 0 for identical, 1 for disjoint
+    pub bhattacharyya: f64,
+}
+
+/// Normalized joint RGB histogram, so images of different sizes are comparable
+fn histogram(img: &DynamicImage, bins: u32) -> Vec<f64> {
+    let rgb = img.to_rgb8();
+    let bins = bins as usize;
+    let mut histogram = vec![0.0f64; bins * bins * bins];
+    for pixel in rgb.pixels() {
+        let [r, g, b] = pixel.0.map(|c| c as usize * bins / 256);
+        histogram[r + g * bins + b * bins * bins] += 1.0;
+    }
+
+    let total = (rgb.width() as f64 * rgb.height() as f64).max(1.0);
+    for value in histogram.iter_mut() {
+        *value /= total;
+    }
+    histogram
+}
+
+impl AugeFilter for HistCompareFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        if !(1..=256).contains(&self.bins) {
+            return Err(AugeError::InvalidArgument(format!(
+                "bins must be between 1 and 256, got {}",
+                self.bins
+            )));
+        }
+
+        let a = histogram(&img, self.bins);
+        let b = histogram(&self.other, self.bins);
+
+        let chi_square = a
+            .iter()
+            .zip(&b)
+            .filter(|(a, b)| **a + **b > 0.0)
+            .map(|(a, b)| (a - b).powi(2) / (a + b))
+            .sum();
+        let coefficient: f64 = a.iter().zip(&b).map(|(a, b)| (a * b).sqrt()).sum();
+
+        let distance = HistogramDistance {
+            bins: self.bins,
+            chi_square,
+            bhattacharyya: (1.0 - coefficient).max(0.0).sqrt(),
+        };
+        Ok(FilterResult::Text(serde_json::to_string(&distance)?))
+    }
+}
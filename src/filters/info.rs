@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use exif::{In, Tag, Value};
+use image::{DynamicImage, ImageFormat};
+use serde::Serialize;
+
+use super::{AugeFilter, FilterResult};
+use crate::inout::decode_frames;
+use crate::types::AugeError;
+
+/// EXIF fields worth showing and their report names
+const EXIF_FIELDS: [(Tag, &str); 11] = [
+    (Tag::Make, "make"),
+    (Tag::Model, "model"),
+    (Tag::LensModel, "lens"),
+    (Tag::Software, "software"),
+    (Tag::DateTimeOriginal, "taken"),
+    (Tag::ExposureTime, "exposure_time"),
+    (Tag::FNumber, "f_number"),
+    (Tag::PhotographicSensitivity, "iso"),
+    (Tag::FocalLength, "focal_length"),
+    (Tag::Flash, "flash"),
+    (Tag::Orientation, "orientation"),
+];
+
+/// Describes the input file: format, size, pixel layout, frames and EXIF fields
+pub struct InfoFilter {
+    /// The encoded input, the decoded image no longer knows its format or metadata
+    pub source: Vec<u8>,
+    pub json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Gps {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Meters above sea level
+    pub altitude: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InfoReport {
+    pub format: Option<String>,
+    pub file_size: usize,
+    pub width: u32,
+    pub height: u32,
+    pub color_type: String,
+    /// Bits per channel
+    pub bit_depth: u16,
+    pub frames: usize,
+    pub exif: BTreeMap<&'static str, String>,
+    pub gps: Option<Gps>,
+}
+
+/// Field value as text, strings without the quotes EXIF display adds
+fn field_text(exif: &exif::Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let text = match &field.value {
+        Value::Ascii(parts) => parts
+            .iter()
+            .map(|part| String::from_utf8_lossy(part).trim().to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => field.display_value().with_unit(exif).to_string(),
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// Degrees, minutes and seconds to signed decimal degrees
+fn coordinate(exif: &exif::Exif, tag: Tag, reference: Tag, negative: u8) -> Option<f64> {
+    let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let degrees = parts
+        .iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, unit)| part.to_f64() / unit)
+        .sum::<f64>();
+    let sign = match &exif.get_field(reference, In::PRIMARY)?.value {
+        Value::Ascii(parts) if parts.first()?.first() == Some(&negative) => -1.0,
+        _ => 1.0,
+    };
+    Some(sign * degrees)
+}
+
+fn gps(exif: &exif::Exif) -> Option<Gps> {
+    let latitude = coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?;
+    let longitude = coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?;
+    let altitude = match &exif
+        .get_field(Tag::GPSAltitude, In::PRIMARY)
+        .map(|f| &f.value)
+    {
+        Some(Value::Rational(parts)) if !parts.is_empty() => {
+            let below = matches!(
+                exif.get_field(Tag::GPSAltitudeRef, In::PRIMARY).map(|f| &f.value),
+                Some(Value::Byte(reference)) if reference.first() == Some(&1)
+            );
+            Some(if below {
+                -parts[0].to_f64()
+            } else {
+                parts[0].to_f64()
+            })
+        }
+        _ => None,
+    };
+    Some(Gps {
+        latitude,
+        longitude,
+        altitude,
+    })
+}
+
+impl InfoReport {
+    fn text(&self) -> String {
+        let mut lines = vec![
+            format!("format: {}", self.format.as_deref().unwrap_or("unknown")),
+            format!("file size: {} bytes", self.file_size),
+            format!("dimensions: {}x{}", self.width, self.height),
+            format!("color type: {}", self.color_type),
+            format!("bit depth: {}", self.bit_depth),
+            format!("frames: {}", self.frames),
+        ];
+        for (name, value) in &self.exif {
+            lines.push(format!("{}: {}", name.replace('_', " "), value));
+        }
+        if let Some(gps) = &self.gps {
+            lines.push(format!("gps: {:.6}, {:.6}", gps.latitude, gps.longitude));
+            if let Some(altitude) = gps.altitude {
+                lines.push(format!("altitude: {:.1} m", altitude));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+impl AugeFilter for InfoFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let format = image::guess_format(&self.source).ok();
+        let frames = match format {
+            Some(ImageFormat::Gif | ImageFormat::WebP | ImageFormat::Png) => {
+                decode_frames(&self.source, None)?.len()
+            }
+            _ => 1,
+        };
+        let exif = exif::Reader::new()
+            .read_from_container(&mut Cursor::new(&self.source))
+            .ok();
+        let color = img.color();
+
+        let report = InfoReport {
+            format: format.map(|format| format!("{:?}", format).to_lowercase()),
+            file_size: self.source.len(),
+            width: img.width(),
+            height: img.height(),
+            color_type: format!("{:?}", color).to_lowercase(),
+            bit_depth: color.bits_per_pixel() / color.channel_count() as u16,
+            frames,
+            exif: exif
+                .as_ref()
+                .map(|exif| {
+                    EXIF_FIELDS
+                        .iter()
+                        .filter_map(|&(tag, name)| Some((name, field_text(exif, tag)?)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            gps: exif.as_ref().and_then(gps),
+        };
+        Ok(FilterResult::Text(if self.json {
+            serde_json::to_string(&report)?
+        } else {
+            report.text()
+        }))
+    }
+}
@@ -0,0 +1,291 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use clap::ValueEnum;
+use image::{DynamicImage, GrayImage, Rgba, RgbaImage};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Isophote transport steps between two diffusion steps of the Navier-Stokes method
+const TRANSPORT_STEPS: u32 = 15;
+const TIME_STEP: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum InpaintMethod {
+    /// Fast marching from the border inwards (Telea), quick and good for small holes
+    Telea,
+    /// Continues edges into the hole along isophotes (Bertalmio, Navier-Stokes style), slower
+    Ns,
+}
+
+const KNOWN: u8 = 0;
+const BAND: u8 = 1;
+const INSIDE: u8 = 2;
+
+/// Reconstructs the pixels under a mask from their surroundings
+pub struct InpaintFilter {
+    /// Same size as the input, white marks the pixels to fill
+    pub mask: GrayImage,
+    pub method: InpaintMethod,
+    /// Neighborhood in pixels each filled pixel is estimated from
+    pub radius: u32,
+    /// Isophote transport steps of the `ns` method
+    pub iterations: u32,
+}
+
+/// Float planes of an image with its fill state, indexed `y * width + x`
+struct Canvas {
+    width: usize,
+    height: usize,
+    channels: [Vec<f32>; 4],
+    flags: Vec<u8>,
+    /// Arrival time of the fill front, distance from the border of the hole
+    time: Vec<f32>,
+}
+
+impl Canvas {
+    fn neighbors(&self, i: usize) -> impl Iterator<Item = usize> + use<> {
+        let (x, y, w, h) = (i % self.width, i / self.width, self.width, self.height);
+        [
+            (x > 0).then(|| i - 1),
+            (x + 1 < w).then(|| i + 1),
+            (y > 0).then(|| i - w),
+            (y + 1 < h).then(|| i + w),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Arrival time at `i` from the upwind neighbors, the solution of |∇T| = 1
+    fn solve_time(&self, i: usize) -> f32 {
+        let (x, y) = (i % self.width, i / self.width);
+        let known = |j: usize| self.flags[j] != INSIDE;
+        let along = |a: Option<usize>, b: Option<usize>| {
+            [a, b]
+                .into_iter()
+                .flatten()
+                .filter(|&j| known(j))
+                .map(|j| self.time[j])
+                .fold(f32::INFINITY, f32::min)
+        };
+        let horizontal = along((x > 0).then(|| i - 1), (x + 1 < self.width).then(|| i + 1));
+        let vertical = along(
+            (y > 0).then(|| i - self.width),
+            (y + 1 < self.height).then(|| i + self.width),
+        );
+        match (horizontal.is_finite(), vertical.is_finite()) {
+            (true, true) if (horizontal - vertical).abs() < 1.0 => {
+                let root = (2.0 - (horizontal - vertical).powi(2)).sqrt();
+                (horizontal + vertical + root) / 2.0
+            }
+            (true, true) => horizontal.min(vertical) + 1.0,
+            (true, false) => horizontal + 1.0,
+            (false, true) => vertical + 1.0,
+            (false, false) => f32::INFINITY,
+        }
+    }
+
+    /// Central difference of `values` at `i`, one-sided or zero where neighbors are still unknown
+    fn gradient(&self, values: &[f32], i: usize) -> (f32, f32) {
+        let (x, y) = (i % self.width, i / self.width);
+        let known = |j: usize| self.flags[j] != INSIDE;
+        let diff = |prev: Option<usize>, next: Option<usize>| match (
+            prev.filter(|&j| known(j)),
+            next.filter(|&j| known(j)),
+        ) {
+            (Some(a), Some(b)) => (values[b] - values[a]) / 2.0,
+            (Some(a), None) => values[i] - values[a],
+            (None, Some(b)) => values[b] - values[i],
+            (None, None) => 0.0,
+        };
+        (
+            diff((x > 0).then(|| i - 1), (x + 1 < self.width).then(|| i + 1)),
+            diff(
+                (y > 0).then(|| i - self.width),
+                (y + 1 < self.height).then(|| i + self.width),
+            ),
+        )
+    }
+
+    /// Telea's estimate: known pixels nearby, extrapolated along their gradient and
+    /// weighted by direction of the front, distance and similar arrival time
+    fn fill(&mut self, i: usize, radius: i64) {
+        let (x, y) = ((i % self.width) as i64, (i / self.width) as i64);
+        let (tx, ty) = self.gradient(&self.time, i);
+        let mut sums = [0.0f32; 4];
+        let mut total = 0.0f32;
+        for qy in (y - radius).max(0)..=(y + radius).min(self.height as i64 - 1) {
+            for qx in (x - radius).max(0)..=(x + radius).min(self.width as i64 - 1) {
+                let (rx, ry) = ((x - qx) as f32, (y - qy) as f32);
+                let length_sq = rx * rx + ry * ry;
+                let q = qy as usize * self.width + qx as usize;
+                if length_sq == 0.0
+                    || length_sq > (radius * radius) as f32
+                    || self.flags[q] == INSIDE
+                {
+                    continue;
+                }
+                let length = length_sq.sqrt();
+                let direction = ((rx * tx + ry * ty) / length).abs().max(1e-6);
+                let distance = 1.0 / length_sq;
+                let level = 1.0 / (1.0 + (self.time[q] - self.time[i]).abs());
+                let weight = direction * distance * level;
+                for (c, sum) in sums.iter_mut().enumerate() {
+                    let (gx, gy) = self.gradient(&self.channels[c], q);
+                    *sum += weight * (self.channels[c][q] + gx * rx + gy * ry);
+                }
+                total += weight;
+            }
+        }
+        if total > 0.0 {
+            for (c, sum) in sums.iter().enumerate() {
+                self.channels[c][i] = (sum / total).clamp(0.0, 255.0);
+            }
+        }
+    }
+
+    /// Fills the hole from its border inwards in order of arrival time
+    fn march(&mut self, radius: u32) {
+        // Non-negative floats order like their bit patterns, which keeps the heap on integers
+        let mut heap = BinaryHeap::new();
+        for i in 0..self.flags.len() {
+            if self.flags[i] == KNOWN && self.neighbors(i).any(|j| self.flags[j] == INSIDE) {
+                self.flags[i] = BAND;
+                heap.push(Reverse((0.0f32.to_bits(), i)));
+            }
+        }
+        while let Some(Reverse((_, i))) = heap.pop() {
+            self.flags[i] = KNOWN;
+            for j in self.neighbors(i).collect::<Vec<_>>() {
+                if self.flags[j] != INSIDE {
+                    continue;
+                }
+                self.time[j] = self.solve_time(j);
+                // Still inside while filling, so gradients around it ignore its old value
+                self.fill(j, radius as i64);
+                self.flags[j] = BAND;
+                heap.push(Reverse((self.time[j].to_bits(), j)));
+            }
+        }
+    }
+
+    /// Bertalmio et al.: smoothness, measured by the laplacian, is transported along
+    /// the isophotes into the hole, with a little diffusion to keep it stable
+    fn transport(&mut self, hole: &[usize], iterations: u32) {
+        let w = self.width;
+        let interior: Vec<usize> = hole
+            .iter()
+            .copied()
+            .filter(|&i| {
+                let (x, y) = (i % w, i / w);
+                x > 0 && y > 0 && x + 1 < w && y + 1 < self.height
+            })
+            .collect();
+        for step in 1..=iterations {
+            for values in self.channels.iter_mut() {
+                let laplacian = |i: usize| {
+                    values[i - 1] + values[i + 1] + values[i - w] + values[i + w] - 4.0 * values[i]
+                };
+                let updates: Vec<f32> = if step % (TRANSPORT_STEPS + 1) == 0 {
+                    interior.iter().map(|&i| 0.2 * laplacian(i)).collect()
+                } else {
+                    interior
+                        .iter()
+                        .map(|&i| {
+                            let at = |j: usize| {
+                                let (x, y) = (j % w, j / w);
+                                if x > 0 && y > 0 && x + 1 < w && y + 1 < self.height {
+                                    laplacian(j)
+                                } else {
+                                    0.0
+                                }
+                            };
+                            let change = (at(i + 1) - at(i - 1), at(i + w) - at(i - w));
+                            let (gx, gy) = (
+                                (values[i + 1] - values[i - 1]) / 2.0,
+                                (values[i + w] - values[i - w]) / 2.0,
+                            );
+                            let norm = (gx * gx + gy * gy).sqrt();
+                            if norm < 1e-6 {
+                                return 0.0;
+                            }
+                            // Isophote direction, perpendicular to the gradient
+                            let beta = (change.0 * -gy + change.1 * gx) / norm;
+                            // Slope-limited gradient magnitude, upwind of the transport
+                            let (back_x, fwd_x) =
+                                (values[i] - values[i - 1], values[i + 1] - values[i]);
+                            let (back_y, fwd_y) =
+                                (values[i] - values[i - w], values[i + w] - values[i]);
+                            let magnitude = if beta > 0.0 {
+                                (back_x.min(0.0).powi(2)
+                                    + fwd_x.max(0.0).powi(2)
+                                    + back_y.min(0.0).powi(2)
+                                    + fwd_y.max(0.0).powi(2))
+                                .sqrt()
+                            } else {
+                                (back_x.max(0.0).powi(2)
+                                    + fwd_x.min(0.0).powi(2)
+                                    + back_y.max(0.0).powi(2)
+                                    + fwd_y.min(0.0).powi(2))
+                                .sqrt()
+                            };
+                            // The scheme is quadratic in intensity, so it runs on a 0..1 scale
+                            TIME_STEP * beta * magnitude / 255.0
+                        })
+                        .collect()
+                };
+                for (&i, update) in interior.iter().zip(updates) {
+                    values[i] = (values[i] + update).clamp(0.0, 255.0);
+                }
+            }
+        }
+    }
+}
+
+impl AugeFilter for InpaintFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let rgba = img.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        if self.mask.dimensions() != (width, height) {
+            return Err(AugeError::InvalidArgument(format!(
+                "mask is {}x{}, expected {}x{} like the input",
+                self.mask.width(),
+                self.mask.height(),
+                width,
+                height
+            )));
+        }
+
+        let flags: Vec<u8> = self
+            .mask
+            .pixels()
+            .map(|p| if p[0] > 127 { INSIDE } else { KNOWN })
+            .collect();
+        let hole: Vec<usize> = (0..flags.len()).filter(|&i| flags[i] == INSIDE).collect();
+        if hole.is_empty() || hole.len() == flags.len() {
+            return Ok(DynamicImage::ImageRgba8(rgba).into());
+        }
+
+        let mut canvas = Canvas {
+            width: width as usize,
+            height: height as usize,
+            channels: std::array::from_fn(|c| rgba.pixels().map(|p| p[c] as f32).collect()),
+            time: flags
+                .iter()
+                .map(|&f| if f == INSIDE { f32::INFINITY } else { 0.0 })
+                .collect(),
+            flags,
+        };
+        canvas.march(self.radius.max(1));
+        if let InpaintMethod::Ns = self.method {
+            canvas.transport(&hole, self.iterations);
+        }
+
+        let out = RgbaImage::from_fn(width, height, |x, y| {
+            let i = (y * width + x) as usize;
+            Rgba(std::array::from_fn(|c| canvas.channels[c][i].round() as u8))
+        });
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
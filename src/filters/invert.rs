@@ -1,12 +1,33 @@
+use image::DynamicImage;
+
 use super::{AugeFilter, FilterResult};
+use crate::simd;
 use crate::types::AugeError;
 
 pub struct InvertFilter;
 
 impl AugeFilter for InvertFilter {
-    fn apply(&self, img: image::DynamicImage) -> Result<FilterResult, AugeError> {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        #[cfg(feature = "gpu")]
+        if let Some(inverted) = crate::gpu::gpu().and_then(|gpu| {
+            let rows = [
+                [-1.0, 0.0, 0.0, 0.0],
+                [0.0, -1.0, 0.0, 0.0],
+                [0.0, 0.0, -1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ];
+            gpu.color(&img, rows, [1.0, 1.0, 1.0, 0.0], f32::MIN)
+        }) {
+            return Ok(inverted.into());
+        }
         let mut img = img;
-        img.invert();
+        match &mut img {
+            DynamicImage::ImageLuma8(buffer) => simd::invert(buffer, 1, false),
+            DynamicImage::ImageLumaA8(buffer) => simd::invert(buffer, 2, true),
+            DynamicImage::ImageRgb8(buffer) => simd::invert(buffer, 3, false),
+            DynamicImage::ImageRgba8(buffer) => simd::invert(buffer, 4, true),
+            img => img.invert(),
+        }
         Ok(img.into())
     }
 }
@@ -0,0 +1,93 @@
+use std::f32::consts::PI;
+
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView, Rgba};
+
+use super::warp::{Interpolation, reflect, warp};
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Point};
+
+pub struct KaleidoscopeFilter {
+    pub wedges: u32,
+    /// Rotation of the sampled wedge, in degrees
+    pub rotation: f32,
+    /// Center of symmetry, relative to image size
+    pub center: Point,
+    pub interpolation: Interpolation,
+}
+
+impl AugeFilter for KaleidoscopeFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let cx = self.center.0 * width as f32;
+        let cy = self.center.1 * height as f32;
+        let rotation = self.rotation.to_radians();
+        let wedge = 2.0 * PI / self.wedges.max(1) as f32;
+
+        let out = warp(
+            &src,
+            width,
+            height,
+            self.interpolation,
+            Rgba([0, 0, 0, 0]),
+            |x, y| {
+                let (dx, dy) = (x - cx, y - cy);
+                let radius = (dx * dx + dy * dy).sqrt();
+                // Fold the angle into the first half-wedge, mirroring every other one
+                let mut angle = (dy.atan2(dx) - rotation).rem_euclid(wedge);
+                if angle > wedge / 2.0 {
+                    angle = wedge - angle;
+                }
+                let angle = angle + rotation;
+                let sx = reflect(cx + radius * angle.cos(), width);
+                let sy = reflect(cy + radius * angle.sin(), height);
+                Some((sx, sy))
+            },
+        );
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum MirrorMode {
+    /// Copy the left half mirrored over the right half
+    LeftOntoRight,
+    /// Copy the right half mirrored over the left half
+    RightOntoLeft,
+    /// Copy the top half mirrored over the bottom half
+    TopOntoBottom,
+    /// Copy the bottom half mirrored over the top half
+    BottomOntoTop,
+}
+
+pub struct MirrorFilter {
+    pub mode: MirrorMode,
+}
+
+impl AugeFilter for MirrorFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let (width, height) = img.dimensions();
+        let mut out = img.to_rgba8();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (mx, my) = (width - 1 - x, height - 1 - y);
+                let source = match self.mode {
+                    MirrorMode::LeftOntoRight if x > mx => Some((mx, y)),
+                    MirrorMode::RightOntoLeft if x < mx => Some((mx, y)),
+                    MirrorMode::TopOntoBottom if y > my => Some((x, my)),
+                    MirrorMode::BottomOntoTop if y < my => Some((x, my)),
+                    _ => None,
+                };
+                if let Some((sx, sy)) = source {
+                    let pixel = *out.get_pixel(sx, sy);
+                    out.put_pixel(x, y, pixel);
+                }
+            }
+        }
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
@@ -0,0 +1,86 @@
+use clap::ValueEnum;
+use image::{DynamicImage, Rgba};
+
+use super::warp::{Interpolation, warp};
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Point};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LensMode {
+    /// Add distortion to a straight image, as a real lens would
+    Simulate,
+    /// Remove distortion described by the coefficients
+    Correct,
+}
+
+/// Radial (Brown-Conrady) lens distortion with two coefficients.
+///
+/// Radii are normalized to half of the image diagonal, so the same coefficients
+/// behave alike regardless of resolution. Negative `k1` gives barrel, positive pincushion.
+pub struct LensFilter {
+    pub mode: LensMode,
+    pub k1: f32,
+    pub k2: f32,
+    /// Optical center, relative to image size
+    pub center: Point,
+    pub interpolation: Interpolation,
+}
+
+impl LensFilter {
+    /// Distorted radius for an undistorted one
+    fn distort(&self, r: f32) -> f32 {
+        let r2 = r * r;
+        r * (1.0 + self.k1 * r2 + self.k2 * r2 * r2)
+    }
+
+    /// Undistorted radius for a distorted one, `None` where the model folds over
+    fn undistort(&self, rd: f32) -> Option<f32> {
+        let mut r = rd;
+        for _ in 0..20 {
+            let r2 = r * r;
+            let slope = 1.0 + 3.0 * self.k1 * r2 + 5.0 * self.k2 * r2 * r2;
+            if slope <= f32::EPSILON {
+                return None;
+            }
+            let step = (self.distort(r) - rd) / slope;
+            r -= step;
+            if step.abs() < 1e-6 {
+                break;
+            }
+        }
+        (r >= 0.0 && (self.distort(r) - rd).abs() < 1e-3).then_some(r)
+    }
+}
+
+impl AugeFilter for LensFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let cx = self.center.0 * width as f32;
+        let cy = self.center.1 * height as f32;
+        let norm = (width as f32).hypot(height as f32).max(1.0) / 2.0;
+
+        let out = warp(
+            &src,
+            width,
+            height,
+            self.interpolation,
+            Rgba([0, 0, 0, 0]),
+            |x, y| {
+                let (dx, dy) = ((x - cx) / norm, (y - cy) / norm);
+                let r = (dx * dx + dy * dy).sqrt();
+                if r == 0.0 {
+                    return Some((x, y));
+                }
+                let source_r = match self.mode {
+                    LensMode::Simulate => self.undistort(r)?,
+                    LensMode::Correct => self.distort(r),
+                };
+                let scale = source_r / r;
+                Some((cx + dx * scale * norm, cy + dy * scale * norm))
+            },
+        );
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
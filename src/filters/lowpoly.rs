@@ -0,0 +1,156 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use image::{DynamicImage, Rgba, RgbaImage};
+use spade::{DelaunayTriangulation, Point2, Triangulation};
+
+use super::points::sample_points;
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum LowpolyOutput {
+    Raster,
+    /// One filled polygon per triangle
+    Svg,
+}
+
+/// Delaunay triangulation over edge-weighted points, every triangle filled with its mean color.
+pub struct LowpolyFilter {
+    pub points: usize,
+    /// 0.0 scatters points evenly, 1.0 packs them along edges
+    pub detail: f32,
+    pub seed: u64,
+    pub output: LowpolyOutput,
+}
+
+/// Corner positions in pixel-center coordinates
+pub type Triangle = [(f32, f32); 3];
+
+/// Calls `f` for every pixel whose center lies inside (or on the border of) the triangle
+pub fn for_each_pixel_in_triangle<F: FnMut(u32, u32)>(
+    corners: Triangle,
+    width: u32,
+    height: u32,
+    mut f: F,
+) {
+    let [a, b, c] = corners;
+    let area = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+    if area.abs() < f32::EPSILON {
+        return;
+    }
+    let edge = |p: (f32, f32), q: (f32, f32), x: f32, y: f32| {
+        ((q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0)) / area
+    };
+
+    let min_x = a.0.min(b.0).min(c.0).floor().max(0.0) as u32;
+    let min_y = a.1.min(b.1).min(c.1).floor().max(0.0) as u32;
+    let max_x = (a.0.max(b.0).max(c.0).ceil() as u32).min(width - 1);
+    let max_y = (a.1.max(b.1).max(c.1).ceil() as u32).min(height - 1);
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let (px, py) = (x as f32, y as f32);
+            let eps = -1e-4;
+            if edge(a, b, px, py) >= eps && edge(b, c, px, py) >= eps && edge(c, a, px, py) >= eps
+            {
+                f(x, y);
+            }
+        }
+    }
+}
+
+impl AugeFilter for LowpolyFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        if width < 2 || height < 2 {
+            return Err(AugeError::InvalidArgument(
+                "lowpoly needs an image of at least 2x2 pixels".to_string(),
+            ));
+        }
+
+        // Corners and a few border points keep the whole frame covered
+        let (right, bottom) = ((width - 1) as f32, (height - 1) as f32);
+        let mut points = vec![(0.0, 0.0), (right, 0.0), (0.0, bottom), (right, bottom)];
+        let border_steps = ((self.points as f32).sqrt() as u32 / 2).max(1);
+        for i in 1..border_steps {
+            let t = i as f32 / border_steps as f32;
+            points.extend([
+                (t * right, 0.0),
+                (t * right, bottom),
+                (0.0, t * bottom),
+                (right, t * bottom),
+            ]);
+        }
+        points.extend(sample_points(&img, self.points, self.detail, self.seed)?);
+
+        let vertices = points
+            .iter()
+            .map(|&(x, y)| Point2::new(x as f64, y as f64))
+            .collect();
+        let triangulation = DelaunayTriangulation::<Point2<f64>>::bulk_load(vertices)
+            .map_err(|err| AugeError::InvalidArgument(format!("triangulation failed: {}", err)))?;
+
+        let triangles: Vec<(Triangle, Rgba<u8>)> = triangulation
+            .inner_faces()
+            .map(|face| {
+                let corners = face.positions().map(|p| (p.x as f32, p.y as f32));
+                let mut sum = [0u64; 4];
+                let mut count = 0u64;
+                for_each_pixel_in_triangle(corners, width, height, |x, y| {
+                    let p = src.get_pixel(x, y);
+                    for c in 0..4 {
+                        sum[c] += p[c] as u64;
+                    }
+                    count += 1;
+                });
+                let color = if count == 0 {
+                    let (cx, cy) = (
+                        (corners[0].0 + corners[1].0 + corners[2].0) / 3.0,
+                        (corners[0].1 + corners[1].1 + corners[2].1) / 3.0,
+                    );
+                    *src.get_pixel(cx.round() as u32, cy.round() as u32)
+                } else {
+                    Rgba(sum.map(|s| (s / count) as u8))
+                };
+                (corners, color)
+            })
+            .collect();
+
+        match self.output {
+            LowpolyOutput::Raster => {
+                let mut out = RgbaImage::new(width, height);
+                for (corners, color) in &triangles {
+                    for_each_pixel_in_triangle(*corners, width, height, |x, y| {
+                        out.put_pixel(x, y, *color);
+                    });
+                }
+                Ok(DynamicImage::ImageRgba8(out).into())
+            }
+            LowpolyOutput::Svg => {
+                // Pixel centers sit at +0.5 in SVG user space, the stroke hides hairline gaps
+                let mut svg = format!(
+                    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+                    w = width,
+                    h = height
+                );
+                for (corners, color) in &triangles {
+                    let points = corners
+                        .iter()
+                        .map(|(x, y)| format!("{:.2},{:.2}", x + 0.5, y + 0.5))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let hex = format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]);
+                    let opacity = color[3] as f32 / 255.0;
+                    let _ = writeln!(
+                        svg,
+                        "<polygon points=\"{}\" fill=\"{hex}\" stroke=\"{hex}\" stroke-width=\"0.5\" stroke-linejoin=\"round\" opacity=\"{:.3}\"/>",
+                        points, opacity
+                    );
+                }
+                svg.push_str("</svg>\n");
+                Ok(FilterResult::Text(svg))
+            }
+        }
+    }
+}
@@ -1,27 +1,100 @@
+pub mod alpha_bbox;
+pub mod annotations;
+pub mod augment;
+pub mod bench;
+pub mod bilateral;
+pub mod blend;
 pub mod blob_detect;
+pub mod cartoon;
+pub mod carve;
+pub mod chroma_view;
+pub mod clut;
+pub mod colormap;
+pub mod compare;
+pub mod contrast_check;
+pub mod crop;
+pub mod crossfade;
+pub mod detail;
+pub mod diff;
+pub mod distort;
 pub mod dotart;
+pub mod dotart_anim;
+pub mod dropshadow;
 pub mod dynthres;
 pub mod edge;
+pub mod exec;
+pub mod expr;
+pub mod fft;
+pub mod flatten;
+pub mod flip;
+pub mod font;
 pub mod gblur;
 pub mod grayscale;
+pub mod grid_overlay;
+pub mod guided;
+pub mod info;
+pub mod inpaint;
 pub mod invert;
+pub mod histcompare;
+pub mod kaleidoscope;
+pub mod lens;
+pub mod lowpoly;
+pub mod montage;
+pub mod ninepatch;
+pub mod offset;
+pub mod overlay;
+pub mod pad_multiple;
+pub mod param_map;
+pub mod pipeline;
+#[cfg(feature = "wasm")]
+pub mod plugin;
+pub mod play;
+pub mod plotter;
+pub mod points;
+pub mod polar;
+pub mod preset;
+pub mod pyramid;
 pub mod resize;
+pub mod retinex;
+pub mod rotate;
+pub mod roundcorners;
+pub mod sample_crops;
+pub mod saturate;
+#[cfg(feature = "script")]
+pub mod script;
+pub mod seamless;
+pub mod seamless_clone;
 pub mod sepia;
+pub mod shrink_retina;
+pub mod sketch;
+pub mod sprite;
+pub mod stack_merge;
+pub mod trace;
+pub mod transform;
+pub mod visual_diff;
+pub mod voronoi;
+pub mod warp;
 
+use clap::Parser;
 use dotart::DotartFilter;
+use annotations::annotated;
 use edge::EdgeFilter;
-use image::{DynamicImage, Rgb};
+use image::{DynamicImage, Frame, Rgb};
 use invert::InvertFilter;
+use param_map::ParamMap;
+use rayon::prelude::*;
 use sepia::SepiaFilter;
 
 use crate::{
-    Command,
-    types::{AugeError, Color},
+    Command, FrameCommand, GenerateKind, PlotterArgs, PresetAction, SpriteAction,
+    inout::{load_frames, load_image},
+    types::{AugeError, Color, ResizeFilterType},
 };
 
 pub enum FilterResult {
     Image(DynamicImage),
     Text(String),
+    Frames(Vec<Frame>),
 }
 
 impl From<DynamicImage> for FilterResult {
@@ -42,11 +115,70 @@ impl AugeFilter for NoOpFilter {
     }
 }
 
+/// Runs `f` on the rows of `samples` across all cores, each row being `row_len` samples
+/// long. `f` gets the row index and the row, rows are independent of each other.
+pub fn par_rows<T, F>(samples: &mut [T], row_len: usize, f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync + Send,
+{
+    if row_len == 0 {
+        return;
+    }
+    samples
+        .par_chunks_mut(row_len)
+        .enumerate()
+        .for_each(|(y, row)| f(y, row));
+}
+
+/// Filter for a command given as arguments after `--`, `None` when there are none.
+/// `name` stands in for the program name in parse errors.
+pub fn filter_from_args(
+    name: &str,
+    args: Vec<String>,
+) -> Result<Option<Box<dyn AugeFilter>>, AugeError> {
+    if args.is_empty() {
+        return Ok(None);
+    }
+    let args = std::iter::once(name.to_string()).chain(args);
+    let command = FrameCommand::try_parse_from(args)
+        .map_err(|e| AugeError::InvalidArgument(e.to_string()))?
+        .command;
+    if !command.needs_input() {
+        return Err(AugeError::InvalidArgument(format!(
+            "the command {} runs must take an image, generators take none",
+            name
+        )));
+    }
+    Ok(Some(filter_from_command(command)?))
+}
+
+/// Plotter settings from the arguments shared by commands with plotter output
+fn plotter_settings(args: PlotterArgs) -> plotter::Plotter {
+    plotter::Plotter {
+        paper: args.paper,
+        margin: args.margin,
+        feed_rate: args.feed_rate,
+        pen_up: args.pen_up,
+        pen_down: args.pen_down,
+    }
+}
+
 pub fn filter_from_command(cmd: Command) -> Result<Box<dyn AugeFilter>, AugeError> {
     match cmd {
         Command::View => Ok(Box::new(NoOpFilter)),
         Command::Grayscale => Ok(Box::new(grayscale::GrayscaleFilter)),
-        Command::GBlur { sigma, fast } => Ok(Box::new(gblur::GBlurFilter { sigma, fast })),
+        Command::GBlur {
+            sigma,
+            fast,
+            fft,
+            param_map,
+        } => Ok(Box::new(gblur::GBlurFilter {
+            sigma,
+            fast,
+            fft,
+            param_map: param_map.as_deref().map(ParamMap::load).transpose()?,
+        })),
         Command::Dotart {
             output,
             scale,
@@ -54,6 +186,17 @@ pub fn filter_from_command(cmd: Command) -> Result<Box<dyn AugeFilter>, AugeErro
             upper_percentile,
             dot_color,
             bg_color,
+            shape,
+            size_mode,
+            min_radius,
+            max_radius,
+            radius,
+            spacing,
+            jitter,
+            seed,
+            json_schema,
+            normalized,
+            plotter,
         } => Ok(Box::new(DotartFilter {
             output,
             scale,
@@ -61,42 +204,749 @@ pub fn filter_from_command(cmd: Command) -> Result<Box<dyn AugeFilter>, AugeErro
             upper_percentile,
             dot_color,
             bg_color,
+            thresholds: None,
+            shape,
+            size_mode,
+            min_radius,
+            max_radius,
+            radius,
+            spacing,
+            jitter,
+            seed,
+            json_schema,
+            normalized,
+            plotter: plotter_settings(plotter),
         })),
         Command::Dynthres {
             lower_percentile,
             upper_percentile,
+            auto,
             dark_color,
             mid_color,
             bright_color,
-        } => Ok(Box::new(dynthres::DynamicThresholdFilter {
-            lower_percentile,
-            upper_percentile,
-            color_black: dark_color.unwrap_or(Color(Rgb::from([0u8; 3]))).0,
-            color_white: bright_color.unwrap_or(Color(Rgb::from([255u8; 3]))).0,
-            color_mid: mid_color.unwrap_or(Color(Rgb::from([127u8; 3]))).0,
-        })),
+            levels,
+            colors,
+            per_channel,
+            output,
+            mask_from,
+        } => {
+            let color_black = dark_color.unwrap_or(Color(Rgb::from([0u8; 3]))).working();
+            let color_white = bright_color
+                .unwrap_or(Color(Rgb::from([255u8; 3])))
+                .working();
+            let levels = match levels {
+                Some(n) => dynthres::ramp(color_black, color_white, n),
+                None => colors.iter().map(Color::working).collect(),
+            };
+            Ok(Box::new(dynthres::DynamicThresholdFilter {
+                lower_percentile: lower_percentile.unwrap_or_default(),
+                upper_percentile: upper_percentile.unwrap_or_default(),
+                auto,
+                thresholds: None,
+                color_black,
+                color_white,
+                color_mid: mid_color.unwrap_or(Color(Rgb::from([127u8; 3]))).working(),
+                levels,
+                per_channel,
+                output,
+                mask_from,
+            }))
+        }
         Command::Resize {
             target,
             exact,
             filter,
-        } => Ok(Box::new(resize::ResizeFilter {
-            target,
-            exact,
-            filter: filter.into(),
-        })),
+            annotations,
+        } => Ok(annotated(
+            resize::ResizeFilter {
+                target,
+                exact,
+                perceptual: matches!(filter, ResizeFilterType::Perceptual),
+                filter: filter.into(),
+            },
+            annotations.annotations,
+            annotations.annotations_out,
+        )),
+        Command::Crop {
+            origin,
+            size,
+            annotations,
+        } => Ok(annotated(
+            crop::CropFilter { origin, size },
+            annotations.annotations,
+            annotations.annotations_out,
+        )),
+        Command::Flip {
+            direction,
+            annotations,
+        } => Ok(annotated(
+            flip::FlipFilter { direction },
+            annotations.annotations,
+            annotations.annotations_out,
+        )),
+        Command::Rotate {
+            angle,
+            interpolation,
+            background,
+            autocrop,
+            annotations,
+        } => Ok(annotated(
+            rotate::RotateFilter {
+                angle,
+                interpolation,
+                background: background.map(|color| {
+                    let rgb = color.working();
+                    image::Rgba([rgb[0], rgb[1], rgb[2], 255])
+                }),
+                autocrop,
+            },
+            annotations.annotations,
+            annotations.annotations_out,
+        )),
         Command::Invert => Ok(Box::new(InvertFilter)),
+        Command::Saturate { amount, param_map } => Ok(Box::new(saturate::SaturateFilter {
+            amount,
+            param_map: param_map.as_deref().map(ParamMap::load).transpose()?,
+        })),
         Command::Sepia => Ok(Box::new(SepiaFilter)),
-        Command::Edge => Ok(Box::new(EdgeFilter)),
+        Command::Edge {
+            algorithm,
+            operator,
+            sigma,
+            low,
+            high,
+            normalize,
+            threshold,
+            param_map,
+            color,
+            overlay,
+        } => Ok(Box::new(EdgeFilter {
+            algorithm,
+            operator,
+            sigma,
+            low,
+            high,
+            normalize,
+            threshold,
+            param_map: param_map.as_deref().map(ParamMap::load).transpose()?,
+            color: color.map(|c| c.working()),
+            overlay,
+        })),
+        Command::FftView => Ok(Box::new(fft::FftViewFilter)),
+        Command::FreqPass {
+            kind,
+            cutoff,
+            ideal,
+        } => Ok(Box::new(fft::FrequencyPassFilter {
+            kind,
+            cutoff,
+            ideal,
+        })),
+        Command::Retinex { scales, gain } => Ok(Box::new(retinex::RetinexFilter { scales, gain })),
+        Command::Clut { hald_clut } => Ok(Box::new(clut::HaldClutFilter::new(&load_image(
+            &hald_clut,
+        )?)?)),
+        Command::Generate { kind } => match kind {
+            GenerateKind::HaldClut { level } => Ok(Box::new(clut::GenerateHaldClutFilter { level })),
+        },
+        Command::Crossfade {
+            other,
+            frames,
+            delay,
+            include_ends,
+        } => Ok(Box::new(crossfade::CrossfadeFilter {
+            other: load_image(&other)?,
+            frames,
+            delay_ms: delay,
+            include_ends,
+        })),
+        Command::Kaleidoscope {
+            wedges,
+            rotation,
+            center,
+            interpolation,
+        } => Ok(Box::new(kaleidoscope::KaleidoscopeFilter {
+            wedges,
+            rotation,
+            center,
+            interpolation,
+        })),
+        Command::Mirror { mode } => Ok(Box::new(kaleidoscope::MirrorFilter { mode })),
+        Command::Polar {
+            mode,
+            center,
+            zoom,
+            interpolation,
+        } => Ok(Box::new(polar::PolarFilter {
+            mode,
+            center,
+            zoom,
+            interpolation,
+        })),
+        Command::Distort {
+            effect,
+            strength,
+            angle,
+            radius,
+            center,
+            interpolation,
+        } => Ok(Box::new(distort::DistortFilter {
+            effect,
+            strength,
+            angle,
+            radius,
+            center,
+            interpolation,
+        })),
+        Command::Dropshadow {
+            offset,
+            sigma,
+            opacity,
+            color,
+        } => Ok(Box::new(dropshadow::DropShadowFilter {
+            offset,
+            sigma,
+            opacity,
+            color: color.working(),
+        })),
+        Command::Expr { program } => Ok(Box::new(expr::ExprFilter {
+            program: expr::Program::compile(&program)?,
+        })),
+        Command::Roundcorners { radius, circle } => {
+            Ok(Box::new(roundcorners::RoundCornersFilter { radius, circle }))
+        }
+        #[cfg(feature = "script")]
+        Command::Script { file, eval } => Ok(Box::new(script::ScriptFilter {
+            source: match (file, eval) {
+                (Some(path), _) => std::fs::read_to_string(path)?,
+                (None, Some(source)) => source,
+                (None, None) => unreachable!("clap requires a script file or --eval"),
+            },
+        })),
+        Command::Overlay {
+            image,
+            gravity,
+            offset,
+            scale,
+            opacity,
+            mode,
+            blend,
+        } => Ok(Box::new(overlay::OverlayFilter {
+            image: load_image(&image)?,
+            gravity,
+            offset,
+            scale,
+            opacity,
+            mode,
+            blend,
+        })),
+        Command::Blend {
+            other,
+            mode,
+            composite,
+            opacity,
+        } => Ok(Box::new(blend::BlendFilter {
+            other: load_image(&other)?,
+            mode,
+            composite,
+            opacity,
+        })),
+        Command::SampleCrops {
+            count,
+            size,
+            seed,
+            min_entropy,
+        } => Ok(Box::new(sample_crops::SampleCropsFilter {
+            count,
+            size,
+            seed,
+            min_entropy,
+        })),
+        Command::Augment {
+            others,
+            count,
+            seed,
+            max_rotation,
+            min_crop,
+            jitter,
+            noise,
+            vertical_flips,
+            log,
+        } => Ok(Box::new(augment::AugmentFilter {
+            others,
+            count,
+            seed,
+            max_rotation,
+            min_crop,
+            jitter,
+            noise,
+            vertical_flips,
+            log,
+        })),
+        Command::Diff {
+            other,
+            tolerance,
+            output,
+            highlight,
+            stats,
+        } => Ok(Box::new(diff::DiffFilter {
+            other: load_image(&other)?,
+            tolerance,
+            output,
+            highlight: highlight.working(),
+            stats,
+        })),
+        Command::VisualDiff {
+            other,
+            threshold,
+            include_aa,
+            ignore,
+            fail_above,
+            marked,
+        } => Ok(Box::new(visual_diff::VisualDiffFilter {
+            other: load_image(&other)?,
+            threshold,
+            include_aa,
+            ignore,
+            fail_above,
+            marked,
+        })),
+        Command::Compare {
+            other,
+            metric,
+            output,
+        } => Ok(Box::new(compare::CompareFilter {
+            other: load_image(&other)?,
+            metric,
+            output,
+        })),
+        Command::PadMultiple {
+            multiple,
+            gravity,
+            mode,
+            color,
+            offsets,
+        } => Ok(Box::new(pad_multiple::PadMultipleFilter {
+            multiple,
+            gravity,
+            mode,
+            color: color.working(),
+            offsets,
+        })),
+        Command::AlphaBbox { threshold, crop } => {
+            Ok(Box::new(alpha_bbox::AlphaBboxFilter { threshold, crop }))
+        }
+        Command::Montage {
+            files,
+            columns,
+            cell,
+            padding,
+            background,
+            labels,
+        } => Ok(Box::new(montage::MontageFilter {
+            files,
+            columns,
+            cell,
+            padding,
+            background: background.working(),
+            labels,
+        })),
+        Command::Ninepatch {
+            target,
+            border,
+            tile,
+            filter,
+        } => {
+            let border = match border[..] {
+                [all] => [all; 4],
+                [horizontal, vertical] => [horizontal, vertical, horizontal, vertical],
+                [left, top, right, bottom] => [left, top, right, bottom],
+                _ => {
+                    return Err(AugeError::InvalidArgument(format!(
+                        "expected 1, 2 or 4 border values, got {}",
+                        border.len()
+                    )));
+                }
+            };
+            Ok(Box::new(ninepatch::NinePatchFilter {
+                target,
+                border,
+                tile,
+                filter: filter.into(),
+            }))
+        }
+        Command::ShrinkRetina { factor, filter, .. } => {
+            Ok(Box::new(shrink_retina::ShrinkRetinaFilter {
+                factor,
+                filter: filter.into(),
+            }))
+        }
+        Command::Sprite { action } => match action {
+            SpriteAction::Pack {
+                files,
+                columns,
+                padding,
+                atlas,
+            } => Ok(Box::new(sprite::SpritePackFilter {
+                files,
+                columns,
+                padding,
+                atlas,
+            })),
+            SpriteAction::Unpack {
+                grid,
+                padding,
+                atlas,
+            } => Ok(Box::new(sprite::SpriteUnpackFilter {
+                grid,
+                padding,
+                atlas,
+            })),
+        },
+        Command::Guided {
+            radius,
+            eps,
+            guide,
+            boost,
+        } => Ok(Box::new(guided::GuidedFilter {
+            radius,
+            eps,
+            guide: guide.as_deref().map(load_image).transpose()?,
+            boost,
+        })),
+        Command::Detail { fine, coarse } => Ok(Box::new(detail::DetailFilter { fine, coarse })),
+        Command::SeamlessClone {
+            source,
+            mask,
+            offset,
+            mixed,
+            iterations,
+        } => Ok(Box::new(seamless_clone::SeamlessCloneFilter {
+            source: load_image(&source)?,
+            mask: mask
+                .as_deref()
+                .map(|path| load_image(path).map(|mask| mask.to_luma8()))
+                .transpose()?,
+            offset,
+            mixed,
+            iterations,
+        })),
+        Command::Inpaint {
+            mask,
+            method,
+            radius,
+            iterations,
+        } => Ok(Box::new(inpaint::InpaintFilter {
+            mask: load_image(&mask)?.to_luma8(),
+            method,
+            radius,
+            iterations,
+        })),
+        Command::GridOverlay {
+            spacing,
+            thirds,
+            crosshair,
+            safe_area,
+            labels,
+            color,
+            opacity,
+            thickness,
+        } => Ok(Box::new(grid_overlay::GridOverlayFilter {
+            spacing,
+            thirds,
+            crosshair,
+            safe_areas: safe_area,
+            labels,
+            color: color.working(),
+            opacity,
+            thickness,
+        })),
+        Command::Trace {
+            threshold,
+            invert,
+            min_area,
+            tolerance,
+            corner_angle,
+            color,
+            output,
+            plotter,
+        } => Ok(Box::new(trace::TraceFilter {
+            threshold,
+            invert,
+            min_area,
+            tolerance,
+            corner_angle,
+            color: color.working(),
+            output,
+            plotter: plotter_settings(plotter),
+        })),
+        Command::ContrastCheck {
+            foreground,
+            background,
+            radius,
+        } => Ok(Box::new(contrast_check::ContrastCheckFilter {
+            foreground,
+            background,
+            radius,
+        })),
+        Command::Flatten {
+            color,
+            checker,
+            checker_color,
+        } => Ok(Box::new(flatten::FlattenFilter {
+            color: color.working(),
+            checker,
+            checker_color: checker_color.working(),
+        })),
+        Command::DotartAnim {
+            inputs,
+            style,
+            scale,
+            lower_percentile,
+            upper_percentile,
+            dot_color,
+            bg_color,
+            columns,
+            delay,
+        } => {
+            let mut frames = Vec::new();
+            for path in &inputs {
+                frames.extend(load_frames(path, delay)?);
+            }
+            Ok(Box::new(dotart_anim::DotartAnimFilter {
+                frames,
+                style,
+                scale,
+                lower_percentile,
+                upper_percentile,
+                dot_color,
+                bg_color,
+                columns,
+            }))
+        }
+        Command::Info { json, source } => Ok(Box::new(info::InfoFilter { source, json })),
+        Command::Play {
+            inputs,
+            loops,
+            speed,
+            delay,
+            width,
+            command,
+        } => {
+            let mut frames = Vec::new();
+            for path in &inputs {
+                frames.extend(load_frames(path, delay)?);
+            }
+            let filter = filter_from_args("play", command)?;
+            Ok(Box::new(play::PlayFilter {
+                frames,
+                loops,
+                speed,
+                width,
+                filter,
+            }))
+        }
+        Command::Monitor { .. } => Err(AugeError::InvalidArgument(
+            "monitor watches a directory on its own, it cannot run on an image".to_string(),
+        )),
+        Command::Run { preset, presets } => {
+            let preset = crate::preset::load_preset(&preset, presets.as_deref())?;
+            let mut steps = Vec::with_capacity(preset.steps.len());
+            for step in &preset.steps {
+                let args = step.args()?;
+                crate::preset::check_step(&args)?;
+                if let Some(filter) = filter_from_args("run", args)? {
+                    steps.push(filter);
+                }
+            }
+            Ok(Box::new(pipeline::PipelineFilter { steps }))
+        }
+        Command::Preset { action, presets } => match action {
+            PresetAction::Save {
+                name,
+                description,
+                force,
+                command,
+            } => Ok(Box::new(preset::PresetSaveFilter {
+                name,
+                description,
+                force,
+                command,
+                presets,
+            })),
+            PresetAction::List => Ok(Box::new(preset::PresetListFilter { presets })),
+            PresetAction::Show { name } => {
+                Ok(Box::new(preset::PresetShowFilter { name, presets }))
+            }
+            PresetAction::Delete { name } => {
+                Ok(Box::new(preset::PresetDeleteFilter { name, presets }))
+            }
+        },
+        #[cfg(feature = "wasm")]
+        Command::Plugin(args) => Ok(Box::new(plugin::PluginFilter::load(args)?)),
+        Command::Exec { cmd, no_header } => Ok(Box::new(exec::ExecFilter {
+            cmd,
+            header: !no_header,
+        })),
+        Command::Bench {
+            commands,
+            runs,
+            warmup,
+            generate,
+            output,
+        } => Ok(Box::new(bench::BenchFilter {
+            commands,
+            runs,
+            warmup,
+            generate,
+            output,
+        })),
+        Command::Lens {
+            mode,
+            k1,
+            k2,
+            center,
+            interpolation,
+        } => Ok(Box::new(lens::LensFilter {
+            mode,
+            k1,
+            k2,
+            center,
+            interpolation,
+        })),
+        Command::Bilateral {
+            sigma_spatial,
+            sigma_color,
+            iterations,
+        } => Ok(Box::new(bilateral::BilateralFilter {
+            sigma_spatial,
+            sigma_color,
+            iterations,
+        })),
+        Command::Cartoon {
+            smoothing,
+            levels,
+            edge_threshold,
+            edge_strength,
+        } => Ok(Box::new(cartoon::CartoonFilter {
+            smoothing,
+            levels,
+            edge_threshold,
+            edge_strength,
+        })),
+        Command::Offset { x, y, half } => Ok(Box::new(offset::OffsetFilter { x, y, half })),
+        Command::Sketch {
+            sigma,
+            colored,
+            saturation,
+        } => Ok(Box::new(sketch::SketchFilter {
+            sigma,
+            colored,
+            saturation,
+        })),
+        Command::Seamless {
+            method,
+            blend_width,
+            preview_tiled,
+        } => Ok(Box::new(seamless::SeamlessFilter {
+            method,
+            blend_width,
+            preview_tiled,
+        })),
+        Command::Histcompare { other, bins } => Ok(Box::new(histcompare::HistCompareFilter {
+            other: load_image(&other)?,
+            bins,
+        })),
+        Command::Lowpoly {
+            output,
+            points,
+            detail,
+            seed,
+        } => Ok(Box::new(lowpoly::LowpolyFilter {
+            points,
+            detail,
+            seed,
+            output,
+        })),
+        Command::StackMerge { mode, others } => {
+            Ok(Box::new(stack_merge::StackMergeFilter { others, mode }))
+        }
+        Command::Voronoi {
+            cells,
+            detail,
+            seed,
+            border,
+            border_width,
+        } => Ok(Box::new(voronoi::VoronoiFilter {
+            cells,
+            detail,
+            seed,
+            border: border.map(|color| color.working()),
+            border_width,
+        })),
+        Command::Carve { target, protect } => Ok(Box::new(carve::CarveFilter {
+            target,
+            protect: protect.map(|path| load_image(&path)).transpose()?.map(|mask| mask.to_luma8()),
+        })),
+        Command::ChromaView { view, gain } => {
+            Ok(Box::new(chroma_view::ChromaViewFilter { view, gain }))
+        }
+        Command::Transform {
+            matrix,
+            from,
+            to,
+            fit,
+            interpolation,
+            background,
+        } => {
+            let matrix = match (matrix, from, to) {
+                (Some(values), _, _) => transform::Homography::from_values(&values)?,
+                (None, Some(from), Some(to)) => transform::Homography::from_corners(&from, &to)?,
+                _ => unreachable!("clap requires either a matrix or both corner sets"),
+            };
+            Ok(Box::new(transform::TransformFilter {
+                matrix,
+                fit,
+                interpolation,
+                background: background
+                    .map(|color| {
+                    let rgb = color.working();
+                    image::Rgba([rgb[0], rgb[1], rgb[2], 255])
+                }),
+            }))
+        }
         Command::BlobDetect {
             threshold,
             mode,
             background,
             color,
+            color_space,
+            colormap,
+            solid_color,
+            output,
+            points,
+            min_size,
+            max_size,
+            top,
+            annotate,
+            contours,
         } => Ok(Box::new(blob_detect::BlobDetectFilter {
             threshold,
+            selection: blob_detect::BlobSelection {
+                min_size,
+                max_size,
+                top: top.map(|top| top as usize),
+            },
             mode,
             background,
-            target_color: color.map(|c| c.0),
+            target_color: color.map(|c| c.working()),
+            color_space,
+            colormap,
+            solid_color: solid_color.working(),
+            output,
+            points,
+            annotate,
+            contours,
         })),
     }
 }
@@ -0,0 +1,176 @@
+use std::path::Path;
+
+use image::{DynamicImage, Rgb, Rgba, RgbaImage, imageops};
+
+use super::font::{GLYPH_HEIGHT, draw_text, text_width};
+use super::{AugeFilter, FilterResult};
+use crate::{
+    color::luma,
+    inout::load_image,
+    types::{AugeError, AutoValue, ResizeInput},
+};
+
+const LABEL_SCALE: u32 = 2;
+
+/// Places equally sized cells row by row, `padding` apart and around the border.
+/// Returns the sheet and the `[x, y, width, height]` of every cell.
+pub fn tile(
+    cells: &[RgbaImage],
+    columns: u32,
+    cell: (u32, u32),
+    padding: u32,
+    background: Rgba<u8>,
+) -> (RgbaImage, Vec<[u32; 4]>) {
+    let columns = columns.clamp(1, (cells.len() as u32).max(1));
+    let rows = (cells.len() as u32).div_ceil(columns);
+    let width = columns * cell.0 + (columns + 1) * padding;
+    let height = rows * cell.1 + (rows + 1) * padding;
+
+    let mut sheet = RgbaImage::from_pixel(width, height, background);
+    let mut rects = Vec::with_capacity(cells.len());
+    for (i, img) in cells.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let x = padding + column * (cell.0 + padding);
+        let y = padding + row * (cell.1 + padding);
+        imageops::overlay(&mut sheet, img, x as i64, y as i64);
+        rects.push([x, y, cell.0, cell.1]);
+    }
+    (sheet, rects)
+}
+
+/// Scales `img` to fit inside `width` x `height` keeping its aspect ratio, centered on a transparent canvas
+fn fit(img: &DynamicImage, width: u32, height: u32) -> RgbaImage {
+    let scale =
+        (width as f32 / img.width().max(1) as f32).min(height as f32 / img.height().max(1) as f32);
+    let w = ((img.width() as f32 * scale).round() as u32).clamp(1, width.max(1));
+    let h = ((img.height() as f32 * scale).round() as u32).clamp(1, height.max(1));
+    let resized = if (w, h) == (img.width(), img.height()) {
+        img.to_rgba8()
+    } else {
+        imageops::resize(&img.to_rgba8(), w, h, imageops::FilterType::Lanczos3)
+    };
+
+    let mut canvas = RgbaImage::new(width, height);
+    imageops::overlay(
+        &mut canvas,
+        &resized,
+        ((width - w) / 2) as i64,
+        ((height - h) / 2) as i64,
+    );
+    canvas
+}
+
+/// Shortens a label with `..` until it fits `width` pixels
+fn shorten(label: &str, width: u32) -> String {
+    if text_width(label, LABEL_SCALE) <= width {
+        return label.to_string();
+    }
+    let mut chars: Vec<char> = label.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let base: String = chars.iter().collect();
+        let candidate = format!("{}..", base.trim_end_matches('.'));
+        if text_width(&candidate, LABEL_SCALE) <= width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+/// Contact sheet of several files laid out in a grid. Reads no input of its own.
+pub struct MontageFilter {
+    pub files: Vec<String>,
+    /// Defaults to a roughly square grid
+    pub columns: Option<u32>,
+    /// Sides default to the largest box around the files, a single `auto` side keeps its aspect
+    pub cell: Option<ResizeInput>,
+    pub padding: u32,
+    pub background: Rgb<u8>,
+    /// Print the file name beneath every cell
+    pub labels: bool,
+}
+
+impl AugeFilter for MontageFilter {
+    fn apply(&self, _img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let images = self
+            .files
+            .iter()
+            .map(|path| load_image(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let largest = images.iter().fold((1, 1), |(w, h), img| {
+            (w.max(img.width()), h.max(img.height()))
+        });
+        // A single auto side follows the aspect ratio of the largest box
+        let (cell_width, cell_height) = match &self.cell {
+            Some(ResizeInput::Absolute(AutoValue::Concrete(w), AutoValue::Auto)) => (
+                *w,
+                (*w as f32 * largest.1 as f32 / largest.0 as f32).round() as u32,
+            ),
+            Some(ResizeInput::Absolute(AutoValue::Auto, AutoValue::Concrete(h))) => (
+                (*h as f32 * largest.0 as f32 / largest.1 as f32).round() as u32,
+                *h,
+            ),
+            Some(cell) => cell.size_keeping_auto(largest.0, largest.1),
+            None => largest,
+        };
+        if cell_width == 0 || cell_height == 0 {
+            return Err(AugeError::InvalidArgument(
+                "cells must be at least one pixel wide and high".to_string(),
+            ));
+        }
+
+        // Labels live in a strip at the bottom of each cell so the grid stays uniform
+        let label_height = if self.labels {
+            (GLYPH_HEIGHT + 4) * LABEL_SCALE
+        } else {
+            0
+        };
+        let cells: Vec<RgbaImage> = images
+            .iter()
+            .map(|img| {
+                let mut cell = RgbaImage::new(cell_width, cell_height + label_height);
+                imageops::overlay(&mut cell, &fit(img, cell_width, cell_height), 0, 0);
+                cell
+            })
+            .collect();
+
+        let columns = self
+            .columns
+            .unwrap_or_else(|| (cells.len() as f64).sqrt().ceil() as u32);
+        let [r, g, b] = self.background.0;
+        let (mut sheet, rects) = tile(
+            &cells,
+            columns,
+            (cell_width, cell_height + label_height),
+            self.padding,
+            Rgba([r, g, b, 255]),
+        );
+
+        if self.labels {
+            let ink = if luma([r, g, b].map(f32::from)) > 127.5 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            };
+            for (path, [x, y, w, h]) in self.files.iter().zip(rects) {
+                let name = Path::new(path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.clone());
+                let label = shorten(&name, w);
+                let left = x + (w - text_width(&label, LABEL_SCALE)) / 2;
+                let top = y + h - label_height + 2 * LABEL_SCALE;
+                draw_text(
+                    &mut sheet,
+                    left as i64,
+                    top as i64,
+                    &label,
+                    LABEL_SCALE,
+                    ink,
+                );
+            }
+        }
+
+        Ok(DynamicImage::ImageRgba8(sheet).into())
+    }
+}
@@ -0,0 +1,68 @@
+use image::{DynamicImage, RgbaImage, imageops};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, ResizeInput};
+
+/// 9-slice scaling: corners keep their size, edges stretch along one axis and
+/// the center along both, so frames and UI chrome resize without distortion.
+pub struct NinePatchFilter {
+    pub target: ResizeInput,
+    /// Unscaled margins as `[left, top, right, bottom]`
+    pub border: [u32; 4],
+    /// Repeat edges and center instead of stretching them
+    pub tile: bool,
+    pub filter: imageops::FilterType,
+}
+
+/// Splits `size` into start, middle and end spans. Margins that do not fit are
+/// shrunk proportionally, since nothing else can give way.
+fn spans(size: u32, start: u32, end: u32) -> [(u32, u32); 3] {
+    let (start, end) = if start + end > size {
+        let scale = size as f32 / (start + end) as f32;
+        let start = (start as f32 * scale).round() as u32;
+        (start, size - start)
+    } else {
+        (start, end)
+    };
+    [(0, start), (start, size - start - end), (size - end, end)]
+}
+
+impl AugeFilter for NinePatchFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let (target_width, target_height) = self.target.size_keeping_auto(width, height);
+        let [left, top, right, bottom] = self.border;
+        if left + right > width || top + bottom > height {
+            return Err(AugeError::InvalidArgument(format!(
+                "borders {},{},{},{} do not fit into a {}x{} image",
+                left, top, right, bottom, width, height
+            )));
+        }
+
+        let (src_columns, src_rows) = (spans(width, left, right), spans(height, top, bottom));
+        let (dst_columns, dst_rows) = (
+            spans(target_width, left, right),
+            spans(target_height, top, bottom),
+        );
+
+        let mut out = RgbaImage::new(target_width, target_height);
+        for (&(sy, sh), &(dy, dh)) in src_rows.iter().zip(&dst_rows) {
+            for (&(sx, sw), &(dx, dw)) in src_columns.iter().zip(&dst_columns) {
+                if sw == 0 || sh == 0 || dw == 0 || dh == 0 {
+                    continue;
+                }
+                let patch = imageops::crop_imm(&src, sx, sy, sw, sh).to_image();
+                let patch = if (sw, sh) == (dw, dh) {
+                    patch
+                } else if self.tile {
+                    RgbaImage::from_fn(dw, dh, |x, y| *patch.get_pixel(x % sw, y % sh))
+                } else {
+                    imageops::resize(&patch, dw, dh, self.filter)
+                };
+                imageops::replace(&mut out, &patch, dx as i64, dy as i64);
+            }
+        }
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
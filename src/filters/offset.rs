@@ -0,0 +1,36 @@
+use image::{DynamicImage, GenericImageView};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Shifts the image with wraparound: whatever leaves one edge comes back on the opposite one.
+pub struct OffsetFilter {
+    pub x: i64,
+    pub y: i64,
+    /// Shift by half of the image size, moving the tile seams to the center
+    pub half: bool,
+}
+
+impl AugeFilter for OffsetFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return Ok(img.into());
+        }
+
+        let (dx, dy) = if self.half {
+            ((width / 2) as i64, (height / 2) as i64)
+        } else {
+            (self.x, self.y)
+        };
+        let dx = dx.rem_euclid(width as i64) as u32;
+        let dy = dy.rem_euclid(height as i64) as u32;
+
+        let src = img.to_rgba8();
+        let out = image::RgbaImage::from_fn(width, height, |x, y| {
+            *src.get_pixel((x + width - dx) % width, (y + height - dy) % height)
+        });
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
@@ -0,0 +1,106 @@
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView, Luma, imageops};
+
+use super::blend::{BlendMode, Composite, blend_onto};
+use super::pyramid::{Plane, pyramid_blend};
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Point};
+
+/// Where the overlay is anchored on the base image
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Gravity {
+    NorthWest,
+    North,
+    NorthEast,
+    West,
+    Center,
+    East,
+    SouthWest,
+    South,
+    SouthEast,
+}
+
+impl Gravity {
+    /// Horizontal and vertical anchor as a fraction of the free space, 0 is left or top
+    pub fn anchor(self) -> (f32, f32) {
+        match self {
+            Gravity::NorthWest => (0.0, 0.0),
+            Gravity::North => (0.5, 0.0),
+            Gravity::NorthEast => (1.0, 0.0),
+            Gravity::West => (0.0, 0.5),
+            Gravity::Center => (0.5, 0.5),
+            Gravity::East => (1.0, 0.5),
+            Gravity::SouthWest => (0.0, 1.0),
+            Gravity::South => (0.5, 1.0),
+            Gravity::SouthEast => (1.0, 1.0),
+        }
+    }
+}
+
+/// How the edges of the overlay meet the base image
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SeamBlend {
+    /// Plain alpha compositing
+    Hard,
+    /// Multi-band blending, the seam fades over a width that grows with the feature size
+    Pyramid,
+}
+
+/// Composites a second image over the input, e.g. a logo or a watermark.
+pub struct OverlayFilter {
+    pub image: DynamicImage,
+    pub gravity: Gravity,
+    /// Distance from the anchored edges in pixels, pointing inwards
+    pub offset: Point,
+    /// Size of the overlay relative to its own size
+    pub scale: f32,
+    /// Overlay opacity in `0..=1`, multiplied with its own alpha
+    pub opacity: f32,
+    pub mode: BlendMode,
+    pub blend: SeamBlend,
+}
+
+impl AugeFilter for OverlayFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut base = img.to_rgba8();
+
+        let scale = self.scale.max(0.0);
+        let width = (self.image.width() as f32 * scale).round() as u32;
+        let height = (self.image.height() as f32 * scale).round() as u32;
+        if width == 0 || height == 0 {
+            return Ok(DynamicImage::ImageRgba8(base).into());
+        }
+        let top = if (width, height) == self.image.dimensions() {
+            self.image.to_rgba8()
+        } else {
+            imageops::resize(&self.image.to_rgba8(), width, height, imageops::FilterType::Lanczos3)
+        };
+
+        // Offsets push away from the anchored edge, centered axes move right and down
+        let (ax, ay) = self.gravity.anchor();
+        let inwards = |anchor: f32| if anchor == 1.0 { -1.0 } else { 1.0 };
+        let x = (base.width() as f32 - width as f32) * ax + self.offset.0 * inwards(ax);
+        let y = (base.height() as f32 - height as f32) * ay + self.offset.1 * inwards(ay);
+        let (x, y) = (x.round() as i64, y.round() as i64);
+        let SeamBlend::Pyramid = self.blend else {
+            blend_onto(&mut base, &top, x, y, self.mode, Composite::Over, self.opacity);
+            return Ok(DynamicImage::ImageRgba8(base).into());
+        };
+
+        // The hard composite at full strength is the target, the pyramid only softens its edges
+        let mut target = base.clone();
+        blend_onto(&mut target, &top, x, y, self.mode, Composite::Over, 1.0);
+        let opacity = self.opacity.clamp(0.0, 1.0);
+        let mask = Plane::from_fn(base.width(), base.height(), |bx, by| {
+            let (tx, ty) = (bx as i64 - x, by as i64 - y);
+            if tx < 0 || ty < 0 || tx >= width as i64 || ty >= height as i64 {
+                return Luma([0.0]);
+            }
+            Luma([top.get_pixel(tx as u32, ty as u32)[3] as f32 / 255.0 * opacity])
+        });
+        // Bands coarser than the overlay itself would spread it over the whole base
+        let levels = (width.min(height) as f32 / 8.0).log2().clamp(1.0, 8.0) as u32;
+        base = pyramid_blend(&base, &target, &mask, levels);
+        Ok(DynamicImage::ImageRgba8(base).into())
+    }
+}
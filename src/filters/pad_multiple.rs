@@ -0,0 +1,98 @@
+use clap::ValueEnum;
+use image::{DynamicImage, Rgb, Rgba, RgbaImage};
+use serde::Serialize;
+
+use super::overlay::Gravity;
+use super::warp::reflect;
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// What fills the added border
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum PadMode {
+    /// A solid color
+    Constant,
+    /// Repeat the outermost pixels
+    Edge,
+    /// Mirror the image at its borders
+    Reflect,
+}
+
+/// Where the original pixels ended up, enough to crop the padding off again
+#[derive(Debug, Serialize)]
+pub struct Padding {
+    pub width: u32,
+    pub height: u32,
+    pub padded_width: u32,
+    pub padded_height: u32,
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+/// Grows the canvas until both sides are multiples of `multiple`, as networks with
+/// strided layers and block based codecs expect.
+pub struct PadMultipleFilter {
+    pub multiple: u32,
+    pub gravity: Gravity,
+    pub mode: PadMode,
+    pub color: Rgb<u8>,
+    /// Write the padding amounts to this JSON file
+    pub offsets: Option<String>,
+}
+
+impl AugeFilter for PadMultipleFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        if self.multiple == 0 {
+            return Err(AugeError::InvalidArgument(
+                "multiple must be at least 1".to_string(),
+            ));
+        }
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let padded_width = width.div_ceil(self.multiple) * self.multiple;
+        let padded_height = height.div_ceil(self.multiple) * self.multiple;
+
+        let (ax, ay) = self.gravity.anchor();
+        let left = ((padded_width - width) as f32 * ax).round() as u32;
+        let top = ((padded_height - height) as f32 * ay).round() as u32;
+        let padding = Padding {
+            width,
+            height,
+            padded_width,
+            padded_height,
+            left,
+            top,
+            right: padded_width - width - left,
+            bottom: padded_height - height - top,
+        };
+
+        let [r, g, b] = self.color.0;
+        let out = RgbaImage::from_fn(padded_width, padded_height, |x, y| {
+            let (sx, sy) = (x as i64 - left as i64, y as i64 - top as i64);
+            if (0..width as i64).contains(&sx) && (0..height as i64).contains(&sy) {
+                return *src.get_pixel(sx as u32, sy as u32);
+            }
+            if width == 0 || height == 0 {
+                return Rgba([r, g, b, 255]);
+            }
+            match self.mode {
+                PadMode::Constant => Rgba([r, g, b, 255]),
+                PadMode::Edge => *src.get_pixel(
+                    sx.clamp(0, width as i64 - 1) as u32,
+                    sy.clamp(0, height as i64 - 1) as u32,
+                ),
+                PadMode::Reflect => *src.get_pixel(
+                    reflect(sx as f32, width) as u32,
+                    reflect(sy as f32, height) as u32,
+                ),
+            }
+        });
+
+        if let Some(path) = &self.offsets {
+            std::fs::write(path, serde_json::to_string_pretty(&padding)?)?;
+        }
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
@@ -0,0 +1,28 @@
+use image::{GrayImage, imageops};
+
+use crate::{inout::load_image, types::AugeError};
+
+/// Grayscale image that drives a filter parameter per pixel instead of a constant.
+///
+/// Black means a weight of 0 and white a weight of 1. The map is stretched to the
+/// size of the image it is applied to, so a small gradient is enough for graduated
+/// or radial adjustments.
+pub struct ParamMap(GrayImage);
+
+impl ParamMap {
+    pub fn load(path: &str) -> Result<Self, AugeError> {
+        Ok(Self(load_image(path)?.to_luma8()))
+    }
+
+    /// Row-major weights in `0..=1` for an image of the given size
+    pub fn weights(&self, width: u32, height: u32) -> Vec<f32> {
+        let resized;
+        let map = if self.0.dimensions() == (width, height) {
+            &self.0
+        } else {
+            resized = imageops::resize(&self.0, width, height, imageops::FilterType::Triangle);
+            &resized
+        };
+        map.pixels().map(|p| p[0] as f32 / 255.0).collect()
+    }
+}
@@ -0,0 +1,26 @@
+use image::DynamicImage;
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Runs commands one after another, every step gets the image of the one before it.
+/// Only the last step may output text or frames.
+pub struct PipelineFilter {
+    pub steps: Vec<Box<dyn AugeFilter>>,
+}
+
+impl AugeFilter for PipelineFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut result = FilterResult::Image(img);
+        for (index, step) in self.steps.iter().enumerate() {
+            let FilterResult::Image(img) = result else {
+                return Err(AugeError::InvalidArgument(format!(
+                    "step {} outputs no image for the steps after it, only the last one may",
+                    index
+                )));
+            };
+            result = step.apply(img)?;
+        }
+        Ok(result)
+    }
+}
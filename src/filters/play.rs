@@ -0,0 +1,105 @@
+use std::io::{Write, stdout};
+use std::time::{Duration, Instant};
+
+use image::{DynamicImage, Frame};
+
+use super::{AugeFilter, FilterResult};
+use crate::color;
+use crate::progress::{self, Progress};
+use crate::inout::{previews, show_frame};
+use crate::types::{AugeError, EncodableFormats};
+
+/// Plays an animation in the terminal with its frame timing, running an optional filter on
+/// every frame as it is first shown. Piped output gets the filtered frames instead.
+pub struct PlayFilter {
+    pub frames: Vec<Frame>,
+    /// Times to play, 0 loops until interrupted
+    pub loops: u32,
+    /// Playback rate, 2 plays twice as fast
+    pub speed: f32,
+    /// Width in terminal columns, fits the terminal when unset
+    pub width: Option<u32>,
+    pub filter: Option<Box<dyn AugeFilter>>,
+}
+
+impl PlayFilter {
+    fn filtered(&self, frame: &Frame) -> Result<Frame, AugeError> {
+        let Some(filter) = &self.filter else {
+            return Ok(frame.clone());
+        };
+        // Frames are in the working space since decoding, main takes them back out
+        match filter.apply(DynamicImage::ImageRgba8(frame.buffer().clone()))? {
+            FilterResult::Image(img) => Ok(Frame::from_parts(
+                img.to_rgba8(),
+                frame.left(),
+                frame.top(),
+                frame.delay(),
+            )),
+            FilterResult::Text(_) | FilterResult::Frames(_) => Err(AugeError::InvalidArgument(
+                "play needs a command that outputs one image per frame".to_string(),
+            )),
+        }
+    }
+
+    fn delay(&self, frame: &Frame) -> Result<Duration, AugeError> {
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let secs = numer as f32 / denom.max(1) as f32 / 1000.0 / self.speed;
+        Duration::try_from_secs_f32(secs).map_err(|_| {
+            AugeError::InvalidArgument(format!(
+                "speed {} stretches a frame to {} seconds",
+                self.speed, secs
+            ))
+        })
+    }
+}
+
+impl AugeFilter for PlayFilter {
+    fn apply(&self, _img: DynamicImage) -> Result<FilterResult, AugeError> {
+        if self.frames.is_empty() {
+            return Err(AugeError::InvalidArgument("no frames to play".to_string()));
+        }
+        if self.speed.is_nan() || self.speed <= 0.0 {
+            return Err(AugeError::InvalidArgument(format!(
+                "speed must be above 0, got {}",
+                self.speed
+            )));
+        }
+        if !previews() {
+            let bar = progress::bar("frames", self.frames.len() as u64);
+            let frames = self
+                .frames
+                .iter()
+                .map(|frame| {
+                    let frame = self.filtered(frame)?;
+                    bar.advance(1);
+                    Ok(frame)
+                })
+                .collect::<Result<Vec<_>, AugeError>>()?;
+            return Ok(FilterResult::Frames(frames));
+        }
+
+        // Filtered frames are kept after the first pass, later loops only draw them
+        let mut shown: Vec<DynamicImage> = Vec::with_capacity(self.frames.len());
+        print!("\x1b[2J");
+        let mut due = Instant::now();
+        let mut pass = 0;
+        while self.loops == 0 || pass < self.loops {
+            for (index, frame) in self.frames.iter().enumerate() {
+                if index == shown.len() {
+                    let frame = self.filtered(frame)?;
+                    let img = DynamicImage::ImageRgba8(frame.into_buffer());
+                    shown.push(color::from_working_space(img, &EncodableFormats::Gif, None));
+                }
+                show_frame(&shown[index], self.width)?;
+                // Deadlines add up from the start, so slow frames do not stretch the animation
+                due = due.checked_add(self.delay(frame)?).ok_or_else(|| {
+                    AugeError::InvalidArgument(format!("speed {} is too slow", self.speed))
+                })?;
+                std::thread::sleep(due.saturating_duration_since(Instant::now()));
+            }
+            pass += 1;
+        }
+        stdout().flush()?;
+        Ok(FilterResult::Frames(Vec::new()))
+    }
+}
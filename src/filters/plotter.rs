@@ -0,0 +1,121 @@
+use std::fmt::Write;
+
+use crate::types::{AugeError, Paper};
+
+/// HPGL plotter units per millimetre
+const HPGL_UNITS: f64 = 40.0;
+
+/// Turns polylines in image pixels into pen plotter toolpaths. The drawing is scaled to fit
+/// the paper inside the margin and centered, with the origin moved to the bottom-left
+/// corner where plotters have it.
+pub struct Plotter {
+    pub paper: Paper,
+    /// Blank border on every side, in mm
+    pub margin: f64,
+    /// Drawing speed in mm per minute
+    pub feed_rate: f64,
+    /// G-code lines lifting and lowering the pen, e.g. a Z move or a servo command
+    pub pen_up: String,
+    pub pen_down: String,
+}
+
+impl Default for Plotter {
+    fn default() -> Self {
+        Self {
+            paper: Paper {
+                width: 210.0,
+                height: 297.0,
+            },
+            margin: 10.0,
+            feed_rate: 3000.0,
+            pen_up: "G0 Z5".to_string(),
+            pen_down: "G0 Z0".to_string(),
+        }
+    }
+}
+
+impl Plotter {
+    /// Maps image coordinates of a `width` by `height` drawing to millimetres on the paper
+    fn fit(&self, width: u32, height: u32) -> Result<impl Fn((f64, f64)) -> (f64, f64), AugeError> {
+        if self.feed_rate.is_nan() || self.feed_rate <= 0.0 {
+            return Err(AugeError::InvalidArgument(format!(
+                "feed rate must be positive, got {}",
+                self.feed_rate
+            )));
+        }
+        let (room_x, room_y) = (
+            self.paper.width - 2.0 * self.margin,
+            self.paper.height - 2.0 * self.margin,
+        );
+        if !(room_x > 0.0 && room_y > 0.0) || self.margin < 0.0 {
+            return Err(AugeError::InvalidArgument(format!(
+                "a margin of {} mm leaves no room on {}x{} mm paper",
+                self.margin, self.paper.width, self.paper.height
+            )));
+        }
+        let (width, height) = (width.max(1) as f64, height.max(1) as f64);
+        let scale = (room_x / width).min(room_y / height);
+        let (left, bottom) = (
+            self.margin + (room_x - width * scale) / 2.0,
+            self.margin + (room_y - height * scale) / 2.0,
+        );
+        Ok(move |(x, y): (f64, f64)| (left + x * scale, bottom + (height - y) * scale))
+    }
+
+    /// G-code in absolute millimetres, rapid moves between paths and feed moves along
+    /// them. A path of a single point is a dot, the pen just touches down.
+    pub fn gcode(&self, paths: &[Vec<(f64, f64)>], width: u32, height: u32) -> Result<String, AugeError> {
+        let to_paper = self.fit(width, height)?;
+        let mut lines = vec![
+            format!(
+                "; {} paths on {}x{} mm paper",
+                paths.len(),
+                self.paper.width,
+                self.paper.height
+            ),
+            "G21".to_string(),
+            "G90".to_string(),
+            self.pen_up.clone(),
+        ];
+        for path in paths {
+            let Some(&start) = path.first() else {
+                continue;
+            };
+            let (x, y) = to_paper(start);
+            lines.push(format!("G0 X{:.3} Y{:.3}", x, y));
+            lines.push(self.pen_down.clone());
+            for &point in &path[1..] {
+                let (x, y) = to_paper(point);
+                lines.push(format!("G1 X{:.3} Y{:.3} F{}", x, y, self.feed_rate));
+            }
+            lines.push(self.pen_up.clone());
+        }
+        lines.push("G0 X0 Y0".to_string());
+        Ok(lines.join("\n"))
+    }
+
+    /// HPGL with the pen in the first stall, the feed rate becomes the pen velocity
+    pub fn hpgl(&self, paths: &[Vec<(f64, f64)>], width: u32, height: u32) -> Result<String, AugeError> {
+        let to_paper = self.fit(width, height)?;
+        let units = |point| {
+            let (x, y) = to_paper(point);
+            ((x * HPGL_UNITS).round() as i64, (y * HPGL_UNITS).round() as i64)
+        };
+        // VS takes centimetres per second
+        let mut hpgl = format!("IN;SP1;VS{:.1};", self.feed_rate / 600.0);
+        for path in paths {
+            let Some(&start) = path.first() else {
+                continue;
+            };
+            let (x, y) = units(start);
+            let _ = write!(hpgl, "\nPU{},{};PD", x, y);
+            for (i, &point) in path[1..].iter().enumerate() {
+                let (x, y) = units(point);
+                let _ = write!(hpgl, "{}{},{}", if i == 0 { "" } else { "," }, x, y);
+            }
+            hpgl.push(';');
+        }
+        hpgl.push_str("\nPU;SP0;");
+        Ok(hpgl)
+    }
+}
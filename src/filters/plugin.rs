@@ -0,0 +1,265 @@
+//! Third-party filters as WebAssembly modules, found as `NAME.wasm` in the plugin directory
+//! and run as `auge NAME [--key value]...`. A module exports its `memory` and two functions:
+//!
+//! - `alloc(len: i32) -> i32` returns a buffer of `len` bytes for the host to fill
+//! - `apply(width: i32, height: i32, pixels: i32, params: i32, params_len: i32) -> i32`
+//!   filters the RGBA bytes at `pixels` in place, `params` holds the options as a JSON
+//!   object, and returns 0 on success
+//!
+//! Modules get no imports, they cannot reach files, the network or the clock. Every run gets
+//! a budget of fuel, roughly one unit per instruction, so a module stuck in a loop is stopped.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use clap::CommandFactory;
+use image::{DynamicImage, RgbaImage};
+use serde_json::{Map, Value};
+use wasmi::core::TrapCode;
+use wasmi::{Config, Engine, Linker, Module, Store};
+
+use super::{AugeFilter, FilterResult};
+use crate::inout::config_dir;
+use crate::types::AugeError;
+
+/// Plugin directory from the command line, set once
+static PLUGIN_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Fuel of one plugin run, 0 for no limit
+static FUEL: OnceLock<u64> = OnceLock::new();
+
+pub fn configure(dir: Option<&str>, fuel: u64) {
+    if let Some(dir) = dir {
+        let _ = PLUGIN_DIR.set(PathBuf::from(dir));
+    }
+    let _ = FUEL.set(fuel);
+}
+
+/// `--plugin-dir` when given, `plugins` of the configuration directory otherwise
+fn plugin_dir() -> Result<PathBuf, AugeError> {
+    match PLUGIN_DIR.get() {
+        Some(dir) => Ok(dir.clone()),
+        None => Ok(config_dir()?.join("plugins")),
+    }
+}
+
+/// Names of the installed plugins, none when the directory does not exist
+pub fn discover() -> Result<Vec<String>, AugeError> {
+    discover_in(&plugin_dir()?)
+}
+
+/// Installed plugins for the end of `--help`. It is built before the arguments are parsed,
+/// so `--plugin-dir` is picked out of them by hand.
+pub fn help() -> String {
+    let args: Vec<String> = std::env::args().collect();
+    let given = args.iter().enumerate().find_map(|(i, arg)| match arg.strip_prefix("--plugin-dir") {
+        Some("") => args.get(i + 1).cloned(),
+        Some(rest) => rest.strip_prefix('=').map(str::to_string),
+        None => None,
+    });
+    let dir = match given {
+        Some(dir) => PathBuf::from(dir),
+        None => match config_dir() {
+            Ok(dir) => dir.join("plugins"),
+            Err(_) => return String::new(),
+        },
+    };
+    match discover_in(&dir) {
+        Ok(names) if !names.is_empty() => format!(
+            "Plugins in {}, run as auge NAME [--key value]...:\n  {}",
+            dir.display(),
+            names.join("\n  ")
+        ),
+        _ => format!("No plugins installed in {}", dir.display()),
+    }
+}
+
+/// Commands within two edits of a mistyped name, closest first
+fn similar_commands(name: &str) -> Vec<String> {
+    let distance = |a: &str, b: &str| {
+        let b: Vec<char> = b.chars().collect();
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, ca) in a.chars().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, &cb) in b.iter().enumerate() {
+                let next = (diagonal + (ca != cb) as usize).min(row[j] + 1).min(row[j + 1] + 1);
+                diagonal = row[j + 1];
+                row[j + 1] = next;
+            }
+        }
+        row[b.len()]
+    };
+    let mut close: Vec<(usize, String)> = crate::Cli::command()
+        .get_subcommands()
+        .map(|command| command.get_name().to_string())
+        .map(|command| (distance(name, &command), command))
+        .filter(|(distance, _)| *distance <= 2)
+        .collect();
+    close.sort();
+    close.into_iter().map(|(_, command)| command).collect()
+}
+
+fn discover_in(dir: &Path) -> Result<Vec<String>, AugeError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "wasm")
+            && let Some(name) = path.file_stem()
+        {
+            names.push(name.to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Option values as JSON, numbers and booleans typed and everything else kept as a string
+fn param_value(text: &str) -> Value {
+    match serde_json::from_str(text) {
+        Ok(value @ (Value::Number(_) | Value::Bool(_))) => value,
+        _ => Value::String(text.to_string()),
+    }
+}
+
+/// `--key value` and `--key=value` pairs as an object, `--flag` alone becomes `true` and
+/// positional arguments are collected under `args`
+fn parse_params(args: &[String]) -> Value {
+    let mut params = Map::new();
+    let mut positional = Vec::new();
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let Some(key) = arg.strip_prefix("--") else {
+            positional.push(Value::String(arg.clone()));
+            continue;
+        };
+        let (key, value) = match key.split_once('=') {
+            Some((key, value)) => (key, param_value(value)),
+            None => match args.next_if(|next| !next.starts_with("--")) {
+                Some(value) => (key, param_value(value)),
+                None => (key, Value::Bool(true)),
+            },
+        };
+        params.insert(key.replace('-', "_"), value);
+    }
+    if !positional.is_empty() {
+        params.insert("args".to_string(), Value::Array(positional));
+    }
+    Value::Object(params)
+}
+
+pub struct PluginFilter {
+    pub name: String,
+    pub wasm: Vec<u8>,
+    pub params: Value,
+}
+
+impl PluginFilter {
+    /// Plugin named by the first argument with options from the rest
+    pub fn load(args: Vec<String>) -> Result<Self, AugeError> {
+        let Some((name, args)) = args.split_first() else {
+            return Err(AugeError::Plugin("no plugin named".to_string()));
+        };
+        let path = plugin_dir()?.join(format!("{}.wasm", name));
+        if !path.is_file() {
+            // Typos of built-in commands end up here too, point them to the command
+            if let Some(command) = similar_commands(name).first() {
+                return Err(AugeError::Plugin(format!(
+                    "{} is not a command, did you mean {}?",
+                    name, command
+                )));
+            }
+            let installed = discover()?;
+            return Err(AugeError::Plugin(format!(
+                "{} is neither a command nor a plugin in {}, installed plugins: {}",
+                name,
+                plugin_dir()?.display(),
+                if installed.is_empty() {
+                    "none".to_string()
+                } else {
+                    installed.join(", ")
+                }
+            )));
+        }
+        Ok(PluginFilter {
+            name: name.clone(),
+            wasm: std::fs::read(path)?,
+            params: parse_params(args),
+        })
+    }
+}
+
+impl AugeFilter for PluginFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let fuel = FUEL.get().copied().unwrap_or(0);
+        let mut config = Config::default();
+        config.consume_fuel(fuel > 0);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &self.wasm)?;
+        let mut store = Store::new(&engine, ());
+        if fuel > 0 {
+            store.set_fuel(fuel).map_err(wasmi::Error::from)?;
+        }
+        let trapped = |err: wasmi::Error| match err.as_trap_code() {
+            Some(TrapCode::OutOfFuel) => AugeError::Plugin(format!(
+                "{} used up its fuel of {}, raise --plugin-fuel if it is just slow",
+                self.name, fuel
+            )),
+            _ => err.into(),
+        };
+        let instance = Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)?
+            .start(&mut store)
+            .map_err(trapped)?;
+        let missing =
+            |export: &str| AugeError::Plugin(format!("{} does not export {}", self.name, export));
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| missing("memory"))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|_| missing("alloc(i32) -> i32"))?;
+        let apply = instance
+            .get_typed_func::<(i32, i32, i32, i32, i32), i32>(&store, "apply")
+            .map_err(|_| missing("apply(i32, i32, i32, i32, i32) -> i32"))?;
+
+        let (width, height) = (img.width(), img.height());
+        let mut pixels = img.to_rgba8().into_raw();
+        let params = serde_json::to_vec(&self.params)?;
+        let pixels_at = alloc.call(&mut store, pixels.len() as i32).map_err(trapped)?;
+        memory
+            .write(&mut store, pixels_at as usize, &pixels)
+            .map_err(wasmi::Error::from)?;
+        let params_at = alloc.call(&mut store, params.len() as i32).map_err(trapped)?;
+        memory
+            .write(&mut store, params_at as usize, &params)
+            .map_err(wasmi::Error::from)?;
+
+        let status = apply
+            .call(
+                &mut store,
+                (
+                    width as i32,
+                    height as i32,
+                    pixels_at,
+                    params_at,
+                    params.len() as i32,
+                ),
+            )
+            .map_err(trapped)?;
+        if status != 0 {
+            return Err(AugeError::Plugin(format!(
+                "{} failed with status {}",
+                self.name, status
+            )));
+        }
+        memory
+            .read(&store, pixels_at as usize, &mut pixels)
+            .map_err(wasmi::Error::from)?;
+        let filtered = RgbaImage::from_raw(width, height, pixels)
+            .expect("buffer holds width * height RGBA pixels");
+        Ok(FilterResult::Image(DynamicImage::ImageRgba8(filtered)))
+    }
+}
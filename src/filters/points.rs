@@ -0,0 +1,53 @@
+use image::DynamicImage;
+use imageproc::filter::gaussian_blur_f32;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::edge::EdgeFilter;
+use crate::types::AugeError;
+
+/// Picks `count` positions where integer coordinates are pixel centers.
+///
+/// `detail` blends between uniform scattering (0.0) and following the
+/// gradient magnitude (1.0), so busy areas receive more points.
+/// The same `seed` always yields the same points.
+pub fn sample_points(
+    img: &DynamicImage,
+    count: usize,
+    detail: f32,
+    seed: u64,
+) -> Result<Vec<(f32, f32)>, AugeError> {
+    let (width, height) = (img.width(), img.height());
+    if width == 0 || height == 0 || count == 0 {
+        return Ok(Vec::new());
+    }
+
+    // Soften first so single noisy pixels do not attract points
+    let smoothed = DynamicImage::ImageLuma8(gaussian_blur_f32(&img.to_luma8(), 1.0));
+    let magnitude = EdgeFilter {
+        normalize: true,
+        ..Default::default()
+    }
+    .edge_map(&smoothed)?;
+
+    let detail = detail.clamp(0.0, 1.0);
+    let mut cumulative = Vec::with_capacity(magnitude.len());
+    let mut total = 0.0f64;
+    for pixel in magnitude.pixels() {
+        total += ((1.0 - detail) + detail * pixel[0] as f32 / 255.0) as f64 + 1e-6;
+        cumulative.push(total);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let points = (0..count)
+        .map(|_| {
+            let target = rng.gen_range(0.0..total);
+            let index = cumulative.partition_point(|&c| c < target);
+            let (x, y) = (index as u32 % width, index as u32 / width);
+            (
+                (x as f32 + rng.gen_range(-0.5..0.5)).clamp(0.0, (width - 1) as f32),
+                (y as f32 + rng.gen_range(-0.5..0.5)).clamp(0.0, (height - 1) as f32),
+            )
+        })
+        .collect();
+    Ok(points)
+}
@@ -0,0 +1,80 @@
+use std::f32::consts::{PI, TAU};
+
+use clap::ValueEnum;
+use image::{DynamicImage, Rgba};
+
+use super::warp::{Interpolation, warp};
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Point};
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum PolarMode {
+    /// Wrap rows into rings around the center, top row in the middle
+    ToPolar,
+    /// Unwrap rings around the center back into rows
+    ToRect,
+    /// Stereographic "little planet", bottom of the image becomes the ground in the middle
+    TinyPlanet,
+}
+
+pub struct PolarFilter {
+    pub mode: PolarMode,
+    /// Center of the rings, relative to image size
+    pub center: Point,
+    /// Horizon radius for tiny planet, relative to half of the shorter side
+    pub zoom: f32,
+    pub interpolation: Interpolation,
+}
+
+impl AugeFilter for PolarFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let (w, h) = (width as f32, height as f32);
+        let cx = self.center.0 * w;
+        let cy = self.center.1 * h;
+
+        // Distance to the farthest corner, so the mapping covers the whole frame
+        let max_radius = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)]
+            .iter()
+            .map(|(x, y)| ((x - cx) * (x - cx) + (y - cy) * (y - cy)).sqrt())
+            .fold(1.0f32, f32::max);
+        let planet_unit = w.min(h) / 2.0;
+        let zoom = self.zoom.max(f32::EPSILON);
+
+        // Angles run clockwise starting from the top
+        let angle_of = |dx: f32, dy: f32| dx.atan2(-dy).rem_euclid(TAU);
+
+        let out = warp(
+            &src,
+            width,
+            height,
+            self.interpolation,
+            Rgba([0, 0, 0, 0]),
+            |x, y| {
+                let (dx, dy) = (x - cx, y - cy);
+                let radius = (dx * dx + dy * dy).sqrt();
+                match self.mode {
+                    PolarMode::ToPolar => Some((
+                        angle_of(dx, dy) / TAU * (w - 1.0),
+                        radius / max_radius * (h - 1.0),
+                    )),
+                    PolarMode::ToRect => {
+                        let angle = x / (w - 1.0).max(1.0) * TAU;
+                        let radius = y / (h - 1.0).max(1.0) * max_radius;
+                        Some((cx + radius * angle.sin(), cy - radius * angle.cos()))
+                    }
+                    PolarMode::TinyPlanet => {
+                        let latitude = 2.0 / PI * (radius / planet_unit / zoom).atan();
+                        Some((
+                            angle_of(dx, dy) / TAU * (w - 1.0),
+                            (1.0 - latitude) * (h - 1.0),
+                        ))
+                    }
+                }
+            },
+        );
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
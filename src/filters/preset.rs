@@ -0,0 +1,111 @@
+use image::DynamicImage;
+use toml_edit::{DocumentMut, Item};
+
+use super::{AugeFilter, FilterResult, filter_from_args};
+use crate::preset::{
+    check_step, load_document, load_presets, not_found, path_or_default, preset_table,
+    write_document,
+};
+use crate::types::AugeError;
+
+/// Stores a command line as a preset for `run`, no input is read
+pub struct PresetSaveFilter {
+    pub name: String,
+    pub description: Option<String>,
+    /// Replace a preset of the same name
+    pub force: bool,
+    pub command: Vec<String>,
+    pub presets: Option<String>,
+}
+
+/// Names and descriptions of the presets, one per line
+pub struct PresetListFilter {
+    pub presets: Option<String>,
+}
+
+/// A preset as it is written in the file
+pub struct PresetShowFilter {
+    pub name: String,
+    pub presets: Option<String>,
+}
+
+pub struct PresetDeleteFilter {
+    pub name: String,
+    pub presets: Option<String>,
+}
+
+impl AugeFilter for PresetSaveFilter {
+    fn apply(&self, _img: DynamicImage) -> Result<FilterResult, AugeError> {
+        // Broken command lines are caught now rather than on the first run
+        check_step(&self.command)?;
+        filter_from_args("preset", self.command.clone())?;
+
+        let path = path_or_default(self.presets.as_deref())?;
+        let mut document = load_document(&path)?;
+        if document.contains_key(&self.name) && !self.force {
+            return Err(AugeError::Preset(format!(
+                "a preset named {} exists already, --force replaces it",
+                self.name
+            )));
+        }
+        document[&self.name] =
+            Item::Table(preset_table(&self.command, self.description.as_deref()));
+        write_document(&path, &document)?;
+        Ok(FilterResult::Text(format!(
+            "saved preset {} to {}",
+            self.name,
+            path.display()
+        )))
+    }
+}
+
+impl AugeFilter for PresetListFilter {
+    fn apply(&self, _img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let presets = load_presets(self.presets.as_deref())?;
+        if presets.is_empty() {
+            let path = path_or_default(self.presets.as_deref())?;
+            return Ok(FilterResult::Text(format!(
+                "no presets in {} yet, add some with `auge preset save`",
+                path.display()
+            )));
+        }
+        let width = presets.keys().map(String::len).max().unwrap_or(0);
+        let lines: Vec<String> = presets
+            .iter()
+            .map(|(name, preset)| match &preset.description {
+                Some(description) => format!("{:width$}  {}", name, description),
+                None => name.clone(),
+            })
+            .collect();
+        Ok(FilterResult::Text(lines.join("\n")))
+    }
+}
+
+impl AugeFilter for PresetShowFilter {
+    fn apply(&self, _img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let path = path_or_default(self.presets.as_deref())?;
+        let document = load_document(&path)?;
+        let Some(item) = document.get(&self.name) else {
+            return Err(not_found(&self.name, self.presets.as_deref()));
+        };
+        let mut shown = DocumentMut::new();
+        shown[&self.name] = item.clone();
+        Ok(FilterResult::Text(shown.to_string().trim().to_string()))
+    }
+}
+
+impl AugeFilter for PresetDeleteFilter {
+    fn apply(&self, _img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let path = path_or_default(self.presets.as_deref())?;
+        let mut document = load_document(&path)?;
+        if document.remove(&self.name).is_none() {
+            return Err(not_found(&self.name, self.presets.as_deref()));
+        }
+        write_document(&path, &document)?;
+        Ok(FilterResult::Text(format!(
+            "deleted preset {} from {}",
+            self.name,
+            path.display()
+        )))
+    }
+}
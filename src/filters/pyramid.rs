@@ -0,0 +1,85 @@
+use image::{ImageBuffer, Luma, Rgba, Rgba32FImage, RgbaImage, imageops};
+
+/// Single channel float image, e.g. a blend mask
+pub type Plane = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+fn to_float(img: &RgbaImage) -> Rgba32FImage {
+    ImageBuffer::from_fn(img.width(), img.height(), |x, y| {
+        Rgba(img.get_pixel(x, y).0.map(|c| c as f32 / 255.0))
+    })
+}
+
+/// Halves the size after a light blur, the next level of a gaussian pyramid
+fn reduce<P>(img: &ImageBuffer<P, Vec<f32>>) -> ImageBuffer<P, Vec<f32>>
+where
+    P: image::Pixel<Subpixel = f32> + 'static,
+{
+    let (width, height) = ((img.width() / 2).max(1), (img.height() / 2).max(1));
+    imageops::resize(
+        &imageops::blur(img, 1.0),
+        width,
+        height,
+        imageops::FilterType::Triangle,
+    )
+}
+
+fn expand(img: &Rgba32FImage, width: u32, height: u32) -> Rgba32FImage {
+    imageops::resize(img, width, height, imageops::FilterType::Triangle)
+}
+
+/// Multi-band blend (Burt and Adelson): both images are split into frequency bands
+/// that are mixed separately with a progressively softer `mask`, so coarse structure
+/// transitions over a wide area while fine detail keeps a sharp seam.
+/// The mask holds the weight of `top` in `0..=1`, all three share one size.
+pub fn pyramid_blend(base: &RgbaImage, top: &RgbaImage, mask: &Plane, levels: u32) -> RgbaImage {
+    let (width, height) = base.dimensions();
+    let mut a = to_float(base);
+    let mut b = to_float(top);
+    let mut m = mask.clone();
+
+    // Band-pass layers from fine to coarse, each holding what the next level loses
+    let mut bands = Vec::new();
+    for _ in 0..levels {
+        if a.width() < 2 || a.height() < 2 {
+            break;
+        }
+        let (next_a, next_b, next_m) = (reduce(&a), reduce(&b), reduce(&m));
+        let (w, h) = a.dimensions();
+        let (up_a, up_b) = (expand(&next_a, w, h), expand(&next_b, w, h));
+        let band = Rgba32FImage::from_fn(w, h, |x, y| {
+            let weight = m.get_pixel(x, y)[0];
+            let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+            let (ua, ub) = (up_a.get_pixel(x, y), up_b.get_pixel(x, y));
+            Rgba(std::array::from_fn(|c| {
+                (pa[c] - ua[c]) * (1.0 - weight) + (pb[c] - ub[c]) * weight
+            }))
+        });
+        bands.push(band);
+        (a, b, m) = (next_a, next_b, next_m);
+    }
+
+    let mut out = Rgba32FImage::from_fn(a.width(), a.height(), |x, y| {
+        let weight = m.get_pixel(x, y)[0];
+        let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+        Rgba(std::array::from_fn(|c| {
+            pa[c] * (1.0 - weight) + pb[c] * weight
+        }))
+    });
+    for band in bands.iter().rev() {
+        let mut up = expand(&out, band.width(), band.height());
+        for (pixel, detail) in up.pixels_mut().zip(band.pixels()) {
+            for c in 0..4 {
+                pixel[c] += detail[c];
+            }
+        }
+        out = up;
+    }
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        Rgba(
+            out.get_pixel(x, y)
+                .0
+                .map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8),
+        )
+    })
+}
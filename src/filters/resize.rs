@@ -1,13 +1,60 @@
-use image::GenericImageView;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use tracing::info;
 
+use super::annotations::Geometric;
 use super::{AugeFilter, FilterResult};
 use crate::types::{AugeError, AutoValue, ResizeInput};
 
 
+/// Blur of the unsharp mask that restores detail after shrinking, in output pixels
+const DETAIL_SIGMA: f32 = 0.8;
+
 pub struct ResizeFilter {
     pub target: ResizeInput,
     pub exact: bool,
-    pub filter: image::imageops::FilterType
+    pub filter: image::imageops::FilterType,
+    /// Boost local contrast in proportion to how much the image shrank
+    pub perceptual: bool,
+}
+
+/// Unsharp mask whose strength grows with every halving of the size, as heavy
+/// reduction averages away more of the fine texture
+fn restore_detail(img: DynamicImage, scale: f32) -> DynamicImage {
+    let amount = (0.35 * scale.log2()).clamp(0.0, 1.0);
+    if amount == 0.0 {
+        return img;
+    }
+    let sharp = img.to_rgba8();
+    let blurred = img.blur(DETAIL_SIGMA).to_rgba8();
+    let out = RgbaImage::from_fn(sharp.width(), sharp.height(), |x, y| {
+        let (s, b) = (sharp.get_pixel(x, y), blurred.get_pixel(x, y));
+        let channel = |c: usize| {
+            (s[c] as f32 + (s[c] as f32 - b[c] as f32) * amount).round().clamp(0.0, 255.0) as u8
+        };
+        Rgba([channel(0), channel(1), channel(2), s[3]])
+    });
+    DynamicImage::ImageRgba8(out)
+}
+
+impl ResizeFilter {
+    fn resample(&self, img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+        #[cfg(feature = "gpu")]
+        if let Some(gpu) = crate::gpu::gpu() {
+            let out = if self.exact {
+                gpu.resize_exact(img, width, height, self.filter)
+            } else {
+                gpu.resize(img, width, height, self.filter)
+            };
+            if let Some(out) = out {
+                return out;
+            }
+        }
+        if self.exact {
+            img.resize_exact(width, height, self.filter)
+        } else {
+            img.resize(width, height, self.filter)
+        }
+    }
 }
 
 impl AugeFilter for ResizeFilter {
@@ -29,11 +76,23 @@ impl AugeFilter for ResizeFilter {
             }
         };
 
-        if self.exact {
-            Ok(img.resize_exact(tx, ty, self.filter).into())
+        let out = self.resample(&img, tx, ty);
+        info!("resized {}x{} to {}x{}", ox, oy, out.width(), out.height());
+
+        if self.perceptual {
+            let scale = (ox as f32 / out.width().max(1) as f32).max(oy as f32 / out.height().max(1) as f32);
+            Ok(restore_detail(out, scale).into())
         } else {
-            Ok(img.resize(tx, ty, self.filter).into())
+            Ok(out.into())
         }
-        
+    }
+}
+
+impl Geometric for ResizeFilter {
+    fn map_point(&self, x: f64, y: f64, input: (u32, u32), output: (u32, u32)) -> (f64, f64) {
+        (
+            x * output.0 as f64 / input.0.max(1) as f64,
+            y * output.1 as f64 / input.1.max(1) as f64,
+        )
     }
 }
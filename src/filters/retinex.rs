@@ -0,0 +1,72 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use super::fft::fft_gaussian_blur_planes;
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Multi-scale Retinex with color preservation.
+///
+/// Works on the intensity channel only and rescales RGB by the intensity gain,
+/// so hues stay intact instead of drifting towards gray like per-channel MSR does.
+pub struct RetinexFilter {
+    pub scales: Vec<f32>,
+    pub gain: f32,
+}
+
+impl Default for RetinexFilter {
+    fn default() -> Self {
+        Self {
+            scales: vec![15.0, 80.0, 250.0],
+            gain: 1.0,
+        }
+    }
+}
+
+impl AugeFilter for RetinexFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let rgba = img.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        let (w, h) = (width as usize, height as usize);
+        if w == 0 || h == 0 || self.scales.is_empty() {
+            return Ok(DynamicImage::ImageRgba8(rgba).into());
+        }
+
+        // +1 keeps the logarithm finite for pure black pixels
+        let intensity: Vec<f32> = rgba
+            .pixels()
+            .map(|p| (p[0] as f32 + p[1] as f32 + p[2] as f32) / 3.0 + 1.0)
+            .collect();
+        let log_intensity: Vec<f32> = intensity.iter().map(|i| i.ln()).collect();
+
+        let mut msr = vec![0.0f32; w * h];
+        for &sigma in &self.scales {
+            let mut surround = [intensity.clone()];
+            fft_gaussian_blur_planes(&mut surround, w, h, sigma);
+            for ((out, log_i), s) in msr.iter_mut().zip(&log_intensity).zip(&surround[0]) {
+                *out += log_i - s.max(1.0).ln();
+            }
+        }
+        let scale_count = self.scales.len() as f32;
+        msr.iter_mut().for_each(|v| *v /= scale_count);
+
+        // Stretch around the mean; gain widens or narrows the resulting contrast
+        let mean = msr.iter().sum::<f32>() / msr.len() as f32;
+        let variance = msr.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / msr.len() as f32;
+        let spread = (variance.sqrt() * 4.0).max(f32::EPSILON);
+
+        let output = RgbaImage::from_fn(width, height, |x, y| {
+            let idx = y as usize * w + x as usize;
+            let target = (0.5 + self.gain * (msr[idx] - mean) / spread).clamp(0.0, 1.0) * 255.0;
+            let ratio = target / intensity[idx].max(1.0);
+            let p = rgba.get_pixel(x, y);
+            Rgba([
+                (p[0] as f32 * ratio).round().clamp(0.0, 255.0) as u8,
+                (p[1] as f32 * ratio).round().clamp(0.0, 255.0) as u8,
+                (p[2] as f32 * ratio).round().clamp(0.0, 255.0) as u8,
+                p[3],
+            ])
+        });
+
+        Ok(DynamicImage::ImageRgba8(output).into())
+    }
+}
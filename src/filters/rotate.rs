@@ -0,0 +1,106 @@
+use image::{DynamicImage, Rgba};
+
+use super::annotations::Geometric;
+use super::warp::{Interpolation, warp};
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Rotates around the center and grows the canvas so no corner is cut off.
+/// Right angles are exact pixel moves, anything else is resampled.
+pub struct RotateFilter {
+    /// Degrees, positive is clockwise
+    pub angle: f32,
+    pub interpolation: Interpolation,
+    /// Fill for the uncovered corners, transparent when `None`
+    pub background: Option<Rgba<u8>>,
+    /// Keep only the largest upright rectangle inside the rotated image, so no corner needs a fill
+    pub autocrop: bool,
+}
+
+impl RotateFilter {
+    fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (sin, cos) = (self.angle as f64).to_radians().sin_cos();
+        let (w, h) = (width as f64, height as f64);
+        // Rounding away float noise keeps right angles from growing by a pixel
+        let side = |a: f64, b: f64| ((a + b) * 1e6).round() / 1e6;
+        if self.autocrop {
+            let (inner_w, inner_h) = inscribed(w, h, sin.abs(), cos.abs());
+            return (
+                (side(inner_w, 0.0).floor() as u32).max(1),
+                (side(inner_h, 0.0).floor() as u32).max(1),
+            );
+        }
+        (
+            side(w * cos.abs(), h * sin.abs()).ceil() as u32,
+            side(w * sin.abs(), h * cos.abs()).ceil() as u32,
+        )
+    }
+}
+
+/// Largest axis-aligned rectangle fitting inside a `w` x `h` rectangle rotated by
+/// an angle with the given absolute sine and cosine
+fn inscribed(w: f64, h: f64, sin: f64, cos: f64) -> (f64, f64) {
+    let (long, short) = if w >= h { (w, h) } else { (h, w) };
+    if short <= 2.0 * sin * cos * long || (sin - cos).abs() < 1e-10 {
+        // Half constrained: two corners of the crop touch the longer sides
+        let half = 0.5 * short;
+        if w >= h {
+            (half / sin, half / cos)
+        } else {
+            (half / cos, half / sin)
+        }
+    } else {
+        let cos_2a = cos * cos - sin * sin;
+        ((w * cos - h * sin) / cos_2a, (h * cos - w * sin) / cos_2a)
+    }
+}
+
+impl AugeFilter for RotateFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let quarter_turns = self.angle / 90.0;
+        if quarter_turns.fract() == 0.0 {
+            return Ok(match (quarter_turns as i64).rem_euclid(4) {
+                1 => img.rotate90(),
+                2 => img.rotate180(),
+                3 => img.rotate270(),
+                _ => img,
+            }
+            .into());
+        }
+
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let (out_width, out_height) = self.output_size(width, height);
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+        let (ocx, ocy) = (out_width as f32 / 2.0, out_height as f32 / 2.0);
+
+        let out = warp(
+            &src,
+            out_width,
+            out_height,
+            self.interpolation,
+            self.background.unwrap_or(Rgba([0, 0, 0, 0])),
+            |x, y| {
+                let (dx, dy) = (x + 0.5 - ocx, y + 0.5 - ocy);
+                Some((
+                    cx + dx * cos + dy * sin - 0.5,
+                    cy - dx * sin + dy * cos - 0.5,
+                ))
+            },
+        );
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
+
+impl Geometric for RotateFilter {
+    fn map_point(&self, x: f64, y: f64, input: (u32, u32), output: (u32, u32)) -> (f64, f64) {
+        let (sin, cos) = (self.angle as f64).to_radians().sin_cos();
+        let (dx, dy) = (x - input.0 as f64 / 2.0, y - input.1 as f64 / 2.0);
+        (
+            output.0 as f64 / 2.0 + dx * cos - dy * sin,
+            output.1 as f64 / 2.0 + dx * sin + dy * cos,
+        )
+    }
+}
@@ -0,0 +1,43 @@
+use image::{DynamicImage, imageops};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Masks the alpha channel with antialiased rounded corners.
+pub struct RoundCornersFilter {
+    /// Corner radius in pixels, capped at half of the shorter side
+    pub radius: f32,
+    /// Crop to the centered square and mask it with a circle instead
+    pub circle: bool,
+}
+
+impl AugeFilter for RoundCornersFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut out = img.to_rgba8();
+        if self.circle {
+            let side = out.width().min(out.height());
+            let (x, y) = ((out.width() - side) / 2, (out.height() - side) / 2);
+            out = imageops::crop_imm(&out, x, y, side, side).to_image();
+        }
+
+        let (width, height) = out.dimensions();
+        let (half_w, half_h) = (width as f32 / 2.0, height as f32 / 2.0);
+        let radius = if self.circle {
+            half_w
+        } else {
+            self.radius.clamp(0.0, half_w.min(half_h))
+        };
+
+        for (x, y, pixel) in out.enumerate_pixels_mut() {
+            // Signed distance from the pixel center to the rounded rectangle outline
+            let qx = (x as f32 + 0.5 - half_w).abs() - (half_w - radius);
+            let qy = (y as f32 + 0.5 - half_h).abs() - (half_h - radius);
+            let outside = qx.max(0.0).hypot(qy.max(0.0));
+            let distance = outside + qx.max(qy).min(0.0) - radius;
+            let coverage = (0.5 - distance).clamp(0.0, 1.0);
+            pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+        }
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
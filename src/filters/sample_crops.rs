@@ -0,0 +1,77 @@
+use image::{Delay, DynamicImage, Frame, GrayImage, imageops};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, ResizeInput};
+
+/// Candidates tried per requested crop before giving up on the entropy threshold
+const ATTEMPTS_PER_CROP: usize = 100;
+
+/// Shannon entropy of the luma histogram in bits, from 0 (flat) to 8
+pub fn luma_entropy(luma: &GrayImage) -> f64 {
+    let mut histogram = [0u64; 256];
+    for pixel in luma.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+    let total = luma.len().max(1) as f64;
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Random fixed-size patches, e.g. for ML training sets. Emitted as frames,
+/// so `--output-template patch_{}.png` writes one file per crop.
+pub struct SampleCropsFilter {
+    pub count: usize,
+    pub size: ResizeInput,
+    pub seed: u64,
+    /// Reject crops whose luma entropy in bits is below this
+    pub min_entropy: Option<f64>,
+}
+
+impl AugeFilter for SampleCropsFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        let (crop_width, crop_height) = self.size.size_keeping_auto(width, height);
+        if crop_width == 0 || crop_height == 0 || crop_width > width || crop_height > height {
+            return Err(AugeError::InvalidArgument(format!(
+                "crop size {}x{} must fit into the {}x{} input",
+                crop_width, crop_height, width, height
+            )));
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut frames = Vec::with_capacity(self.count);
+        let mut attempts = 0;
+        while frames.len() < self.count && attempts < self.count * ATTEMPTS_PER_CROP {
+            attempts += 1;
+            let x = rng.gen_range(0..=width - crop_width);
+            let y = rng.gen_range(0..=height - crop_height);
+            let crop = imageops::crop_imm(&src, x, y, crop_width, crop_height).to_image();
+
+            if let Some(min_entropy) = self.min_entropy {
+                let luma = DynamicImage::ImageRgba8(crop.clone()).to_luma8();
+                if luma_entropy(&luma) < min_entropy {
+                    continue;
+                }
+            }
+            frames.push(Frame::from_parts(crop, 0, 0, Delay::from_numer_denom_ms(500, 1)));
+        }
+
+        if frames.len() < self.count {
+            return Err(AugeError::InvalidArgument(format!(
+                "only {} of {} crops reached an entropy of {}, lower --min-entropy",
+                frames.len(),
+                self.count,
+                self.min_entropy.unwrap_or_default()
+            )));
+        }
+        Ok(FilterResult::Frames(frames))
+    }
+}
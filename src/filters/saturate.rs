@@ -0,0 +1,53 @@
+use image::{DynamicImage, Rgba, Rgba32FImage};
+
+use super::{AugeFilter, FilterResult, param_map::ParamMap};
+use crate::color::luma;
+use crate::types::{AugeError, SampleDepth};
+
+/// Pushes colors away from or towards their gray level.
+pub struct SaturateFilter {
+    /// 0 is grayscale, 1 leaves the image unchanged, above 1 boosts colors
+    pub amount: f32,
+    /// Blends from unchanged (black) to the full amount (white) per pixel
+    pub param_map: Option<ParamMap>,
+}
+
+impl AugeFilter for SaturateFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let amount = self.amount.max(0.0);
+        // Per-pixel amounts from a map stay on the CPU
+        #[cfg(feature = "gpu")]
+        if self.param_map.is_none()
+            && let Some(saturated) = crate::gpu::gpu().and_then(|gpu| {
+                let luma = crate::color::LUMA.map(|weight| weight * (1.0 - amount));
+                let row = |c: usize| {
+                    let mut row = [luma[0], luma[1], luma[2], 0.0];
+                    row[c] += amount;
+                    row
+                };
+                let rows = [row(0), row(1), row(2), [0.0, 0.0, 0.0, 1.0]];
+                gpu.color(&img, rows, [0.0; 4], 0.0)
+            })
+        {
+            let saturated = DynamicImage::ImageRgba32F(saturated.to_rgba32f());
+            return Ok(SampleDepth::of(&img).convert(&saturated).into());
+        }
+
+        let src = img.to_rgba32f();
+        let (width, height) = src.dimensions();
+        let weights = self.param_map.as_ref().map(|map| map.weights(width, height));
+
+        let out = Rgba32FImage::from_fn(width, height, |x, y| {
+            let p = src.get_pixel(x, y);
+            let weight = weights
+                .as_ref()
+                .map_or(1.0, |weights| weights[(y * width + x) as usize]);
+            let amount = 1.0 + (amount - 1.0) * weight;
+            let luma = luma([p[0], p[1], p[2]]);
+            let adjust = |c: f32| (luma + (c - luma) * amount).max(0.0);
+            Rgba([adjust(p[0]), adjust(p[1]), adjust(p[2]), p[3]])
+        });
+
+        Ok(SampleDepth::of(&img).convert(&DynamicImage::ImageRgba32F(out)).into())
+    }
+}
@@ -0,0 +1,144 @@
+use clap::Parser;
+use image::{DynamicImage, Rgba, RgbaImage, imageops};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, INT, Scope};
+
+use super::{AugeFilter, FilterResult, filter_from_command};
+use crate::{Command, types::AugeError};
+
+/// Built-in filter invocation from inside a script, parsed like the command line
+#[derive(Debug, Parser)]
+#[command(no_binary_name = true)]
+struct Step {
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Image handle exposed to scripts as `Image`
+#[derive(Debug, Clone)]
+struct ScriptImage(RgbaImage);
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+impl ScriptImage {
+    fn position(&self, x: INT, y: INT) -> ScriptResult<(u32, u32)> {
+        let (width, height) = self.0.dimensions();
+        if x < 0 || y < 0 || x >= width as INT || y >= height as INT {
+            return Err(format!(
+                "pixel ({}, {}) is outside of the {}x{} image",
+                x, y, width, height
+            )
+            .into());
+        }
+        Ok((x as u32, y as u32))
+    }
+
+    fn get(&mut self, x: INT, y: INT) -> ScriptResult<Array> {
+        let (x, y) = self.position(x, y)?;
+        Ok(self
+            .0
+            .get_pixel(x, y)
+            .0
+            .iter()
+            .map(|&c| Dynamic::from_int(c as INT))
+            .collect())
+    }
+
+    /// Takes `[r, g, b]` or `[r, g, b, a]`, values are clamped to `0..=255`
+    fn set(&mut self, x: INT, y: INT, rgba: Array) -> ScriptResult<()> {
+        let (x, y) = self.position(x, y)?;
+        if rgba.len() != 3 && rgba.len() != 4 {
+            return Err(format!(
+                "expected [r, g, b] or [r, g, b, a], got {} values",
+                rgba.len()
+            )
+            .into());
+        }
+        let pixel = self.0.get_pixel_mut(x, y);
+        for (c, value) in rgba.into_iter().enumerate() {
+            let value = match value.as_int() {
+                Ok(value) => value as f64,
+                Err(_) => value
+                    .as_float()
+                    .map_err(|kind| format!("channel must be a number, got {}", kind))?,
+            };
+            pixel[c] = value.round().clamp(0.0, 255.0) as u8;
+        }
+        Ok(())
+    }
+
+    /// Runs a built-in command on the image in place, e.g. `img.apply("g-blur -s 2")`
+    fn apply(&mut self, args: &str) -> ScriptResult<()> {
+        let step = Step::try_parse_from(args.split_whitespace()).map_err(|err| err.to_string())?;
+        let filter = filter_from_command(step.command).map_err(|err| err.to_string())?;
+        let result = filter
+            .apply(DynamicImage::ImageRgba8(self.0.clone()))
+            .map_err(|err| err.to_string())?;
+        match result {
+            FilterResult::Image(img) => {
+                self.0 = img.to_rgba8();
+                Ok(())
+            }
+            _ => Err(format!("'{}' does not produce a single image", args).into()),
+        }
+    }
+
+    fn crop(&mut self, x: INT, y: INT, width: INT, height: INT) -> ScriptResult<ScriptImage> {
+        let (x, y) = self.position(x, y)?;
+        let width = width.clamp(0, (self.0.width() - x) as INT) as u32;
+        let height = height.clamp(0, (self.0.height() - y) as INT) as u32;
+        Ok(ScriptImage(
+            imageops::crop_imm(&self.0, x, y, width, height).to_image(),
+        ))
+    }
+
+    /// Copies `other` over the image with its top-left corner at `x, y`
+    fn paste(&mut self, other: ScriptImage, x: INT, y: INT) {
+        imageops::replace(&mut self.0, &other.0, x, y);
+    }
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    // Standard output carries the image, script chatter goes to stderr
+    engine.on_print(|text| eprintln!("{}", text));
+    engine.on_debug(|text, _, _| eprintln!("{}", text));
+
+    engine
+        .register_type_with_name::<ScriptImage>("Image")
+        .register_fn("new_image", |width: INT, height: INT| {
+            ScriptImage(RgbaImage::from_pixel(
+                width.max(0) as u32,
+                height.max(0) as u32,
+                Rgba([0, 0, 0, 0]),
+            ))
+        })
+        .register_get("width", |img: &mut ScriptImage| img.0.width() as INT)
+        .register_get("height", |img: &mut ScriptImage| img.0.height() as INT)
+        .register_fn("get", ScriptImage::get)
+        .register_fn("set", ScriptImage::set)
+        .register_fn("apply", ScriptImage::apply)
+        .register_fn("crop", ScriptImage::crop)
+        .register_fn("paste", ScriptImage::paste);
+    engine
+}
+
+/// Runs a Rhai script with the input bound to `img`, whatever `img` holds at the end is the output.
+pub struct ScriptFilter {
+    pub source: String,
+}
+
+impl AugeFilter for ScriptFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut scope = Scope::new();
+        scope.push("img", ScriptImage(img.to_rgba8()));
+
+        engine()
+            .run_with_scope(&mut scope, &self.source)
+            .map_err(|err| AugeError::Script(err.to_string()))?;
+
+        let out = scope
+            .get_value::<ScriptImage>("img")
+            .ok_or_else(|| AugeError::Script("`img` no longer holds an image".to_string()))?;
+        Ok(DynamicImage::ImageRgba8(out.0).into())
+    }
+}
@@ -0,0 +1,104 @@
+use clap::ValueEnum;
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Grid};
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum SeamlessMethod {
+    /// Cross-fade with a half-size offset copy near the borders, keeps the size
+    Blend,
+    /// Quilt the image with its mirrored copies, doubles the size but is always exact
+    Mirror,
+}
+
+pub struct SeamlessFilter {
+    pub method: SeamlessMethod,
+    /// Width of the cross-fade band as a fraction of the image size
+    pub blend_width: f32,
+    /// Repeat the result in a grid to eyeball the seams
+    pub preview_tiled: Option<Grid>,
+}
+
+/// Repeats the image `cols` x `rows` times
+pub fn tile(img: &RgbaImage, cols: u32, rows: u32) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    RgbaImage::from_fn(width * cols, height * rows, |x, y| {
+        *img.get_pixel(x % width, y % height)
+    })
+}
+
+/// Weight of the offset copy: 1 at the borders, fading to 0 over `band` pixels
+fn border_weight(pos: u32, size: u32, band: f32) -> f32 {
+    let distance = pos.min(size - 1 - pos) as f32;
+    let t = (1.0 - distance / band.max(1.0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// One axis of the blend method: the offset copy has no seam at the borders,
+/// the original has none in the middle, so mixing them removes both
+fn blend_axis(img: &RgbaImage, band_fraction: f32, horizontal: bool) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let (size, band) = if horizontal {
+        (width, width as f32 * band_fraction)
+    } else {
+        (height, height as f32 * band_fraction)
+    };
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (pos, shifted) = if horizontal {
+            (x, img.get_pixel((x + width / 2) % width, y))
+        } else {
+            (y, img.get_pixel(x, (y + height / 2) % height))
+        };
+        let t = border_weight(pos, size, band);
+        let a = img.get_pixel(x, y);
+        Rgba(std::array::from_fn(|c| {
+            (a[c] as f32 * (1.0 - t) + shifted[c] as f32 * t).round() as u8
+        }))
+    })
+}
+
+impl AugeFilter for SeamlessFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        if src.width() == 0 || src.height() == 0 {
+            return Ok(img.into());
+        }
+
+        let result = match self.method {
+            SeamlessMethod::Blend => {
+                if !(0.0..=0.5).contains(&self.blend_width) {
+                    return Err(AugeError::InvalidArgument(format!(
+                        "blend width must be between 0.0 and 0.5, got {}",
+                        self.blend_width
+                    )));
+                }
+                let horizontal = blend_axis(&src, self.blend_width, true);
+                blend_axis(&horizontal, self.blend_width, false)
+            }
+            SeamlessMethod::Mirror => {
+                let (width, height) = src.dimensions();
+                let mut quilt = RgbaImage::new(width * 2, height * 2);
+                let flipped = imageops::flip_horizontal(&src);
+                imageops::replace(&mut quilt, &src, 0, 0);
+                imageops::replace(&mut quilt, &flipped, width as i64, 0);
+                imageops::replace(&mut quilt, &imageops::flip_vertical(&src), 0, height as i64);
+                imageops::replace(
+                    &mut quilt,
+                    &imageops::flip_vertical(&flipped),
+                    width as i64,
+                    height as i64,
+                );
+                quilt
+            }
+        };
+
+        let result = match self.preview_tiled {
+            Some(Grid(cols, rows)) => tile(&result, cols, rows),
+            None => result,
+        };
+
+        Ok(DynamicImage::ImageRgba8(result).into())
+    }
+}
@@ -0,0 +1,140 @@
+use image::{DynamicImage, GrayImage, Rgba};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Point};
+
+/// Over-relaxation factor of the Gauss-Seidel solver
+const RELAXATION: f32 = 1.9;
+
+/// Poisson image editing (Pérez et al.): the masked part of `source` is pasted into
+/// the input by solving for pixels whose gradients match the source while the
+/// border matches the input, so lighting and color blend in without a visible edge.
+pub struct SeamlessCloneFilter {
+    pub source: DynamicImage,
+    /// Region of the source to transfer, same size as the source, white is inside.
+    /// Everything but the outermost source pixels when `None`
+    pub mask: Option<GrayImage>,
+    /// Top-left corner of the source in the input
+    pub offset: Point,
+    /// Keep the input's gradients where they are stronger, so its texture shows through
+    pub mixed: bool,
+    pub iterations: u32,
+}
+
+impl AugeFilter for SeamlessCloneFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let mut dest = img.to_rgba8();
+        let src = self.source.to_rgba8();
+        let (sw, sh) = src.dimensions();
+        if let Some(mask) = &self.mask
+            && mask.dimensions() != (sw, sh)
+        {
+            return Err(AugeError::InvalidArgument(format!(
+                "mask is {}x{}, expected {}x{} like the source",
+                mask.width(),
+                mask.height(),
+                sw,
+                sh
+            )));
+        }
+
+        let (ox, oy) = (self.offset.0.round() as i64, self.offset.1.round() as i64);
+        let (dw, dh) = (dest.width() as i64, dest.height() as i64);
+        // Unknowns need all four neighbors inside the input to take their boundary values from
+        let inside = |x: i64, y: i64| {
+            if x <= 0 || y <= 0 || x >= sw as i64 - 1 || y >= sh as i64 - 1 {
+                return false;
+            }
+            let (dx, dy) = (x + ox, y + oy);
+            if dx <= 0 || dy <= 0 || dx >= dw - 1 || dy >= dh - 1 {
+                return false;
+            }
+            self.mask
+                .as_ref()
+                .is_none_or(|mask| mask.get_pixel(x as u32, y as u32)[0] > 127)
+        };
+
+        let unknowns: Vec<(i64, i64)> = (0..sh as i64)
+            .flat_map(|y| (0..sw as i64).map(move |x| (x, y)))
+            .filter(|&(x, y)| inside(x, y))
+            .collect();
+        if unknowns.is_empty() {
+            return Ok(DynamicImage::ImageRgba8(dest).into());
+        }
+        let index = |x: i64, y: i64| (y * sw as i64 + x) as usize;
+        let mut is_unknown = vec![false; (sw * sh) as usize];
+        for &(x, y) in &unknowns {
+            is_unknown[index(x, y)] = true;
+        }
+
+        const NEIGHBORS: [(i64, i64); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        for c in 0..3 {
+            let g = |x: i64, y: i64| src.get_pixel(x as u32, y as u32)[c] as f32;
+            let d = |x: i64, y: i64| dest.get_pixel((x + ox) as u32, (y + oy) as u32)[c] as f32;
+
+            // Right-hand side: guidance field plus the fixed border values
+            let rhs: Vec<f32> = unknowns
+                .iter()
+                .map(|&(x, y)| {
+                    NEIGHBORS
+                        .iter()
+                        .map(|&(nx, ny)| {
+                            let (qx, qy) = (x + nx, y + ny);
+                            let source_gradient = g(x, y) - g(qx, qy);
+                            let dest_gradient = d(x, y) - d(qx, qy);
+                            let guidance =
+                                if self.mixed && dest_gradient.abs() > source_gradient.abs() {
+                                    dest_gradient
+                                } else {
+                                    source_gradient
+                                };
+                            let border = if is_unknown[index(qx, qy)] {
+                                0.0
+                            } else {
+                                d(qx, qy)
+                            };
+                            guidance + border
+                        })
+                        .sum()
+                })
+                .collect();
+
+            // Start from the source shifted by the mean border offset, close to the solution
+            let shift = unknowns
+                .iter()
+                .map(|&(x, y)| d(x, y) - g(x, y))
+                .sum::<f32>()
+                / unknowns.len() as f32;
+            let mut f = vec![0.0f32; (sw * sh) as usize];
+            for &(x, y) in &unknowns {
+                f[index(x, y)] = g(x, y) + shift;
+            }
+
+            for _ in 0..self.iterations {
+                for (&(x, y), rhs) in unknowns.iter().zip(&rhs) {
+                    let neighbors: f32 = NEIGHBORS
+                        .iter()
+                        .map(|&(nx, ny)| index(x + nx, y + ny))
+                        .filter(|&i| is_unknown[i])
+                        .map(|i| f[i])
+                        .sum();
+                    let i = index(x, y);
+                    let solved = (rhs + neighbors) / 4.0;
+                    f[i] += RELAXATION * (solved - f[i]);
+                }
+            }
+
+            for &(x, y) in &unknowns {
+                let pixel = dest.get_pixel_mut((x + ox) as u32, (y + oy) as u32);
+                pixel[c] = f[index(x, y)].round().clamp(0.0, 255.0) as u8;
+            }
+        }
+
+        for &(x, y) in &unknowns {
+            let Rgba([_, _, _, alpha]) = *src.get_pixel(x as u32, y as u32);
+            let pixel = dest.get_pixel_mut((x + ox) as u32, (y + oy) as u32);
+            pixel[3] = pixel[3].max(alpha);
+        }
+        Ok(DynamicImage::ImageRgba8(dest).into())
+    }
+}
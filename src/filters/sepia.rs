@@ -1,26 +1,35 @@
-use image::{DynamicImage, Rgb};
-use crate::types::AugeError;
-use super::{FilterResult, AugeFilter};
+use image::DynamicImage;
+use crate::simd;
+use crate::types::{AugeError, SampleDepth};
+use super::{FilterResult, AugeFilter, par_rows};
 
 pub struct SepiaFilter;
 
+/// Sepia tone transformation, rows of output red, green and blue
+const SEPIA: [[f32; 3]; 3] = [
+    [0.393, 0.769, 0.189],
+    [0.349, 0.686, 0.168],
+    [0.272, 0.534, 0.131],
+];
+
 impl AugeFilter for SepiaFilter {
     fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
-        let mut img = img.to_rgb8();
-        
-        for pixel in img.pixels_mut() {
-            let r = pixel[0] as f32;
-            let g = pixel[1] as f32;
-            let b = pixel[2] as f32;
-            
-            // Apply sepia tone transformation
-            let new_r = (r * 0.393 + g * 0.769 + b * 0.189).min(255.0) as u8;
-            let new_g = (r * 0.349 + g * 0.686 + b * 0.168).min(255.0) as u8;
-            let new_b = (r * 0.272 + g * 0.534 + b * 0.131).min(255.0) as u8;
-            
-            *pixel = Rgb([new_r, new_g, new_b]);
+        let depth = SampleDepth::of(&img);
+        let mut img = img.to_rgb32f();
+
+        #[cfg(feature = "gpu")]
+        if let Some(toned) = crate::gpu::gpu().and_then(|gpu| {
+            let [r, g, b] = SEPIA.map(|[x, y, z]| [x, y, z, 0.0]);
+            let rows = [r, g, b, [0.0, 0.0, 0.0, 1.0]];
+            gpu.color(&DynamicImage::ImageRgb32F(img.clone()), rows, [0.0; 4], f32::MIN)
+        }) {
+            return Ok(depth.convert(&toned).into());
         }
-        
-        Ok(DynamicImage::ImageRgb8(img).into())
+
+        // Apply sepia tone transformation, quantizing to the output depth clips at white
+        let row_len = img.width() as usize * 3;
+        par_rows(&mut img, row_len, |_, row| simd::color_matrix(row, &SEPIA));
+
+        Ok(depth.convert(&DynamicImage::ImageRgb32F(img)).into())
     }
 }
@@ -0,0 +1,74 @@
+use image::{DynamicImage, ImageBuffer, Rgba, Rgba32FImage, RgbaImage, imageops};
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Decodes an sRGB channel in `0..=1` to linear light
+pub fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear light channel in `0..=1` back to sRGB
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Downscales HiDPI screenshots in linear light, so thin bright text on dark
+/// backgrounds keeps its weight instead of turning dim and muddy.
+pub struct ShrinkRetinaFilter {
+    /// Both sides are divided by this, 2 for a typical retina capture
+    pub factor: f32,
+    pub filter: imageops::FilterType,
+}
+
+impl AugeFilter for ShrinkRetinaFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        if self.factor < 1.0 {
+            return Err(AugeError::InvalidArgument(format!(
+                "factor must be at least 1, got {}",
+                self.factor
+            )));
+        }
+        let src = img.to_rgba8();
+        let width = ((src.width() as f32 / self.factor).round() as u32).max(1);
+        let height = ((src.height() as f32 / self.factor).round() as u32).max(1);
+
+        // Premultiplied so transparent pixels do not bleed their color into the edges
+        let linear: Rgba32FImage = ImageBuffer::from_fn(src.width(), src.height(), |x, y| {
+            let p = src.get_pixel(x, y);
+            let alpha = p[3] as f32 / 255.0;
+            let channel = |c: u8| srgb_to_linear(c as f32 / 255.0) * alpha;
+            Rgba([channel(p[0]), channel(p[1]), channel(p[2]), alpha])
+        });
+        let resized = imageops::resize(&linear, width, height, self.filter);
+
+        let out = RgbaImage::from_fn(width, height, |x, y| {
+            let p = resized.get_pixel(x, y);
+            let alpha = p[3].clamp(0.0, 1.0);
+            let channel = |c: f32| {
+                let straight = if alpha > 0.0 { c / alpha } else { 0.0 };
+                (linear_to_srgb(straight.clamp(0.0, 1.0)) * 255.0).round() as u8
+            };
+            Rgba([
+                channel(p[0]),
+                channel(p[1]),
+                channel(p[2]),
+                (alpha * 255.0).round() as u8,
+            ])
+        });
+        let out = DynamicImage::ImageRgba8(out);
+        if img.color().has_alpha() {
+            Ok(out.into())
+        } else {
+            Ok(DynamicImage::ImageRgb8(out.to_rgb8()).into())
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+use imageproc::filter::gaussian_blur_f32;
+
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Pencil sketch via the classic grayscale, invert, blur and color dodge recipe.
+pub struct SketchFilter {
+    /// Blur of the inverted layer, larger values give thicker strokes
+    pub sigma: f32,
+    /// Tint the paper with a muted version of the original colors
+    pub colored: bool,
+    /// How much of the original saturation the colored pencils keep
+    pub saturation: f32,
+}
+
+fn dodge(base: u8, blend: u8) -> u8 {
+    if blend == 255 {
+        255
+    } else {
+        (base as u32 * 255 / (255 - blend as u32)).min(255) as u8
+    }
+}
+
+impl AugeFilter for SketchFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let gray = img.to_luma8();
+        let mut inverted = gray.clone();
+        for pixel in inverted.pixels_mut() {
+            pixel[0] = 255 - pixel[0];
+        }
+        let blurred = if self.sigma > 0.0 {
+            gaussian_blur_f32(&inverted, self.sigma)
+        } else {
+            inverted
+        };
+
+        let sketch = GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+            Luma([dodge(gray.get_pixel(x, y)[0], blurred.get_pixel(x, y)[0])])
+        });
+
+        if !self.colored {
+            return Ok(DynamicImage::ImageLuma8(sketch).into());
+        }
+
+        // Pastel version of the original, darkened by the pencil strokes
+        let saturation = self.saturation.clamp(0.0, 1.0);
+        let src = img.to_rgba8();
+        let out = RgbaImage::from_fn(src.width(), src.height(), |x, y| {
+            let p = src.get_pixel(x, y);
+            let stroke = sketch.get_pixel(x, y)[0] as f32 / 255.0;
+            let tint = |c: u8| 255.0 - saturation * (255.0 - c as f32);
+            Rgba([
+                (tint(p[0]) * stroke).round() as u8,
+                (tint(p[1]) * stroke).round() as u8,
+                (tint(p[2]) * stroke).round() as u8,
+                p[3],
+            ])
+        });
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
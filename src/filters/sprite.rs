@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use image::{Delay, DynamicImage, Frame, Rgba, RgbaImage, imageops};
+use serde::{Deserialize, Serialize};
+
+use super::montage::tile;
+use super::{AugeFilter, FilterResult};
+use crate::{
+    inout::load_image,
+    types::{AugeError, ResizeInput},
+};
+
+/// Where every frame sits on a sprite sheet
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Atlas {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<AtlasFrame>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AtlasFrame {
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Packs frames into a grid of equal cells sized after the largest frame. Reads no input.
+pub struct SpritePackFilter {
+    pub files: Vec<String>,
+    /// Defaults to a roughly square sheet
+    pub columns: Option<u32>,
+    pub padding: u32,
+    /// Write the frame positions to this JSON file
+    pub atlas: Option<String>,
+}
+
+impl AugeFilter for SpritePackFilter {
+    fn apply(&self, _img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let frames = self
+            .files
+            .iter()
+            .map(|path| load_image(path).map(|img| img.to_rgba8()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let cell = frames.iter().fold((1, 1), |(w, h), frame| {
+            (w.max(frame.width()), h.max(frame.height()))
+        });
+        let columns = self
+            .columns
+            .unwrap_or_else(|| (frames.len() as f64).sqrt().ceil() as u32);
+        let (sheet, rects) = tile(&frames, columns, cell, self.padding, Rgba([0, 0, 0, 0]));
+
+        if let Some(path) = &self.atlas {
+            let atlas = Atlas {
+                width: sheet.width(),
+                height: sheet.height(),
+                frames: self
+                    .files
+                    .iter()
+                    .zip(&frames)
+                    .zip(rects)
+                    .map(|((file, frame), [x, y, _, _])| AtlasFrame {
+                        name: Path::new(file)
+                            .file_stem()
+                            .map(|stem| stem.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| file.clone()),
+                        x,
+                        y,
+                        width: frame.width(),
+                        height: frame.height(),
+                    })
+                    .collect(),
+            };
+            std::fs::write(path, serde_json::to_string_pretty(&atlas)?)?;
+        }
+        Ok(DynamicImage::ImageRgba8(sheet).into())
+    }
+}
+
+/// Cuts a sprite sheet back into frames, either along a regular grid or by an atlas
+pub struct SpriteUnpackFilter {
+    /// Cell size, with `padding` between cells and around the sheet as `sprite pack` lays them out
+    pub grid: Option<ResizeInput>,
+    pub padding: u32,
+    pub atlas: Option<String>,
+}
+
+impl SpriteUnpackFilter {
+    fn rects(&self, width: u32, height: u32) -> Result<Vec<[u32; 4]>, AugeError> {
+        if let Some(path) = &self.atlas {
+            let atlas: Atlas = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            return Ok(atlas
+                .frames
+                .iter()
+                .map(|frame| [frame.x, frame.y, frame.width, frame.height])
+                .collect());
+        }
+
+        let (cell_width, cell_height) = self
+            .grid
+            .as_ref()
+            .map(|grid| grid.size_keeping_auto(width, height))
+            .unwrap_or((width, height));
+        if cell_width == 0 || cell_height == 0 {
+            return Err(AugeError::InvalidArgument(
+                "grid cells must be at least one pixel wide and high".to_string(),
+            ));
+        }
+        let fits =
+            |size: u32, cell: u32| (size.saturating_sub(self.padding)) / (cell + self.padding);
+        let (columns, rows) = (fits(width, cell_width), fits(height, cell_height));
+        Ok((0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                [
+                    self.padding + column * (cell_width + self.padding),
+                    self.padding + row * (cell_height + self.padding),
+                    cell_width,
+                    cell_height,
+                ]
+            })
+            .collect())
+    }
+}
+
+impl AugeFilter for SpriteUnpackFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let sheet = img.to_rgba8();
+        let (width, height) = sheet.dimensions();
+
+        let mut frames = Vec::new();
+        for [x, y, w, h] in self.rects(width, height)? {
+            if x + w > width || y + h > height {
+                return Err(AugeError::InvalidArgument(format!(
+                    "frame {}x{} at {},{} lies outside the {}x{} sheet",
+                    w, h, x, y, width, height
+                )));
+            }
+            let frame: RgbaImage = imageops::crop_imm(&sheet, x, y, w, h).to_image();
+            // Unused grid cells, e.g. the rest of the last row, are left out
+            if self.atlas.is_none() && frame.pixels().all(|p| p[3] == 0) {
+                continue;
+            }
+            frames.push(Frame::from_parts(
+                frame,
+                0,
+                0,
+                Delay::from_numer_denom_ms(100, 1),
+            ));
+        }
+        Ok(FilterResult::Frames(frames))
+    }
+}
@@ -0,0 +1,140 @@
+use clap::ValueEnum;
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+
+use super::{AugeFilter, FilterResult, par_rows};
+use crate::progress::{self, Progress};
+use crate::{inout::load_image, types::AugeError};
+
+/// Bytes of frame data the median keeps in memory at once
+const MEDIAN_BUDGET: usize = 256 << 20;
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum StackMode {
+    /// Average, reduces noise. Frames are streamed, only running sums are kept
+    Mean,
+    /// Middle value, drops anything that shows up in less than half of the frames. Large
+    /// stacks are read in bands of rows, decoding every frame once per band
+    Median,
+    /// Brightest value, long exposures and star trails
+    #[value(alias = "max")]
+    Lighten,
+    /// Darkest value
+    #[value(alias = "min")]
+    Darken,
+    /// Adds up light from every frame, clipping at white
+    Sum,
+}
+
+/// Per-pixel statistics over the input image and a list of aligned frames on disk.
+pub struct StackMergeFilter {
+    pub others: Vec<String>,
+    pub mode: StackMode,
+}
+
+impl StackMergeFilter {
+    /// Loads the next frame, refusing anything that is not aligned with the first one
+    fn load(&self, path: &str, width: u32, height: u32) -> Result<RgbaImage, AugeError> {
+        let frame = load_image(path)?.to_rgba8();
+        if frame.dimensions() != (width, height) {
+            return Err(AugeError::InvalidArgument(format!(
+                "{} is {}x{}, expected {}x{} like the first frame",
+                path,
+                frame.width(),
+                frame.height(),
+                width,
+                height
+            )));
+        }
+        Ok(frame)
+    }
+
+    /// Folds frames one by one into a per-channel accumulator, so only one frame is in memory
+    fn stream<C, F>(&self, first: RgbaImage, combine: C, finish: F) -> Result<RgbaImage, AugeError>
+    where
+        C: Fn(u32, u8) -> u32 + Sync,
+        F: Fn(u32) -> u8,
+    {
+        let (width, height) = first.dimensions();
+        let mut acc: Vec<u32> = first.as_raw().iter().map(|&v| v as u32).collect();
+        drop(first);
+
+        let bar = progress::bar("frames", self.others.len() as u64);
+        for path in &self.others {
+            let frame = self.load(path, width, height)?;
+            acc.par_iter_mut()
+                .zip(frame.as_raw().par_iter())
+                .for_each(|(acc, &v)| *acc = combine(*acc, v));
+            bar.advance(1);
+        }
+
+        let raw = acc.into_iter().map(finish).collect();
+        Ok(RgbaImage::from_raw(width, height, raw).expect("buffer matches dimensions"))
+    }
+
+    /// Works through the frames in bands of rows, holding only one band of every frame plus
+    /// the frame being decoded. Stacks within the budget take a single band, larger ones
+    /// decode every frame once per band.
+    fn median(&self, first: RgbaImage) -> Result<RgbaImage, AugeError> {
+        let (width, height) = first.dimensions();
+        let row_len = width as usize * 4;
+        let count = self.others.len() + 1;
+        let band_rows = (MEDIAN_BUDGET / (row_len * count).max(1)).clamp(1, height.max(1) as usize);
+        let bands = (height as usize).div_ceil(band_rows);
+
+        let mut out = vec![0u8; row_len * height as usize];
+        let bar = progress::bar("median", (bands * self.others.len()) as u64);
+        for band_start in (0..height as usize).step_by(band_rows) {
+            let rows = band_rows.min(height as usize - band_start);
+            let range = band_start * row_len..(band_start + rows) * row_len;
+            let mut others = Vec::with_capacity(self.others.len());
+            for path in &self.others {
+                others.push(self.load(path, width, height)?.as_raw()[range.clone()].to_vec());
+                bar.advance(1);
+            }
+            let frames: Vec<&[u8]> = std::iter::once(&first.as_raw()[range.clone()])
+                .chain(others.iter().map(Vec::as_slice))
+                .collect();
+
+            par_rows(&mut out[range], row_len, |y, row| {
+                let mut values = vec![0u8; frames.len()];
+                for (i, value) in row.iter_mut().enumerate() {
+                    let offset = y * row_len + i;
+                    for (slot, frame) in values.iter_mut().zip(&frames) {
+                        *slot = frame[offset];
+                    }
+                    values.sort_unstable();
+                    let mid = values.len() / 2;
+                    *value = if values.len().is_multiple_of(2) {
+                        (values[mid - 1] as u16 + values[mid] as u16).div_ceil(2) as u8
+                    } else {
+                        values[mid]
+                    };
+                }
+            });
+        }
+        Ok(RgbaImage::from_raw(width, height, out).expect("buffer matches dimensions"))
+    }
+}
+
+impl AugeFilter for StackMergeFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let first = img.to_rgba8();
+        drop(img);
+        let merged = match self.mode {
+            StackMode::Mean => {
+                let count = self.others.len() as u32 + 1;
+                self.stream(first, |acc, v| acc + v as u32, |sum| {
+                    ((sum + count / 2) / count) as u8
+                })?
+            }
+            StackMode::Median => self.median(first)?,
+            StackMode::Lighten => self.stream(first, |acc, v| acc.max(v as u32), |v| v as u8)?,
+            StackMode::Darken => self.stream(first, |acc, v| acc.min(v as u32), |v| v as u8)?,
+            StackMode::Sum => {
+                self.stream(first, |acc, v| (acc + v as u32).min(255), |v| v as u8)?
+            }
+        };
+        Ok(DynamicImage::ImageRgba8(merged).into())
+    }
+}
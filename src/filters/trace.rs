@@ -0,0 +1,294 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use image::{DynamicImage, Rgb};
+
+use super::plotter::Plotter;
+use super::{AugeFilter, FilterResult};
+use crate::color::luma;
+use crate::types::AugeError;
+
+/// Steps along the pixel corner lattice in clockwise order: east, south, west, north
+const STEPS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+/// Straight pieces each curve is split into for plotters
+const CURVE_STEPS: usize = 8;
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum TraceOutput {
+    /// Filled paths to edit or scale
+    Svg,
+    /// Outlines as G-code toolpaths for a pen plotter
+    Gcode,
+    /// Outlines as HPGL for a pen plotter
+    Hpgl,
+}
+
+/// Converts line art into SVG paths, potrace-style: the outlines of the foreground are
+/// followed along pixel corners, simplified to polygons and rounded into curves
+/// everywhere but at sharp corners.
+pub struct TraceFilter {
+    /// Pixels with a luma below this are foreground
+    pub threshold: u8,
+    /// Trace the light parts instead
+    pub invert: bool,
+    /// Outlines and holes enclosing fewer pixels are dropped as speckles
+    pub min_area: f64,
+    /// How far in pixels the simplified polygon may stray from the pixel outline
+    pub tolerance: f64,
+    /// Vertices turning sharper than this many degrees stay corners, the rest become curves
+    pub corner_angle: f64,
+    pub color: Rgb<u8>,
+    pub output: TraceOutput,
+    /// Paper and pen settings for plotter output
+    pub plotter: Plotter,
+}
+
+/// Closed loops of pixel corners around the foreground, outlines clockwise and holes
+/// counter-clockwise, with a vertex only where the direction changes
+fn contours(fg: &[bool], width: usize, height: usize) -> Vec<Vec<(f64, f64)>> {
+    let stride = width + 1;
+    let filled = |x: i64, y: i64| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < width
+            && (y as usize) < height
+            && fg[y as usize * width + x as usize]
+    };
+
+    // Outgoing boundary edges of every lattice vertex as a bit per step, foreground on the right
+    let mut outgoing = vec![0u8; stride * (height + 1)];
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            if !filled(x, y) {
+                continue;
+            }
+            let vertex = |vx: i64, vy: i64| vy as usize * stride + vx as usize;
+            if !filled(x, y - 1) {
+                outgoing[vertex(x, y)] |= 1 << 0;
+            }
+            if !filled(x + 1, y) {
+                outgoing[vertex(x + 1, y)] |= 1 << 1;
+            }
+            if !filled(x, y + 1) {
+                outgoing[vertex(x + 1, y + 1)] |= 1 << 2;
+            }
+            if !filled(x - 1, y) {
+                outgoing[vertex(x, y + 1)] |= 1 << 3;
+            }
+        }
+    }
+
+    let mut loops = Vec::new();
+    for start in 0..outgoing.len() {
+        while outgoing[start] != 0 {
+            let (mut x, mut y) = ((start % stride) as i64, (start / stride) as i64);
+            let mut direction = outgoing[start].trailing_zeros() as usize;
+            let mut points = Vec::new();
+            let mut previous = None;
+            loop {
+                let here = y as usize * stride + x as usize;
+                // At a saddle, where two pixels touch diagonally, turning right keeps them apart
+                if let Some(incoming) = previous {
+                    direction = [1, 0, 3]
+                        .iter()
+                        .map(|turn| (incoming + turn) % 4)
+                        .find(|&d| outgoing[here] & (1u8 << d) != 0)
+                        .expect("every boundary vertex has as many edges out as in");
+                    if direction != incoming {
+                        points.push((x as f64, y as f64));
+                    }
+                } else {
+                    points.push((x as f64, y as f64));
+                }
+                outgoing[here] &= !(1 << direction);
+                x += STEPS[direction].0;
+                y += STEPS[direction].1;
+                previous = Some(direction);
+                if y as usize * stride + x as usize == start {
+                    break;
+                }
+            }
+            // The start only counts as a vertex if the loop turns there
+            let first = outgoing_direction(&points);
+            if first == previous && points.len() > 1 {
+                points.remove(0);
+            }
+            loops.push(points);
+        }
+    }
+    loops
+}
+
+/// Direction of the first edge of a loop, if it runs along an axis
+fn outgoing_direction(points: &[(f64, f64)]) -> Option<usize> {
+    let (a, b) = (points.first()?, points.get(1)?);
+    let step = ((b.0 - a.0).signum() as i64, (b.1 - a.1).signum() as i64);
+    STEPS.iter().position(|&s| s == step)
+}
+
+/// Shoelace area, positive for clockwise loops in image coordinates
+fn area(points: &[(f64, f64)]) -> f64 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    ((p.0 - a.0 - t * dx).powi(2) + (p.1 - a.1 - t * dy).powi(2)).sqrt()
+}
+
+/// Douglas-Peucker on an open polyline, keeps both ends
+fn simplify_open(points: &[(f64, f64)], tolerance: f64, out: &mut Vec<(f64, f64)>) {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let farthest = (1..points.len() - 1)
+        .map(|i| (i, distance_to_segment(points[i], first, last)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match farthest {
+        Some((i, distance)) if distance > tolerance => {
+            simplify_open(&points[..=i], tolerance, out);
+            out.pop();
+            simplify_open(&points[i..], tolerance, out);
+        }
+        _ => out.extend([first, last]),
+    }
+}
+
+/// Douglas-Peucker on a closed loop, split at the vertex farthest from the first
+fn simplify(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    let origin = points[0];
+    let far = (1..points.len())
+        .max_by(|&a, &b| {
+            let d = |p: (f64, f64)| (p.0 - origin.0).powi(2) + (p.1 - origin.1).powi(2);
+            d(points[a]).total_cmp(&d(points[b]))
+        })
+        .unwrap_or(0);
+    let mut closed = points.to_vec();
+    closed.push(origin);
+
+    let mut out = Vec::new();
+    simplify_open(&closed[..=far], tolerance, &mut out);
+    out.pop();
+    simplify_open(&closed[far..], tolerance, &mut out);
+    out.pop();
+    // Tiny loops would collapse into a line, they keep their pixel outline
+    if out.len() < 3 { points.to_vec() } else { out }
+}
+
+/// Angle in degrees the outline turns by at `b`, 0 for straight on
+fn turn(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    let (ux, uy) = (b.0 - a.0, b.1 - a.1);
+    let (vx, vy) = (c.0 - b.0, c.1 - b.1);
+    (ux * vy - uy * vx)
+        .atan2(ux * vx + uy * vy)
+        .abs()
+        .to_degrees()
+}
+
+impl TraceFilter {
+    /// Path data for one loop: quadratic curves between edge midpoints, with the vertex
+    /// as control point, or straight lines through it at corners
+    fn path(&self, points: &[(f64, f64)], d: &mut String) {
+        let n = points.len();
+        let mid = |i: usize| {
+            let (a, b) = (points[i % n], points[(i + 1) % n]);
+            ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+        };
+        let start = mid(n - 1);
+        let _ = write!(d, "M{:.2} {:.2}", start.0, start.1);
+        for i in 0..n {
+            let (prev, here, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+            let end = mid(i);
+            if turn(prev, here, next) > self.corner_angle {
+                let _ = write!(d, "L{} {}L{:.2} {:.2}", here.0, here.1, end.0, end.1);
+            } else {
+                let _ = write!(d, "Q{} {} {:.2} {:.2}", here.0, here.1, end.0, end.1);
+            }
+        }
+        d.push('Z');
+    }
+
+    /// The same outline as `path` as a closed polyline, curves flattened for plotting
+    fn polyline(&self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        let n = points.len();
+        let mid = |i: usize| {
+            let (a, b) = (points[i % n], points[(i + 1) % n]);
+            ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+        };
+        let mut line = vec![mid(n - 1)];
+        for i in 0..n {
+            let (prev, here, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+            let (start, end) = (mid(i + n - 1), mid(i));
+            if turn(prev, here, next) > self.corner_angle {
+                line.extend([here, end]);
+            } else {
+                line.extend((1..=CURVE_STEPS).map(|step| {
+                    let t = step as f64 / CURVE_STEPS as f64;
+                    let (a, b, c) = ((1.0 - t) * (1.0 - t), 2.0 * t * (1.0 - t), t * t);
+                    (
+                        a * start.0 + b * here.0 + c * end.0,
+                        a * start.1 + b * here.1 + c * end.1,
+                    )
+                }));
+            }
+        }
+        line
+    }
+}
+
+impl AugeFilter for TraceFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let rgba = img.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        // Transparent pixels are background either way
+        let fg: Vec<bool> = rgba
+            .pixels()
+            .map(|p| {
+                let luma = luma([p[0], p[1], p[2]].map(f32::from));
+                p[3] >= 128 && ((luma < self.threshold as f32) != self.invert)
+            })
+            .collect();
+
+        let outlines: Vec<Vec<(f64, f64)>> = contours(&fg, width as usize, height as usize)
+            .into_iter()
+            .filter(|points| points.len() >= 3 && area(points).abs() >= self.min_area)
+            .map(|points| simplify(&points, self.tolerance.max(0.0)))
+            .collect();
+        let polylines = || -> Vec<_> { outlines.iter().map(|points| self.polyline(points)).collect() };
+        match self.output {
+            TraceOutput::Svg => {}
+            TraceOutput::Gcode => {
+                return Ok(FilterResult::Text(self.plotter.gcode(&polylines(), width, height)?));
+            }
+            TraceOutput::Hpgl => {
+                return Ok(FilterResult::Text(self.plotter.hpgl(&polylines(), width, height)?));
+            }
+        }
+
+        let mut d = String::new();
+        for points in &outlines {
+            self.path(points, &mut d);
+        }
+
+        let [r, g, b] = self.color.0;
+        Ok(FilterResult::Text(format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+             <path fill=\"#{r:02x}{g:02x}{b:02x}\" fill-rule=\"evenodd\" d=\"{d}\"/>\n\
+             </svg>",
+            w = width,
+            h = height,
+        )))
+    }
+}
@@ -0,0 +1,169 @@
+use image::{DynamicImage, Rgba};
+
+use super::warp::{Interpolation, warp};
+use super::{AugeFilter, FilterResult};
+use crate::types::{AugeError, Point};
+
+/// Row-major 3x3 projective matrix mapping source positions to destination positions
+#[derive(Debug, Clone, Copy)]
+pub struct Homography(pub [f64; 9]);
+
+impl Homography {
+    /// Accepts 6 values for an affine matrix (last row `0,0,1` implied) or 9 for a full one
+    pub fn from_values(values: &[f64]) -> Result<Self, AugeError> {
+        match values.len() {
+            6 => {
+                let mut m = [0.0; 9];
+                m[..6].copy_from_slice(values);
+                m[8] = 1.0;
+                Ok(Self(m))
+            }
+            9 => Ok(Self(values.try_into().expect("length checked"))),
+            n => Err(AugeError::InvalidArgument(format!(
+                "matrix needs 6 (affine) or 9 (perspective) values, got {}",
+                n
+            ))),
+        }
+    }
+
+    /// Solves for the perspective transform that moves every `from` corner onto its `to` pair
+    pub fn from_corners(from: &[Point], to: &[Point]) -> Result<Self, AugeError> {
+        if from.len() != 4 || to.len() != 4 {
+            return Err(AugeError::InvalidArgument(
+                "perspective needs exactly four source and four destination corners".to_string(),
+            ));
+        }
+
+        // Eight equations for h0..h7, h8 fixed to 1
+        let mut system = [[0.0f64; 9]; 8];
+        for (i, (src, dst)) in from.iter().zip(to).enumerate() {
+            let (x, y) = (src.0 as f64, src.1 as f64);
+            let (u, v) = (dst.0 as f64, dst.1 as f64);
+            system[2 * i] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+            system[2 * i + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+        }
+
+        for col in 0..8 {
+            let pivot = (col..8)
+                .max_by(|&a, &b| system[a][col].abs().total_cmp(&system[b][col].abs()))
+                .expect("non-empty range");
+            if system[pivot][col].abs() < 1e-12 {
+                return Err(AugeError::InvalidArgument(
+                    "corners are degenerate, three of them must not lie on one line".to_string(),
+                ));
+            }
+            system.swap(col, pivot);
+            let pivot_row = system[col];
+            for (r, row) in system.iter_mut().enumerate() {
+                if r != col {
+                    let factor = row[col] / pivot_row[col];
+                    for (value, pivot_value) in row.iter_mut().zip(pivot_row).skip(col) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+        }
+
+        let mut m = [1.0; 9];
+        for (i, row) in system.iter().enumerate() {
+            m[i] = row[8] / row[i];
+        }
+        Ok(Self(m))
+    }
+
+    pub fn inverse(&self) -> Result<Self, AugeError> {
+        let [a, b, c, d, e, f, g, h, i] = self.0;
+        let det = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+        if det.abs() < 1e-12 {
+            return Err(AugeError::InvalidArgument(
+                "matrix is not invertible".to_string(),
+            ));
+        }
+        let adjugate = [
+            e * i - f * h,
+            c * h - b * i,
+            b * f - c * e,
+            f * g - d * i,
+            a * i - c * g,
+            c * d - a * f,
+            d * h - e * g,
+            b * g - a * h,
+            a * e - b * d,
+        ];
+        Ok(Self(adjugate.map(|v| v / det)))
+    }
+
+    /// Applies the matrix to a point, `None` when it lands at infinity or behind the viewer
+    pub fn map(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let m = &self.0;
+        let w = m[6] * x + m[7] * y + m[8];
+        if w <= 1e-9 {
+            return None;
+        }
+        Some((
+            (m[0] * x + m[1] * y + m[2]) / w,
+            (m[3] * x + m[4] * y + m[5]) / w,
+        ))
+    }
+}
+
+/// Affine or perspective transform, sampled through the shared warp engine.
+pub struct TransformFilter {
+    pub matrix: Homography,
+    /// Grow or shrink the canvas to the transformed bounds instead of keeping the input size
+    pub fit: bool,
+    pub interpolation: Interpolation,
+    /// Fill for areas outside of the source, transparent when `None`
+    pub background: Option<Rgba<u8>>,
+}
+
+impl AugeFilter for TransformFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+
+        let (mut out_width, mut out_height) = (width, height);
+        let (mut offset_x, mut offset_y) = (0.0, 0.0);
+        if self.fit {
+            let (right, bottom) = (width as f64, height as f64);
+            let corners = [(0.0, 0.0), (right, 0.0), (0.0, bottom), (right, bottom)]
+                .map(|(x, y)| self.matrix.map(x, y));
+            let corners: Vec<(f64, f64)> = corners.into_iter().flatten().collect();
+            if corners.len() != 4 {
+                return Err(AugeError::InvalidArgument(
+                    "cannot fit a transform that sends the image to infinity".to_string(),
+                ));
+            }
+            let min_x = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min);
+            let max_x = corners
+                .iter()
+                .map(|c| c.0)
+                .fold(f64::NEG_INFINITY, f64::max);
+            let min_y = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min);
+            let max_y = corners
+                .iter()
+                .map(|c| c.1)
+                .fold(f64::NEG_INFINITY, f64::max);
+            out_width = (max_x - min_x).ceil().clamp(1.0, 32768.0) as u32;
+            out_height = (max_y - min_y).ceil().clamp(1.0, 32768.0) as u32;
+            (offset_x, offset_y) = (min_x, min_y);
+        }
+
+        // Corners above are in edge coordinates, the warp engine works with pixel centers
+        let inverse = self.matrix.inverse()?;
+        let out = warp(
+            &src,
+            out_width,
+            out_height,
+            self.interpolation,
+            self.background.unwrap_or(Rgba([0, 0, 0, 0])),
+            |x, y| {
+                inverse
+                    .map(x as f64 + 0.5 + offset_x, y as f64 + 0.5 + offset_y)
+                    .map(|(sx, sy)| ((sx - 0.5) as f32, (sy - 0.5) as f32))
+            },
+        );
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
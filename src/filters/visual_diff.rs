@@ -0,0 +1,184 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::Serialize;
+
+use super::{AugeFilter, FilterResult};
+use crate::inout::{EncodeOptions, tee_image};
+use crate::types::{AugeError, EncodableFormats, Rect};
+
+/// Largest possible YIQ color delta, between black and white
+const MAX_DELTA: f64 = 35215.0;
+
+const CHANGED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const ANTI_ALIASED: Rgba<u8> = Rgba([255, 200, 0, 255]);
+const IGNORED: Rgba<u8> = Rgba([120, 160, 255, 255]);
+
+/// Screenshot comparison after pixelmatch: colors are compared by perceived difference
+/// and pixels that only differ by anti-aliasing are reported but not counted.
+pub struct VisualDiffFilter {
+    pub other: DynamicImage,
+    /// Color difference in `0..=1` below which pixels count as equal
+    pub threshold: f64,
+    /// Count anti-aliased pixels as changes too
+    pub include_aa: bool,
+    /// Areas left out of the comparison, e.g. clocks or ads
+    pub ignore: Vec<Rect>,
+    /// Largest share of changed pixels in percent that still passes
+    pub fail_above: f64,
+    /// Where to save the marked image
+    pub marked: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VisualDiffReport {
+    pub pass: bool,
+    pub width: u32,
+    pub height: u32,
+    pub changed_pixels: u64,
+    pub changed_percent: f64,
+    pub anti_aliased_pixels: u64,
+    pub ignored_pixels: u64,
+}
+
+/// Pixel blended over white, as floats
+fn blended(p: &Rgba<u8>) -> [f64; 3] {
+    let alpha = p[3] as f64 / 255.0;
+    [0, 1, 2].map(|c| 255.0 + (p[c] as f64 - 255.0) * alpha)
+}
+
+fn brightness([r, g, b]: [f64; 3]) -> f64 {
+    r * 0.29889531 + g * 0.58662247 + b * 0.11448223
+}
+
+/// Squared perceptual distance in YIQ space, signed by which side is brighter
+fn color_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f64 {
+    if a == b {
+        return 0.0;
+    }
+    let ([r1, g1, b1], [r2, g2, b2]) = (blended(a), blended(b));
+    let y = brightness([r1, g1, b1]) - brightness([r2, g2, b2]);
+    let i = (r1 - r2) * 0.59597799 - (g1 - g2) * 0.2741761 - (b1 - b2) * 0.32180189;
+    let q = (r1 - r2) * 0.21147017 - (g1 - g2) * 0.52261711 + (b1 - b2) * 0.31114694;
+    let delta = 0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q;
+    if y > 0.0 { -delta } else { delta }
+}
+
+/// The 3x3 neighborhood of a pixel clipped to the image, center excluded
+fn neighbors(x: u32, y: u32, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    let (x0, y0) = (x.saturating_sub(1), y.saturating_sub(1));
+    let (x1, y1) = ((x + 1).min(width - 1), (y + 1).min(height - 1));
+    (y0..=y1)
+        .flat_map(move |ny| (x0..=x1).map(move |nx| (nx, ny)))
+        .filter(move |&n| n != (x, y))
+}
+
+/// Whether at least three neighbors share the pixel's exact color, image borders count as one
+fn has_many_siblings(img: &RgbaImage, x: u32, y: u32) -> bool {
+    let (width, height) = img.dimensions();
+    let on_edge = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+    let center = img.get_pixel(x, y);
+    let same = neighbors(x, y, width, height)
+        .filter(|&(nx, ny)| img.get_pixel(nx, ny) == center)
+        .count();
+    same + on_edge as usize > 2
+}
+
+/// A pixel is likely anti-aliased when it sits between a darker and a brighter
+/// neighbor and one of those belongs to a flat area in both images
+fn anti_aliased(img: &RgbaImage, other: &RgbaImage, x: u32, y: u32) -> bool {
+    let (width, height) = img.dimensions();
+    let on_edge = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+    let center = img.get_pixel(x, y);
+    let mut zeroes = on_edge as usize;
+    let (mut min, mut max) = (0.0, 0.0);
+    let (mut darkest, mut brightest) = ((x, y), (x, y));
+
+    for (nx, ny) in neighbors(x, y, width, height) {
+        let (a, b) = (blended(center), blended(img.get_pixel(nx, ny)));
+        let delta = brightness(a) - brightness(b);
+        if delta == 0.0 {
+            zeroes += 1;
+            if zeroes > 2 {
+                return false;
+            }
+        } else if delta < min {
+            min = delta;
+            darkest = (nx, ny);
+        } else if delta > max {
+            max = delta;
+            brightest = (nx, ny);
+        }
+    }
+    if min == 0.0 || max == 0.0 {
+        return false;
+    }
+    let flat =
+        |(px, py): (u32, u32)| has_many_siblings(img, px, py) && has_many_siblings(other, px, py);
+    flat(darkest) || flat(brightest)
+}
+
+impl AugeFilter for VisualDiffFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let a = img.to_rgba8();
+        let b = self.other.to_rgba8();
+        if a.dimensions() != b.dimensions() {
+            return Err(AugeError::InvalidArgument(format!(
+                "cannot diff a {}x{} screenshot against a {}x{} one",
+                a.width(),
+                a.height(),
+                b.width(),
+                b.height()
+            )));
+        }
+        let (width, height) = a.dimensions();
+        let max_delta = MAX_DELTA * self.threshold.clamp(0.0, 1.0).powi(2);
+
+        let (mut changed, mut aa, mut ignored) = (0u64, 0u64, 0u64);
+        let marked = RgbaImage::from_fn(width, height, |x, y| {
+            let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+            if self.ignore.iter().any(|rect| rect.contains(x, y)) {
+                ignored += 1;
+                return faded(pa, IGNORED);
+            }
+            if color_delta(pa, pb).abs() <= max_delta {
+                return faded(pa, Rgba([255, 255, 255, 255]));
+            }
+            if !self.include_aa && (anti_aliased(&a, &b, x, y) || anti_aliased(&b, &a, x, y)) {
+                aa += 1;
+                ANTI_ALIASED
+            } else {
+                changed += 1;
+                CHANGED
+            }
+        });
+
+        if let Some(path) = &self.marked {
+            let img = DynamicImage::ImageRgba8(marked);
+            tee_image(
+                &img,
+                path,
+                &EncodableFormats::Png,
+                &EncodeOptions::default(),
+            )?;
+        }
+        let pixels = (width as u64 * height as u64 - ignored).max(1);
+        let changed_percent = changed as f64 * 100.0 / pixels as f64;
+        let report = VisualDiffReport {
+            pass: changed_percent <= self.fail_above,
+            width,
+            height,
+            changed_pixels: changed,
+            changed_percent,
+            anti_aliased_pixels: aa,
+            ignored_pixels: ignored,
+        };
+        Ok(FilterResult::Text(serde_json::to_string(&report)?))
+    }
+}
+
+/// Light grayscale copy of the pixel tinted towards `tint`, to keep the markings readable
+fn faded(p: &Rgba<u8>, tint: Rgba<u8>) -> Rgba<u8> {
+    let luma = brightness(blended(p));
+    let gray = 255.0 - (255.0 - luma) * 0.25;
+    let [r, g, b] = [0, 1, 2].map(|c| (gray * tint[c] as f64 / 255.0).round() as u8);
+    Rgba([r, g, b, 255])
+}
@@ -0,0 +1,93 @@
+use image::{DynamicImage, Rgb, Rgba, RgbaImage};
+use rayon::prelude::*;
+use spade::{DelaunayTriangulation, Point2, Triangulation};
+
+use super::points::sample_points;
+use super::{AugeFilter, FilterResult};
+use crate::types::AugeError;
+
+/// Stained glass: every pixel joins its nearest seed and cells are filled with their mean color.
+pub struct VoronoiFilter {
+    pub cells: usize,
+    /// 0.0 scatters seeds evenly, 1.0 packs them along edges
+    pub detail: f32,
+    pub seed: u64,
+    /// Stroke cell borders in this color
+    pub border: Option<Rgb<u8>>,
+    pub border_width: u32,
+}
+
+impl AugeFilter for VoronoiFilter {
+    fn apply(&self, img: DynamicImage) -> Result<FilterResult, AugeError> {
+        let src = img.to_rgba8();
+        let (width, height) = src.dimensions();
+        if self.cells == 0 {
+            return Err(AugeError::InvalidArgument(
+                "voronoi needs at least one cell".to_string(),
+            ));
+        }
+
+        let seeds = sample_points(&img, self.cells, self.detail, self.seed)?
+            .into_iter()
+            .map(|(x, y)| Point2::new(x as f64, y as f64))
+            .collect();
+        let triangulation = DelaunayTriangulation::<Point2<f64>>::bulk_load(seeds)
+            .map_err(|err| AugeError::InvalidArgument(format!("triangulation failed: {}", err)))?;
+        if triangulation.num_vertices() == 0 {
+            return Ok(img.into());
+        }
+
+        // Nearest seed per pixel, the vertex index doubles as the cell id
+        let labels: Vec<usize> = (0..width as usize * height as usize)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % width as usize, i / width as usize);
+                triangulation
+                    .nearest_neighbor(Point2::new(x as f64, y as f64))
+                    .map(|vertex| vertex.fix().index())
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut sums = vec![[0u64; 5]; triangulation.num_vertices()];
+        for (pixel, &label) in src.pixels().zip(&labels) {
+            let sum = &mut sums[label];
+            for c in 0..4 {
+                sum[c] += pixel[c] as u64;
+            }
+            sum[4] += 1;
+        }
+        let colors: Vec<Rgba<u8>> = sums
+            .iter()
+            .map(|sum| {
+                let count = sum[4].max(1);
+                Rgba([0, 1, 2, 3].map(|c| (sum[c] / count) as u8))
+            })
+            .collect();
+
+        let label_at = |x: i64, y: i64| {
+            let x = x.clamp(0, width as i64 - 1) as usize;
+            let y = y.clamp(0, height as i64 - 1) as usize;
+            labels[y * width as usize + x]
+        };
+        let radius = (self.border_width.saturating_sub(1) / 2) as i64;
+
+        let out = RgbaImage::from_fn(width, height, |x, y| {
+            let label = labels[(y * width + x) as usize];
+            if let Some(border) = self.border {
+                let (x, y) = (x as i64, y as i64);
+                let on_border = label_at(x + 1, y) != label
+                    || label_at(x, y + 1) != label
+                    || (-radius..=radius).any(|dy| {
+                        (-radius..=radius).any(|dx| label_at(x + dx, y + dy) != label)
+                    });
+                if on_border {
+                    return Rgba([border[0], border[1], border[2], 255]);
+                }
+            }
+            colors[label]
+        });
+
+        Ok(DynamicImage::ImageRgba8(out).into())
+    }
+}
@@ -0,0 +1,123 @@
+use clap::ValueEnum;
+use image::{Rgba, RgbaImage};
+
+use super::par_rows;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Interpolation {
+    /// Blocky, but keeps exact pixel values
+    Nearest,
+    /// Smooth, slightly soft
+    Bilinear,
+    /// Sharper than bilinear, may ring on hard edges
+    Bicubic,
+}
+
+/// Samples `src` at a fractional position where integer coordinates are pixel centers.
+/// Positions more than half a pixel outside of the image are `None`.
+pub fn sample(src: &RgbaImage, x: f32, y: f32, interpolation: Interpolation) -> Option<Rgba<u8>> {
+    let (width, height) = src.dimensions();
+    if width == 0
+        || height == 0
+        || x < -0.5
+        || y < -0.5
+        || x > width as f32 - 0.5
+        || y > height as f32 - 0.5
+    {
+        return None;
+    }
+
+    let clamped = |px: i64, py: i64| {
+        let cx = px.clamp(0, width as i64 - 1) as u32;
+        let cy = py.clamp(0, height as i64 - 1) as u32;
+        src.get_pixel(cx, cy)
+    };
+
+    match interpolation {
+        Interpolation::Nearest => Some(*clamped(x.round() as i64, y.round() as i64)),
+        Interpolation::Bilinear => {
+            let (x0, y0) = (x.floor(), y.floor());
+            let (tx, ty) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let (p00, p10) = (clamped(x0, y0), clamped(x0 + 1, y0));
+            let (p01, p11) = (clamped(x0, y0 + 1), clamped(x0 + 1, y0 + 1));
+            Some(Rgba(std::array::from_fn(|c| {
+                let top = p00[c] as f32 * (1.0 - tx) + p10[c] as f32 * tx;
+                let bottom = p01[c] as f32 * (1.0 - tx) + p11[c] as f32 * tx;
+                (top * (1.0 - ty) + bottom * ty).round() as u8
+            })))
+        }
+        Interpolation::Bicubic => {
+            let (x0, y0) = (x.floor(), y.floor());
+            let (tx, ty) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let wx = catmull_rom_weights(tx);
+            let wy = catmull_rom_weights(ty);
+            let mut acc = [0.0f32; 4];
+            for (j, wy) in wy.iter().enumerate() {
+                for (i, wx) in wx.iter().enumerate() {
+                    let p = clamped(x0 + i as i64 - 1, y0 + j as i64 - 1);
+                    for c in 0..4 {
+                        acc[c] += p[c] as f32 * wx * wy;
+                    }
+                }
+            }
+            Some(Rgba(acc.map(|v| v.round().clamp(0.0, 255.0) as u8)))
+        }
+    }
+}
+
+fn catmull_rom_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t3 + 2.0 * t2 - t),
+        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+/// Folds a coordinate back into `[0, size - 1]` by mirroring at the borders
+pub fn reflect(v: f32, size: u32) -> f32 {
+    let max = (size.max(1) - 1) as f32;
+    if max == 0.0 {
+        return 0.0;
+    }
+    let period = 2.0 * max;
+    let v = v.rem_euclid(period);
+    if v > max { period - v } else { v }
+}
+
+/// Inverse-mapping warp engine shared by the geometric filters.
+///
+/// For every output pixel `map` returns the source position to sample, or `None`
+/// to leave the pixel filled with `background`. Rows are processed in parallel.
+pub fn warp<F>(
+    src: &RgbaImage,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+    background: Rgba<u8>,
+    map: F,
+) -> RgbaImage
+where
+    F: Fn(f32, f32) -> Option<(f32, f32)> + Sync,
+{
+    let mut out = RgbaImage::from_pixel(width, height, background);
+    if width == 0 || height == 0 {
+        return out;
+    }
+
+    par_rows(&mut out, width as usize * 4, |y, row| {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let sampled = map(x as f32, y as f32)
+                .and_then(|(sx, sy)| sample(src, sx, sy, interpolation));
+            if let Some(color) = sampled {
+                pixel.copy_from_slice(&color.0);
+            }
+        }
+    });
+
+    out
+}
@@ -0,0 +1,419 @@
+//! GPU backend for the heavy filters, blurs, resizes and per-pixel color transforms run as
+//! compute shaders. Without an adapter, or for images past the device limits, filters fall
+//! back to their CPU code, so `--backend gpu` never fails where the CPU would not.
+
+use std::f32::consts::PI;
+use std::sync::OnceLock;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba32FImage};
+use tracing::{debug, info, warn};
+use wgpu::util::DeviceExt;
+
+use crate::types::{Backend, SampleDepth};
+
+/// Backend from the command line, set once
+static BACKEND: OnceLock<Backend> = OnceLock::new();
+/// Device, created on first use and `None` without an adapter
+static GPU: OnceLock<Option<Gpu>> = OnceLock::new();
+
+pub fn configure(backend: Backend) {
+    let _ = BACKEND.set(backend);
+}
+
+/// The device when `--backend gpu` was asked for and an adapter was found
+pub fn gpu() -> Option<&'static Gpu> {
+    if BACKEND.get().copied().unwrap_or_default() != Backend::Gpu {
+        return None;
+    }
+    GPU.get_or_init(|| {
+        let gpu = pollster::block_on(Gpu::new());
+        if gpu.is_none() {
+            warn!("no GPU adapter found, running on the CPU");
+        }
+        gpu
+    })
+    .as_ref()
+}
+
+pub struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    resample: wgpu::ComputePipeline,
+    color: wgpu::ComputePipeline,
+}
+
+/// Pixel count of a 16 by 16 resample workgroup and a color workgroup
+const TILE: u32 = 16;
+const GROUP: u32 = 256;
+
+fn floats(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn words(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Kernel of the `image` crate's resampling filters and its support radius
+fn kernel(filter: FilterType) -> (fn(f32) -> f32, f32) {
+    fn cubic(x: f32) -> f32 {
+        // Catmull-Rom, the B = 0 and C = 0.5 spline
+        let a = x.abs();
+        let k = if a < 1.0 {
+            9.0 * a.powi(3) - 15.0 * a.powi(2) + 6.0
+        } else if a < 2.0 {
+            -3.0 * a.powi(3) + 15.0 * a.powi(2) - 24.0 * a + 12.0
+        } else {
+            0.0
+        };
+        k / 6.0
+    }
+    fn sinc(x: f32) -> f32 {
+        if x == 0.0 {
+            1.0
+        } else {
+            (PI * x).sin() / (PI * x)
+        }
+    }
+    match filter {
+        FilterType::Nearest => (|_| 1.0, 0.0),
+        FilterType::Triangle => (|x| (1.0 - x.abs()).max(0.0), 1.0),
+        FilterType::CatmullRom => (cubic, 2.0),
+        FilterType::Gaussian => (|x| (-2.0 * x * x).exp(), 3.0),
+        FilterType::Lanczos3 => (
+            |x| {
+                if x.abs() < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            },
+            3.0,
+        ),
+    }
+}
+
+/// Normalized taps of every output position along one axis, `[first, count, offset, 0]`
+/// spans into one weight list
+type Taps = (Vec<u32>, Vec<f32>);
+
+fn push_span(taps: &mut Taps, first: u32, span: Vec<f32>) {
+    let sum: f32 = span.iter().sum();
+    let offset = taps.1.len() as u32;
+    taps.0.extend([first, span.len() as u32, offset, 0]);
+    taps.1
+        .extend(span.iter().map(|w| if sum == 0.0 { 0.0 } else { w / sum }));
+}
+
+/// Taps like `image::imageops::resize`, whose filters widen with the reduction
+fn resize_taps(src: u32, dst: u32, filter: FilterType) -> Taps {
+    let (kernel, support) = kernel(filter);
+    let ratio = src as f32 / dst as f32;
+    let scale = ratio.max(1.0);
+    let reach = support * scale;
+    let mut taps = (Vec::new(), Vec::new());
+    for out in 0..dst {
+        let center = (out as f32 + 0.5) * ratio;
+        let left = ((center - reach).floor() as i64).clamp(0, src as i64 - 1);
+        let right = ((center + reach).ceil() as i64).clamp(left + 1, src as i64);
+        let span = (left..right)
+            .map(|i| kernel((i as f32 - (center - 0.5)) / scale))
+            .collect();
+        push_span(&mut taps, left as u32, span);
+    }
+    taps
+}
+
+/// Gaussian taps over three sigmas, cut at the edges and renormalized
+fn blur_taps(len: u32, sigma: f32) -> Taps {
+    let radius = (3.0 * sigma).ceil() as i64;
+    let mut taps = (Vec::new(), Vec::new());
+    for out in 0..len as i64 {
+        let left = (out - radius).max(0);
+        let right = (out + radius + 1).min(len as i64);
+        let span = (left..right)
+            .map(|i| (-((i - out) as f32).powi(2) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        push_span(&mut taps, left as u32, span);
+    }
+    taps
+}
+
+/// Float result in the layout and depth of the input
+fn restore(pixels: Rgba32FImage, like: &DynamicImage) -> DynamicImage {
+    let pixels = DynamicImage::ImageRgba32F(pixels);
+    let layout = match (like.color().has_color(), like.color().has_alpha()) {
+        (true, true) => pixels,
+        (true, false) => DynamicImage::ImageRgb32F(pixels.to_rgb32f()),
+        (false, true) => DynamicImage::ImageLumaA16(pixels.to_luma_alpha16()),
+        (false, false) => DynamicImage::ImageLuma16(pixels.to_luma16()),
+    };
+    SampleDepth::of(like).convert(&layout)
+}
+
+impl Gpu {
+    async fn new() -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..Default::default()
+            })
+            .await
+            .ok()?;
+        let info = adapter.get_info();
+        info!("running on {} through {}", info.name, info.backend);
+        // The largest buffers the adapter allows, photos outgrow the default limits fast
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("auge"),
+                required_limits: adapter.limits(),
+                ..Default::default()
+            })
+            .await
+            .ok()?;
+        let pipeline = |source: &str, entry: &str| {
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(entry),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry),
+                layout: None,
+                module: &module,
+                entry_point: Some(entry),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let resample = pipeline(include_str!("gpu/resample.wgsl"), "resample");
+        let color = pipeline(include_str!("gpu/color.wgsl"), "color");
+        Some(Gpu {
+            device,
+            queue,
+            resample,
+            color,
+        })
+    }
+
+    /// Whether a float buffer of the size fits the device
+    fn fits(&self, width: u32, height: u32) -> bool {
+        let limits = self.device.limits();
+        let size = width as u64 * height as u64 * 16;
+        let fits =
+            size <= limits.max_storage_buffer_binding_size as u64 && size <= limits.max_buffer_size;
+        if !fits {
+            debug!("{}x{} is past the device limits, falling back to the CPU", width, height);
+        }
+        fits
+    }
+
+    fn storage(&self, contents: &[u8], copy: bool) -> wgpu::Buffer {
+        let mut usage = wgpu::BufferUsages::STORAGE;
+        if copy {
+            usage |= wgpu::BufferUsages::COPY_SRC;
+        }
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents,
+                usage,
+            })
+    }
+
+    fn empty(&self, size: u64) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn uniform(&self, contents: &[u8]) -> wgpu::Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents,
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+    }
+
+    fn bind(&self, pipeline: &wgpu::ComputePipeline, buffers: &[&wgpu::Buffer]) -> wgpu::BindGroup {
+        let entries: Vec<wgpu::BindGroupEntry> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        })
+    }
+
+    fn dispatch(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        group: &wgpu::BindGroup,
+        groups: (u32, u32),
+    ) {
+        let mut pass = encoder.begin_compute_pass(&Default::default());
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, group, &[]);
+        pass.dispatch_workgroups(groups.0, groups.1, 1);
+    }
+
+    /// Runs the encoded passes and reads `buffer` back as an image
+    fn finish(
+        &self,
+        mut encoder: wgpu::CommandEncoder,
+        buffer: &wgpu::Buffer,
+        width: u32,
+        height: u32,
+    ) -> Option<Rgba32FImage> {
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: buffer.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        staging
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.device.poll(wgpu::PollType::Wait).ok()?;
+        receiver.recv().ok()?.ok()?;
+        let pixels = staging
+            .slice(..)
+            .get_mapped_range()
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        staging.unmap();
+        Rgba32FImage::from_raw(width, height, pixels)
+    }
+
+    /// Two passes of a separable filter, rows first
+    fn separable(
+        &self,
+        img: &DynamicImage,
+        (width, height): (u32, u32),
+        horizontal: Taps,
+        vertical: Taps,
+    ) -> Option<DynamicImage> {
+        let (src_width, src_height) = (img.width(), img.height());
+        if !self.fits(src_width.max(width), src_height.max(height)) {
+            return None;
+        }
+        let src = self.storage(&floats(img.to_rgba32f().as_raw()), false);
+        let between = self.empty(width as u64 * src_height as u64 * 16);
+        let dst = self.empty(width as u64 * height as u64 * 16);
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        let passes = [
+            (
+                &src,
+                &between,
+                horizontal,
+                (src_width, width, src_height, 1),
+            ),
+            (&between, &dst, vertical, (width, width, height, 0)),
+        ];
+        for (from, to, (spans, weights), (stride, out_width, out_height, axis)) in passes {
+            let params = self.uniform(&words(&[stride, out_width, out_height, axis]));
+            let spans = self.storage(&words(&spans), false);
+            // Empty bindings are not allowed, nearest neighbor still has one weight each
+            let weights = self.storage(&floats(&weights), false);
+            let group = self.bind(&self.resample, &[&params, from, to, &spans, &weights]);
+            let groups = (out_width.div_ceil(TILE), out_height.div_ceil(TILE));
+            self.dispatch(&mut encoder, &self.resample, &group, groups);
+        }
+        Some(restore(self.finish(encoder, &dst, width, height)?, img))
+    }
+
+    /// `DynamicImage::resize`, the largest size within `width` by `height` keeping the aspect
+    pub fn resize(
+        &self,
+        img: &DynamicImage,
+        width: u32,
+        height: u32,
+        filter: FilterType,
+    ) -> Option<DynamicImage> {
+        let ratio = (width as f64 / img.width() as f64).min(height as f64 / img.height() as f64);
+        let fit = |len: u32| ((len as f64 * ratio).round() as u32).max(1);
+        self.resize_exact(img, fit(img.width()), fit(img.height()), filter)
+    }
+
+    /// `DynamicImage::resize_exact`
+    pub fn resize_exact(
+        &self,
+        img: &DynamicImage,
+        width: u32,
+        height: u32,
+        filter: FilterType,
+    ) -> Option<DynamicImage> {
+        if width == 0 || height == 0 || img.width() == 0 || img.height() == 0 {
+            return None;
+        }
+        let horizontal = resize_taps(img.width(), width, filter);
+        let vertical = resize_taps(img.height(), height, filter);
+        self.separable(img, (width, height), horizontal, vertical)
+    }
+
+    pub fn blur(&self, img: &DynamicImage, sigma: f32) -> Option<DynamicImage> {
+        if img.width() == 0 || img.height() == 0 {
+            return None;
+        }
+        let horizontal = blur_taps(img.width(), sigma);
+        let vertical = blur_taps(img.height(), sigma);
+        self.separable(img, (img.width(), img.height()), horizontal, vertical)
+    }
+
+    /// `rows` times RGBA plus `offset` for every pixel, color channels kept at `lower` or above
+    pub fn color(
+        &self,
+        img: &DynamicImage,
+        rows: [[f32; 4]; 4],
+        offset: [f32; 4],
+        lower: f32,
+    ) -> Option<DynamicImage> {
+        let (width, height) = (img.width(), img.height());
+        let count = width as u64 * height as u64;
+        if count == 0 || count > u32::MAX as u64 || !self.fits(width, height) {
+            return None;
+        }
+        // WGSL matrices are stored column by column
+        let mut transform: Vec<u8> = (0..16)
+            .flat_map(|i| rows[i % 4][i / 4].to_le_bytes())
+            .collect();
+        transform.extend(floats(&offset));
+        transform.extend(floats(&[lower]));
+        transform.extend(words(&[count as u32, 0, 0]));
+
+        let params = self.uniform(&transform);
+        let pixels = self.storage(&floats(img.to_rgba32f().as_raw()), true);
+        let group = self.bind(&self.color, &[&params, &pixels]);
+        let total = (count as u32).div_ceil(GROUP);
+        let max = self.device.limits().max_compute_workgroups_per_dimension;
+        let groups = (total.min(max), total.div_ceil(max));
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.dispatch(&mut encoder, &self.color, &group, groups);
+        Some(restore(self.finish(encoder, &pixels, width, height)?, img))
+    }
+}
@@ -0,0 +1,26 @@
+// Per-pixel pass of the GPU backend. Pixels are straight RGBA floats, one vec4 each.
+
+struct Transform {
+    matrix: mat4x4<f32>,
+    offset: vec4<f32>,
+    // Color channels are kept at or above this
+    lower: f32,
+    count: u32,
+}
+
+@group(0) @binding(0) var<uniform> transform: Transform;
+@group(0) @binding(1) var<storage, read_write> pixels: array<vec4<f32>>;
+
+// Affine color transform, for filters that map every pixel on its own
+@compute @workgroup_size(256)
+fn color(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+) {
+    let index = id.y * groups.x * 256u + id.x;
+    if (index >= transform.count) {
+        return;
+    }
+    let pixel = transform.matrix * pixels[index] + transform.offset;
+    pixels[index] = vec4<f32>(max(pixel.rgb, vec3<f32>(transform.lower)), pixel.a);
+}
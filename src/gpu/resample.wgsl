@@ -0,0 +1,37 @@
+// Separable filter pass of the GPU backend. Pixels are straight RGBA floats, one vec4 each.
+
+struct Resample {
+    src_width: u32,
+    dst_width: u32,
+    dst_height: u32,
+    horizontal: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Resample;
+@group(0) @binding(1) var<storage, read> src: array<vec4<f32>>;
+@group(0) @binding(2) var<storage, read_write> dst: array<vec4<f32>>;
+// Per output column or row: first source pixel, number of taps and offset into weights
+@group(0) @binding(3) var<storage, read> spans: array<vec4<u32>>;
+@group(0) @binding(4) var<storage, read> weights: array<f32>;
+
+// One axis of a separable filter, blurs keep the size and resizes change it
+@compute @workgroup_size(16, 16)
+fn resample(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.dst_width || id.y >= params.dst_height) {
+        return;
+    }
+    var sum = vec4<f32>(0.0);
+    if (params.horizontal == 1u) {
+        let span = spans[id.x];
+        let row = id.y * params.src_width;
+        for (var i = 0u; i < span.y; i++) {
+            sum += src[row + span.x + i] * weights[span.z + i];
+        }
+    } else {
+        let span = spans[id.y];
+        for (var i = 0u; i < span.y; i++) {
+            sum += src[(span.x + i) * params.src_width + id.x] * weights[span.z + i];
+        }
+    }
+    dst[id.y * params.dst_width + id.x] = sum;
+}
@@ -1,98 +1,1097 @@
-use std::io::{stdout, BufWriter, IsTerminal, Write, Cursor};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{stdout, BufWriter, IsTerminal, Read, Write, Cursor, ErrorKind};
+use std::sync::{Once, OnceLock};
+use std::time::Instant;
 
 use image::{
     codecs::{
         bmp::BmpEncoder,
         farbfeld::FarbfeldEncoder,
+        gif::{GifDecoder, GifEncoder, Repeat},
         hdr::HdrEncoder,
         ico::IcoEncoder,
-        jpeg::JpegEncoder,
         openexr::OpenExrEncoder,
-        png::PngEncoder,
+        png::PngDecoder,
         pnm::PnmEncoder,
         qoi::QoiEncoder,
         tga::TgaEncoder,
         tiff::TiffEncoder,
-        webp::WebPEncoder,
+        webp::{WebPDecoder, WebPEncoder},
     },
-    DynamicImage, ImageEncoder,
+    AnimationDecoder, Delay, DynamicImage, Frame, GrayImage, ImageDecoder, ImageEncoder,
+    ImageFormat, ImageReader, RgbImage, RgbaImage, imageops::FilterType,
 };
-use viuer::{print, Config};
+use color_quant::NeuQuant;
+use icy_sixel::SixelImage;
+use jpeg_encoder::{ColorType, Encoder as JpegEncoder, SamplingFactor};
+use tracing::{info, warn};
+use viuer::{get_kitty_support, is_iterm_supported, print, Config, KittySupport};
 
-use crate::types::{AugeError, EncodableFormats};
+use crate::progress::{self, Progress};
+use crate::{color, roi};
+use crate::types::{
+    AugeError, EncodableFormats, PixelFormat, PngCompression, PngDepth, PngFilter,
+    PreviewProtocol, RoiQuality, SampleDepth, Subsampling,
+};
 
+/// Whether results are previewed, `None` to follow STDOUT being a terminal, and the
+/// protocol to draw them with, set once from the command line
+static PREVIEW: OnceLock<(Option<bool>, PreviewProtocol)> = OnceLock::new();
 
-/// This function outputs image to terminal, or writes into pipe in a specified format
-pub fn print_image(img: &DynamicImage, format: EncodableFormats) -> Result<(), AugeError> {
-    if stdout().is_terminal() {
-        print(&img, &Config::default())?;
+/// Warns only once that the chosen protocol is not available, animations draw many times
+static FALLBACK: Once = Once::new();
+
+pub fn configure_preview(forced: Option<bool>, protocol: PreviewProtocol) {
+    let _ = PREVIEW.set((forced, protocol));
+}
+
+/// Whether results are drawn in the terminal instead of encoded to STDOUT
+pub fn previews() -> bool {
+    PREVIEW
+        .get()
+        .and_then(|(forced, _)| *forced)
+        .unwrap_or_else(|| stdout().is_terminal())
+}
+
+/// viuer settings drawing with the chosen protocol only, viuer falls back to half blocks
+/// when the terminal does not answer to it
+fn preview_config(width: Option<u32>) -> Config {
+    let protocol = PREVIEW.get().map_or_else(PreviewProtocol::default, |(_, protocol)| *protocol);
+    let (use_kitty, use_iterm) = match protocol {
+        PreviewProtocol::Auto => (true, true),
+        PreviewProtocol::Kitty => (true, false),
+        PreviewProtocol::Iterm => (false, true),
+        PreviewProtocol::Blocks => (false, false),
+        PreviewProtocol::Sixel => unreachable!("sixel previews are drawn without viuer"),
+    };
+    let missing = match protocol {
+        PreviewProtocol::Kitty => get_kitty_support() == KittySupport::None,
+        PreviewProtocol::Iterm => !is_iterm_supported(),
+        _ => false,
+    };
+    if missing {
+        FALLBACK.call_once(|| {
+            warn!("the terminal does not answer to the {:?} protocol, drawing half blocks", protocol)
+        });
+    }
+    Config {
+        width,
+        use_kitty,
+        use_iterm,
+        ..Default::default()
+    }
+}
+
+/// Draws a preview with the chosen protocol, `width` in terminal columns
+fn draw(img: &DynamicImage, width: Option<u32>) -> Result<(), AugeError> {
+    let protocol = PREVIEW.get().map_or_else(PreviewProtocol::default, |(_, protocol)| *protocol);
+    if protocol == PreviewProtocol::Sixel {
+        return draw_sixel(img, width);
+    }
+    print(img, &preview_config(width))?;
+    Ok(())
+}
+
+/// Sixel images are sized in pixels, so the image is scaled down to fit `width` columns,
+/// or the terminal when unset, at the cell size the terminal reports
+fn draw_sixel(img: &DynamicImage, width: Option<u32>) -> Result<(), AugeError> {
+    // Forced previews may not write to a terminal at all, those get 80x24 cells
+    let size = crossterm::terminal::window_size().unwrap_or(crossterm::terminal::WindowSize {
+        rows: 24,
+        columns: 80,
+        width: 0,
+        height: 0,
+    });
+    let (columns, rows) = (size.columns.max(1) as u32, size.rows.max(1) as u32);
+    // Terminals that do not report their size in pixels get a common 8x16 cell
+    let (cell_width, cell_height) = if size.width > 0 && size.height > 0 {
+        (size.width as u32 / columns, size.height as u32 / rows)
     } else {
-        let stdout_handle = stdout().lock();
-        let mut writer = BufWriter::new(stdout_handle);
-        
-        let pixels = img.as_bytes();
-        let color_type = img.color();
-        let (width, height) = (img.width(), img.height());
-        
-        match format {
-            EncodableFormats::Bmp => {
-                let encoder = BmpEncoder::new(&mut writer);
-                encoder.write_image(pixels, width, height, color_type.into())?;
-            }
-            EncodableFormats::Farbfeld => {
-                let encoder = FarbfeldEncoder::new(writer);
-                encoder.write_image(pixels, width, height, color_type.into())?;
-            }
-            EncodableFormats::Hdr => {
-                let encoder = HdrEncoder::new(writer);
-                encoder.write_image(pixels, width, height, img.color().into())?;
-            }
-            EncodableFormats::Ico => {
-                let encoder = IcoEncoder::new(writer);
-                encoder.write_image(pixels, width, height, color_type.into())?;
+        (8, 16)
+    };
+    let max_width = width.unwrap_or(columns) * cell_width.max(1);
+    // The last row stays free for the prompt
+    let max_height = match width {
+        Some(_) => u32::MAX,
+        None => rows.saturating_sub(1).max(1) * cell_height.max(1),
+    };
+    let rgba = if img.width() > max_width || img.height() > max_height {
+        img.resize(max_width, max_height, FilterType::Triangle).to_rgba8()
+    } else {
+        img.to_rgba8()
+    };
+    let (w, h) = (rgba.width() as usize, rgba.height() as usize);
+    let sixel = SixelImage::from_rgba(rgba.into_raw(), w, h).encode()?;
+    let mut out = stdout().lock();
+    // From the top left corner, the way viuer draws the other protocols
+    write!(out, "\x1b[1;1H{}", sixel)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Encoder settings from the command line, each format picks the ones it supports
+#[derive(Debug, Clone, Default)]
+pub struct EncodeOptions {
+    /// Resolution to record, PNG and JPEG only
+    pub dpi: Option<u16>,
+    /// 1 to 100, JPEG and lossy WebP default to 75
+    pub quality: Option<u8>,
+    /// JPEG defaults to full resolution color
+    pub subsampling: Option<Subsampling>,
+    pub png_compression: PngCompression,
+    pub png_filter: PngFilter,
+    pub png_depth: PngDepth,
+    /// WebP is lossless unless set
+    pub webp_lossy: bool,
+    /// Regions kept at a higher quality than the rest, JPEG and lossy WebP only
+    pub roi: Vec<RoiQuality>,
+    /// Written to PNG, JPEG and WebP, empty unless the input's is kept
+    pub metadata: Metadata,
+}
+
+/// Bare JPEG XL codestream and ISOBMFF container signatures
+const JPEG_XL_SIGNATURES: [&[u8]; 2] = [
+    &[0xff, 0x0a],
+    &[0x00, 0x00, 0x00, 0x0c, b'J', b'X', b'L', b' ', 0x0d, 0x0a, 0x87, 0x0a],
+];
+
+/// No JPEG XL codec is available to this build, say so instead of "unknown format". Every
+/// reader of encoded input goes through here before the image crate sees the bytes.
+fn check_supported(bytes: &[u8]) -> Result<(), AugeError> {
+    if JPEG_XL_SIGNATURES.iter().any(|signature| bytes.starts_with(signature)) {
+        return Err(AugeError::InvalidArgument(
+            "JPEG XL input is not supported, convert it first, e.g. with djxl".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Decodes an image held in memory, guessing the format from its contents
+pub fn decode_image(bytes: &[u8]) -> Result<DynamicImage, AugeError> {
+    check_supported(bytes)?;
+    // Pixels are turned upright so the result looks the same once the EXIF tag is gone
+    let started = Instant::now();
+    let bar = progress::bytes("decode", bytes.len() as u64);
+    let reader = progress::Reader::new(Cursor::new(bytes), &bar);
+    let reader = ImageReader::new(reader).with_guessed_format()?;
+    let format = reader.format().map_or("image", |format| format.extensions_str()[0]);
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let icc = decoder.icc_profile()?;
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    info!(
+        "decoded {}x{} {} as {:?} in {:.1?}",
+        img.width(),
+        img.height(),
+        format,
+        img.color(),
+        started.elapsed()
+    );
+    color::to_working_space(img, icc.as_deref())
+}
+
+/// The ICC profile an encoded image embeds, without decoding its pixels
+pub fn read_icc(bytes: &[u8]) -> Result<Option<Vec<u8>>, AugeError> {
+    check_supported(bytes)?;
+    let mut decoder = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?.into_decoder()?;
+    Ok(decoder.icc_profile()?)
+}
+
+/// Whether an input names an HTTP or HTTPS URL rather than a file
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Downloads an encoded image, failing after `timeout` seconds or past `max_size` bytes
+#[cfg(feature = "http")]
+pub fn fetch(url: &str, timeout: u64, max_size: u64) -> Result<Vec<u8>, AugeError> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(std::time::Duration::from_secs(timeout)))
+        .build()
+        .into();
+    let mut response = agent.get(url).call()?;
+    Ok(response.body_mut().with_config().limit(max_size).read_to_vec()?)
+}
+
+/// Reads and decodes an image from disk, guessing the format from its contents
+pub fn load_image(path: &str) -> Result<DynamicImage, AugeError> {
+    decode_image(&std::fs::read(path)?)
+}
+
+/// Frame with the delay given in milliseconds, 100 by default
+fn still(img: DynamicImage, delay: Option<u32>) -> Frame {
+    let delay = Delay::from_numer_denom_ms(delay.unwrap_or(100), 1);
+    Frame::from_parts(img.to_rgba8(), 0, 0, delay)
+}
+
+/// Frames of an animated GIF, WebP or PNG held in memory, or a still image as one frame.
+/// `delay` in milliseconds replaces the frame timing.
+pub fn decode_frames(bytes: &[u8], delay: Option<u32>) -> Result<Vec<Frame>, AugeError> {
+    let bar = progress::bytes("decode", bytes.len() as u64);
+    let reader = || progress::Reader::new(Cursor::new(bytes), &bar);
+    let frames = match image::guess_format(bytes) {
+        Ok(ImageFormat::Gif) => GifDecoder::new(reader())?.into_frames().collect_frames()?,
+        Ok(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(reader())?;
+            if !decoder.has_animation() {
+                return Ok(vec![still(decode_image(bytes)?, delay)]);
             }
-            EncodableFormats::Jpeg => {
-                let encoder = JpegEncoder::new(writer);
-                encoder.write_image(pixels, width, height, color_type.into())?;
+            decoder.into_frames().collect_frames()?
+        }
+        Ok(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(reader())?;
+            if !decoder.is_apng()? {
+                return Ok(vec![still(decode_image(bytes)?, delay)]);
             }
-            EncodableFormats::Png => {
-                let encoder = PngEncoder::new(writer);
-                encoder.write_image(pixels, width, height, color_type.into())?;
+            decoder.apng()?.into_frames().collect_frames()?
+        }
+        _ => return Ok(vec![still(decode_image(bytes)?, delay)]),
+    };
+    // Frames go to the working space here, like stills through decode_image, and come back
+    // from it once before encoding
+    frames
+        .into_iter()
+        .map(|frame| {
+            let (left, top) = (frame.left(), frame.top());
+            let delay = delay.map_or(frame.delay(), |ms| Delay::from_numer_denom_ms(ms, 1));
+            let img = color::to_working_space(DynamicImage::ImageRgba8(frame.into_buffer()), None)?;
+            Ok(Frame::from_parts(img.to_rgba8(), left, top, delay))
+        })
+        .collect()
+}
+
+/// Frames of an animation file, or of every image in a directory in name order
+pub fn load_frames(path: &str, delay: Option<u32>) -> Result<Vec<Frame>, AugeError> {
+    if !std::path::Path::new(path).is_dir() {
+        return decode_frames(&std::fs::read(path)?, delay);
+    }
+    let mut paths = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+    let bar = progress::bar("load", paths.len() as u64);
+    paths
+        .iter()
+        .map(|path| {
+            let frame = still(decode_image(&std::fs::read(path)?)?, delay);
+            bar.advance(1);
+            Ok(frame)
+        })
+        .collect()
+}
+
+/// `$XDG_CONFIG_HOME/auge`, or `~/.config/auge` when that is unset
+pub fn config_dir() -> Result<std::path::PathBuf, AugeError> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => std::path::PathBuf::from(dir),
+        _ => {
+            let home = std::env::var_os("HOME").ok_or_else(|| {
+                AugeError::InvalidArgument("no HOME to find the configuration in".to_string())
+            })?;
+            std::path::PathBuf::from(home).join(".config")
+        }
+    };
+    Ok(config.join("auge"))
+}
+
+/// Prefix of EXIF data in a JPEG APP1 segment, other containers usually store it bare
+const EXIF_PREFIX: &[u8] = b"Exif\0\0";
+/// Prefix of XMP data in a JPEG APP1 segment
+const XMP_PREFIX: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// Keyword of the PNG iTXt chunk holding XMP
+const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// Metadata carried over from the input, all empty means nothing is written
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub icc: Option<Vec<u8>>,
+    /// TIFF structure without the JPEG `Exif` prefix
+    pub exif: Option<Vec<u8>>,
+    pub xmp: Option<Vec<u8>>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self.icc.is_none() && self.exif.is_none() && self.xmp.is_none()
+    }
+
+    /// Blanks what makes outputs of the same input differ between runs or exports:
+    /// the creation date of ICC profiles and the dates in EXIF and XMP
+    pub fn strip_timestamps(&mut self) {
+        if let Some(icc) = &mut self.icc
+            && icc.len() >= 128
+        {
+            icc[24..36].fill(0);
+        }
+        if let Some(exif) = &mut self.exif {
+            blank_exif_dates(exif);
+        }
+        if let Some(xmp) = &mut self.xmp {
+            *xmp = blank_xmp_dates(xmp);
+        }
+    }
+}
+
+/// EXIF tags holding dates and times: DateTime, DateTimeOriginal and DateTimeDigitized,
+/// their time zone offsets and subsecond fractions
+const EXIF_DATE_TAGS: [usize; 9] = [
+    0x0132, 0x9003, 0x9004, 0x9010, 0x9011, 0x9012, 0x9290, 0x9291, 0x9292,
+];
+/// EXIF tag pointing to the Exif sub-IFD
+const EXIF_IFD_POINTER: usize = 0x8769;
+
+/// Whether a TIFF structure is little endian, `None` when it is not one
+fn exif_little_endian(exif: &[u8]) -> Option<bool> {
+    match exif.get(..2) {
+        Some(b"II") => Some(true),
+        Some(b"MM") => Some(false),
+        _ => None,
+    }
+}
+
+/// Unsigned integer of `len` bytes at `at` in the byte order of the TIFF structure
+fn exif_read(exif: &[u8], little: bool, at: usize, len: usize) -> Option<usize> {
+    let field = exif.get(at..at + len)?;
+    let fold = |acc: usize, &b: &u8| acc << 8 | b as usize;
+    Some(if little { field.iter().rev().fold(0, fold) } else { field.iter().fold(0, fold) })
+}
+
+/// Offsets of the 12-byte entries of the IFD at `ifd`
+fn exif_entries(exif: &[u8], little: bool, ifd: usize) -> Vec<usize> {
+    let count = exif_read(exif, little, ifd, 2).unwrap_or(0);
+    (0..count).map(|i| ifd + 2 + i * 12).filter(|&entry| entry + 12 <= exif.len()).collect()
+}
+
+/// Sets the EXIF orientation tag to upright, once decoding has applied it to the pixels
+fn reset_orientation(exif: &mut [u8]) {
+    let Some(little) = exif_little_endian(exif) else { return };
+    let Some(ifd) = exif_read(exif, little, 4, 4) else { return };
+    for entry in exif_entries(exif, little, ifd) {
+        if exif_read(exif, little, entry, 2) == Some(0x0112) {
+            let upright: [u8; 2] = if little { 1u16.to_le_bytes() } else { 1u16.to_be_bytes() };
+            exif[entry + 8..entry + 10].copy_from_slice(&upright);
+        }
+    }
+}
+
+/// Fills the date fields of IFD0 and the Exif sub-IFD with spaces, how EXIF marks unknown dates
+fn blank_exif_dates(exif: &mut [u8]) {
+    let Some(little) = exif_little_endian(exif) else { return };
+    let Some(ifd0) = exif_read(exif, little, 4, 4) else { return };
+    let mut entries = exif_entries(exif, little, ifd0);
+    let sub_ifd = entries
+        .iter()
+        .find(|&&entry| exif_read(exif, little, entry, 2) == Some(EXIF_IFD_POINTER))
+        .and_then(|&entry| exif_read(exif, little, entry + 8, 4));
+    if let Some(sub_ifd) = sub_ifd {
+        entries.extend(exif_entries(exif, little, sub_ifd));
+    }
+
+    for entry in entries {
+        let tag = exif_read(exif, little, entry, 2).unwrap_or(0);
+        // ASCII values only, up to 4 bytes are stored in the entry itself
+        if !EXIF_DATE_TAGS.contains(&tag) || exif_read(exif, little, entry + 2, 2) != Some(2) {
+            continue;
+        }
+        let Some(count) = exif_read(exif, little, entry + 4, 4) else { continue };
+        let at = if count <= 4 {
+            entry + 8
+        } else {
+            exif_read(exif, little, entry + 8, 4).unwrap_or(usize::MAX)
+        };
+        if let Some(value) = exif.get_mut(at..at.saturating_add(count)) {
+            let text = value.len().saturating_sub(1);
+            value[..text].fill(b' ');
+        }
+    }
+}
+
+/// XMP with the values of date properties emptied, e.g. xmp:ModifyDate, whether they are
+/// written as attributes or as elements
+fn blank_xmp_dates(xmp: &[u8]) -> Vec<u8> {
+    let text = String::from_utf8_lossy(xmp);
+    let mut out = String::with_capacity(text.len());
+    let mut rest: &str = &text;
+    while let Some(at) = rest.find("Date") {
+        let name_end = rest[at..]
+            .find(|c: char| !c.is_alphanumeric())
+            .map_or(rest.len(), |end| at + end);
+        let (head, tail) = rest.split_at(name_end);
+        out.push_str(head);
+        // Closing tags start with `</`, their content has been emptied already
+        let opening_tag = head
+            .rfind('<')
+            .is_some_and(|lt| !head[lt..].starts_with("</") && !head[lt..].contains('>'));
+        let value = if tail.starts_with("=\"") {
+            Some(("=\"", '"'))
+        } else if tail.starts_with('>') && opening_tag {
+            Some((">", '<'))
+        } else {
+            None
+        };
+        rest = match value {
+            Some((open, close)) => {
+                out.push_str(open);
+                let body = &tail[open.len()..];
+                &body[body.find(close).unwrap_or(body.len())..]
             }
-            EncodableFormats::Pnm => {
-                let encoder = PnmEncoder::new(writer);
-                encoder.write_image(pixels, width, height, color_type.into())?;
+            None => tail,
+        };
+    }
+    out.push_str(rest);
+    out.into_bytes()
+}
+
+/// EXIF and XMP from JPEG APP1 segments
+fn scan_jpeg(bytes: &[u8], metadata: &mut Metadata) {
+    let mut at = 2;
+    while at + 4 <= bytes.len() && bytes[at] == 0xff {
+        let marker = bytes[at + 1];
+        if marker == 0xff {
+            at += 1;
+            continue;
+        }
+        // Start of scan, only entropy coded data follows
+        if marker == 0xda || marker == 0xd9 {
+            break;
+        }
+        let len = u16::from_be_bytes([bytes[at + 2], bytes[at + 3]]) as usize;
+        let Some(data) = bytes.get(at + 4..at + 2 + len) else { break };
+        if marker == 0xe1 {
+            if let Some(exif) = data.strip_prefix(EXIF_PREFIX) {
+                metadata.exif = Some(exif.to_vec());
+            } else if let Some(xmp) = data.strip_prefix(XMP_PREFIX) {
+                metadata.xmp = Some(xmp.to_vec());
             }
-            EncodableFormats::Qoi => {
-                let encoder = QoiEncoder::new(writer);
-                encoder.write_image(pixels, width, height, color_type.into())?;
+        }
+        at += 2 + len;
+    }
+}
+
+/// EXIF from eXIf and XMP from an uncompressed iTXt chunk
+fn scan_png(bytes: &[u8], metadata: &mut Metadata) {
+    let mut at = 8;
+    while let Some(header) = bytes.get(at..at + 8) {
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let Some(data) = bytes.get(at + 8..at + 8 + len) else { break };
+        match &header[4..] {
+            b"eXIf" => metadata.exif = Some(data.to_vec()),
+            b"iTXt" => {
+                // Keyword, compression flag and method, language tag, translated keyword, text
+                let mut fields = data.splitn(2, |&b| b == 0);
+                if fields.next() == Some(XMP_KEYWORD.as_bytes()) {
+                    let rest = fields.next().unwrap_or_default();
+                    if rest.first() == Some(&0) {
+                        let text = rest.get(2..).unwrap_or_default().splitn(3, |&b| b == 0).nth(2);
+                        metadata.xmp = text.map(<[u8]>::to_vec);
+                    }
+                }
             }
-            EncodableFormats::Tga => {
-                let encoder = TgaEncoder::new(writer);
-                encoder.write_image(pixels, width, height, color_type.into())?;
+            b"IEND" => break,
+            _ => {}
+        }
+        at += 12 + len;
+    }
+}
+
+/// EXIF and XMP chunks of an extended WebP
+fn scan_webp(bytes: &[u8], metadata: &mut Metadata) {
+    let mut at = 12;
+    while let Some(header) = bytes.get(at..at + 8) {
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let Some(data) = bytes.get(at + 8..at + 8 + len) else { break };
+        match &header[..4] {
+            b"EXIF" => {
+                metadata.exif = Some(data.strip_prefix(EXIF_PREFIX).unwrap_or(data).to_vec())
             }
-            EncodableFormats::Webp => {
-                let encoder = WebPEncoder::new_lossless(writer);
+            b"XMP " => metadata.xmp = Some(data.to_vec()),
+            _ => {}
+        }
+        at += 8 + len + len % 2;
+    }
+}
+
+/// Reads ICC, EXIF and XMP from an encoded image. ICC comes from any format the decoder
+/// knows, EXIF and XMP from JPEG, PNG and WebP. The orientation is reset to upright.
+pub fn read_metadata(bytes: &[u8]) -> Result<Metadata, AugeError> {
+    let mut metadata = Metadata {
+        icc: read_icc(bytes)?,
+        ..Metadata::default()
+    };
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        scan_jpeg(bytes, &mut metadata);
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        scan_png(bytes, &mut metadata);
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        scan_webp(bytes, &mut metadata);
+    }
+    if let Some(exif) = &mut metadata.exif {
+        reset_orientation(exif);
+    }
+    Ok(metadata)
+}
+
+/// Palette of at most 256 colors and the palette index of every pixel.
+/// Images with few enough colors keep them exactly, others are quantized.
+fn palettize(img: &RgbaImage) -> (Vec<[u8; 4]>, Vec<u8>) {
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut lookup = std::collections::HashMap::new();
+    let exact = img.pixels().all(|p| {
+        if lookup.contains_key(&p.0) {
+            return true;
+        }
+        if palette.len() == 256 {
+            return false;
+        }
+        lookup.insert(p.0, palette.len() as u8);
+        palette.push(p.0);
+        true
+    });
+    if exact {
+        let indices = img.pixels().map(|p| lookup[&p.0]).collect();
+        return (palette, indices);
+    }
+
+    let quantizer = NeuQuant::new(10, 256, img.as_raw());
+    let palette = quantizer
+        .color_map_rgba()
+        .chunks_exact(4)
+        .map(|c| [c[0], c[1], c[2], c[3]])
+        .collect();
+    let indices = img.pixels().map(|p| quantizer.index_of(&p.0) as u8).collect();
+    (palette, indices)
+}
+
+/// PNG encoder with the compression, filter, resolution and metadata of `options`
+fn png_encoder<'a, W: Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    options: &'a EncodeOptions,
+) -> Result<png::Encoder<'a, W>, AugeError> {
+    let mut info = png::Info::with_size(width, height);
+    info.icc_profile = options.metadata.icc.as_deref().map(Cow::Borrowed);
+    info.exif_metadata = options.metadata.exif.as_deref().map(Cow::Borrowed);
+    info.pixel_dims = options.dpi.map(|dpi| {
+        let pixels_per_meter = (dpi as f64 / 0.0254).round() as u32;
+        png::PixelDimensions {
+            xppu: pixels_per_meter,
+            yppu: pixels_per_meter,
+            unit: png::Unit::Meter,
+        }
+    });
+    let mut encoder = png::Encoder::with_info(writer, info)?;
+    encoder.set_compression(match options.png_compression {
+        PngCompression::Fast => png::Compression::Fast,
+        PngCompression::Default => png::Compression::Default,
+        PngCompression::Best => png::Compression::Best,
+    });
+    match options.png_filter {
+        PngFilter::Adaptive => encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive),
+        PngFilter::None => encoder.set_filter(png::FilterType::NoFilter),
+        PngFilter::Sub => encoder.set_filter(png::FilterType::Sub),
+        PngFilter::Up => encoder.set_filter(png::FilterType::Up),
+        PngFilter::Avg => encoder.set_filter(png::FilterType::Avg),
+        PngFilter::Paeth => encoder.set_filter(png::FilterType::Paeth),
+    }
+    if let Some(xmp) = &options.metadata.xmp {
+        encoder.add_itxt_chunk(XMP_KEYWORD.to_string(), String::from_utf8_lossy(xmp).into_owned())?;
+    }
+    Ok(encoder)
+}
+
+/// Indexed PNG, a palette of at most 256 colors
+fn write_indexed_png<W: Write>(
+    img: &DynamicImage,
+    options: &EncodeOptions,
+    writer: W,
+) -> Result<(), AugeError> {
+    let (palette, indices) = palettize(&img.to_rgba8());
+    let mut encoder = png_encoder(writer, img.width(), img.height(), options)?;
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect::<Vec<_>>());
+    if palette.iter().any(|c| c[3] < 255) {
+        encoder.set_trns(palette.iter().map(|c| c[3]).collect::<Vec<_>>());
+    }
+    encoder.write_header()?.write_image_data(&indices)?;
+    Ok(())
+}
+
+fn write_png<W: Write>(
+    img: &DynamicImage,
+    options: &EncodeOptions,
+    writer: W,
+) -> Result<(), AugeError> {
+    let img = match options.png_depth {
+        PngDepth::Auto => Cow::Borrowed(img),
+        PngDepth::Indexed => return write_indexed_png(img, options, writer),
+        PngDepth::Eight => Cow::Owned(SampleDepth::Eight.convert(img)),
+        PngDepth::Sixteen => Cow::Owned(SampleDepth::Sixteen.convert(img)),
+    };
+    // PNG has no float samples, those keep the most precision at 16 bits
+    let img = match img.as_ref() {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            Cow::Owned(SampleDepth::Sixteen.convert(&img))
+        }
+        _ => img,
+    };
+    let (color, depth) = match img.as_ref() {
+        DynamicImage::ImageLuma8(_) => (png::ColorType::Grayscale, png::BitDepth::Eight),
+        DynamicImage::ImageLumaA8(_) => (png::ColorType::GrayscaleAlpha, png::BitDepth::Eight),
+        DynamicImage::ImageRgb8(_) => (png::ColorType::Rgb, png::BitDepth::Eight),
+        DynamicImage::ImageLuma16(_) => (png::ColorType::Grayscale, png::BitDepth::Sixteen),
+        DynamicImage::ImageLumaA16(_) => (png::ColorType::GrayscaleAlpha, png::BitDepth::Sixteen),
+        DynamicImage::ImageRgb16(_) => (png::ColorType::Rgb, png::BitDepth::Sixteen),
+        DynamicImage::ImageRgba8(_) => (png::ColorType::Rgba, png::BitDepth::Eight),
+        DynamicImage::ImageRgba16(_) => (png::ColorType::Rgba, png::BitDepth::Sixteen),
+        _ => unreachable!("float images are converted to 16 bits above"),
+    };
+    // 16-bit samples are big-endian in PNG
+    let data = match depth {
+        png::BitDepth::Sixteen => Cow::Owned(
+            img.as_bytes()
+                .chunks_exact(2)
+                .flat_map(|sample| u16::from_ne_bytes([sample[0], sample[1]]).to_be_bytes())
+                .collect(),
+        ),
+        _ => Cow::Borrowed(img.as_bytes()),
+    };
+
+    let mut encoder = png_encoder(writer, img.width(), img.height(), options)?;
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    encoder.write_header()?.write_image_data(&data)?;
+    Ok(())
+}
+
+/// Rewraps a WebP file in the extended format with ICCP, EXIF and XMP chunks
+fn webp_with_metadata(
+    webp: &[u8],
+    width: u32,
+    height: u32,
+    alpha: bool,
+    metadata: &Metadata,
+) -> Vec<u8> {
+    let chunk = |kind: &[u8], data: &[u8]| {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    };
+
+    // Image chunks are kept in order, a VP8X header from the encoder is replaced
+    let mut flags = 0u8;
+    let mut image = Vec::new();
+    let mut at = 12;
+    while let Some(header) = webp.get(at..at + 8) {
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let Some(data) = webp.get(at + 8..at + 8 + len) else { break };
+        match &header[..4] {
+            b"VP8X" => flags = data[0],
+            b"ICCP" | b"EXIF" | b"XMP " => {}
+            kind => image.extend(chunk(kind, data)),
+        }
+        at += 8 + len + len % 2;
+    }
+    if alpha {
+        flags |= 0x10;
+    }
+
+    let mut body = b"WEBP".to_vec();
+    let mut extra = Vec::new();
+    if let Some(icc) = &metadata.icc {
+        flags |= 0x20;
+        body.extend(chunk(b"ICCP", icc));
+    }
+    if let Some(exif) = &metadata.exif {
+        flags |= 0x08;
+        extra.extend(chunk(b"EXIF", exif));
+    }
+    if let Some(xmp) = &metadata.xmp {
+        flags |= 0x04;
+        extra.extend(chunk(b"XMP ", xmp));
+    }
+    let mut header = vec![flags, 0, 0, 0];
+    header.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+    header.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+    body.splice(4..4, chunk(b"VP8X", &header));
+    body.extend(image);
+    body.extend(extra);
+
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend(body);
+    out
+}
+
+/// JPEG holds 8-bit gray or color without alpha, anything else is converted first.
+/// Alpha is dropped by the encoder.
+fn write_jpeg<W: Write>(
+    img: &DynamicImage,
+    options: &EncodeOptions,
+    writer: W,
+) -> Result<(), AugeError> {
+    let (width, height) = (
+        u16::try_from(img.width()).ok(),
+        u16::try_from(img.height()).ok(),
+    );
+    let (Some(width), Some(height)) = (width, height) else {
+        return Err(AugeError::InvalidArgument(format!(
+            "JPEG is limited to 65535 pixels per side, got {}x{}",
+            img.width(),
+            img.height()
+        )));
+    };
+
+    let mut encoder = JpegEncoder::new(writer, options.quality.unwrap_or(75));
+    encoder.set_sampling_factor(match options.subsampling.unwrap_or(Subsampling::Yuv444) {
+        Subsampling::Yuv444 => SamplingFactor::R_4_4_4,
+        Subsampling::Yuv422 => SamplingFactor::R_4_2_2,
+        Subsampling::Yuv420 => SamplingFactor::R_4_2_0,
+    });
+    if let Some(dpi) = options.dpi {
+        encoder.set_density(jpeg_encoder::PixelDensity::dpi(dpi));
+    }
+    if let Some(icc) = &options.metadata.icc {
+        encoder.add_icc_profile(icc)?;
+    }
+    if let Some(exif) = &options.metadata.exif {
+        encoder.add_app_segment(1, [EXIF_PREFIX, exif].concat())?;
+    }
+    if let Some(xmp) = &options.metadata.xmp {
+        encoder.add_app_segment(1, [XMP_PREFIX, xmp].concat())?;
+    }
+    match img {
+        DynamicImage::ImageLuma8(gray) => encoder.encode(gray, width, height, ColorType::Luma)?,
+        DynamicImage::ImageLumaA8(_) | DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) => {
+            encoder.encode(&img.to_luma8(), width, height, ColorType::Luma)?
+        }
+        DynamicImage::ImageRgb8(rgb) => encoder.encode(rgb, width, height, ColorType::Rgb)?,
+        DynamicImage::ImageRgba8(rgba) => encoder.encode(rgba, width, height, ColorType::Rgba)?,
+        _ => encoder.encode(&img.to_rgb8(), width, height, ColorType::Rgb)?,
+    }
+    Ok(())
+}
+
+/// Image and settings to encode instead when quality regions apply to the format
+fn with_roi(
+    img: &DynamicImage,
+    format: &EncodableFormats,
+    options: &EncodeOptions,
+) -> Option<(DynamicImage, EncodeOptions)> {
+    let lossy = match format {
+        EncodableFormats::Jpeg => true,
+        EncodableFormats::Webp => options.webp_lossy,
+        _ => false,
+    };
+    if !lossy {
+        return None;
+    }
+    let (img, quality) = roi::prequantize(img, &options.roi, options.quality.unwrap_or(75))?;
+    let options = EncodeOptions {
+        quality: Some(quality),
+        roi: Vec::new(),
+        ..options.clone()
+    };
+    Some((img, options))
+}
+
+/// Encodes image in a specified format into any writer,
+/// applying whichever of the `options` the format supports.
+pub fn write_image<W: Write>(
+    img: &DynamicImage,
+    format: &EncodableFormats,
+    options: &EncodeOptions,
+    mut writer: W,
+) -> Result<(), AugeError> {
+    if let Some((img, options)) = with_roi(img, format, options) {
+        return write_image(&img, format, &options, writer);
+    }
+    // Filters keep the input depth, this is where it meets what the format can store
+    if !format.supports_depth(SampleDepth::of(img)) {
+        let depth = if format.supports_depth(SampleDepth::Eight) {
+            SampleDepth::Eight
+        } else {
+            SampleDepth::F32
+        };
+        return write_image(&depth.convert(img), format, options, writer);
+    }
+    let pixels = img.as_bytes();
+    let color_type = img.color();
+    let (width, height) = (img.width(), img.height());
+
+    match format {
+        EncodableFormats::Bmp => {
+            let encoder = BmpEncoder::new(&mut writer);
+            encoder.write_image(pixels, width, height, color_type.into())?;
+        }
+        EncodableFormats::Farbfeld => {
+            let encoder = FarbfeldEncoder::new(writer);
+            encoder.write_image(pixels, width, height, color_type.into())?;
+        }
+        EncodableFormats::Gif => {
+            let mut encoder = GifEncoder::new(writer);
+            encoder.encode_frame(Frame::new(img.to_rgba8()))?;
+        }
+        EncodableFormats::Hdr => {
+            let encoder = HdrEncoder::new(writer);
+            encoder.write_image(pixels, width, height, img.color().into())?;
+        }
+        EncodableFormats::Ico => {
+            let encoder = IcoEncoder::new(writer);
+            encoder.write_image(pixels, width, height, color_type.into())?;
+        }
+        EncodableFormats::Jpeg => write_jpeg(img, options, writer)?,
+        EncodableFormats::Png => write_png(img, options, writer)?,
+        EncodableFormats::Pnm => {
+            let encoder = PnmEncoder::new(writer);
+            encoder.write_image(pixels, width, height, color_type.into())?;
+        }
+        EncodableFormats::Qoi => {
+            let encoder = QoiEncoder::new(writer);
+            encoder.write_image(pixels, width, height, color_type.into())?;
+        }
+        EncodableFormats::Tga => {
+            let encoder = TgaEncoder::new(writer);
+            encoder.write_image(pixels, width, height, color_type.into())?;
+        }
+        EncodableFormats::Webp => {
+            let encoded = if options.webp_lossy {
+                // libwebp takes 8-bit RGB or RGBA only
+                let quality = options.quality.unwrap_or(75) as f32;
+                if color_type.has_alpha() {
+                    let rgba = img.to_rgba8();
+                    webp::Encoder::from_rgba(&rgba, width, height).encode_simple(false, quality)
+                } else {
+                    let rgb = img.to_rgb8();
+                    webp::Encoder::from_rgb(&rgb, width, height).encode_simple(false, quality)
+                }
+                .map_err(|err| {
+                    AugeError::InvalidArgument(format!("WebP encoding failed: {:?}", err))
+                })?
+                .to_vec()
+            } else {
+                let mut buffer = Vec::new();
+                let encoder = WebPEncoder::new_lossless(&mut buffer);
                 encoder.write_image(pixels, width, height, img.color().into())?;
+                buffer
+            };
+            if options.metadata.is_empty() {
+                writer.write_all(&encoded)?;
+            } else {
+                let alpha = color_type.has_alpha();
+                writer.write_all(&webp_with_metadata(&encoded, width, height, alpha, &options.metadata))?;
             }
-            EncodableFormats::Exr | EncodableFormats::Tiff => {
-                let mut buffer = Cursor::new(Vec::new());
-                
-                match format {
-                    EncodableFormats::Exr => {
-                        let encoder = OpenExrEncoder::new(&mut buffer);
-                        encoder.write_image(pixels, width, height, color_type.into())?;
-                    }
-                    EncodableFormats::Tiff => {
-                        let encoder = TiffEncoder::new(&mut buffer);
-                        encoder.write_image(pixels, width, height, color_type.into())?;
-                    }
-                    _ => unreachable!(),
+            writer.flush()?;
+        }
+        EncodableFormats::Exr | EncodableFormats::Tiff => {
+            let mut buffer = Cursor::new(Vec::new());
+
+            match format {
+                EncodableFormats::Exr => {
+                    let encoder = OpenExrEncoder::new(&mut buffer);
+                    encoder.write_image(pixels, width, height, color_type.into())?;
                 }
-                writer.write_all(buffer.get_ref())?;
-                writer.flush()?;
+                EncodableFormats::Tiff => {
+                    let encoder = TiffEncoder::new(&mut buffer);
+                    encoder.write_image(pixels, width, height, color_type.into())?;
+                }
+                _ => unreachable!(),
             }
+            writer.write_all(buffer.get_ref())?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// This function outputs image to terminal, or writes into pipe in a specified format
+pub fn print_image(
+    img: &DynamicImage,
+    format: EncodableFormats,
+    options: &EncodeOptions,
+) -> Result<(), AugeError> {
+    if previews() {
+        draw(img, None)?;
+    } else {
+        let started = Instant::now();
+        let stdout_handle = stdout().lock();
+        write_image(img, &format, options, BufWriter::new(stdout_handle))?;
+        info!(
+            "encoded {}x{} as {} in {:.1?}",
+            img.width(),
+            img.height(),
+            format.extension(),
+            started.elapsed()
+        );
+    }
+    Ok(())
+}
+
+/// Draws one frame of an animation over the previous one, from the top left corner
+/// of the terminal, `width` in columns
+pub fn show_frame(img: &DynamicImage, width: Option<u32>) -> Result<(), AugeError> {
+    draw(img, width)?;
+    stdout().flush()?;
+    Ok(())
+}
+
+/// Saves a snapshot of an intermediate result, the format follows the file extension
+/// and falls back to the output format when the extension is unknown
+pub fn tee_image(
+    img: &DynamicImage,
+    path: &str,
+    format: &EncodableFormats,
+    options: &EncodeOptions,
+) -> Result<(), AugeError> {
+    let format = EncodableFormats::from_path(path).unwrap_or_else(|| format.clone());
+    let started = Instant::now();
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_image(img, &format, options, &mut writer)?;
+    writer.flush()?;
+    info!("wrote {} as {} in {:.1?}", path, format.extension(), started.elapsed());
+    Ok(())
+}
+
+/// Substitutes `{}` in the template with a zero padded frame index
+pub fn template_path(template: &str, index: usize, total: usize) -> String {
+    let digits = total.max(1).to_string().len();
+    template.replace("{}", &format!("{:0width$}", index, width = digits))
+}
+
+/// Outputs a sequence of frames: one file per frame when a template is given,
+/// previews when stdout is a terminal or previews are forced, and an animated GIF otherwise
+pub fn print_frames(
+    frames: Vec<Frame>,
+    format: EncodableFormats,
+    options: &EncodeOptions,
+    template: Option<&str>,
+) -> Result<(), AugeError> {
+    if let Some(template) = template {
+        if !template.contains("{}") {
+            return Err(AugeError::InvalidArgument(format!(
+                "output template must contain '{{}}' for the frame index, got {}",
+                template
+            )));
+        }
+        let total = frames.len();
+        for (index, frame) in frames.into_iter().enumerate() {
+            let file = File::create(template_path(template, index, total))?;
+            let img = DynamicImage::ImageRgba8(frame.into_buffer());
+            write_image(&img, &format, options, BufWriter::new(file))?;
+        }
+    } else if previews() {
+        for frame in frames {
+            draw(&DynamicImage::ImageRgba8(frame.into_buffer()), None)?;
         }
+    } else {
+        let EncodableFormats::Gif = format else {
+            return Err(AugeError::InvalidArgument(
+                "multiple frames can only be piped as gif, use -f gif or --output-template"
+                    .to_string(),
+            ));
+        };
+        write_gif(frames, BufWriter::new(stdout().lock()))?;
+    }
+    Ok(())
+}
+
+/// Encodes frames as a looping animated GIF
+pub fn write_gif<W: Write>(frames: Vec<Frame>, writer: W) -> Result<(), AugeError> {
+    let mut encoder = GifEncoder::new(writer);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let started = Instant::now();
+    let count = frames.len();
+    let bar = progress::bar("encode", count as u64);
+    for frame in frames {
+        encoder.encode_frame(frame)?;
+        bar.advance(1);
+    }
+    info!("encoded {} GIF frames in {:.1?}", count, started.elapsed());
+    Ok(())
+}
+
+/// Reads the next image of a length-prefixed stream, `None` once the stream has ended
+pub fn read_framed<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>, AugeError> {
+    let mut length = [0u8; 8];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let length = u64::from_be_bytes(length);
+    let mut bytes = Vec::new();
+    reader.take(length).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < length {
+        return Err(AugeError::InvalidArgument(format!(
+            "stream ended inside an image, got {} of {} bytes",
+            bytes.len(),
+            length
+        )));
+    }
+    Ok(Some(bytes))
+}
+
+/// Writes one image of a length-prefixed stream
+pub fn write_framed<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), AugeError> {
+    writer.write_all(&(bytes.len() as u64).to_be_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+/// Reads the next raw frame of a back-to-back stream, `None` once the stream has ended
+pub fn read_raw_frame<R: Read>(
+    reader: &mut R,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+) -> Result<Option<DynamicImage>, AugeError> {
+    let mut buffer = vec![0u8; width as usize * height as usize * format.bytes_per_pixel()];
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if filled == 0 {
+        return Ok(None);
+    }
+    if filled < buffer.len() {
+        return Err(AugeError::InvalidArgument(format!(
+            "stream ended inside a frame, got {} of {} bytes, check --size and --pixfmt",
+            filled,
+            buffer.len()
+        )));
+    }
+
+    let img = match format {
+        PixelFormat::Rgb24 => RgbImage::from_raw(width, height, buffer).map(DynamicImage::ImageRgb8),
+        PixelFormat::Rgba => RgbaImage::from_raw(width, height, buffer).map(DynamicImage::ImageRgba8),
+        PixelFormat::Gray => GrayImage::from_raw(width, height, buffer).map(DynamicImage::ImageLuma8),
+    };
+    Ok(Some(img.expect("buffer matches dimensions")))
+}
+
+/// Writes a frame as raw bytes in the given pixel layout
+pub fn write_raw_frame<W: Write>(
+    writer: &mut W,
+    img: &DynamicImage,
+    format: PixelFormat,
+) -> Result<(), AugeError> {
+    match format {
+        PixelFormat::Rgb24 => writer.write_all(img.to_rgb8().as_raw())?,
+        PixelFormat::Rgba => writer.write_all(img.to_rgba8().as_raw())?,
+        PixelFormat::Gray => writer.write_all(img.to_luma8().as_raw())?,
     }
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,19 +1,66 @@
+mod color;
+mod condition;
+mod digest;
 mod filters;
+#[cfg(feature = "gpu")]
+mod gpu;
 mod inout;
+mod monitor;
+mod preset;
+mod progress;
+mod roi;
+mod simd;
 mod types;
 
-use std::io::{Read, stdin};
+use std::io::{BufWriter, IsTerminal, Read, Write, stdin, stdout};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+#[cfg(feature = "wasm")]
+use clap::{CommandFactory, FromArgMatches};
+use condition::Condition;
 use filters::{
-    FilterResult,
-    blob_detect::{BlobBackground, BlobColorMode},
-    filter_from_command,
+    FilterResult, NoOpFilter,
+    bench::BenchOutput,
+    blend::{BlendMode, Composite},
+    blob_detect::{BlobBackground, BlobColorMode, BlobColorSpace, BlobOutput},
+    chroma_view::ChromaView,
+    colormap::Colormap,
+    compare::CompareMetric,
+    distort::DistortEffect,
+    dotart::{DotOutput, DotSizeMode},
+    dotart_anim::AnimStyle,
+    dynthres::{AutoThreshold, ThresholdOutput},
+    edge::{EdgeAlgorithm, EdgeOperator},
+    fft::PassKind,
+    flip::FlipDirection,
+    inpaint::InpaintMethod,
+    kaleidoscope::MirrorMode,
+    lens::LensMode,
+    lowpoly::LowpolyOutput,
+    overlay::{Gravity, SeamBlend},
+    pad_multiple::PadMode,
+    polar::PolarMode,
+    seamless::SeamlessMethod,
+    stack_merge::StackMode,
+    trace::TraceOutput,
+    warp::Interpolation,
+    filter_from_args, filter_from_command,
 };
-use image::ImageReader;
+use image::{DynamicImage, Frame};
+use notify::{EventKind, RecursiveMode, Watcher};
+use tracing::{Level, debug, error, info};
 
-use inout::print_image;
-use types::{AugeError, Color, DotColorSource, EncodableFormats, OutputKind, ResizeInput};
+use inout::{EncodeOptions, Metadata, decode_image, is_url, print_frames, print_image, read_icc, read_metadata, read_framed, read_raw_frame, tee_image, write_framed, write_gif, write_image, write_raw_frame};
+use progress::Progress;
+use types::{
+    AugeError, Color, ColorSample, DotColorSource, DotJsonSchema, DotShape, EncodableFormats, Grid, OutputKind, Paper, PixelFormat, PngCompression,
+    PngDepth, PngFilter, Point, PreviewProtocol, Rect, ResizeInput, RoiQuality, SampleDepth, StreamKind, Subsampling, WorkingSpace,
+};
+#[cfg(feature = "gpu")]
+use types::Backend;
 
 #[derive(Debug, Parser)]
 #[command(version, about="Auge is a CLI image editing tool", long_about = None)]
@@ -22,13 +69,209 @@ struct Cli {
         long,
         short,
         value_name = "FILE",
-        help = "File to read from disk. If ommited STDIN is read."
+        help = "File to read from disk, or an http(s) URL with the http feature. If ommited STDIN is read."
     )]
     input: Option<String>,
 
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "SECONDS", default_value = "30", help = "Give up on downloading --input after this long")]
+    http_timeout: u64,
+
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "MB", default_value = "50", help = "Refuse downloads of --input larger than this")]
+    http_max_size: u64,
+
+    #[cfg(feature = "gpu")]
+    #[arg(long, value_enum, default_value = "cpu", help = "Run blurs, resizes and color filters on the GPU")]
+    backend: Backend,
+
+    #[cfg(feature = "wasm")]
+    #[arg(long, value_name = "DIR", help = "Load plugins from DIR instead of ~/.config/auge/plugins")]
+    plugin_dir: Option<String>,
+
+    #[cfg(feature = "wasm")]
+    #[arg(
+        long,
+        value_name = "FUEL",
+        default_value = "10000000000",
+        help = "Stop a plugin after roughly this many instructions, 0 for no limit"
+    )]
+    plugin_fuel: u64,
+
     #[arg(long, short, value_enum, default_value = "png", help = "Output format")]
     format: EncodableFormats,
 
+    #[arg(
+        long,
+        short,
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        help = "Encoding quality from 1 to 100 for lossy formats, JPEG and lossy WebP default to 75"
+    )]
+    quality: Option<u8>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "JPEG chroma subsampling, 420 gives the smallest files [default: 444]"
+    )]
+    subsampling: Option<Subsampling>,
+
+    #[arg(long, value_enum, default_value = "fast", help = "PNG compression effort")]
+    png_compression: PngCompression,
+
+    #[arg(long, value_enum, default_value = "adaptive", help = "PNG row filter")]
+    png_filter: PngFilter,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "auto",
+        help = "PNG sample depth, indexed writes a palette of at most 256 colors"
+    )]
+    png_depth: PngDepth,
+
+    #[arg(long, help = "Encode WebP lossily at --quality, for small delivery files")]
+    webp_lossy: bool,
+
+    #[arg(
+        long,
+        value_name = "XxY+WxH:QUALITY",
+        help = "Keep a region at a higher quality than the rest, JPEG and lossy WebP only, e.g. 120x40+200x240:90, can be repeated"
+    )]
+    roi_quality: Vec<RoiQuality>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "srgb",
+        help = "Color space filters work in, inputs with an ICC profile are converted to sRGB first and output is always sRGB"
+    )]
+    working_space: WorkingSpace,
+
+    #[arg(
+        long,
+        conflicts_with = "working_space",
+        help = "Blur, resize and blend in linear light, short for --working-space linear"
+    )]
+    linear: bool,
+
+    #[arg(long, help = "Leave pixels as they are whatever ICC profile the input embeds")]
+    ignore_icc: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Output sample depth, f32 for EXR, HDR and TIFF only [default: the result's own]"
+    )]
+    depth: Option<SampleDepth>,
+
+    #[arg(
+        long,
+        help = "Copy EXIF, XMP and ICC data from the input to PNG, JPEG and WebP output, with the orientation reset to upright"
+    )]
+    keep_metadata: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "keep_metadata",
+        help = "Write no EXIF, XMP or ICC data, the default"
+    )]
+    strip_metadata: bool,
+
+    #[arg(
+        long,
+        help = "Print SHA-256 digests of the encoded output and of the raw pixels to STDERR as JSON"
+    )]
+    emit_digest: bool,
+
+    #[arg(
+        long,
+        help = "Blank the dates in written ICC, EXIF and XMP data, so repeated runs give identical bytes"
+    )]
+    deterministic: bool,
+
+    #[arg(
+        long,
+        help = "Draw progress bars on STDERR while decoding, working through batches and animation frames, and in slow filters"
+    )]
+    progress: bool,
+
+    #[arg(
+        long,
+        short,
+        action = clap::ArgAction::Count,
+        help = "Log decode and encode times, chosen sizes and computed values like thresholds to STDERR, -vv for more detail"
+    )]
+    verbose: u8,
+
+    #[arg(long, conflicts_with = "verbose", help = "Print nothing but errors to STDERR")]
+    quiet: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "auto",
+        help = "Terminal graphics protocol of previews, auto picks the best one the terminal answers to"
+    )]
+    preview_protocol: PreviewProtocol,
+
+    #[arg(long, help = "Encode the result to STDOUT even when it is a terminal")]
+    no_preview: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "no_preview",
+        help = "Preview the result even when STDOUT is not a terminal, e.g. inside tmux"
+    )]
+    force_preview: bool,
+
+    #[arg(
+        long,
+        help = "Run again whenever the --input file changes, redrawing the terminal preview or rewriting the --tee file"
+    )]
+    watch: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Also run again when FILE changes, e.g. a CLUT or overlay the command loads, implies --watch"
+    )]
+    watch_file: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        help = "Write multi-frame results to files, '{}' is replaced with the frame index, e.g. frame_{}.png"
+    )]
+    output_template: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Also save the result to FILE, to keep snapshots of intermediate steps in a chain, e.g. auge --tee step1.png grayscale | auge g-blur -s 2"
+    )]
+    tee: Option<String>,
+
+    #[arg(
+        long = "if",
+        value_name = "CONDITION",
+        help = "Only run the command when the input matches, otherwise pass it through unchanged. Repeat for several conditions that must all hold, e.g. --if 'width > 2000'. Metrics: width, height, aspect, brightness, sharpness, alpha"
+    )]
+    conditions: Vec<Condition>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Filter a stream of images from STDIN to STDOUT in one process: raw frames of --size, e.g. ffmpeg -i in.mp4 -f rawvideo -pix_fmt rgb24 - | auge --stream rawvideo --size 1920x1080 g-blur -s 2 | ffmpeg -f rawvideo -pix_fmt rgb24 -s 1920x1080 -i - out.mp4, a tar archive of image files, or images each after an 8-byte big-endian length"
+    )]
+    stream: Option<StreamKind>,
+
+    #[arg(long, value_name = "WIDTHxHEIGHT", help = "Size of the frames of a raw stream")]
+    size: Option<Grid>,
+
+    #[arg(long, value_enum, default_value = "rgb24", help = "Pixel layout of raw stream frames, in and out")]
+    pixfmt: PixelFormat,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -47,12 +290,30 @@ enum Command {
         sigma: f32,
         #[arg(long, short, help = "Use fast, less accurate version")]
         fast: bool,
+        #[arg(
+            long,
+            conflicts_with = "fast",
+            help = "Convolve in the frequency domain, faster for very large sigma"
+        )]
+        fft: bool,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Grayscale map scaling sigma per pixel, black stays sharp and white gets the full blur"
+        )]
+        param_map: Option<String>,
     },
 
     #[command(about = "Apply dot art filter")]
     Dotart {
-        #[arg(long, short, value_enum, default_value = "raster")]
-        output: OutputKind,
+        #[arg(
+            long,
+            short,
+            value_enum,
+            default_value = "raster",
+            help = "Image, dots as JSON, or plotter toolpaths tracing the dot outlines"
+        )]
+        output: DotOutput,
         #[arg(
             long,
             short,
@@ -83,6 +344,93 @@ enum Command {
             default_value = "#000000"
         )]
         bg_color: Color,
+        #[arg(
+            long,
+            default_value = "circle",
+            help = "Dot shape of raster output: circle, square, diamond, ring, or char:C to draw the character C in every cell"
+        )]
+        shape: DotShape,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "fixed",
+            help = "Keep every dot the same size, or grow dots with brightness like a halftone"
+        )]
+        size_mode: DotSizeMode,
+        #[arg(long, default_value = "1", help = "Radius of the darkest dots with --size-mode luma")]
+        min_radius: u32,
+        #[arg(
+            long,
+            help = "Radius of the brightest dots with --size-mode luma [default: half of --spacing]"
+        )]
+        max_radius: Option<u32>,
+        #[arg(long, help = "Radius of every dot with --size-mode fixed [default: a quarter of --spacing]")]
+        radius: Option<u32>,
+        #[arg(
+            long,
+            help = "Distance between dot centers, the output grows or shrinks to fit [default: --scale]"
+        )]
+        spacing: Option<u32>,
+        #[arg(
+            long,
+            short = 'j',
+            default_value = "0",
+            help = "Scatter dots off the grid by up to this fraction of --spacing, e.g. 1 for stippling"
+        )]
+        jitter: f32,
+        #[arg(long, default_value = "0", help = "Seed for --jitter")]
+        seed: u64,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "v2",
+            help = "JSON layout, v1 for the unversioned background and dots only"
+        )]
+        json_schema: DotJsonSchema,
+        #[arg(long, help = "Also give dot positions in the JSON as fractions of the image size")]
+        normalized: bool,
+        #[command(flatten)]
+        plotter: PlotterArgs,
+    },
+
+    #[command(about = "Apply dot art to every frame of an animation, as a GIF or a terminal playback script")]
+    DotartAnim {
+        #[arg(
+            required = true,
+            help = "Animated GIF, WebP or PNG files, still frames, or directories of frames in name order"
+        )]
+        inputs: Vec<String>,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "dots",
+            help = "Dots give frames to pipe as -f gif, braille and ascii a shell script that plays in a terminal"
+        )]
+        style: AnimStyle,
+        #[arg(long, short, default_value = "16", help = "What area will one dot cover")]
+        scale: u32,
+        #[arg(
+            long,
+            short = 'l',
+            default_value = "0.75",
+            help = "Lower luma bound over all frames, e.g 0.1 = consider 10% darkest pixels as pitch black"
+        )]
+        lower_percentile: f32,
+        #[arg(
+            long,
+            short = 'u',
+            default_value = "0.1",
+            help = "Upper luma bound over all frames, e.g. 0.1 = consider 10% brightest pixels as white"
+        )]
+        upper_percentile: f32,
+        #[arg(long, short = 'c', help = "Color of dots", default_value = "preserve")]
+        dot_color: DotColorSource,
+        #[arg(long, short = 'b', help = "Color for background", default_value = "#000000")]
+        bg_color: Color,
+        #[arg(long, short = 'w', default_value = "80", help = "Characters per line for braille and ascii")]
+        columns: u32,
+        #[arg(long, short, help = "Frame delay in milliseconds, replaces the input timing [default for stills: 100]")]
+        delay: Option<u32>,
     },
 
     #[command(about = "Apply dynamic threshold filter")]
@@ -91,22 +439,66 @@ enum Command {
         #[arg(
             long,
             short = 'l',
-            help = "Lower luma bound, e.g 0.1 = consider 10% darkest pixels as black"
+            help = "Lower luma bound, e.g 0.1 = consider 10% darkest pixels as black",
+            required_unless_present_any = ["auto", "levels", "colors"]
         )]
-        lower_percentile: f32,
+        lower_percentile: Option<f32>,
         #[arg(
             long,
             short = 'u',
-            help = "Upper luma bound, e.g. 0.1 = consider 10% brightest pixels as white"
+            help = "Upper luma bound, e.g. 0.1 = consider 10% brightest pixels as white",
+            required_unless_present_any = ["auto", "levels", "colors"]
         )]
-        upper_percentile: f32,
+        upper_percentile: Option<f32>,
+        #[arg(
+            long,
+            short = 'a',
+            value_enum,
+            help = "Derive thresholds from the histogram instead of percentiles"
+        )]
+        auto: Option<AutoThreshold>,
 
-        #[arg(long, short = 'd', help = "Color to use for dark pixels")]
+        #[arg(long, short = 'd', help = "Color to use for dark pixels, the darkest level with --levels")]
         dark_color: Option<Color>,
-        #[arg(long, short = 'm', help = "Color to use for midtone pixels")]
+        #[arg(long, short = 'm', conflicts_with_all = ["levels", "colors"], help = "Color to use for midtone pixels")]
         mid_color: Option<Color>,
-        #[arg(long, short = 'b', help = "Color to use for bright pixels")]
+        #[arg(long, short = 'b', help = "Color to use for bright pixels, the brightest level with --levels")]
         bright_color: Option<Color>,
+        #[arg(
+            long,
+            short = 'n',
+            conflicts_with_all = ["lower_percentile", "upper_percentile", "auto", "colors"],
+            value_parser = clap::value_parser!(u32).range(2..=256),
+            help = "Posterize into N levels of equal pixel count, colored on a ramp from the dark to the bright color"
+        )]
+        levels: Option<u32>,
+        #[arg(
+            long,
+            short = 'c',
+            value_delimiter = ',',
+            conflicts_with_all = ["lower_percentile", "upper_percentile", "auto", "dark_color", "bright_color"],
+            help = "Comma separated colors of the levels, darkest first, posterizing into as many levels of equal pixel count, 256 at most"
+        )]
+        colors: Vec<Color>,
+        #[arg(
+            long,
+            help = "Threshold red, green and blue each on their own histogram instead of luma, every channel taking its value from the class color"
+        )]
+        per_channel: bool,
+        #[arg(
+            long,
+            short,
+            value_enum,
+            default_value = "image",
+            help = "Colored bands, a single channel binary mask, or the input cut out by the mask through its alpha"
+        )]
+        output: ThresholdOutput,
+        #[arg(
+            long,
+            default_value = "1",
+            help = "First band in the mask, 0 being the darkest; the default keeps all but the dark pixels"
+        )]
+        mask_from: u8,
     },
 
     #[command(about = "Resize image")]
@@ -125,71 +517,1800 @@ enum Command {
             help = "Filter to use for resizing",
             default_value = "catmull-rom"
         )]
-        filter: types::FilterType,
+        filter: types::ResizeFilterType,
+        #[command(flatten)]
+        annotations: AnnotationArgs,
+    },
+
+    #[command(about = "Cut out a rectangle")]
+    Crop {
+        #[arg(long, short, default_value = "0,0", help = "Top-left corner in pixels as X,Y")]
+        origin: Point,
+        #[arg(
+            long,
+            short,
+            help = "Size of the cut, e.g. 640x480, 640x (down to the bottom edge) or 50%"
+        )]
+        size: ResizeInput,
+        #[command(flatten)]
+        annotations: AnnotationArgs,
+    },
+
+    #[command(about = "Mirror the image")]
+    Flip {
+        #[arg(value_enum, default_value = "horizontal")]
+        direction: FlipDirection,
+        #[command(flatten)]
+        annotations: AnnotationArgs,
+    },
+
+    #[command(about = "Rotate around the center, growing the canvas to fit")]
+    Rotate {
+        #[arg(allow_hyphen_values = true, help = "Angle in degrees, positive is clockwise")]
+        angle: f32,
+        #[arg(long, short, value_enum, default_value = "bilinear", help = "Sampling interpolation")]
+        interpolation: Interpolation,
+        #[arg(long, short, help = "Fill for uncovered corners, transparent if omitted")]
+        background: Option<Color>,
+        #[arg(long, help = "Crop to the largest rectangle without uncovered corners")]
+        autocrop: bool,
+        #[command(flatten)]
+        annotations: AnnotationArgs,
     },
 
     #[command(about = "Invert colors")]
     Invert,
 
+    #[command(about = "Adjust color saturation")]
+    Saturate {
+        #[arg(
+            long,
+            short,
+            default_value = "1.5",
+            help = "Saturation factor, 0 is grayscale, 1 unchanged and above 1 more vivid"
+        )]
+        amount: f32,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Grayscale map blending from unchanged (black) to the full amount (white) per pixel"
+        )]
+        param_map: Option<String>,
+    },
+
     #[command(about = "Apply sepia tone filter")]
     Sepia,
 
     #[command(about = "Apply edge detection filter")]
-    Edge,
-
-    #[command(about = "Detect and colorize blobs of pixels")]
-    BlobDetect {
+    Edge {
         #[arg(
             long,
             short,
-            default_value = "10",
-            help = "Threshold (0-255). Pixels matching the target color within this threshold are considered part of the blob."
+            value_enum,
+            default_value = "gradient",
+            help = "Edge detection algorithm"
         )]
-        threshold: u8,
-
-        #[arg(long, short, help = "Target color to detect blobs of.")]
+        algorithm: EdgeAlgorithm,
+        #[arg(
+            long,
+            short,
+            value_enum,
+            default_value = "sobel",
+            help = "Gradient operator"
+        )]
+        operator: EdgeOperator,
+        #[arg(
+            long,
+            short,
+            default_value = "1.4",
+            help = "Gaussian pre-smoothing strength (canny only)"
+        )]
+        sigma: f32,
+        #[arg(
+            long,
+            short,
+            default_value = "50",
+            help = "Weak edge gradient threshold, weak edges survive only when connected to strong ones (canny only)"
+        )]
+        low: f32,
+        #[arg(
+            long,
+            short = 'H',
+            default_value = "100",
+            help = "Strong edge gradient threshold (canny only)"
+        )]
+        high: f32,
+        #[arg(long, short, help = "Stretch the gradient map so the strongest edge is white")]
+        normalize: bool,
+        #[arg(
+            long,
+            short,
+            help = "Produce a binary edge map, gradients at or above this value become edges"
+        )]
+        threshold: Option<f32>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            requires = "threshold",
+            help = "Grayscale map scaling the threshold per pixel, darker areas pick up fainter edges"
+        )]
+        param_map: Option<String>,
+        #[arg(long, short, help = "Color of edges, white on overlay by default")]
         color: Option<Color>,
+        #[arg(long, help = "Draw edges on top of the original image")]
+        overlay: bool,
+    },
+
+    #[command(about = "Show the log-scaled magnitude spectrum of the image")]
+    FftView,
 
+    #[command(about = "Apply low- or high-pass filter in the frequency domain")]
+    FreqPass {
+        #[arg(long, short, value_enum, default_value = "low", help = "Which frequencies to keep")]
+        kind: PassKind,
         #[arg(
             long,
             short,
-            value_enum,
-            default_value = "rainbow",
-            help = "Coloring strategy for blobs"
+            help = "Cutoff frequency relative to Nyquist (0.0-1.0). Lower values remove more detail for low-pass"
         )]
-        mode: BlobColorMode,
+        cutoff: f32,
+        #[arg(long, short, help = "Use a hard cutoff instead of a smooth gaussian rolloff")]
+        ideal: bool,
+    },
 
+    #[command(about = "Recover detail in hazy or backlit photos with multi-scale Retinex")]
+    Retinex {
         #[arg(
             long,
-            short = 'b',
-            value_enum,
-            default_value = "black",
-            help = "Background style"
+            short,
+            value_delimiter = ',',
+            default_value = "15,80,250",
+            help = "Comma separated blur sigmas of the surround functions"
         )]
-        background: BlobBackground,
+        scales: Vec<f32>,
+        #[arg(long, short, default_value = "1.0", help = "Contrast gain of the output")]
+        gain: f32,
     },
-}
 
-fn main() -> Result<(), AugeError> {
-    let cli = Cli::parse();
+    #[command(about = "Apply a color lookup table")]
+    Clut {
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Hald CLUT image, as used by GIMP, ImageMagick and darktable"
+        )]
+        hald_clut: String,
+    },
 
-    let img = if let Some(path) = cli.input {
-        ImageReader::open(&path)?.decode()?
-    } else {
-        let mut handle = stdin().lock();
-        let mut buffer = Vec::new();
-        handle.read_to_end(&mut buffer)?;
-        image::load_from_memory(&buffer)?
-    };
+    #[command(about = "Generate an image from scratch, no input is read")]
+    Generate {
+        #[command(subcommand)]
+        kind: GenerateKind,
+    },
+
+    #[command(about = "Generate transition frames blending into another image")]
+    Crossfade {
+        #[arg(long, short, value_name = "FILE", help = "Image to fade into")]
+        other: String,
+        #[arg(long, short, default_value = "8", help = "Number of intermediate frames")]
+        frames: u32,
+        #[arg(long, short, default_value = "100", help = "Frame delay in milliseconds for animated output")]
+        delay: u32,
+        #[arg(long, short, help = "Also emit both inputs as the first and last frame")]
+        include_ends: bool,
+    },
+
+    #[command(about = "Fold the image into a kaleidoscope of mirrored wedges")]
+    Kaleidoscope {
+        #[arg(long, short, default_value = "6", help = "Number of wedges around the center")]
+        wedges: u32,
+        #[arg(long, short, default_value = "0", help = "Rotation of the sampled wedge in degrees")]
+        rotation: f32,
+        #[arg(
+            long,
+            short,
+            default_value = "0.5,0.5",
+            help = "Center of symmetry relative to image size, e.g. 0.5,0.5 for the middle"
+        )]
+        center: Point,
+        #[arg(long, short, value_enum, default_value = "bilinear", help = "Sampling interpolation")]
+        interpolation: Interpolation,
+    },
+
+    #[command(about = "Make the image symmetric by mirroring one half onto the other")]
+    Mirror {
+        #[arg(long, short, value_enum, default_value = "left-onto-right", help = "Which half is kept")]
+        mode: MirrorMode,
+    },
+
+    #[command(about = "Remap between rectangular and polar coordinates")]
+    Polar {
+        #[arg(long, short, value_enum, default_value = "to-polar", help = "Direction of the remapping")]
+        mode: PolarMode,
+        #[arg(
+            long,
+            short,
+            default_value = "0.5,0.5",
+            help = "Center of the rings relative to image size"
+        )]
+        center: Point,
+        #[arg(
+            long,
+            short,
+            default_value = "1.0",
+            help = "Horizon radius for tiny-planet, relative to half of the shorter side"
+        )]
+        zoom: f32,
+        #[arg(long, short, value_enum, default_value = "bilinear", help = "Sampling interpolation")]
+        interpolation: Interpolation,
+    },
+
+    #[command(about = "Apply fisheye, pinch or swirl distortion")]
+    Distort {
+        #[arg(value_enum, help = "Distortion effect")]
+        effect: DistortEffect,
+        #[arg(long, short, default_value = "1.0", help = "Fisheye and pinch intensity")]
+        strength: f32,
+        #[arg(long, short, default_value = "90", help = "Swirl rotation at the center in degrees")]
+        angle: f32,
+        #[arg(
+            long,
+            short,
+            default_value = "1.0",
+            help = "Radius of the affected area relative to half of the shorter side"
+        )]
+        radius: f32,
+        #[arg(
+            long,
+            short,
+            default_value = "0.5,0.5",
+            help = "Center of the effect relative to image size"
+        )]
+        center: Point,
+        #[arg(long, short, value_enum, default_value = "bilinear", help = "Sampling interpolation")]
+        interpolation: Interpolation,
+    },
+
+    #[command(about = "Cast a soft shadow beneath the opaque parts of the image")]
+    Dropshadow {
+        #[arg(
+            long,
+            short,
+            default_value = "8,8",
+            allow_hyphen_values = true,
+            help = "Shadow offset in pixels as X,Y, positive values go right and down"
+        )]
+        offset: Point,
+        #[arg(long, short, default_value = "6.0", help = "Softness of the shadow edge")]
+        sigma: f32,
+        #[arg(long, short = 'p', default_value = "0.6", help = "Shadow opacity (0.0-1.0)")]
+        opacity: f32,
+        #[arg(long, short, default_value = "#000000", help = "Shadow color")]
+        color: Color,
+    },
+
+    #[command(about = "Evaluate per-pixel math over the channels")]
+    Expr {
+        #[arg(
+            help = "Assignments separated by ';', e.g. 'r = clamp(r * 1.1 + 10); a = lum > 0.9 ? 0 : a'. \
+                    Variables: r, g, b, a (0-255), lum (0-1), x, y, w, h, pi. \
+                    Functions: abs, sqrt, pow, sin, cos, floor, ceil, round, min, max, clamp, mix"
+        )]
+        program: String,
+    },
+
+    #[command(about = "Round the corners or cut out a circle, leaving the rest transparent")]
+    Roundcorners {
+        #[arg(
+            long,
+            short,
+            default_value = "32",
+            help = "Corner radius in pixels, capped at half of the shorter side"
+        )]
+        radius: f32,
+        #[arg(long, short, help = "Crop to the centered square and cut out a circle")]
+        circle: bool,
+    },
+
+    #[cfg(feature = "script")]
+    #[command(
+        about = "Run a Rhai script over the image",
+        long_about = "Run a Rhai script over the image. The input is bound to `img` and whatever `img` \
+                      holds at the end becomes the output. Images expose `width`, `height`, \
+                      `get(x, y)` returning [r, g, b, a], `set(x, y, [r, g, b, a])`, \
+                      `apply(\"g-blur -s 2\")` to run a built-in command in place, `crop(x, y, w, h)` \
+                      and `paste(other, x, y)`. `new_image(w, h)` creates a transparent canvas."
+    )]
+    Script {
+        #[arg(value_name = "FILE", required_unless_present = "eval", help = "Script to run")]
+        file: Option<String>,
+        #[arg(long, short, conflicts_with = "file", help = "Run the given source instead of a file")]
+        eval: Option<String>,
+    },
+
+    #[command(about = "Composite a second image on top, e.g. a watermark")]
+    Overlay {
+        #[arg(long, short = 'I', value_name = "FILE", help = "Image to place on top")]
+        image: String,
+        #[arg(long, short, value_enum, default_value = "south-east", help = "Where to anchor the overlay")]
+        gravity: Gravity,
+        #[arg(
+            long,
+            short,
+            default_value = "0,0",
+            allow_hyphen_values = true,
+            help = "Margin from the anchored edges in pixels as X,Y"
+        )]
+        offset: Point,
+        #[arg(long, short, default_value = "1.0", help = "Resize the overlay by this factor")]
+        scale: f32,
+        #[arg(long, short = 'p', default_value = "1.0", help = "Overlay opacity (0.0-1.0)")]
+        opacity: f32,
+        #[arg(long, short, value_enum, default_value = "normal", help = "Blend mode of the overlay")]
+        mode: BlendMode,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "hard",
+            help = "Seam between overlay and base, pyramid fades it across frequency bands"
+        )]
+        blend: SeamBlend,
+    },
+
+    #[command(about = "Blend a second image over the input with a blend mode")]
+    Blend {
+        #[arg(long, short, value_name = "FILE", help = "Top layer, stretched to the input size")]
+        other: String,
+        #[arg(long, short, value_enum, default_value = "normal", help = "How colors of both layers mix")]
+        mode: BlendMode,
+        #[arg(
+            long,
+            short,
+            value_enum,
+            default_value = "over",
+            help = "Porter-Duff operator deciding which parts of both layers are kept"
+        )]
+        composite: Composite,
+        #[arg(long, short = 'p', default_value = "1.0", help = "Top layer opacity (0.0-1.0)")]
+        opacity: f32,
+    },
+
+    #[command(about = "Cut random fixed-size patches, e.g. for training data")]
+    SampleCrops {
+        #[arg(long, short = 'n', default_value = "16", help = "Number of crops")]
+        count: usize,
+        #[arg(long, short, help = "Crop size, e.g. 256x256, 256x (full height) or 25%")]
+        size: ResizeInput,
+        #[arg(long, default_value = "0", help = "Seed for crop placement")]
+        seed: u64,
+        #[arg(
+            long,
+            short = 'e',
+            help = "Skip crops whose luma entropy is below this many bits (0-8), e.g. 4 to drop flat sky"
+        )]
+        min_entropy: Option<f64>,
+    },
+
+    #[command(about = "Randomly flip, rotate, crop, color jitter and add noise for training data")]
+    Augment {
+        #[arg(value_name = "FILE", help = "More images to augment after the input")]
+        others: Vec<String>,
+        #[arg(long, short = 'n', default_value = "8", help = "Augmented copies per image")]
+        count: usize,
+        #[arg(long, default_value = "0", help = "Seed for all random choices")]
+        seed: u64,
+        #[arg(long, short = 'r', default_value = "15", help = "Largest rotation in degrees either way")]
+        max_rotation: f32,
+        #[arg(
+            long,
+            short = 'c',
+            default_value = "0.8",
+            help = "Smallest crop side relative to the image side, crops are resized back"
+        )]
+        min_crop: f32,
+        #[arg(
+            long,
+            short,
+            default_value = "0.2",
+            help = "Largest relative change of brightness, contrast and saturation"
+        )]
+        jitter: f32,
+        #[arg(long, default_value = "8", help = "Largest gaussian noise deviation in channel units (0-255)")]
+        noise: f32,
+        #[arg(long, help = "Also flip upside down, off by default as it rarely suits photos")]
+        vertical_flips: bool,
+        #[arg(long, short, value_name = "FILE", help = "Write the parameters of every copy as JSON, keyed by input file")]
+        log: Option<String>,
+    },
+
+    #[command(about = "Mark pixels that differ from another image, or summarize the differences")]
+    Diff {
+        #[arg(long, short, value_name = "FILE", help = "Image to compare against, same size as the input")]
+        other: String,
+        #[arg(
+            long,
+            short,
+            default_value = "0",
+            help = "Largest per-channel difference (0-255) still treated as unchanged"
+        )]
+        tolerance: u8,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "raster",
+            help = "Marked image, or JSON with changed pixel count, percentage and difference stats"
+        )]
+        output: OutputKind,
+        #[arg(long, short = 'c', default_value = "#ff0000", help = "Color of changed pixels")]
+        highlight: Color,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Also write the JSON stats to FILE, next to the marked image"
+        )]
+        stats: Option<String>,
+    },
+
+    #[command(about = "Compare UI screenshots, ignoring anti-aliasing, and print a pass/fail verdict as JSON")]
+    VisualDiff {
+        #[arg(long, short, value_name = "FILE", help = "Baseline screenshot, same size as the input")]
+        other: String,
+        #[arg(
+            long,
+            short,
+            default_value = "0.1",
+            help = "Perceived color difference (0.0-1.0) below which pixels count as equal"
+        )]
+        threshold: f64,
+        #[arg(long, help = "Count anti-aliasing differences as changes too")]
+        include_aa: bool,
+        #[arg(
+            long,
+            short,
+            value_name = "X,Y,WxH",
+            help = "Region left out of the comparison, repeat for several"
+        )]
+        ignore: Vec<Rect>,
+        #[arg(
+            long,
+            short,
+            default_value = "0",
+            help = "Largest percentage of changed pixels that still passes"
+        )]
+        fail_above: f64,
+        #[arg(
+            long,
+            short,
+            value_name = "FILE",
+            help = "Save an image marking changes red, anti-aliasing yellow and ignored regions blue"
+        )]
+        marked: Option<String>,
+    },
+
+    #[command(about = "Score the similarity to another image with SSIM, PSNR or MSE")]
+    Compare {
+        #[arg(long, short, value_name = "FILE", help = "Reference image, same size as the input")]
+        other: String,
+        #[arg(long, short, value_enum, default_value = "ssim")]
+        metric: CompareMetric,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "json",
+            help = "Score as JSON, or a per-pixel SSIM heatmap that is dark where structure differs"
+        )]
+        output: OutputKind,
+    },
+
+    #[command(about = "Pad width and height up to multiples of N, e.g. before feeding a neural network")]
+    PadMultiple {
+        #[arg(long, short = 'n', default_value = "32", help = "Both sides become multiples of this")]
+        multiple: u32,
+        #[arg(
+            long,
+            short,
+            value_enum,
+            default_value = "north-west",
+            help = "Where the original sits, north-west pads only right and bottom"
+        )]
+        gravity: Gravity,
+        #[arg(long, short, value_enum, default_value = "constant", help = "How the padding is filled")]
+        mode: PadMode,
+        #[arg(long, short, default_value = "#000000", help = "Padding color in constant mode")]
+        color: Color,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Write the added padding per side as JSON, to undo it later with crop"
+        )]
+        offsets: Option<String>,
+    },
+
+    #[command(about = "Report the bounding box of non-transparent content as JSON, or trim to it")]
+    AlphaBbox {
+        #[arg(
+            long,
+            short,
+            default_value = "0",
+            help = "Alpha (0-255) at or below which a pixel counts as transparent"
+        )]
+        threshold: u8,
+        #[arg(long, short, help = "Output the image cropped to the content instead of the report")]
+        crop: bool,
+    },
+
+    #[command(about = "Lay out several images in a grid as a contact sheet, no input is read")]
+    Montage {
+        #[arg(required = true, value_name = "FILE", help = "Images to place, in order")]
+        files: Vec<String>,
+        #[arg(long, short, help = "Images per row, a roughly square grid by default")]
+        columns: Option<u32>,
+        #[arg(
+            long,
+            short = 's',
+            help = "Cell size, e.g. 256x256 or 256x keeping the aspect; defaults to the largest image"
+        )]
+        cell: Option<ResizeInput>,
+        #[arg(long, short, default_value = "8", help = "Gap between cells and around the sheet in pixels")]
+        padding: u32,
+        #[arg(long, short, default_value = "#ffffff", help = "Sheet color")]
+        background: Color,
+        #[arg(long, short, help = "Print file names beneath the cells")]
+        labels: bool,
+    },
+
+    #[command(about = "Resize while keeping the corners unscaled (9-slice scaling)")]
+    Ninepatch {
+        #[arg(long, short, help = "Output size, e.g. 400x120, 400x (height unchanged) or 150%")]
+        target: ResizeInput,
+        #[arg(
+            long,
+            short,
+            value_delimiter = ',',
+            default_value = "16",
+            help = "Unscaled margins in pixels: ALL, HORIZONTAL,VERTICAL or LEFT,TOP,RIGHT,BOTTOM"
+        )]
+        border: Vec<u32>,
+        #[arg(long, help = "Repeat edges and center instead of stretching them")]
+        tile: bool,
+        #[arg(long, short, default_value = "catmull-rom", help = "Filter for the stretched parts")]
+        filter: types::FilterType,
+    },
+
+    #[command(about = "Downscale HiDPI screenshots in linear light and tag the output DPI")]
+    ShrinkRetina {
+        #[arg(long, short = 'd', default_value = "2", help = "Divide both sides by this")]
+        factor: f32,
+        #[arg(long, default_value = "72", help = "Resolution recorded in PNG and JPEG output")]
+        dpi: u16,
+        #[arg(long, short, default_value = "lanczos3", help = "Filter to use for resizing")]
+        filter: types::FilterType,
+    },
+
+    #[command(about = "Pack frames into a sprite sheet with a JSON atlas, or cut a sheet back into frames")]
+    Sprite {
+        #[command(subcommand)]
+        action: SpriteAction,
+    },
+
+    #[command(about = "Paste a patch with gradient-domain blending so it heals into the input")]
+    SeamlessClone {
+        #[arg(long, short, value_name = "FILE", help = "Patch to insert")]
+        source: String,
+        #[arg(
+            long,
+            short,
+            value_name = "FILE",
+            help = "Grayscale mask of the patch, white parts are transferred; the whole patch if omitted"
+        )]
+        mask: Option<String>,
+        #[arg(
+            long,
+            short,
+            default_value = "0,0",
+            allow_hyphen_values = true,
+            help = "Top-left corner of the patch in the input as X,Y"
+        )]
+        offset: Point,
+        #[arg(long, help = "Keep the input's texture where it is stronger than the patch's")]
+        mixed: bool,
+        #[arg(long, short, default_value = "400", help = "Solver iterations, more for large patches")]
+        iterations: u32,
+    },
+
+    #[command(about = "Reconstruct masked regions from their surroundings, e.g. blemishes, logos or sensor dust")]
+    Inpaint {
+        #[arg(
+            long,
+            short,
+            value_name = "FILE",
+            help = "Grayscale mask the size of the input, white parts are filled"
+        )]
+        mask: String,
+        #[arg(long, value_enum, default_value = "telea", help = "Reconstruction method")]
+        method: InpaintMethod,
+        #[arg(long, short, default_value = "5", help = "Neighborhood radius each pixel is estimated from")]
+        radius: u32,
+        #[arg(
+            long,
+            short,
+            default_value = "300",
+            help = "Isophote transport steps of the ns method"
+        )]
+        iterations: u32,
+    },
+
+    #[command(about = "Draw pixel grids, thirds, a center crosshair and safe-area frames for design review")]
+    GridOverlay {
+        #[arg(long, short, help = "Grid line every N pixels")]
+        spacing: Option<u32>,
+        #[arg(long, short, help = "Rule-of-thirds guides")]
+        thirds: bool,
+        #[arg(long, short = 'x', help = "Crosshair at the center")]
+        crosshair: bool,
+        #[arg(
+            long,
+            value_name = "PERCENT",
+            help = "Safe-area frame inset by this percentage on every side, repeat for several, e.g. --safe-area 5 --safe-area 10"
+        )]
+        safe_area: Vec<f32>,
+        #[arg(long, short, help = "Label grid lines with their pixel coordinate")]
+        labels: bool,
+        #[arg(long, short, default_value = "#ff00ff", help = "Guide color")]
+        color: Color,
+        #[arg(long, short = 'p', default_value = "0.6", help = "Guide opacity (0.0-1.0)")]
+        opacity: f32,
+        #[arg(long, default_value = "1", help = "Line thickness in pixels")]
+        thickness: u32,
+    },
+
+    #[command(about = "Trace dark shapes into SVG paths, for scalable line art from thresholded or edge images")]
+    Trace {
+        #[arg(long, short, default_value = "128", help = "Pixels with a luma below this are traced")]
+        threshold: u8,
+        #[arg(long, short, help = "Trace the light parts instead")]
+        invert: bool,
+        #[arg(long, short, default_value = "4", help = "Drop shapes and holes smaller than this many pixels")]
+        min_area: f64,
+        #[arg(
+            long,
+            default_value = "1.0",
+            help = "How far in pixels the outline may be straightened, higher gives fewer nodes"
+        )]
+        tolerance: f64,
+        #[arg(
+            long,
+            short = 'a',
+            default_value = "60",
+            help = "Turns sharper than this many degrees stay corners, 0 keeps polygons, 180 rounds everything"
+        )]
+        corner_angle: f64,
+        #[arg(long, short, default_value = "#000000", help = "Fill color of the paths")]
+        color: Color,
+        #[arg(
+            long,
+            short,
+            value_enum,
+            default_value = "svg",
+            help = "SVG, or plotter toolpaths along the outlines with curves flattened"
+        )]
+        output: TraceOutput,
+        #[command(flatten)]
+        plotter: PlotterArgs,
+    },
+
+    #[command(about = "Report the WCAG contrast ratio of two colors and which levels it passes, as JSON")]
+    ContrastCheck {
+        #[arg(
+            long,
+            short,
+            value_name = "COLOR|X,Y",
+            help = "Text color, as #rrggbb or a point of the input to sample"
+        )]
+        foreground: ColorSample,
+        #[arg(
+            long,
+            short,
+            value_name = "COLOR|X,Y",
+            help = "Background color, as #rrggbb or a point of the input to sample"
+        )]
+        background: ColorSample,
+        #[arg(
+            long,
+            short,
+            default_value = "0",
+            help = "Average samples over a square of this radius, to skip anti-aliased pixels"
+        )]
+        radius: u32,
+    },
+
+    #[command(about = "Composite transparent images over a solid color or checkerboard, for formats without alpha")]
+    Flatten {
+        #[arg(long, short, default_value = "#ffffff", help = "Background color")]
+        color: Color,
+        #[arg(long, short = 'k', value_name = "SIZE", help = "Checkerboard with cells of this many pixels instead of a solid background")]
+        checker: Option<u32>,
+        #[arg(long, default_value = "#cccccc", help = "Color of every other checkerboard cell")]
+        checker_color: Color,
+    },
+
+    #[command(about = "Describe the input: format, file size, dimensions, color type, frames and EXIF fields")]
+    Info {
+        #[arg(long, short, help = "Print JSON instead of text")]
+        json: bool,
+        /// The encoded input, filled in by main
+        #[arg(skip)]
+        source: Vec<u8>,
+    },
+
+    #[command(about = "Watch a directory and log stats, or the output of a command, for every new image as JSON lines")]
+    Monitor {
+        #[arg(help = "Directory to watch, e.g. a camera upload folder")]
+        dir: String,
+        #[arg(long, short, value_name = "FILE", help = "Append JSON lines to FILE instead of printing them")]
+        log: Option<String>,
+        #[arg(
+            long,
+            short,
+            value_name = "DIR",
+            help = "Write image results of the command to DIR, named after the input with the -f extension"
+        )]
+        output_dir: Option<String>,
+        #[arg(long, short, default_value = "1000", help = "Milliseconds between directory scans")]
+        interval: u64,
+        #[arg(long, help = "Also process the images already in the directory")]
+        existing: bool,
+        #[arg(long, help = "Process what has arrived once and exit instead of watching")]
+        once: bool,
+        #[arg(
+            last = true,
+            value_name = "COMMAND",
+            help = "Command to run on every new image, after --, e.g. auge monitor uploads -- alpha-bbox"
+        )]
+        command: Vec<String>,
+    },
+
+    #[command(about = "Play an animation in the terminal, optionally running a command on every frame")]
+    Play {
+        #[arg(
+            required = true,
+            help = "Animated GIF, WebP or PNG files, still frames, or directories of frames in name order"
+        )]
+        inputs: Vec<String>,
+        #[arg(long, short = 'n', default_value = "0", help = "Times to play, 0 loops until Ctrl-C")]
+        loops: u32,
+        #[arg(long, short, default_value = "1.0", help = "Playback rate, e.g. 0.5 for half speed")]
+        speed: f32,
+        #[arg(long, short, help = "Frame delay in milliseconds, replaces the input timing [default for stills: 100]")]
+        delay: Option<u32>,
+        #[arg(long, short, help = "Width in terminal columns, fits the terminal by default")]
+        width: Option<u32>,
+        #[arg(
+            last = true,
+            value_name = "COMMAND",
+            help = "Command to run on every frame, after --, e.g. auge play anim.gif -- g-blur -s 2"
+        )]
+        command: Vec<String>,
+    },
+
+    #[command(about = "Run a named preset, a sequence of commands from ~/.config/auge/presets.toml")]
+    Run {
+        #[arg(long, short, help = "Name of the preset, e.g. thumbnail")]
+        preset: String,
+        #[arg(long, value_name = "FILE", help = "Presets file to read instead of ~/.config/auge/presets.toml")]
+        presets: Option<String>,
+    },
+
+    #[command(about = "Save command lines as presets for `run`, or list, show and delete them")]
+    Preset {
+        #[command(subcommand)]
+        action: PresetAction,
+        #[arg(
+            long,
+            global = true,
+            value_name = "FILE",
+            help = "Presets file to use instead of ~/.config/auge/presets.toml"
+        )]
+        presets: Option<String>,
+    },
+
+    #[command(
+        about = "Pipe the RGBA pixels through a shell command, e.g. a Python script or ImageMagick",
+        long_about = "Pipe the RGBA pixels through a shell command, e.g. a Python script or ImageMagick. \
+                      The command reads the width and height as big-endian 32-bit integers followed \
+                      by the 8-bit RGBA pixels row by row, and writes the result the same way. \
+                      The size is also in AUGE_WIDTH and AUGE_HEIGHT, which --no-header commands \
+                      need: auge exec --no-header -c 'magick -size ${AUGE_WIDTH}x${AUGE_HEIGHT} \
+                      -depth 8 rgba:- -blur 0x3 rgba:-'"
+    )]
+    Exec {
+        #[arg(long, short, help = "Command to run through the shell")]
+        cmd: String,
+        #[arg(long, help = "Send and expect bare pixels of the input size, without the size header")]
+        no_header: bool,
+    },
+
+    /// Any other name runs the WebAssembly plugin of that name
+    #[cfg(feature = "wasm")]
+    #[command(external_subcommand)]
+    Plugin(Vec<String>),
+
+    #[command(about = "Time commands on the input or a generated image, e.g. auge bench 'g-blur -s 3' sepia")]
+    Bench {
+        #[arg(
+            value_name = "COMMAND",
+            help = "Commands to time, each as one argument with its options, a suite of common filters when none are given"
+        )]
+        commands: Vec<String>,
+        #[arg(
+            long,
+            short = 'n',
+            default_value = "10",
+            value_parser = clap::value_parser!(u32).range(1..),
+            help = "Timed runs of every command"
+        )]
+        runs: u32,
+        #[arg(long, default_value = "1", help = "Untimed runs before, to warm up caches and threads")]
+        warmup: u32,
+        #[arg(
+            long,
+            value_name = "WIDTHxHEIGHT",
+            help = "Time on a generated test image of this size, no input is read"
+        )]
+        generate: Option<Grid>,
+        #[arg(long, short, value_enum, default_value = "table")]
+        output: BenchOutput,
+    },
+
+    #[command(about = "Simulate or correct barrel and pincushion lens distortion")]
+    Lens {
+        #[arg(value_enum, default_value = "correct", help = "Add distortion or remove it")]
+        mode: LensMode,
+        #[arg(
+            long,
+            allow_hyphen_values = true,
+            default_value = "0.0",
+            help = "Primary radial coefficient, negative for barrel and positive for pincushion"
+        )]
+        k1: f32,
+        #[arg(
+            long,
+            allow_hyphen_values = true,
+            default_value = "0.0",
+            help = "Secondary radial coefficient, shapes the distortion near the corners"
+        )]
+        k2: f32,
+        #[arg(
+            long,
+            short,
+            default_value = "0.5,0.5",
+            help = "Optical center relative to image size"
+        )]
+        center: Point,
+        #[arg(long, short, value_enum, default_value = "bilinear", help = "Sampling interpolation")]
+        interpolation: Interpolation,
+    },
+
+    #[command(about = "Edge-preserving bilateral smoothing")]
+    Bilateral {
+        #[arg(long, short, default_value = "3.0", help = "Spatial extent of the smoothing")]
+        sigma_spatial: f32,
+        #[arg(
+            long,
+            short = 'c',
+            default_value = "30.0",
+            help = "How different colors may be and still get averaged together"
+        )]
+        sigma_color: f32,
+        #[arg(long, short, default_value = "1", help = "Number of passes")]
+        iterations: u32,
+    },
+
+    #[command(about = "Edge-preserving guided filter, fast at any radius")]
+    Guided {
+        #[arg(long, short, default_value = "8", help = "Window radius in pixels")]
+        radius: u32,
+        #[arg(
+            long,
+            short,
+            default_value = "0.01",
+            help = "Regularization above 0, larger values smooth across stronger edges"
+        )]
+        eps: f32,
+        #[arg(long, short, value_name = "FILE", help = "Guide image whose edges are kept, the input by default")]
+        guide: Option<String>,
+        #[arg(
+            long,
+            short,
+            default_value = "0.0",
+            help = "Add the removed detail back this many times, above 0 enhances detail"
+        )]
+        boost: f32,
+    },
+
+    #[command(about = "Boost or soften texture and local contrast without halos")]
+    Detail {
+        #[arg(
+            long,
+            short,
+            default_value = "1.0",
+            allow_hyphen_values = true,
+            help = "Extra gain of fine texture, negative values smooth it"
+        )]
+        fine: f32,
+        #[arg(
+            long,
+            short,
+            default_value = "0.5",
+            allow_hyphen_values = true,
+            help = "Extra gain of mid-scale local contrast (clarity), negative values flatten it"
+        )]
+        coarse: f32,
+    },
+
+    #[command(about = "Cartoon / cel-shading stylization")]
+    Cartoon {
+        #[arg(long, short, default_value = "4", help = "Number of bilateral smoothing passes")]
+        smoothing: u32,
+        #[arg(long, short, default_value = "6", help = "Color tones per channel")]
+        levels: u8,
+        #[arg(
+            long,
+            short = 't',
+            default_value = "120",
+            help = "Gradient magnitude above which outlines are drawn"
+        )]
+        edge_threshold: f32,
+        #[arg(long, short = 'e', default_value = "1.0", help = "Outline opacity (0.0-1.0)")]
+        edge_strength: f32,
+    },
+
+    #[command(about = "Shift the image with wraparound, handy for checking tileable textures")]
+    Offset {
+        #[arg(
+            long,
+            short,
+            default_value = "0",
+            allow_hyphen_values = true,
+            help = "Horizontal shift in pixels"
+        )]
+        x: i64,
+        #[arg(
+            long,
+            short,
+            default_value = "0",
+            allow_hyphen_values = true,
+            help = "Vertical shift in pixels"
+        )]
+        y: i64,
+        #[arg(long, conflicts_with_all = ["x", "y"], help = "Shift by half of the image size")]
+        half: bool,
+    },
+
+    #[command(about = "Pencil sketch")]
+    Sketch {
+        #[arg(
+            long,
+            short,
+            default_value = "8.0",
+            help = "Stroke softness, larger gives thicker lines"
+        )]
+        sigma: f32,
+        #[arg(long, short, help = "Colored pencils, keeping a muted hint of the original hues")]
+        colored: bool,
+        #[arg(
+            long,
+            default_value = "0.5",
+            requires = "colored",
+            help = "Share of the original saturation kept in colored mode (0.0-1.0)"
+        )]
+        saturation: f32,
+    },
+
+    #[command(about = "Make an image tileable")]
+    Seamless {
+        #[arg(long, short, default_value = "blend", help = "How to hide the seams")]
+        method: SeamlessMethod,
+        #[arg(
+            long,
+            short,
+            default_value = "0.25",
+            help = "Blend method: cross-fade band as a fraction of the size (0.0-0.5)"
+        )]
+        blend_width: f32,
+        #[arg(
+            long,
+            short,
+            value_name = "COLSxROWS",
+            help = "Tile the result to check the seams, e.g. 3x3"
+        )]
+        preview_tiled: Option<Grid>,
+    },
+
+    #[command(about = "Compare color histograms of two images, prints distances as JSON")]
+    Histcompare {
+        #[arg(long, short, value_name = "FILE", help = "Image to compare against")]
+        other: String,
+        #[arg(long, short, default_value = "16", help = "Histogram bins per channel")]
+        bins: u32,
+    },
+
+    #[command(about = "Low-poly look from a Delaunay triangulation")]
+    Lowpoly {
+        #[arg(long, short, value_enum, default_value = "raster")]
+        output: LowpolyOutput,
+        #[arg(long, short, default_value = "1500", help = "Number of sampled points")]
+        points: usize,
+        #[arg(
+            long,
+            short,
+            default_value = "0.7",
+            help = "How strongly points gather along edges (0.0 = evenly spread, 1.0 = edges only)"
+        )]
+        detail: f32,
+        #[arg(long, default_value = "0", help = "Seed for point sampling")]
+        seed: u64,
+    },
+
+    #[command(visible_alias = "stack", about = "Merge the input with more aligned frames pixel by pixel")]
+    StackMerge {
+        #[arg(long, short, value_enum, default_value = "mean")]
+        mode: StackMode,
+        #[arg(required = true, value_name = "FILE", help = "Frames to merge with the input")]
+        others: Vec<String>,
+    },
+
+    #[command(about = "Voronoi stained-glass mosaic")]
+    Voronoi {
+        #[arg(long, short, default_value = "800", help = "Number of cells")]
+        cells: usize,
+        #[arg(
+            long,
+            short,
+            default_value = "0.0",
+            help = "How strongly cells gather along edges (0.0 = uniform, 1.0 = edges only)"
+        )]
+        detail: f32,
+        #[arg(long, default_value = "0", help = "Seed for cell placement")]
+        seed: u64,
+        #[arg(long, short, help = "Stroke cell borders with this color, e.g. #202020")]
+        border: Option<Color>,
+        #[arg(long, short = 'w', default_value = "2", help = "Border width in pixels")]
+        border_width: u32,
+    },
+
+    #[command(about = "Content-aware resize by seam carving")]
+    Carve {
+        #[arg(
+            long,
+            short,
+            help = "Target size, e.g. 600x768, 600x (height unchanged) or 80%"
+        )]
+        target: ResizeInput,
+        #[arg(
+            long,
+            short,
+            value_name = "FILE",
+            help = "Grayscale mask of the input size, white areas are kept intact"
+        )]
+        protect: Option<String>,
+    },
+
+    #[command(about = "Inspect luma and chroma planes separately")]
+    ChromaView {
+        #[arg(long, short, value_enum, default_value = "planes", help = "What to show")]
+        view: ChromaView,
+        #[arg(long, short, default_value = "1.0", help = "Chroma amplification")]
+        gain: f32,
+    },
+
+    #[command(about = "Affine or perspective transform")]
+    Transform {
+        #[arg(
+            long,
+            short,
+            value_delimiter = ',',
+            allow_hyphen_values = true,
+            required_unless_present = "from",
+            conflicts_with_all = ["from", "to"],
+            help = "Row-major matrix from source to destination, 6 (affine) or 9 comma separated values"
+        )]
+        matrix: Option<Vec<f64>>,
+        #[arg(
+            long,
+            num_args = 4,
+            requires = "to",
+            allow_hyphen_values = true,
+            value_name = "X,Y",
+            help = "Four source corners in pixels, e.g. --from 10,20 500,15 520,400 5,410"
+        )]
+        from: Option<Vec<Point>>,
+        #[arg(
+            long,
+            num_args = 4,
+            requires = "from",
+            allow_hyphen_values = true,
+            value_name = "X,Y",
+            help = "Where the source corners should end up, in the same order"
+        )]
+        to: Option<Vec<Point>>,
+        #[arg(long, help = "Resize the canvas to the transformed image instead of keeping the input size")]
+        fit: bool,
+        #[arg(long, short, value_enum, default_value = "bilinear", help = "Sampling interpolation")]
+        interpolation: Interpolation,
+        #[arg(long, short, help = "Fill for uncovered areas, transparent if omitted")]
+        background: Option<Color>,
+    },
+
+    #[command(about = "Detect and colorize blobs of pixels")]
+    BlobDetect {
+        #[arg(
+            long,
+            short,
+            default_value = "10",
+            help = "Threshold (0-255). Pixels matching the target color within this threshold are considered part of the blob."
+        )]
+        threshold: u8,
+
+        #[arg(long, short, help = "Target color to detect blobs of.")]
+        color: Option<Color>,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value = "rgb",
+            help = "Space the distance to --color is measured in, lab makes the threshold a ΔE that follows perceived difference"
+        )]
+        color_space: BlobColorSpace,
+
+        #[arg(
+            long,
+            short,
+            value_enum,
+            default_value = "rainbow",
+            help = "Coloring strategy for blobs"
+        )]
+        mode: BlobColorMode,
+
+        #[arg(long, value_enum, default_value = "heat", help = "Colormap of the heatmap mode")]
+        colormap: Colormap,
+
+        #[arg(long, default_value = "#00ff00", help = "Color of every blob in the solid mode")]
+        solid_color: Color,
+
+        #[arg(
+            long,
+            short = 'b',
+            value_enum,
+            default_value = "black",
+            help = "Background style"
+        )]
+        background: BlobBackground,
+
+        #[arg(
+            long,
+            short,
+            value_enum,
+            default_value = "raster",
+            help = "Colored blobs, their sizes, bounding boxes and centroids as JSON, their contours as SVG polygons, or a CSV row per blob with its mean color"
+        )]
+        output: BlobOutput,
+
+        #[arg(long, help = "List the pixels of every blob in the JSON output")]
+        points: bool,
+
+        #[arg(long, default_value = "1", help = "Drop blobs of fewer pixels, e.g. specks of noise")]
+        min_size: usize,
+
+        #[arg(long, help = "Drop blobs of more pixels")]
+        max_size: Option<usize>,
+
+        #[arg(
+            long,
+            value_name = "N",
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Keep only the N largest blobs within the size limits"
+        )]
+        top: Option<u64>,
+
+        #[arg(
+            long,
+            help = "Outline blobs with their bounding box, centroid and size instead of filling them"
+        )]
+        annotate: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "annotate",
+            help = "Trace the outer contour of every blob, drawn alone in raster output and listed with the perimeter in JSON, -o svg always traces"
+        )]
+        contours: bool,
+    },
+}
 
+/// Annotation passthrough shared by the geometric commands
+#[derive(Debug, Args)]
+struct AnnotationArgs {
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "annotations_out",
+        help = "COCO-style JSON with bounding boxes of the input, moved along with the pixels"
+    )]
+    annotations: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "annotations",
+        help = "Where to write the transformed annotations"
+    )]
+    annotations_out: Option<String>,
+}
+
+/// Paper and pen settings shared by the commands with plotter output
+#[derive(Debug, Args)]
+struct PlotterArgs {
+    #[arg(
+        long,
+        default_value = "a4",
+        help = "Paper to fit -o gcode and hpgl on: a3, a4, a5, letter or WIDTHxHEIGHT in mm, e.g. 297x210 for landscape"
+    )]
+    paper: Paper,
+    #[arg(long, default_value = "10", help = "Blank border on every side of the paper in mm")]
+    margin: f64,
+    #[arg(long, default_value = "3000", help = "Drawing speed in mm per minute, the pen velocity in HPGL")]
+    feed_rate: f64,
+    #[arg(long, default_value = "G0 Z5", help = "G-code line lifting the pen, e.g. M3 S30 for a servo")]
+    pen_up: String,
+    #[arg(long, default_value = "G0 Z0", help = "G-code line lowering the pen, e.g. M3 S90 for a servo")]
+    pen_down: String,
+}
+
+/// A command given as arguments, like the frame command of `play`
+#[derive(Debug, Parser)]
+struct FrameCommand {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum SpriteAction {
+    #[command(about = "Assemble frames into one sheet, no input is read")]
+    Pack {
+        #[arg(required = true, value_name = "FILE", help = "Frames to pack, in order")]
+        files: Vec<String>,
+        #[arg(long, short, help = "Frames per row, a roughly square sheet by default")]
+        columns: Option<u32>,
+        #[arg(long, short, default_value = "0", help = "Gap between cells and around the sheet in pixels")]
+        padding: u32,
+        #[arg(long, short, value_name = "FILE", help = "Write frame names and positions as JSON")]
+        atlas: Option<String>,
+    },
+    #[command(about = "Cut the input sheet into frames, write them with --output-template")]
+    Unpack {
+        #[arg(
+            long,
+            short,
+            conflicts_with = "atlas",
+            help = "Cell size, e.g. 32x32; empty cells are skipped"
+        )]
+        grid: Option<ResizeInput>,
+        #[arg(long, short, default_value = "0", help = "Gap between grid cells and around the sheet in pixels")]
+        padding: u32,
+        #[arg(long, short, value_name = "FILE", help = "Cut the frames listed in a JSON atlas")]
+        atlas: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum PresetAction {
+    #[command(about = "Save a command line under NAME, e.g. auge preset save thumb -- resize -t 256xauto")]
+    Save {
+        name: String,
+        #[arg(long, short, help = "Description shown by preset list")]
+        description: Option<String>,
+        #[arg(long, help = "Replace a preset of the same name")]
+        force: bool,
+        #[arg(last = true, required = true, value_name = "COMMAND", help = "Command and arguments to save, after --")]
+        command: Vec<String>,
+    },
+    #[command(about = "List the presets with their descriptions")]
+    List,
+    #[command(about = "Print a preset as it is written in the file")]
+    Show { name: String },
+    #[command(about = "Remove a preset from the file")]
+    Delete { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum GenerateKind {
+    #[command(about = "Identity Hald CLUT, grade it in an image editor and apply with `clut`")]
+    HaldClut {
+        #[arg(
+            long,
+            short,
+            default_value = "8",
+            help = "CLUT level (2-16), the image is level^3 pixels wide"
+        )]
+        level: u32,
+    },
+}
+
+impl Command {
+    /// Generators produce images on their own and must not block on STDIN
+    fn needs_input(&self) -> bool {
+        !matches!(
+            self,
+            Command::Generate { .. }
+                | Command::DotartAnim { .. }
+                | Command::Monitor { .. }
+                | Command::Play { .. }
+                | Command::Preset { .. }
+                | Command::Bench {
+                    generate: Some(_),
+                    ..
+                }
+                | Command::Montage { .. }
+                | Command::Sprite {
+                    action: SpriteAction::Pack { .. }
+                }
+                | Command::ContrastCheck {
+                    foreground: ColorSample::Fixed(_),
+                    background: ColorSample::Fixed(_),
+                    ..
+                }
+        )
+    }
+
+    /// Resolution to record in the output file, for commands that know it
+    fn output_dpi(&self) -> Option<u16> {
+        match self {
+            Command::ShrinkRetina { dpi, .. } => Some(*dpi),
+            _ => None,
+        }
+    }
+}
+
+/// Metadata to write for an encoded input, empty unless kept or the pixels were converted
+fn input_metadata(cli: &Cli, buffer: &[u8]) -> Result<Metadata, AugeError> {
+    let mut metadata = Metadata::default();
+    if cli.keep_metadata {
+        metadata = read_metadata(buffer)?;
+    }
+    // Converted pixels are sRGB now, say so unless the profile was left in charge
+    if !cli.ignore_icc && !cli.strip_metadata && read_icc(buffer)?.is_some() {
+        metadata.icc = Some(color::srgb_icc()?);
+    }
+    if cli.deterministic {
+        metadata.strip_timestamps();
+    }
+    Ok(metadata)
+}
+
+fn check_depth(cli: &Cli) -> Result<(), AugeError> {
+    if let Some(depth) = cli.depth
+        && !cli.format.supports_depth(depth)
+    {
+        return Err(AugeError::InvalidArgument(format!(
+            "{:?} output cannot store --depth {}",
+            cli.format,
+            depth.to_possible_value().map_or_else(String::new, |value| value.get_name().to_string())
+        )));
+    }
+    Ok(())
+}
+
+fn encode_options(cli: &Cli, metadata: Metadata) -> EncodeOptions {
+    EncodeOptions {
+        dpi: cli.command.output_dpi(),
+        quality: cli.quality,
+        subsampling: cli.subsampling,
+        png_compression: cli.png_compression,
+        png_filter: cli.png_filter,
+        png_depth: cli.png_depth,
+        webp_lossy: cli.webp_lossy,
+        roi: cli.roi_quality.clone(),
+        metadata,
+    }
+}
+
+/// Runs the command on every frame of a raw stream, conditions are checked per frame
+fn stream_frames(cli: Cli, size: Grid) -> Result<(), AugeError> {
+    if !cli.command.needs_input() {
+        return Err(AugeError::InvalidArgument(
+            "streams feed frames into the command, generators take none".to_string(),
+        ));
+    }
+    let Grid(width, height) = size;
     let filter = filter_from_command(cli.command)?;
-    match filter.apply(img)? {
+    let mut reader = stdin().lock();
+    let mut writer = BufWriter::new(stdout().lock());
+    let frames = progress::counter("frames");
+
+    while let Some(frame) = read_raw_frame(&mut reader, width, height, cli.pixfmt)? {
+        let frame = color::to_working_space(frame, None)?;
+        let result = if cli.conditions.iter().all(|condition| condition.holds(&frame)) {
+            filter.apply(frame)?
+        } else {
+            FilterResult::Image(frame)
+        };
+        let FilterResult::Image(img) = result else {
+            return Err(AugeError::InvalidArgument(
+                "streams need a command that outputs one image per frame".to_string(),
+            ));
+        };
+        let img = color::from_working_space(img, &EncodableFormats::Png, None);
+        write_raw_frame(&mut writer, &img, cli.pixfmt)?;
+        frames.advance(1);
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Encoded result of the command on one encoded image, and the file extension it calls for
+fn process_encoded(
+    cli: &Cli,
+    filter: &dyn filters::AugeFilter,
+    dpi: Option<u16>,
+    bytes: &[u8],
+) -> Result<(Vec<u8>, &'static str), AugeError> {
+    let options = EncodeOptions {
+        dpi,
+        ..encode_options(cli, input_metadata(cli, bytes)?)
+    };
+    let img = decode_image(bytes)?;
+    let result = if cli.conditions.iter().all(|condition| condition.holds(&img)) {
+        filter.apply(img)?
+    } else {
+        FilterResult::Image(img)
+    };
+    let mut encoded = Vec::new();
+    let extension = match result {
+        FilterResult::Image(img) => {
+            let img = color::from_working_space(img, &cli.format, cli.depth);
+            write_image(&img, &cli.format, &options, &mut encoded)?;
+            cli.format.extension()
+        }
+        FilterResult::Text(text) => {
+            let json = serde_json::from_str::<serde_json::Value>(&text).is_ok();
+            encoded = format!("{}\n", text).into_bytes();
+            if json { "json" } else { "txt" }
+        }
+        FilterResult::Frames(frames) => {
+            write_gif(frames, &mut encoded)?;
+            "gif"
+        }
+    };
+    Ok((encoded, extension))
+}
+
+/// Runs the command on every image of a tar archive or length-prefixed stream on STDIN,
+/// writing the results in the same container to STDOUT
+fn stream_images(mut cli: Cli, kind: StreamKind) -> Result<(), AugeError> {
+    if !cli.command.needs_input() {
+        return Err(AugeError::InvalidArgument(
+            "streams feed images into the command, generators take none".to_string(),
+        ));
+    }
+    check_depth(&cli)?;
+    // The command moves into the filter, what the encoder needs from it is taken first
+    let dpi = cli.command.output_dpi();
+    let filter = filter_from_command(std::mem::replace(&mut cli.command, Command::View))?;
+    let mut reader = stdin().lock();
+    let writer = BufWriter::new(stdout().lock());
+    let images = progress::counter("images");
+
+    if let StreamKind::Framed = kind {
+        let mut writer = writer;
+        while let Some(bytes) = read_framed(&mut reader)? {
+            let (encoded, _) = process_encoded(&cli, filter.as_ref(), dpi, &bytes)?;
+            write_framed(&mut writer, &encoded)?;
+            images.advance(1);
+        }
+        writer.flush()?;
+        return Ok(());
+    }
+
+    let mut archive = tar::Archive::new(reader);
+    let mut builder = tar::Builder::new(writer);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        debug!("processing {}", path.display());
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        let (encoded, extension) = process_encoded(&cli, filter.as_ref(), dpi, &bytes)
+            .map_err(|e| AugeError::InvalidArgument(format!("{}: {}", path.display(), e)))?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(encoded.len() as u64);
+        header.set_mode(0o644);
+        // No timestamp, so the archive only depends on its contents
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, path.with_extension(extension), encoded.as_slice())?;
+        images.advance(1);
+    }
+    builder.into_inner()?.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "http")]
+fn fetch_input(cli: &Cli, url: &str) -> Result<Vec<u8>, AugeError> {
+    inout::fetch(url, cli.http_timeout, cli.http_max_size.saturating_mul(1024 * 1024))
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_input(_cli: &Cli, url: &str) -> Result<Vec<u8>, AugeError> {
+    Err(AugeError::InvalidArgument(format!(
+        "reading {} needs auge built with the http feature, or pipe it in with curl",
+        url
+    )))
+}
+
+/// Re-runs the command whenever a watched file changes. Every run parses the command line
+/// afresh, so files the command loads, like a CLUT, are read again too.
+fn watch(cli: Cli) -> Result<(), AugeError> {
+    let files: Vec<PathBuf> = cli
+        .input
+        .iter()
+        .filter(|input| !is_url(input))
+        .chain(&cli.watch_file)
+        .map(std::path::absolute)
+        .collect::<Result<_, _>>()?;
+    if files.is_empty() {
+        return Err(AugeError::InvalidArgument(
+            "--watch needs an --input file or --watch-file, STDIN can only be read once".to_string(),
+        ));
+    }
+    if cli.stream.is_some() {
+        return Err(AugeError::InvalidArgument("streams cannot be watched".to_string()));
+    }
+    let terminal = inout::previews();
+    if !terminal && cli.tee.is_none() && cli.output_template.is_none() {
+        return Err(AugeError::InvalidArgument(
+            "--watch previews in a terminal or rewrites the --tee file, piped results would pile up"
+                .to_string(),
+        ));
+    }
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    // Editors often save by replacing the file, so the directories are watched instead
+    let mut dirs: Vec<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+    dirs.sort();
+    dirs.dedup();
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+
+    loop {
+        if terminal {
+            print!("\x1b[2J\x1b[H");
+        }
+        // A half-saved input fails to decode, the next save fixes it
+        if let Err(err) = run(parse_cli()) {
+            error!("{}", err);
+        }
+        loop {
+            let Ok(event) = events.recv() else {
+                return Ok(());
+            };
+            let event = event?;
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event.paths.iter().any(|path| files.contains(path))
+            {
+                break;
+            }
+        }
+        // Saves come in several events, the file has settled once they stop
+        std::thread::sleep(Duration::from_millis(100));
+        while events.try_recv().is_ok() {}
+    }
+}
+
+/// Warnings and errors reach STDERR by default, `-v` adds what was done and `-vv` details
+fn init_logging(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => Level::ERROR,
+        (false, 0) => Level::WARN,
+        (false, 1) => Level::INFO,
+        (false, 2) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_max_level(level)
+        .with_target(false)
+        .without_time()
+        .init();
+}
+
+/// The command line, with the installed plugins listed at the end of `--help`
+fn parse_cli() -> Cli {
+    #[cfg(feature = "wasm")]
+    {
+        let matches = Cli::command().after_help(filters::plugin::help()).get_matches();
+        Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit())
+    }
+    #[cfg(not(feature = "wasm"))]
+    Cli::parse()
+}
+
+fn main() -> Result<(), AugeError> {
+    let cli = parse_cli();
+
+    init_logging(cli.verbose, cli.quiet);
+
+    // Gamma-space filtering darkens high-contrast edges, linear light keeps them right
+    let working_space = if cli.linear {
+        WorkingSpace::Linear
+    } else {
+        cli.working_space
+    };
+    color::configure(working_space, cli.ignore_icc);
+    progress::configure(cli.progress && !cli.quiet);
+    inout::configure_preview(
+        match (cli.no_preview, cli.force_preview) {
+            (true, _) => Some(false),
+            (_, true) => Some(true),
+            _ => None,
+        },
+        cli.preview_protocol,
+    );
+    #[cfg(feature = "gpu")]
+    gpu::configure(cli.backend);
+    #[cfg(feature = "wasm")]
+    filters::plugin::configure(cli.plugin_dir.as_deref(), cli.plugin_fuel);
+    if cli.watch || !cli.watch_file.is_empty() {
+        return watch(cli);
+    }
+    run(cli)
+}
+
+/// Reads the input, runs the command and writes the result
+fn run(mut cli: Cli) -> Result<(), AugeError> {
+    match (&cli.stream, cli.size) {
+        (Some(StreamKind::Rawvideo), Some(size)) => return stream_frames(cli, size),
+        (Some(StreamKind::Rawvideo), None) => {
+            return Err(AugeError::InvalidArgument(
+                "raw frames carry no size, give it with --size".to_string(),
+            ));
+        }
+        (Some(kind @ (StreamKind::Tar | StreamKind::Framed)), _) => {
+            let kind = kind.clone();
+            return stream_images(cli, kind);
+        }
+        (None, _) => {}
+    }
+
+    let mut metadata = Metadata::default();
+    let img = if !cli.command.needs_input() {
+        DynamicImage::new_rgb8(0, 0)
+    } else {
+        let buffer = if let Some(path) = &cli.input {
+            if is_url(path) {
+                fetch_input(&cli, path)?
+            } else {
+                std::fs::read(path)?
+            }
+        } else {
+            let mut handle = stdin().lock();
+            let mut buffer = Vec::new();
+            handle.read_to_end(&mut buffer)?;
+            buffer
+        };
+        metadata = input_metadata(&cli, &buffer)?;
+        let img = decode_image(&buffer)?;
+        if let Command::Info { source, .. } = &mut cli.command {
+            *source = buffer;
+        }
+        img
+    };
+
+    if !cli.conditions.is_empty() && !cli.command.needs_input() {
+        return Err(AugeError::InvalidArgument(
+            "conditions are checked against the input image, generators have none".to_string(),
+        ));
+    }
+    check_depth(&cli)?;
+    let options = encode_options(&cli, metadata);
+    if let Command::Monitor {
+        dir,
+        log,
+        output_dir,
+        interval,
+        existing,
+        once,
+        command,
+    } = cli.command
+    {
+        let monitor = monitor::Monitor {
+            dir,
+            log,
+            output_dir,
+            interval,
+            existing,
+            once,
+            filter: filter_from_args("monitor", command)?,
+            format: cli.format,
+            depth: cli.depth,
+            options,
+        };
+        return monitor.run();
+    }
+    let filter: Box<dyn filters::AugeFilter> =
+        if cli.conditions.iter().all(|condition| condition.holds(&img)) {
+            filter_from_command(cli.command)?
+        } else {
+            info!("conditions do not hold, passing the input through");
+            Box::new(NoOpFilter)
+        };
+    let started = Instant::now();
+    let result = filter.apply(img)?;
+    debug!("command took {:.1?}", started.elapsed());
+    match result {
         FilterResult::Image(img) => {
-            print_image(&img, cli.format)?;
+            let img = color::from_working_space(img, &cli.format, cli.depth);
+            if let Some(path) = &cli.tee {
+                tee_image(&img, path, &cli.format, &options)?;
+            }
+            if cli.emit_digest {
+                // Encoded once, so the digest covers exactly the bytes written
+                let mut encoded = Vec::new();
+                write_image(&img, &cli.format, &options, &mut encoded)?;
+                eprintln!("{}", digest::image_report(&img, &encoded)?);
+                if inout::previews() {
+                    print_image(&img, cli.format, &options)?;
+                } else {
+                    stdout().lock().write_all(&encoded)?;
+                }
+            } else {
+                print_image(&img, cli.format, &options)?;
+            }
         }
         FilterResult::Text(text) => {
+            if let Some(path) = &cli.tee {
+                std::fs::write(path, format!("{}\n", text))?;
+            }
+            if cli.emit_digest {
+                eprintln!("{}", digest::text_report(&text)?);
+            }
             println!("{}", text)
         }
+        FilterResult::Frames(frames) => {
+            if cli.tee.is_some() {
+                return Err(AugeError::InvalidArgument(
+                    "--tee takes a single image, use --output-template to keep frames".to_string(),
+                ));
+            }
+            let frames: Vec<Frame> = frames
+                .into_iter()
+                .map(|frame| {
+                    let (left, top, delay) = (frame.left(), frame.top(), frame.delay());
+                    let img = DynamicImage::ImageRgba8(frame.into_buffer());
+                    let img = color::from_working_space(img, &cli.format, None);
+                    Frame::from_parts(img.to_rgba8(), left, top, delay)
+                })
+                .collect();
+            let piped = cli.output_template.is_none() && !inout::previews();
+            if cli.emit_digest && piped && matches!(cli.format, EncodableFormats::Gif) {
+                let pixels = digest::frame_digests(&frames);
+                let mut encoded = Vec::new();
+                write_gif(frames, &mut encoded)?;
+                eprintln!("{}", digest::frames_report(pixels, Some(&encoded))?);
+                stdout().lock().write_all(&encoded)?;
+            } else {
+                if cli.emit_digest {
+                    eprintln!("{}", digest::frames_report(digest::frame_digests(&frames), None)?);
+                }
+                print_frames(frames, cli.format, &options, cli.output_template.as_deref())?;
+            }
+        }
     }
 
     Ok(())
@@ -0,0 +1,159 @@
+//! Directory monitoring. The directory is scanned at an interval rather than watched through
+//! OS events, which also works on network shares where uploads usually land. A file counts as
+//! arrived once its size stayed the same between two scans, so half-written uploads are skipped.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write, stdout};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{Map, Value, json};
+
+use crate::color;
+use crate::condition::Metric;
+use crate::filters::{AugeFilter, FilterResult};
+use crate::inout::{EncodeOptions, decode_image, write_image};
+use crate::types::{AugeError, EncodableFormats, SampleDepth};
+
+pub struct Monitor {
+    pub dir: String,
+    /// JSON lines are appended here, printed when unset
+    pub log: Option<String>,
+    /// Where image results are written, they are not kept when unset
+    pub output_dir: Option<String>,
+    /// Milliseconds between scans
+    pub interval: u64,
+    /// Process files present at startup too
+    pub existing: bool,
+    /// Stop once nothing is left to process
+    pub once: bool,
+    /// Stats only when unset
+    pub filter: Option<Box<dyn AugeFilter>>,
+    pub format: EncodableFormats,
+    pub depth: Option<SampleDepth>,
+    pub options: EncodeOptions,
+}
+
+/// Regular files of the directory with their sizes, hidden ones like partial uploads left out
+fn scan(dir: &Path) -> Result<Vec<(PathBuf, u64)>, AugeError> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if metadata.is_file() && !hidden {
+            files.push((entry.path(), metadata.len()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+impl Monitor {
+    /// Runs until interrupted, or until the files present have been handled with `once`
+    pub fn run(&self) -> Result<(), AugeError> {
+        let dir = Path::new(&self.dir);
+        if !dir.is_dir() {
+            return Err(AugeError::InvalidArgument(format!(
+                "{} is not a directory",
+                self.dir
+            )));
+        }
+        if let Some(output_dir) = &self.output_dir {
+            std::fs::create_dir_all(output_dir)?;
+        }
+        let mut log: Box<dyn Write> = match &self.log {
+            Some(path) => Box::new(BufWriter::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => Box::new(stdout().lock()),
+        };
+
+        let mut done: HashSet<PathBuf> = HashSet::new();
+        if !self.existing {
+            done.extend(scan(dir)?.into_iter().map(|(path, _)| path));
+        }
+        // Sizes seen on the previous scan, a file is ready once its size holds still
+        let mut pending: HashMap<PathBuf, u64> = HashMap::new();
+        loop {
+            for (path, size) in scan(dir)? {
+                if done.contains(&path) {
+                    continue;
+                }
+                // Files of a single pass are complete already
+                if self.once || pending.get(&path) == Some(&size) {
+                    pending.remove(&path);
+                    let record = self.process(&path);
+                    writeln!(log, "{}", serde_json::to_string(&record)?)?;
+                    log.flush()?;
+                    done.insert(path);
+                } else {
+                    pending.insert(path, size);
+                }
+            }
+            if self.once {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(self.interval));
+        }
+    }
+
+    /// Log record of one file, failures are recorded rather than stopping the monitor
+    fn process(&self, path: &Path) -> Value {
+        let mut record = Map::new();
+        record.insert("file".to_string(), json!(path.to_string_lossy()));
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        record.insert("time".to_string(), json!(time));
+        if let Err(err) = self.analyze(path, &mut record) {
+            record.insert("error".to_string(), json!(err.to_string()));
+        }
+        Value::Object(record)
+    }
+
+    fn analyze(&self, path: &Path, record: &mut Map<String, Value>) -> Result<(), AugeError> {
+        let img = decode_image(&std::fs::read(path)?)?;
+        record.insert("width".to_string(), json!(img.width()));
+        record.insert("height".to_string(), json!(img.height()));
+        record.insert(
+            "brightness".to_string(),
+            json!(Metric::Brightness.measure(&img)),
+        );
+        record.insert(
+            "sharpness".to_string(),
+            json!(Metric::Sharpness.measure(&img)),
+        );
+
+        let Some(filter) = &self.filter else {
+            return Ok(());
+        };
+        match filter.apply(img)? {
+            FilterResult::Image(img) => {
+                let Some(output_dir) = &self.output_dir else {
+                    return Err(AugeError::InvalidArgument(
+                        "the command outputs images, keep them with --output-dir".to_string(),
+                    ));
+                };
+                let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                let output = Path::new(output_dir)
+                    .join(format!("{}.{}", name, self.format.extension()));
+                let img = color::from_working_space(img, &self.format, self.depth);
+                let mut writer = BufWriter::new(File::create(&output)?);
+                write_image(&img, &self.format, &self.options, &mut writer)?;
+                writer.flush()?;
+                record.insert("output".to_string(), json!(output.to_string_lossy()));
+            }
+            // JSON reports are nested as they are, anything else is kept as a string
+            FilterResult::Text(text) => {
+                let result = serde_json::from_str(&text).unwrap_or(Value::String(text));
+                record.insert("result".to_string(), result);
+            }
+            FilterResult::Frames(frames) => {
+                record.insert("frames".to_string(), json!(frames.len()));
+            }
+        }
+        Ok(())
+    }
+}
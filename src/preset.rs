@@ -0,0 +1,154 @@
+//! Named pipelines from a TOML file, `~/.config/auge/presets.toml` unless given. Every preset
+//! is a table with a list of steps, written as on the command line or as tables of parameters:
+//!
+//! ```toml
+//! [thumbnail]
+//! description = "Small punchy previews"
+//! steps = [
+//!     "resize -t 256xauto",
+//!     { command = "saturate", amount = 1.2 },
+//! ]
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use toml_edit::{Array, DocumentMut, Item, Table, value};
+
+use crate::inout::config_dir;
+use crate::types::AugeError;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Preset {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Step {
+    /// Command and arguments as typed after `auge`, quoted the way a shell quotes them
+    Line(String),
+    /// Command and arguments one by one, for arguments with spaces in them
+    Words(Vec<String>),
+    /// Command with its options as keys, `args` holds positional arguments
+    Table {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(flatten)]
+        params: BTreeMap<String, toml::Value>,
+    },
+}
+
+/// Option value as typed on the command line
+fn argument(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl Step {
+    /// Command line words of the step, options of tables become `--long-name value`
+    pub fn args(&self) -> Result<Vec<String>, AugeError> {
+        match self {
+            Step::Line(line) => shell_words::split(line)
+                .map_err(|err| AugeError::Preset(format!("{}: {}", line, err))),
+            Step::Words(words) => Ok(words.clone()),
+            Step::Table {
+                command,
+                args,
+                params,
+            } => {
+                let mut words = vec![command.clone()];
+                for (key, value) in params {
+                    let flag = format!("--{}", key.replace('_', "-"));
+                    match value {
+                        toml::Value::Boolean(true) => words.push(flag),
+                        toml::Value::Boolean(false) => {}
+                        toml::Value::Array(values) => {
+                            for value in values {
+                                words.extend([flag.clone(), argument(value)]);
+                            }
+                        }
+                        value => words.extend([flag, argument(value)]),
+                    }
+                }
+                words.extend(args.iter().cloned());
+                Ok(words)
+            }
+        }
+    }
+}
+
+/// `--presets` when given, `presets.toml` of the configuration directory otherwise
+pub fn path_or_default(path: Option<&str>) -> Result<PathBuf, AugeError> {
+    match path {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => Ok(config_dir()?.join("presets.toml")),
+    }
+}
+
+/// All presets of the file by name, none when the file does not exist yet
+pub fn load_presets(path: Option<&str>) -> Result<BTreeMap<String, Preset>, AugeError> {
+    let path = path_or_default(path)?;
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let text = std::fs::read_to_string(&path)?;
+    toml::from_str(&text).map_err(|err| AugeError::Preset(format!("{}: {}", path.display(), err)))
+}
+
+pub fn load_preset(name: &str, path: Option<&str>) -> Result<Preset, AugeError> {
+    load_presets(path)?
+        .remove(name)
+        .ok_or_else(|| not_found(name, path))
+}
+
+pub fn not_found(name: &str, path: Option<&str>) -> AugeError {
+    let path = path_or_default(path).unwrap_or_default();
+    AugeError::Preset(format!("no preset named {} in {}", name, path.display()))
+}
+
+/// Presets run built-in commands only, running presets from presets could loop forever
+pub fn check_step(args: &[String]) -> Result<(), AugeError> {
+    if matches!(args.first().map(String::as_str), Some("run" | "preset")) {
+        return Err(AugeError::Preset(
+            "presets cannot run other presets".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The file for editing, with its comments and layout, empty when it does not exist yet
+pub fn load_document(path: &Path) -> Result<DocumentMut, AugeError> {
+    if !path.exists() {
+        return Ok(DocumentMut::new());
+    }
+    std::fs::read_to_string(path)?
+        .parse()
+        .map_err(|err| AugeError::Preset(format!("{}: {}", path.display(), err)))
+}
+
+pub fn write_document(path: &Path, document: &DocumentMut) -> Result<(), AugeError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+/// Preset table running one command, written as a line with arguments quoted as needed
+pub fn preset_table(args: &[String], description: Option<&str>) -> Table {
+    let mut table = Table::new();
+    if let Some(description) = description {
+        table["description"] = value(description);
+    }
+    let mut steps = Array::new();
+    steps.push(shell_words::join(args));
+    table["steps"] = Item::Value(steps.into());
+    table
+}
@@ -0,0 +1,190 @@
+//! Progress bars on STDERR for `--progress`. Long operations report finished units of work
+//! into a [`Progress`], bars stay silent without the flag and for work done in a blink.
+
+use std::io::{BufRead, Read, Seek, SeekFrom, Write, stderr};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Whether bars are drawn, set once from the command line
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Work finishing sooner than this never shows a bar
+const GRACE: Duration = Duration::from_millis(250);
+/// Time between redraws
+const INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 30;
+
+pub fn configure(enabled: bool) {
+    let _ = ENABLED.set(enabled);
+}
+
+fn enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+/// Receives progress reports, from several threads at once if need be
+pub trait Progress: Sync {
+    /// `n` more units of work are done
+    fn advance(&self, n: u64);
+}
+
+/// A labelled bar on one line of STDERR, wiped once dropped
+pub struct Bar {
+    label: String,
+    /// Units of the whole work, unknown for streams
+    total: Option<u64>,
+    /// Print the counts next to the percentage, bytes read in the millions do not
+    counts: bool,
+    done: AtomicU64,
+    started: Instant,
+    /// When the bar was drawn last, `None` before the first time
+    drawn: Mutex<Option<Instant>>,
+    /// Length of the longest line drawn, to wipe it
+    width: AtomicUsize,
+}
+
+/// Bar for `total` items, e.g. frames or files
+pub fn bar(label: &str, total: u64) -> Bar {
+    Bar::new(label, Some(total), true)
+}
+
+/// Bar for `total` bytes, only showing the percentage
+pub fn bytes(label: &str, total: u64) -> Bar {
+    Bar::new(label, Some(total), false)
+}
+
+/// Count of items with no known end, e.g. images of a stream
+pub fn counter(label: &str) -> Bar {
+    Bar::new(label, None, true)
+}
+
+impl Bar {
+    fn new(label: &str, total: Option<u64>, counts: bool) -> Self {
+        Self {
+            label: label.to_string(),
+            total,
+            counts,
+            done: AtomicU64::new(0),
+            started: Instant::now(),
+            drawn: Mutex::new(None),
+            width: AtomicUsize::new(0),
+        }
+    }
+
+    fn line(&self, done: u64) -> String {
+        let Some(total) = self.total else {
+            return format!("{} {}", self.label, done);
+        };
+        let fraction = if total == 0 {
+            1.0
+        } else {
+            (done as f64 / total as f64).min(1.0)
+        };
+        let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+        let mut line = format!(
+            "{} [{}{}] {:>3}%",
+            self.label,
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            (fraction * 100.0) as u32
+        );
+        if self.counts {
+            line += &format!(" {}/{}", done, total);
+        }
+        if done > 0 && done < total {
+            let elapsed = self.started.elapsed().as_secs_f64();
+            let left = elapsed / done as f64 * (total - done) as f64;
+            line += &format!(" eta {}s", left.ceil() as u64);
+        }
+        line
+    }
+
+    fn draw(&self, line: &str) {
+        let width = self
+            .width
+            .fetch_max(line.len(), Ordering::Relaxed)
+            .max(line.len());
+        let _ = write!(stderr().lock(), "\r{:width$}", line);
+    }
+}
+
+impl Progress for Bar {
+    fn advance(&self, n: u64) {
+        let done = self.done.fetch_add(n, Ordering::Relaxed) + n;
+        if !enabled() || self.started.elapsed() < GRACE {
+            return;
+        }
+        // Threads finding another one drawing skip their turn
+        let Ok(mut drawn) = self.drawn.try_lock() else {
+            return;
+        };
+        let now = Instant::now();
+        if drawn.is_some_and(|at| now - at < INTERVAL) {
+            return;
+        }
+        *drawn = Some(now);
+        self.draw(&self.line(done));
+    }
+}
+
+impl Drop for Bar {
+    fn drop(&mut self) {
+        if self.drawn.get_mut().is_ok_and(|drawn| drawn.is_some()) {
+            let width = *self.width.get_mut();
+            let _ = write!(stderr().lock(), "\r{:width$}\r", "");
+        }
+    }
+}
+
+/// Reports bytes read from the wrapped reader, counting those read again after a seek back
+/// only once. Decoders take it in place of their buffer.
+pub struct Reader<'a, R> {
+    inner: R,
+    progress: &'a dyn Progress,
+    reported: u64,
+}
+
+impl<'a, R: Seek> Reader<'a, R> {
+    pub fn new(inner: R, progress: &'a dyn Progress) -> Self {
+        Self {
+            inner,
+            progress,
+            reported: 0,
+        }
+    }
+
+    fn report(&mut self) {
+        if let Ok(position) = self.inner.stream_position()
+            && position > self.reported
+        {
+            self.progress.advance(position - self.reported);
+            self.reported = position;
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for Reader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.report();
+        Ok(read)
+    }
+}
+
+impl<R: BufRead + Seek> BufRead for Reader<'_, R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount);
+        self.report();
+    }
+}
+
+impl<R: Seek> Seek for Reader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
@@ -0,0 +1,169 @@
+//! Region-of-interest quality for lossy output. Neither encoder can vary its quality
+//! across the image, so the whole image is encoded at the best region quality after
+//! the background has been quantized as the lower quality would have, block by block.
+//! Those blocks lose the detail the encoder would otherwise spend bytes on.
+
+use std::f32::consts::PI;
+
+use image::{DynamicImage, Rgb, RgbImage, RgbaImage};
+
+use crate::types::{Rect, RoiQuality};
+
+/// Quantization tables from Annex K of the JPEG standard, row by row
+const LUMA_TABLE: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, //
+    12, 12, 14, 19, 26, 58, 60, 55, //
+    14, 13, 16, 24, 40, 57, 69, 56, //
+    14, 17, 22, 29, 51, 87, 80, 62, //
+    18, 22, 37, 56, 68, 109, 103, 77, //
+    24, 35, 55, 64, 81, 104, 113, 92, //
+    49, 64, 78, 87, 103, 121, 120, 101, //
+    72, 92, 95, 98, 112, 100, 103, 99,
+];
+const CHROMA_TABLE: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, //
+    18, 21, 26, 66, 99, 99, 99, 99, //
+    24, 26, 56, 99, 99, 99, 99, 99, //
+    47, 66, 99, 99, 99, 99, 99, 99, //
+    99, 99, 99, 99, 99, 99, 99, 99, //
+    99, 99, 99, 99, 99, 99, 99, 99, //
+    99, 99, 99, 99, 99, 99, 99, 99, //
+    99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+/// Scales a standard table to a quality the way libjpeg does
+fn scaled(table: &[u16; 64], quality: u8) -> [f32; 64] {
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+    table.map(|step| ((step as u32 * scale + 50) / 100).clamp(1, 255) as f32)
+}
+
+/// Orthonormal 8-point DCT-II basis, `BASIS[k][n]`
+fn basis() -> [[f32; 8]; 8] {
+    std::array::from_fn(|k| {
+        let norm = if k == 0 {
+            (1.0f32 / 8.0).sqrt()
+        } else {
+            (2.0f32 / 8.0).sqrt()
+        };
+        std::array::from_fn(|n| norm * ((2 * n + 1) as f32 * k as f32 * PI / 16.0).cos())
+    })
+}
+
+/// Forward or inverse separable 2D DCT of a row-major 8x8 block
+fn transform(block: &[f32; 64], basis: &[[f32; 8]; 8], inverse: bool) -> [f32; 64] {
+    let coefficient = |k: usize, n: usize| if inverse { basis[n][k] } else { basis[k][n] };
+    let mut rows = [0.0f32; 64];
+    for y in 0..8 {
+        for k in 0..8 {
+            rows[y * 8 + k] = (0..8).map(|n| coefficient(k, n) * block[y * 8 + n]).sum();
+        }
+    }
+    let mut out = [0.0f32; 64];
+    for x in 0..8 {
+        for k in 0..8 {
+            out[k * 8 + x] = (0..8).map(|n| coefficient(k, n) * rows[n * 8 + x]).sum();
+        }
+    }
+    out
+}
+
+/// JFIF YCbCr, the color space both encoders quantize in
+fn to_ycbcr(Rgb([r, g, b]): Rgb<u8>) -> [f32; 3] {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b,
+        128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b,
+    ]
+}
+
+fn to_rgb([y, cb, cr]: [f32; 3]) -> Rgb<u8> {
+    let (cb, cr) = (cb - 128.0, cr - 128.0);
+    Rgb([
+        y + 1.402 * cr,
+        y - 0.344136 * cb - 0.714136 * cr,
+        y + 1.772 * cb,
+    ]
+    .map(|c| c.round().clamp(0.0, 255.0) as u8))
+}
+
+/// Quantizes every 8x8 block outside the regions at `background` quality and returns the
+/// quality to encode with, that of the best region. `None` when there is nothing to do.
+pub fn prequantize(
+    img: &DynamicImage,
+    regions: &[RoiQuality],
+    background: u8,
+) -> Option<(DynamicImage, u8)> {
+    let best = regions.iter().map(|roi| roi.quality).max()?;
+    if best <= background {
+        return None;
+    }
+
+    let mut rgb: RgbImage = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let tables = [
+        scaled(&LUMA_TABLE, background),
+        scaled(&CHROMA_TABLE, background),
+        scaled(&CHROMA_TABLE, background),
+    ];
+    let basis = basis();
+
+    for by in (0..height).step_by(8) {
+        for bx in (0..width).step_by(8) {
+            let block = Rect {
+                x: bx,
+                y: by,
+                width: 8,
+                height: 8,
+            };
+            // Blocks touching a region keep its quality, edges included
+            if regions.iter().any(|roi| roi.rect.intersects(&block)) {
+                continue;
+            }
+            // Partial blocks at the border repeat their last row and column, like encoders pad
+            let at = |i: usize| {
+                let (x, y) = (
+                    (bx + (i % 8) as u32).min(width - 1),
+                    (by + (i / 8) as u32).min(height - 1),
+                );
+                to_ycbcr(*rgb.get_pixel(x, y))
+            };
+            let samples: [[f32; 3]; 64] = std::array::from_fn(at);
+
+            let mut planes = [[0.0f32; 64]; 3];
+            for (c, plane) in planes.iter_mut().enumerate() {
+                let centered: [f32; 64] = std::array::from_fn(|i| samples[i][c] - 128.0);
+                let mut coefficients = transform(&centered, &basis, false);
+                for (coefficient, step) in coefficients.iter_mut().zip(tables[c]) {
+                    *coefficient = (*coefficient / step).round() * step;
+                }
+                *plane = transform(&coefficients, &basis, true).map(|v| v + 128.0);
+            }
+
+            let pixels: [Rgb<u8>; 64] =
+                std::array::from_fn(|i| to_rgb([planes[0][i], planes[1][i], planes[2][i]]));
+            for (i, pixel) in pixels.into_iter().enumerate() {
+                let (x, y) = (bx + (i % 8) as u32, by + (i / 8) as u32);
+                if x < width && y < height {
+                    rgb.put_pixel(x, y, pixel);
+                }
+            }
+        }
+    }
+
+    let out = if img.color().has_alpha() {
+        let alpha = img.to_rgba8();
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+            let Rgb([r, g, b]) = *rgb.get_pixel(x, y);
+            image::Rgba([r, g, b, alpha.get_pixel(x, y)[3]])
+        }))
+    } else {
+        DynamicImage::ImageRgb8(rgb)
+    };
+    Some((out, best))
+}
@@ -0,0 +1,347 @@
+//! Vectorized inner loops of the per-pixel filters. Every kernel has an SSE path on x86-64
+//! and a NEON one on AArch64, picked at runtime, and a scalar one for everything else. The
+//! paths agree bit for bit, products and sums are taken in the same order everywhere.
+
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+use image::{DynamicImage, GrayImage};
+
+/// Byte mask of 16 samples, 0xFF for color channels and 0 for alpha
+fn invert_mask(channels: usize, alpha: bool) -> [u8; 16] {
+    std::array::from_fn(|i| {
+        if alpha && i % channels == channels - 1 {
+            0
+        } else {
+            0xFF
+        }
+    })
+}
+
+/// Inverts 8-bit samples in place, leaving the last of every `channels` alone with `alpha`
+pub fn invert(samples: &mut [u8], channels: usize, alpha: bool) {
+    let mask = invert_mask(channels, alpha);
+    let mut done = 0;
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("sse2") {
+        // SAFETY: SSE2 is available
+        done = unsafe { invert_sse2(samples, &mask) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON is available
+        done = unsafe { invert_neon(samples, &mask) };
+    }
+    // The mask repeats every 16 samples and vector paths stop on a multiple of 16
+    for (i, sample) in samples.iter_mut().enumerate().skip(done) {
+        *sample ^= mask[i % 16];
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn invert_sse2(samples: &mut [u8], mask: &[u8; 16]) -> usize {
+    let mut chunks = samples.chunks_exact_mut(16);
+    // SAFETY: every load and store stays within a 16-byte chunk
+    unsafe {
+        let mask = _mm_loadu_si128(mask.as_ptr().cast());
+        for chunk in &mut chunks {
+            let v = _mm_loadu_si128(chunk.as_ptr().cast());
+            _mm_storeu_si128(chunk.as_mut_ptr().cast(), _mm_xor_si128(v, mask));
+        }
+    }
+    samples.len() - samples.len() % 16
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn invert_neon(samples: &mut [u8], mask: &[u8; 16]) -> usize {
+    let mut chunks = samples.chunks_exact_mut(16);
+    // SAFETY: every load and store stays within a 16-byte chunk
+    unsafe {
+        let mask = vld1q_u8(mask.as_ptr());
+        for chunk in &mut chunks {
+            let v = vld1q_u8(chunk.as_ptr());
+            vst1q_u8(chunk.as_mut_ptr(), veorq_u8(v, mask));
+        }
+    }
+    samples.len() - samples.len() % 16
+}
+
+/// Rec. 709 luma weights in ten-thousandths, as `image` converts to gray
+const LUMA: [u32; 3] = [2126, 7152, 722];
+
+fn luma_scalar(pixel: &[u8]) -> u8 {
+    let sum = LUMA[0] * pixel[0] as u32 + LUMA[1] * pixel[1] as u32 + LUMA[2] * pixel[2] as u32;
+    (sum / 10000) as u8
+}
+
+/// Gray level of every RGB or RGBA pixel, the same as `image`'s `to_luma8`
+pub fn luma(samples: &[u8], channels: usize) -> Vec<u8> {
+    let mut luma = vec![0; samples.len() / channels];
+    let mut done = 0;
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("sse4.1") {
+        // SAFETY: SSE4.1 and the SSSE3 it implies are available
+        done = unsafe { luma_sse41(samples, channels, &mut luma) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON is available
+        done = unsafe { luma_neon(samples, channels, &mut luma) };
+    }
+    for (pixel, gray) in samples.chunks_exact(channels).zip(&mut luma).skip(done) {
+        *gray = luma_scalar(pixel);
+    }
+    luma
+}
+
+/// Luma of four pixels, whose channels are the low three bytes of every lane.
+/// The float sums are exact and the division truncates like the integer one.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+fn luma4_sse41(pixels: __m128i) -> __m128i {
+    let byte = _mm_set1_epi32(0xFF);
+    let channel = |shift| {
+        let lane = match shift {
+            0 => pixels,
+            8 => _mm_srli_epi32::<8>(pixels),
+            _ => _mm_srli_epi32::<16>(pixels),
+        };
+        _mm_cvtepi32_ps(_mm_and_si128(lane, byte))
+    };
+    let sum = _mm_add_ps(
+        _mm_add_ps(
+            _mm_mul_ps(channel(0), _mm_set1_ps(LUMA[0] as f32)),
+            _mm_mul_ps(channel(8), _mm_set1_ps(LUMA[1] as f32)),
+        ),
+        _mm_mul_ps(channel(16), _mm_set1_ps(LUMA[2] as f32)),
+    );
+    _mm_cvttps_epi32(_mm_div_ps(sum, _mm_set1_ps(10000.0)))
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.1")]
+unsafe fn luma_sse41(samples: &[u8], channels: usize, luma: &mut [u8]) -> usize {
+    // Spreads four packed RGB pixels over the lanes, the fourth byte of each is zeroed
+    let spread = _mm_setr_epi8(0, 1, 2, -1, 3, 4, 5, -1, 6, 7, 8, -1, 9, 10, 11, -1);
+    let mut done = 0;
+    // Loads take 16 bytes, RGB stops while a full load still fits
+    while done + 4 <= luma.len() && done * channels + 16 <= samples.len() {
+        // SAFETY: the 16-byte load fits in `samples` and the store in `luma`
+        unsafe {
+            let v = _mm_loadu_si128(samples.as_ptr().add(done * channels).cast());
+            let v = if channels == 3 {
+                _mm_shuffle_epi8(v, spread)
+            } else {
+                v
+            };
+            let gray = luma4_sse41(v);
+            let gray = _mm_packus_epi16(_mm_packus_epi32(gray, gray), _mm_setzero_si128());
+            let bytes = _mm_cvtsi128_si32(gray).to_le_bytes();
+            luma[done..done + 4].copy_from_slice(&bytes);
+        }
+        done += 4;
+    }
+    done
+}
+
+/// Luma of four pixels from their widened channels
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+fn luma4_neon(r: uint16x4_t, g: uint16x4_t, b: uint16x4_t) -> uint16x4_t {
+    let float = |c| vcvtq_f32_u32(vmovl_u16(c));
+    let sum = vaddq_f32(
+        vaddq_f32(
+            vmulq_n_f32(float(r), LUMA[0] as f32),
+            vmulq_n_f32(float(g), LUMA[1] as f32),
+        ),
+        vmulq_n_f32(float(b), LUMA[2] as f32),
+    );
+    vmovn_u32(vcvtq_u32_f32(vdivq_f32(sum, vdupq_n_f32(10000.0))))
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn luma_neon(samples: &[u8], channels: usize, luma: &mut [u8]) -> usize {
+    let mut done = 0;
+    while done + 16 <= luma.len() {
+        // SAFETY: 16 pixels are left in `samples` and 16 values in `luma`
+        let (r, g, b) = unsafe {
+            let at = samples.as_ptr().add(done * channels);
+            if channels == 3 {
+                let v = vld3q_u8(at);
+                (v.0, v.1, v.2)
+            } else {
+                let v = vld4q_u8(at);
+                (v.0, v.1, v.2)
+            }
+        };
+        let half = |r: uint16x8_t, g: uint16x8_t, b: uint16x8_t| {
+            let low = luma4_neon(vget_low_u16(r), vget_low_u16(g), vget_low_u16(b));
+            let high = luma4_neon(vget_high_u16(r), vget_high_u16(g), vget_high_u16(b));
+            vmovn_u16(vcombine_u16(low, high))
+        };
+        let low = half(
+            vmovl_u8(vget_low_u8(r)),
+            vmovl_u8(vget_low_u8(g)),
+            vmovl_u8(vget_low_u8(b)),
+        );
+        let high = half(
+            vmovl_u8(vget_high_u8(r)),
+            vmovl_u8(vget_high_u8(g)),
+            vmovl_u8(vget_high_u8(b)),
+        );
+        // SAFETY: 16 values are left in `luma`
+        unsafe { vst1q_u8(luma.as_mut_ptr().add(done), vcombine_u8(low, high)) };
+        done += 16;
+    }
+    done
+}
+
+/// `img.to_luma8()`, vectorized for 8-bit RGB and RGBA
+pub fn to_luma8(img: &DynamicImage) -> GrayImage {
+    let (width, height) = (img.width(), img.height());
+    let luma = match img {
+        DynamicImage::ImageRgb8(buffer) => luma(buffer, 3),
+        DynamicImage::ImageRgba8(buffer) => luma(buffer, 4),
+        _ => return img.to_luma8(),
+    };
+    GrayImage::from_raw(width, height, luma).expect("one value per pixel")
+}
+
+/// Applies a 3x3 color matrix, given as output rows, to interleaved float RGB in place
+pub fn color_matrix(samples: &mut [f32], rows: &[[f32; 3]; 3]) {
+    let mut done = 0;
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("sse") {
+        // SAFETY: SSE is available
+        done = unsafe { color_matrix_sse(samples, rows) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON is available
+        done = unsafe { color_matrix_neon(samples, rows) };
+    }
+    for pixel in samples[done..].chunks_exact_mut(3) {
+        let [r, g, b] = [pixel[0], pixel[1], pixel[2]];
+        for (out, [x, y, z]) in pixel.iter_mut().zip(rows) {
+            *out = r * x + g * y + b * z;
+        }
+    }
+}
+
+/// Four pixels at a time with the channels gathered into lanes, returns the samples done
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse")]
+unsafe fn color_matrix_sse(samples: &mut [f32], rows: &[[f32; 3]; 3]) -> usize {
+    let mut chunks = samples.chunks_exact_mut(12);
+    for chunk in &mut chunks {
+        let channel = |c: usize| _mm_setr_ps(chunk[c], chunk[3 + c], chunk[6 + c], chunk[9 + c]);
+        let (r, g, b) = (channel(0), channel(1), channel(2));
+        let mut out = [[0.0f32; 4]; 3];
+        for (out, [x, y, z]) in out.iter_mut().zip(rows) {
+            let v = _mm_add_ps(
+                _mm_add_ps(
+                    _mm_mul_ps(r, _mm_set1_ps(*x)),
+                    _mm_mul_ps(g, _mm_set1_ps(*y)),
+                ),
+                _mm_mul_ps(b, _mm_set1_ps(*z)),
+            );
+            // SAFETY: `out` holds four floats
+            unsafe { _mm_storeu_ps(out.as_mut_ptr(), v) };
+        }
+        for (i, pixel) in chunk.chunks_exact_mut(3).enumerate() {
+            pixel.copy_from_slice(&[out[0][i], out[1][i], out[2][i]]);
+        }
+    }
+    samples.len() - samples.len() % 12
+}
+
+/// Four pixels at a time, the structured loads split the channels into lanes
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn color_matrix_neon(samples: &mut [f32], rows: &[[f32; 3]; 3]) -> usize {
+    let mut chunks = samples.chunks_exact_mut(12);
+    for chunk in &mut chunks {
+        // SAFETY: the chunk holds the 12 floats loaded and stored
+        let v = unsafe { vld3q_f32(chunk.as_ptr()) };
+        let channel = |[x, y, z]: [f32; 3]| {
+            vaddq_f32(
+                vaddq_f32(vmulq_n_f32(v.0, x), vmulq_n_f32(v.1, y)),
+                vmulq_n_f32(v.2, z),
+            )
+        };
+        let out = float32x4x3_t(channel(rows[0]), channel(rows[1]), channel(rows[2]));
+        // SAFETY: as above
+        unsafe { vst3q_f32(chunk.as_mut_ptr(), out) };
+    }
+    samples.len() - samples.len() % 12
+}
+
+/// Three-level class of every luma value: 0 up to `low`, 2 from `high` on and 1 between,
+/// with `low` taking precedence when the thresholds cross
+pub fn classify(luma: &[u8], low: u8, high: u8) -> Vec<u8> {
+    let mut classes = vec![0; luma.len()];
+    let mut done = 0;
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("sse2") {
+        // SAFETY: SSE2 is available
+        done = unsafe { classify_sse2(luma, low, high, &mut classes) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        // SAFETY: NEON is available
+        done = unsafe { classify_neon(luma, low, high, &mut classes) };
+    }
+    for (class, &value) in classes.iter_mut().zip(luma).skip(done) {
+        *class = if value <= low {
+            0
+        } else if value >= high {
+            2
+        } else {
+            1
+        };
+    }
+    classes
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn classify_sse2(luma: &[u8], low: u8, high: u8, classes: &mut [u8]) -> usize {
+    let (low, high, one) = (
+        _mm_set1_epi8(low as i8),
+        _mm_set1_epi8(high as i8),
+        _mm_set1_epi8(1),
+    );
+    for (values, out) in luma.chunks_exact(16).zip(classes.chunks_exact_mut(16)) {
+        // SAFETY: both chunks hold 16 bytes
+        unsafe {
+            let v = _mm_loadu_si128(values.as_ptr().cast());
+            // Unsigned comparisons through min and max, SSE2 only compares signed bytes
+            let at_most_low = _mm_cmpeq_epi8(_mm_min_epu8(v, low), v);
+            let from_high = _mm_cmpeq_epi8(_mm_max_epu8(v, high), v);
+            let above_low = _mm_andnot_si128(at_most_low, one);
+            let class = _mm_add_epi8(above_low, _mm_and_si128(above_low, from_high));
+            _mm_storeu_si128(out.as_mut_ptr().cast(), class);
+        }
+    }
+    luma.len() - luma.len() % 16
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn classify_neon(luma: &[u8], low: u8, high: u8, classes: &mut [u8]) -> usize {
+    let (low, high, one) = (vdupq_n_u8(low), vdupq_n_u8(high), vdupq_n_u8(1));
+    for (values, out) in luma.chunks_exact(16).zip(classes.chunks_exact_mut(16)) {
+        // SAFETY: both chunks hold 16 bytes
+        let v = unsafe { vld1q_u8(values.as_ptr()) };
+        let above_low = vandq_u8(vcgtq_u8(v, low), one);
+        let class = vaddq_u8(above_low, vandq_u8(above_low, vcgeq_u8(v, high)));
+        // SAFETY: as above
+        unsafe { vst1q_u8(out.as_mut_ptr(), class) };
+    }
+    luma.len() - luma.len() % 16
+}
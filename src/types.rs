@@ -1,7 +1,7 @@
 use std::{num::{ParseIntError, ParseFloatError}, str::FromStr};
 
 use clap::ValueEnum;
-use image::Rgb;
+use image::{DynamicImage, Rgb};
 use serde::Serialize;
 
 #[derive(thiserror::Error, Debug)]
@@ -22,12 +22,41 @@ pub enum AugeError {
     InvalidResizeFormat(String),
     #[error("{0}")]
     Serde(#[from] serde_json::Error),
+    #[error("Invalid CLUT: {0}")]
+    InvalidClut(String),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("JPEG encoding error: {0}")]
+    Jpeg(#[from] jpeg_encoder::EncodingError),
+    #[error("PNG encoding error: {0}")]
+    Png(#[from] png::EncodingError),
+    #[error("Sixel encoding error: {0}")]
+    Sixel(#[from] icy_sixel::SixelError),
+    #[error("Color management error: {0}, --ignore-icc skips the profile")]
+    Icc(#[from] lcms2::Error),
+    #[error("Watch error: {0}")]
+    Watch(#[from] notify::Error),
+    #[error("Preset error: {0}")]
+    Preset(String),
+    #[cfg(feature = "script")]
+    #[error("Script error: {0}")]
+    Script(String),
+    #[cfg(feature = "http")]
+    #[error("HTTP error: {0}")]
+    Http(#[from] ureq::Error),
+    #[cfg(feature = "wasm")]
+    #[error("Plugin error: {0}")]
+    Plugin(String),
+    #[cfg(feature = "wasm")]
+    #[error("Plugin error: {0}")]
+    Wasm(#[from] wasmi::Error),
 }
 
 #[derive(Debug, Clone, ValueEnum)]
 pub enum EncodableFormats {
     Bmp,
     Farbfeld,
+    Gif,
     Hdr,
     Ico,
     Jpeg,
@@ -40,6 +69,212 @@ pub enum EncodableFormats {
     Webp,
 }
 
+impl EncodableFormats {
+    /// Picks the format matching a file extension, `None` for unknown or missing ones
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "bmp" => Some(Self::Bmp),
+            "ff" | "farbfeld" => Some(Self::Farbfeld),
+            "gif" => Some(Self::Gif),
+            "hdr" => Some(Self::Hdr),
+            "ico" => Some(Self::Ico),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "exr" => Some(Self::Exr),
+            "png" => Some(Self::Png),
+            "pnm" | "pbm" | "pgm" | "ppm" | "pam" => Some(Self::Pnm),
+            "qoi" => Some(Self::Qoi),
+            "tga" => Some(Self::Tga),
+            "tif" | "tiff" => Some(Self::Tiff),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    /// File extension for output written in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Bmp => "bmp",
+            Self::Farbfeld => "ff",
+            Self::Gif => "gif",
+            Self::Hdr => "hdr",
+            Self::Ico => "ico",
+            Self::Jpeg => "jpg",
+            Self::Exr => "exr",
+            Self::Png => "png",
+            Self::Pnm => "pnm",
+            Self::Qoi => "qoi",
+            Self::Tga => "tga",
+            Self::Tiff => "tiff",
+            Self::Webp => "webp",
+        }
+    }
+
+    /// Whether the encoder can store samples of this depth
+    pub fn supports_depth(&self, depth: SampleDepth) -> bool {
+        match depth {
+            SampleDepth::Eight => !matches!(self, Self::Exr | Self::Hdr),
+            SampleDepth::Sixteen => matches!(self, Self::Farbfeld | Self::Png | Self::Tiff),
+            SampleDepth::F32 => matches!(self, Self::Exr | Self::Hdr | Self::Tiff),
+        }
+    }
+}
+
+/// Container of a stream on STDIN, answered with the same container on STDOUT
+#[derive(Debug, Clone, ValueEnum)]
+pub enum StreamKind {
+    /// Bare back-to-back raw frames of `--size`
+    Rawvideo,
+    /// Tar archive of encoded images, e.g. from `find . -name '*.jpg' | tar -cT - `
+    Tar,
+    /// Encoded images, each after its length as an 8-byte big-endian integer
+    Framed,
+}
+
+/// Pixel layouts of raw frames, named like ffmpeg's `-pix_fmt`
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum PixelFormat {
+    Rgb24,
+    Rgba,
+    Gray,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb24 => 3,
+            PixelFormat::Rgba => 4,
+            PixelFormat::Gray => 1,
+        }
+    }
+}
+
+/// Chroma subsampling of JPEG output, named after the usual J:a:b notation
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Subsampling {
+    /// Full resolution color
+    #[value(name = "444")]
+    Yuv444,
+    /// Color at half the horizontal resolution
+    #[value(name = "422")]
+    Yuv422,
+    /// Color at half the resolution both ways, the smallest files
+    #[value(name = "420")]
+    Yuv420,
+}
+
+/// zlib effort of PNG output
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum PngCompression {
+    #[default]
+    Fast,
+    Default,
+    /// Smallest files, slowest
+    Best,
+}
+
+/// Row filter applied before PNG compression
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// Picks the best filter for every row
+    #[default]
+    Adaptive,
+}
+
+/// Sample layout of PNG output
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum PngDepth {
+    /// Whatever the image holds
+    #[default]
+    Auto,
+    #[value(name = "8")]
+    Eight,
+    #[value(name = "16")]
+    Sixteen,
+    /// A palette of at most 256 colors, quantized when the image has more
+    Indexed,
+}
+
+/// Where the heavy filters run, the GPU falls back to the CPU without an adapter
+#[cfg(feature = "gpu")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum Backend {
+    #[default]
+    Cpu,
+    /// Blurs, resizes and per-pixel color filters as compute shaders
+    Gpu,
+}
+
+/// Color space filters operate in, inputs are converted into it on decode
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum WorkingSpace {
+    /// 8 or 16-bit sRGB, what filters were written for
+    #[default]
+    Srgb,
+    /// sRGB primaries in linear light as floats, for physically correct blurs and blends
+    Linear,
+}
+
+/// Terminal graphics protocol previews are drawn with
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum PreviewProtocol {
+    /// The best one the terminal answers to, half blocks when none
+    #[default]
+    Auto,
+    Kitty,
+    Iterm,
+    /// DEC sixel graphics, for xterm, foot, WezTerm, mlterm and others
+    Sixel,
+    /// Colored half blocks, work in any terminal with truecolor
+    Blocks,
+}
+
+/// Sample type of an image, filters keep the one of their input
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum SampleDepth {
+    #[value(name = "8")]
+    Eight,
+    #[value(name = "16")]
+    Sixteen,
+    /// 32-bit floats, RGB or RGBA only
+    F32,
+}
+
+impl SampleDepth {
+    pub fn of(img: &DynamicImage) -> Self {
+        match img {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => Self::F32,
+            DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_) => Self::Sixteen,
+            _ => Self::Eight,
+        }
+    }
+
+    /// The image at this depth, keeping gray and alpha channels where the depth has them
+    pub fn convert(self, img: &DynamicImage) -> DynamicImage {
+        let (color, alpha) = (img.color().has_color(), img.color().has_alpha());
+        match (self, color, alpha) {
+            (Self::Eight, false, false) => DynamicImage::ImageLuma8(img.to_luma8()),
+            (Self::Eight, false, true) => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+            (Self::Eight, true, false) => DynamicImage::ImageRgb8(img.to_rgb8()),
+            (Self::Eight, true, true) => DynamicImage::ImageRgba8(img.to_rgba8()),
+            (Self::Sixteen, false, false) => DynamicImage::ImageLuma16(img.to_luma16()),
+            (Self::Sixteen, false, true) => DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+            (Self::Sixteen, true, false) => DynamicImage::ImageRgb16(img.to_rgb16()),
+            (Self::Sixteen, true, true) => DynamicImage::ImageRgba16(img.to_rgba16()),
+            (Self::F32, _, false) => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+            (Self::F32, _, true) => DynamicImage::ImageRgba32F(img.to_rgba32f()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum OutputKind {
     Raster,
@@ -49,6 +284,14 @@ pub enum OutputKind {
 #[derive(Debug, Clone)]
 pub struct Color(pub Rgb<u8>);
 
+impl Color {
+    /// The color in the working space, the way filters see pixels of the input. Colors are
+    /// given in sRGB, so under `--working-space linear` they are linearized like the pixels.
+    pub fn working(&self) -> Rgb<u8> {
+        crate::color::srgb_to_working(self.0)
+    }
+}
+
 impl FromStr for Color {
     type Err = AugeError;
 
@@ -107,19 +350,260 @@ impl FromStr for DotColorSource {
     }
 }
 
+/// What a dot of dot art looks like, written as `circle`, `square`, `diamond`, `ring`
+/// or `char:C` for a character
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DotShape {
+    #[default]
+    Circle,
+    Square,
+    Diamond,
+    Ring,
+    Char(char),
+}
+
+impl FromStr for DotShape {
+    type Err = AugeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(text) = s.strip_prefix("char:") {
+            let mut chars = text.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) if (' '..='~').contains(&c) => Ok(DotShape::Char(c)),
+                _ => Err(AugeError::InvalidArgument(format!(
+                    "char: takes a single printable ASCII character, got '{}'",
+                    text
+                ))),
+            };
+        }
+        match s.to_lowercase().as_str() {
+            "circle" => Ok(DotShape::Circle),
+            "square" => Ok(DotShape::Square),
+            "diamond" => Ok(DotShape::Diamond),
+            "ring" => Ok(DotShape::Ring),
+            _ => Err(AugeError::InvalidArgument(format!(
+                "unknown dot shape '{}', expected circle, square, diamond, ring or char:C",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for DotShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DotShape::Circle => write!(f, "circle"),
+            DotShape::Square => write!(f, "square"),
+            DotShape::Diamond => write!(f, "diamond"),
+            DotShape::Ring => write!(f, "ring"),
+            DotShape::Char(c) => write!(f, "char:{}", c),
+        }
+    }
+}
+
+/// Layout of dotart's JSON output
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum DotJsonSchema {
+    /// Background and dots only, as written before the schema had a version
+    V1,
+    /// Versioned, with the image size, grid and dot shape
+    #[default]
+    V2,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Dot {
     pub pos: (u32, u32),
     pub radius: i32,
-    pub color: Color
+    pub color: Color,
+    /// `pos` divided by the image size, when asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub norm: Option<(f64, f64)>,
+}
+
+/// What a consumer needs to place the dots, written from schema version 2 on
+#[derive(Debug, Clone, Serialize)]
+pub struct DotJsonMeta {
+    pub version: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Input pixels every dot samples
+    pub scale: u32,
+    /// Distance between dot centers before any jitter
+    pub spacing: u32,
+    /// `square`, or `jittered` when dots stray from the grid
+    pub grid: &'static str,
+    pub shape: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct DotFilterJson {
+    #[serde(flatten)]
+    pub meta: Option<DotJsonMeta>,
     pub bg: Color,
     pub points: Vec<Dot>
 }
 
+/// A pair of numbers written as `X,Y`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point(pub f32, pub f32);
+
+impl FromStr for Point {
+    type Err = AugeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (x, y) = s
+            .split_once(',')
+            .ok_or_else(|| AugeError::InvalidArgument(format!("expected 'X,Y', got {}", s)))?;
+        Ok(Point(x.trim().parse()?, y.trim().parse()?))
+    }
+}
+
+/// A color written as `#rrggbb`, or the pixel at `X,Y` of the input
+#[derive(Debug, Clone)]
+pub enum ColorSample {
+    Fixed(Color),
+    At(Point),
+}
+
+impl FromStr for ColorSample {
+    type Err = AugeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(',') {
+            Ok(ColorSample::At(s.parse()?))
+        } else {
+            Ok(ColorSample::Fixed(s.parse()?))
+        }
+    }
+}
+
+/// Columns and rows written as `COLSxROWS`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid(pub u32, pub u32);
+
+impl FromStr for Grid {
+    type Err = AugeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (cols, rows) = s
+            .split_once('x')
+            .ok_or_else(|| AugeError::InvalidArgument(format!("expected 'COLSxROWS', got {}", s)))?;
+        let grid = Grid(cols.trim().parse()?, rows.trim().parse()?);
+        if grid.0 == 0 || grid.1 == 0 {
+            return Err(AugeError::InvalidArgument(format!("grid cannot be empty, got {}", s)));
+        }
+        Ok(grid)
+    }
+}
+
+/// Paper size in millimetres, a name like `a4` or `WIDTHxHEIGHT`, e.g. `297x210` for landscape
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Paper {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl FromStr for Paper {
+    type Err = AugeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = match s.to_lowercase().as_str() {
+            "a3" => (297.0, 420.0),
+            "a4" => (210.0, 297.0),
+            "a5" => (148.0, 210.0),
+            "letter" => (215.9, 279.4),
+            other => {
+                let (width, height) = other.split_once('x').ok_or_else(|| {
+                    AugeError::InvalidArgument(format!(
+                        "expected a3, a4, a5, letter or 'WIDTHxHEIGHT' in mm, got {}",
+                        s
+                    ))
+                })?;
+                (width.trim().parse()?, height.trim().parse()?)
+            }
+        };
+        if !(width > 0.0 && height > 0.0) {
+            return Err(AugeError::InvalidArgument(format!("paper cannot be empty, got {}", s)));
+        }
+        Ok(Paper { width, height })
+    }
+}
+
+/// A rectangle written as `X,Y,WxH`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+impl FromStr for Rect {
+    type Err = AugeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || AugeError::InvalidArgument(format!("expected 'X,Y,WxH', got {}", s));
+        let (x, rest) = s.split_once(',').ok_or_else(invalid)?;
+        let (y, size) = rest.split_once(',').ok_or_else(invalid)?;
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        Ok(Rect {
+            x: x.trim().parse()?,
+            y: y.trim().parse()?,
+            width: width.trim().parse()?,
+            height: height.trim().parse()?,
+        })
+    }
+}
+
+/// Encoding quality for a region, written as `XxY+WxH:QUALITY`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoiQuality {
+    pub rect: Rect,
+    pub quality: u8,
+}
+
+impl FromStr for RoiQuality {
+    type Err = AugeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || AugeError::InvalidArgument(format!("expected 'XxY+WxH:QUALITY', got {}", s));
+        let (geometry, quality) = s.rsplit_once(':').ok_or_else(invalid)?;
+        let (origin, size) = geometry.split_once('+').ok_or_else(invalid)?;
+        let (x, y) = origin.split_once('x').ok_or_else(invalid)?;
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        let quality: u8 = quality.trim().parse()?;
+        if !(1..=100).contains(&quality) {
+            return Err(AugeError::InvalidArgument(format!(
+                "region quality must be between 1 and 100, got {}",
+                quality
+            )));
+        }
+        Ok(RoiQuality {
+            rect: Rect {
+                x: x.trim().parse()?,
+                y: y.trim().parse()?,
+                width: width.trim().parse()?,
+                height: height.trim().parse()?,
+            },
+            quality,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AutoValue<T> where T: FromStr {
     Auto,
@@ -150,6 +634,23 @@ pub enum ResizeInput {
     Absolute(AutoValue<u32>, AutoValue<u32>)
 }
 
+impl ResizeInput {
+    /// Pixel size for an image of `width` x `height` where `auto` keeps that side as it is
+    pub fn size_keeping_auto(&self, width: u32, height: u32) -> (u32, u32) {
+        let side = |value: &AutoValue<u32>, original: u32| match value {
+            AutoValue::Auto => original,
+            AutoValue::Concrete(v) => *v,
+        };
+        match self {
+            ResizeInput::Relative(multiplier) => (
+                (width as f32 * multiplier).round() as u32,
+                (height as f32 * multiplier).round() as u32,
+            ),
+            ResizeInput::Absolute(tx, ty) => (side(tx, width), side(ty, height)),
+        }
+    }
+}
+
 
 impl FromStr for ResizeInput {
     type Err = AugeError;
@@ -207,14 +708,50 @@ pub enum FilterType {
     Lanczos3,
 }
 
-impl Into<image::imageops::FilterType> for FilterType {
-    fn into(self) -> image::imageops::FilterType {
-        match self {
-            Self::Nearest => image::imageops::FilterType::Nearest,
-            Self::Triangle => image::imageops::FilterType::Triangle,
-            Self::CatmullRom => image::imageops::FilterType::CatmullRom,
-            Self::Gaussian => image::imageops::FilterType::Gaussian,
-            Self::Lanczos3 => image::imageops::FilterType::Lanczos3,
+impl From<FilterType> for image::imageops::FilterType {
+    fn from(value: FilterType) -> Self {
+        match value {
+            FilterType::Nearest => image::imageops::FilterType::Nearest,
+            FilterType::Triangle => image::imageops::FilterType::Triangle,
+            FilterType::CatmullRom => image::imageops::FilterType::CatmullRom,
+            FilterType::Gaussian => image::imageops::FilterType::Gaussian,
+            FilterType::Lanczos3 => image::imageops::FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Filters of the resize command, the shared ones plus perceptual
+#[derive(Debug, Clone, ValueEnum)]
+pub enum ResizeFilterType {
+    /// Nearest Neighbor
+    Nearest,
+
+    /// Linear Filter
+    Triangle,
+
+    /// Cubic Filter
+    CatmullRom,
+
+    /// Gaussian Filter
+    Gaussian,
+
+    /// Lanczos with window 3
+    Lanczos3,
+
+    /// Lanczos with window 3 plus a local contrast boost when shrinking, for crisp thumbnails
+    Perceptual,
+}
+
+impl From<ResizeFilterType> for image::imageops::FilterType {
+    fn from(value: ResizeFilterType) -> Self {
+        match value {
+            ResizeFilterType::Nearest => image::imageops::FilterType::Nearest,
+            ResizeFilterType::Triangle => image::imageops::FilterType::Triangle,
+            ResizeFilterType::CatmullRom => image::imageops::FilterType::CatmullRom,
+            ResizeFilterType::Gaussian => image::imageops::FilterType::Gaussian,
+            ResizeFilterType::Lanczos3 | ResizeFilterType::Perceptual => {
+                image::imageops::FilterType::Lanczos3
+            }
         }
     }
 }